@@ -3,12 +3,18 @@
 //! CLI simplifiée:
 //! - `import`: EDIGEO → PostGIS avec versioning
 //! - `export`: EDIGEO → GeoJSON (sans DB)
+//! - `snapshot`: vues instantanées des tables versionnées
+//! - `diff`: changements entre deux millésimes
+//! - `lineage`: filiation des parcelles entre deux millésimes
 
+use crate::config::ChangeDetection;
+use crate::export::postgres::ExistingHashes;
 use crate::reproject_lite::SmartReprojector;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 use anyhow::{Context, Result};
 use bytes::BytesMut;
@@ -19,7 +25,7 @@ use geozero::wkt::WktWriter;
 use geozero::GeozeroGeometry;
 use rayon::prelude::*;
 use tokio::sync::mpsc;
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
 /// Sous-commande optionnelle (to-geojson uniquement, PostGIS est le défaut)
 #[derive(Subcommand)]
@@ -38,6 +44,118 @@ pub enum Commands {
         #[arg(long)]
         srid: Option<u32>,
     },
+
+    /// Créer ou rafraîchir les vues instantanées (<table>_<yyyy>_<mm>, <table>_current)
+    Snapshot {
+        /// Date du millésime (format YYYY-MM). Sans date, seules les vues `_current` sont créées
+        #[arg(short, long)]
+        date: Option<String>,
+
+        /// Schéma PostgreSQL
+        #[arg(long, default_value = "cadastre")]
+        schema: String,
+
+        /// Preset de config (full/light/bati) ou chemin vers un fichier JSON
+        #[arg(long, default_value = "full")]
+        config: String,
+
+        /// Créer des vues matérialisées (rafraîchies si elles existent)
+        #[arg(long)]
+        materialized: bool,
+    },
+
+    /// Lister les entités créées, supprimées ou modifiées entre deux millésimes
+    Diff(DiffArgs),
+
+    /// Reconstruire la filiation des parcelles (divisions, fusions) entre deux millésimes
+    Lineage(LineageArgs),
+}
+
+/// Arguments de la commande diff
+#[derive(clap::Args)]
+pub struct DiffArgs {
+    /// Premier millésime (format YYYY-MM)
+    #[arg(long)]
+    pub from: String,
+
+    /// Second millésime (format YYYY-MM)
+    #[arg(long)]
+    pub to: String,
+
+    /// Limiter à un département (ex: 38, 2A)
+    #[arg(long)]
+    pub dep: Option<String>,
+
+    /// Limiter à une table (ex: edi_parcelles)
+    #[arg(long)]
+    pub table: Option<String>,
+
+    /// Schéma PostgreSQL
+    #[arg(long, default_value = "cadastre")]
+    pub schema: String,
+
+    /// Preset de config (full/light/bati) ou chemin vers un fichier JSON
+    #[arg(long, default_value = "full")]
+    pub config: String,
+
+    /// Format de sortie
+    #[arg(long, value_enum, default_value_t = DiffFormat::Table)]
+    pub format: DiffFormat,
+
+    /// Fichier de sortie (défaut: sortie standard)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Arguments de la commande lineage
+///
+/// Deux sources possibles: deux millésimes importés (`--from`/`--to`, écrit
+/// `<schema>.parcelle_lineage`) ou deux archives EDIGEO (`--old-path`/`--new-path`,
+/// sortie CSV).
+#[derive(clap::Args)]
+pub struct LineageArgs {
+    /// Premier millésime importé (format YYYY-MM)
+    #[arg(long, requires = "to", required_unless_present = "old_path")]
+    pub from: Option<String>,
+
+    /// Second millésime importé (format YYYY-MM)
+    #[arg(long, requires = "from")]
+    pub to: Option<String>,
+
+    /// Archive EDIGEO du premier millésime
+    #[arg(long, requires = "new_path", conflicts_with_all = ["from", "to"])]
+    pub old_path: Option<PathBuf>,
+
+    /// Archive EDIGEO du second millésime
+    #[arg(long, requires = "old_path")]
+    pub new_path: Option<PathBuf>,
+
+    /// Limiter à un département en mode millésimes importés (ex: 38, 2A)
+    #[arg(long)]
+    pub dep: Option<String>,
+
+    /// Table des parcelles
+    #[arg(long, default_value = "edi_parcelles")]
+    pub table: String,
+
+    /// Schéma PostgreSQL
+    #[arg(long, default_value = "cadastre")]
+    pub schema: String,
+
+    /// Fichier CSV de sortie en mode archives (défaut: sortie standard)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Format de sortie de la commande diff
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DiffFormat {
+    /// Tableau texte avec un récapitulatif par nature de changement
+    Table,
+    /// CSV `table,departement,id,kind`
+    Csv,
+    /// FeatureCollection avec la géométrie la plus récente
+    Geojson,
 }
 
 /// Arguments pour l'export vers PostGIS (commande par défaut)
@@ -55,7 +173,7 @@ pub struct PostgisArgs {
     #[arg(long, default_value = "cadastre")]
     pub schema: String,
 
-    /// Preset de config (full/light/bati) ou chemin vers un fichier JSON
+    /// Preset de config (full/light/bati/auto) ou chemin vers un fichier JSON
     #[arg(long, default_value = "full")]
     pub config: String,
 
@@ -79,6 +197,17 @@ pub struct PostgisArgs {
     #[arg(long)]
     pub precision: Option<u8>,
 
+    /// Arrondi topologique: reprojette et arrondit les arcs partagés avant
+    /// l'assemblage des polygones (frontières communes identiques, sans
+    /// auto-intersection)
+    #[arg(long)]
+    pub snap_rounding: bool,
+
+    /// Distance de fusion des sommets d'arcs lors de l'assemblage des polygones
+    /// (unités de la projection source, ou du SRID cible avec --snap-rounding)
+    #[arg(long, default_value_t = edigeo::repair::ring::DEFAULT_TOLERANCE)]
+    pub node_tolerance: f64,
+
     /// Code département (ex: 38, 2A) ou "fromFile" pour lire depuis l'archive
     #[arg(long)]
     pub dep: Option<String>,
@@ -87,51 +216,93 @@ pub struct PostgisArgs {
     #[arg(long, alias = "threads")]
     pub jobs: Option<usize>,
 
+    /// Détection des entités inchangées: préchargement des hash en mémoire,
+    /// anti-jointure côté serveur, ou choix selon la taille des tables
+    #[arg(long, value_enum, default_value_t = DedupStrategy::Auto)]
+    pub dedup: DedupStrategy,
+}
+
+/// Paramètres de connexion PostgreSQL (surchargent les variables d'environnement)
+///
+/// Options globales: communes à l'import PostGIS et aux sous-commandes base de données.
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct ConnectionArgs {
     /// Hôte PostgreSQL (défaut: $PGHOST ou localhost)
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub host: Option<String>,
 
     /// Nom de la base PostgreSQL (défaut: $PGDATABASE ou cadastre)
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub database: Option<String>,
 
     /// Utilisateur PostgreSQL (défaut: $PGUSER ou postgres)
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub user: Option<String>,
 
     /// Mot de passe PostgreSQL (défaut: $PGPASSWORD)
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub password: Option<String>,
 
     /// Port PostgreSQL (défaut: $PGPORT ou 5432)
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub port: Option<u16>,
 
     /// Mode SSL: disable, prefer, require (défaut: $PGSSLMODE ou disable)
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub ssl: Option<String>,
 }
 
+impl ConnectionArgs {
+    /// Configuration de connexion: variables d'environnement puis options CLI
+    pub fn database_config(self) -> crate::export::pool::DatabaseConfig {
+        let mut config = crate::export::pool::DatabaseConfig::from_env();
+        apply_database_overrides(
+            &mut config,
+            self.host,
+            self.database,
+            self.user,
+            self.password,
+            self.port,
+            self.ssl,
+        );
+        config
+    }
+}
+
+/// Stratégie de détection des entités inchangées lors d'un import incrémental
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DedupStrategy {
+    /// Mémoire si le périmètre préchargé reste sous `AUTO_PRELOAD_MAX_ROWS`, serveur sinon
+    Auto,
+    /// Préchargement des hash des versions actives du périmètre importé
+    Memory,
+    /// Aucun préchargement: toutes les entités sont copiées en staging et
+    /// l'anti-jointure du merge écarte celles qui n'ont pas changé
+    Server,
+}
+
+/// Seuil de versions actives au-delà duquel `--dedup auto` passe côté serveur
+const AUTO_PRELOAD_MAX_ROWS: u64 = 5_000_000;
+
 /// Exécute la commande import
-pub async fn cmd_import(
-    path: &Path,
-    date: &str,
-    schema: &str,
-    config_spec: &str,
-    drop_schema: bool,
-    drop_table: bool,
-    skip_indexes: bool,
-    srid: u32,
-    precision: Option<u8>,
-    dep: Option<String>,
-    host: Option<String>,
-    database: Option<String>,
-    user: Option<String>,
-    password: Option<String>,
-    port: Option<u16>,
-    ssl: Option<String>,
-    jobs: Option<usize>,
-) -> Result<()> {
+pub async fn cmd_import(args: PostgisArgs, connection: ConnectionArgs) -> Result<()> {
+    let PostgisArgs {
+        ref path,
+        ref date,
+        ref schema,
+        config: ref config_spec,
+        drop_schema,
+        drop_table,
+        skip_indexes,
+        srid,
+        precision,
+        snap_rounding,
+        node_tolerance,
+        dep,
+        jobs,
+        dedup,
+    } = args;
+
     // Valider le format de date
     validate_date_format(date)?;
     let valid_from = format!("{}-01", date); // YYYY-MM-01
@@ -164,8 +335,14 @@ pub async fn cmd_import(
             .unwrap_or(4)
     });
 
-    // Charger la configuration (presets Rust "full/light/bati" ou fichier JSON)
-    let config = load_import_config(config_spec)?;
+    // Charger la configuration (presets Rust "full/light/bati" ou fichier JSON).
+    // En mode `auto`, les tables sont créées au fil des types d'objets des lots.
+    let auto_config = config_spec == "auto";
+    let config = if auto_config {
+        crate::config::Config::default()
+    } else {
+        load_import_config(config_spec)?
+    };
     let (table_specs, feature_type_to_table) = build_import_specs(&config)?;
 
     let dep_label = dep.as_deref().unwrap_or("auto").to_string();
@@ -182,11 +359,12 @@ pub async fn cmd_import(
     println!("Skip indexes: {}", skip_indexes);
     println!("Target SRID: {}", srid);
     println!("Coordinate precision: {} decimals", coord_precision);
+    println!("Snap rounding: {}", snap_rounding);
+    println!("Node tolerance: {}", node_tolerance);
     println!("Departement override: {}", dep_label);
 
     // Connecter à PostgreSQL
-    let mut db_config = crate::export::pool::DatabaseConfig::from_env();
-    apply_database_overrides(&mut db_config, host, database, user, password, port, ssl);
+    let db_config = connection.database_config();
     println!(
         "Database: {}@{}:{}/{} (SSL: {:?})",
         db_config.user, db_config.host, db_config.port, db_config.dbname, db_config.ssl_mode
@@ -197,23 +375,7 @@ pub async fn cmd_import(
     println!("Connected to PostgreSQL");
 
     // Créer le schéma et les tables
-    let pg_tables = table_specs
-        .iter()
-        .map(|t| crate::export::postgres::TableConfig {
-            name: t.name.clone(),
-            geometry_type: "Geometry".to_string(),
-            srid,
-            columns: t
-                .columns
-                .iter()
-                .map(|c| crate::export::postgres::ColumnConfig {
-                    name: c.name.clone(),
-                    pg_type: pg_type_for(&c.data_type).to_string(),
-                    source: c.source.clone(),
-                })
-                .collect(),
-        })
-        .collect::<Vec<_>>();
+    let pg_tables = pg_table_configs(&table_specs, srid);
 
     if drop_table {
         crate::export::postgres::drop_tables(&pool, schema, &pg_tables).await?;
@@ -222,65 +384,97 @@ pub async fn cmd_import(
     crate::export::postgres::create_staging_tables(&pool, schema, &pg_tables).await?;
     println!("Schema ready");
 
-    // Pre-load existing hashes for incremental import (skip unchanged features)
+    // Pre-load active hashes for incremental import (unchanged features are only marked as seen)
     let preload_started_at = std::time::Instant::now();
-    let mut existing_hashes: HashMap<usize, std::collections::HashSet<[u8; 32]>> = HashMap::new();
+    let preload_scope = build_preload_scope(&archives, dep_override.as_deref());
+    match &preload_scope {
+        Some(scope) => println!(
+            "Preload scope: departements {} ({} communes)",
+            scope.departements.join(", "),
+            scope.id_prefixes.len()
+        ),
+        None => println!("Preload scope: unknown (departement not derivable from archive names)"),
+    }
+
+    let mut existing_hashes: HashMap<usize, HashMap<String, ExistingHashes>> = HashMap::new();
     for (idx, table) in table_specs.iter().enumerate() {
-        if table.hash_geom {
-            let hashes =
-                crate::export::postgres::load_existing_hashes(&pool, schema, &table.name).await?;
-            if !hashes.is_empty() {
-                println!("  {} existing hashes for {}", hashes.len(), table.name);
-                existing_hashes.insert(idx, hashes);
+        // Sans hash de géométrie, une comparaison géométrique ne peut se faire qu'en SQL
+        if !table.hash_geom && table.change_detection.geometry() {
+            continue;
+        }
+        // Sans périmètre connu, charger la table entière n'est pas borné: dédup serveur
+        let Some(scope) = &preload_scope else {
+            continue;
+        };
+        let scope = &table_preload_scope(scope, table);
+
+        let use_memory = match dedup {
+            DedupStrategy::Memory => true,
+            DedupStrategy::Server => false,
+            DedupStrategy::Auto => {
+                let rows = crate::export::postgres::count_preload_rows(
+                    &pool,
+                    schema,
+                    &table.name,
+                    scope,
+                    AUTO_PRELOAD_MAX_ROWS,
+                )
+                .await?;
+                rows <= AUTO_PRELOAD_MAX_ROWS
             }
+        };
+        if !use_memory {
+            println!("  {}: server-side dedup", table.name);
+            continue;
+        }
+
+        let hashes =
+            crate::export::postgres::load_existing_hashes(&pool, schema, &table.name, scope)
+                .await?;
+        if !hashes.is_empty() {
+            println!("  {} existing hashes for {}", hashes.len(), table.name);
+            existing_hashes.insert(idx, hashes);
         }
     }
     let preload_duration = preload_started_at.elapsed();
     if !existing_hashes.is_empty() {
         println!("Hash preload: {:.2?}", preload_duration);
     }
-    let existing_hashes = Arc::new(existing_hashes);
 
     let copy_started_at = std::time::Instant::now();
 
     // Démarrer 1 COPY stream par table (réduit drastiquement le nombre de transactions)
     let mut senders: Vec<mpsc::Sender<crate::export::postgres::CopyChunk>> = Vec::new();
-    let mut copy_handles: Vec<(String, tokio::task::JoinHandle<Result<u64>>)> = Vec::new();
-
+    let mut copy_handles: Vec<(String, CopyHandle)> = Vec::new();
     for table in &table_specs {
-        let (tx, rx) = mpsc::channel::<crate::export::postgres::CopyChunk>(16);
+        let (tx, handle) = spawn_copy(&pool, schema, table);
         senders.push(tx);
-
-        let pool = pool.clone();
-        let schema = schema.to_string();
-        let logical_table = table.name.clone();
-        let table_name = format!("_staging_{}", table.name);
-        let dynamic_cols = table
-            .columns
-            .iter()
-            .map(|c| c.name.clone())
-            .collect::<Vec<_>>();
-
-        copy_handles.push((
-            logical_table,
-            tokio::spawn(async move {
-                crate::export::postgres::copy_csv_chunks(
-                    &pool,
-                    &schema,
-                    &table_name,
-                    &dynamic_cols,
-                    rx,
-                )
-                .await
-                .with_context(|| format!("COPY failed for {}.{}", schema, table_name))
-            }),
-        ));
+        copy_handles.push((table.name.clone(), handle));
     }
 
     // Parser et streamer en parallèle
-    let senders = Arc::new(senders);
-    let table_specs = Arc::new(table_specs);
-    let feature_type_to_table = Arc::new(feature_type_to_table);
+    let settings = Arc::new(EncodeSettings {
+        tables: Mutex::new(Arc::new(ImportTables {
+            specs: table_specs,
+            feature_type_to_table,
+            senders,
+        })),
+        copy_handles: Mutex::new(copy_handles),
+        auto_tables: auto_config.then(|| AutoTables {
+            pool: pool.clone(),
+            schema: schema.to_string(),
+            srid,
+            drop_table,
+        }),
+        existing_hashes,
+        next_lot: AtomicI64::new(0),
+        valid_from: valid_from.clone(),
+        dep_override: dep_override.as_ref().clone(),
+        srid,
+        precision: coord_precision,
+        snap_rounding,
+        node_tolerance,
+    });
 
     let processed = Arc::new(AtomicUsize::new(0));
     let parse_errors = Arc::new(AtomicUsize::new(0));
@@ -288,25 +482,37 @@ pub async fn cmd_import(
     let invalid_geometries = Arc::new(AtomicUsize::new(0));
     let skipped_existing = Arc::new(AtomicUsize::new(0));
     let skipped_archives = Arc::new(AtomicUsize::new(0));
+    let rejected_features = Arc::new(AtomicUsize::new(0));
+    let file_errors = Arc::new(AtomicUsize::new(0));
+
+    // Schéma des lots importés (colonnes des tables `auto_fields`)
+    let lots_schema = Arc::new(Mutex::new(edigeo::Schema::default()));
+    // Périmètre du millésime: départements relus et archives conservées telles quelles
+    let imported_departements = Arc::new(Mutex::new(BTreeSet::<String>::new()));
+    let kept_archives = Arc::new(Mutex::new(Vec::<String>::new()));
+    // Lignes envoyées par les lots en erreur, retirées de la staging avant la fusion
+    let discarded_lots = Arc::new(Mutex::new(Vec::<i64>::new()));
+    let discarded_archives = Arc::new(Mutex::new(Vec::<String>::new()));
 
     let pool_arc = Arc::new(pool.clone());
     let schema_arc = Arc::new(schema.to_string());
 
     stream::iter(archives.into_iter())
         .for_each_concurrent(jobs, |archive_path| {
-            let senders = Arc::clone(&senders);
-            let table_specs = Arc::clone(&table_specs);
-            let feature_type_to_table = Arc::clone(&feature_type_to_table);
-            let existing_hashes = Arc::clone(&existing_hashes);
-            let valid_from = valid_from.clone();
+            let settings = Arc::clone(&settings);
             let processed = Arc::clone(&processed);
             let parse_errors = Arc::clone(&parse_errors);
             let skipped_types = Arc::clone(&skipped_types);
             let invalid_geometries = Arc::clone(&invalid_geometries);
             let skipped_existing = Arc::clone(&skipped_existing);
             let skipped_archives = Arc::clone(&skipped_archives);
-            let dep_override = Arc::clone(&dep_override);
-            let precision = coord_precision;
+            let rejected_features = Arc::clone(&rejected_features);
+            let file_errors = Arc::clone(&file_errors);
+            let lots_schema = Arc::clone(&lots_schema);
+            let imported_departements = Arc::clone(&imported_departements);
+            let kept_archives = Arc::clone(&kept_archives);
+            let discarded_lots = Arc::clone(&discarded_lots);
+            let discarded_archives = Arc::clone(&discarded_archives);
             let pool = Arc::clone(&pool_arc);
             let schema = Arc::clone(&schema_arc);
 
@@ -326,7 +532,11 @@ pub async fn cmd_import(
                 {
                     Ok(Ok(cs)) => cs,
                     Ok(Err(e)) => {
-                        warn!("Failed to compute checksum for {}: {}", archive_path.display(), e);
+                        warn!(
+                            "Failed to compute checksum for {}: {}",
+                            archive_path.display(),
+                            e
+                        );
                         String::new()
                     }
                     Err(e) => {
@@ -338,12 +548,16 @@ pub async fn cmd_import(
                 // Vérifier si l'archive a déjà été importée avec le même checksum
                 if !checksum.is_empty() {
                     match crate::export::postgres::is_archive_already_imported(
-                        &pool, &schema, &archive_name, &checksum,
+                        &pool,
+                        &schema,
+                        &archive_name,
+                        &checksum,
                     )
                     .await
                     {
                         Ok(true) => {
-                            // Archive déjà importée, skip
+                            // Archive déjà importée, skip (ses entités restent actives)
+                            kept_archives.lock().unwrap().push(archive_name);
                             skipped_archives.fetch_add(1, Ordering::Relaxed);
                             processed.fetch_add(1, Ordering::Relaxed);
                             return;
@@ -355,185 +569,61 @@ pub async fn cmd_import(
                     }
                 }
 
-                let parse = tokio::task::spawn_blocking({
+                // Parsing en streaming et encodage COPY sur un thread bloquant:
+                // seul le fichier VEC en cours est en mémoire
+                let encoded = tokio::task::spawn_blocking({
+                    let settings = Arc::clone(&settings);
                     let archive_path = archive_path.clone();
-                    move || edigeo::parse(&archive_path)
+                    let archive_name = archive_name.clone();
+                    move || encode_archive(&settings, &archive_path, &archive_name)
                 })
                 .await;
 
-                let result = match parse {
-                    Ok(Ok(r)) => r,
-                    Ok(Err(e)) => {
-                        warn!("Failed to parse {}: {}", archive_path.display(), e);
-                        parse_errors.fetch_add(1, Ordering::Relaxed);
-                        processed.fetch_add(1, Ordering::Relaxed);
-                        return;
-                    }
+                let stats = match encoded {
+                    Ok(stats) => stats,
                     Err(e) => {
+                        // Lots de l'archive inconnus: toutes ses lignes sont retirées
                         warn!(
                             "Failed to join parse task for {}: {}",
                             archive_path.display(),
                             e
                         );
+                        kept_archives.lock().unwrap().push(archive_name.clone());
+                        discarded_archives.lock().unwrap().push(archive_name);
                         parse_errors.fetch_add(1, Ordering::Relaxed);
                         processed.fetch_add(1, Ordering::Relaxed);
                         return;
                     }
                 };
 
-                let departement = match &*dep_override {
-                    Some(value) if value.eq_ignore_ascii_case("fromfile") => {
-                        derive_dep_from_archive(&archive_path)
-                            .unwrap_or_else(|| result.departement.clone())
-                    }
-                    Some(value) => value.clone(),
-                    None => result.departement.clone(),
-                };
-                let epsg = result.projection.epsg;
-                let ewkt_prefix = format!("SRID={};", srid).into_bytes();
-
-                let reprojector = match SmartReprojector::new(epsg, srid) {
-                    Ok(r) => r,
-                    Err(e) => {
-                        warn!("Failed to build reprojector ({} → {}): {}", epsg, srid, e);
-                        parse_errors.fetch_add(1, Ordering::Relaxed);
-                        processed.fetch_add(1, Ordering::Relaxed);
-                        return;
-                    }
-                };
-
-                const BATCH_SIZE: u64 = 5000;
-
-                let table_count = table_specs.len();
-                let mut buffers: Vec<BytesMut> =
-                    (0..table_count).map(|_| BytesMut::new()).collect();
-                let mut buffer_rows: Vec<u64> = vec![0; table_count];
-
-                let mut wkt_buf: Vec<u8> = Vec::with_capacity(1024);
-
-                // Extraire les valeurs calculées (comme Node.js)
-                let commune_idu = result
-                    .features
-                    .get("COMMUNE_id")
-                    .and_then(|f| f.first())
-                    .and_then(|f| f.properties.get("IDU"))
-                    .cloned()
-                    .unwrap_or_default();
-
-                let section_idu = result
-                    .features
-                    .get("SECTION_id")
-                    .and_then(|f| f.first())
-                    .and_then(|f| f.properties.get("IDU"))
-                    .cloned()
-                    .unwrap_or_default();
-
-                // Contexte calculé pour l'archive
-                let computed_context = ComputedContext {
-                    commune_id: commune_idu,
-                    section_id: section_idu,
-                };
-
-                for (feature_type, features) in result.features {
-                    let key = normalize_feature_type(&feature_type);
-                    let Some(&table_idx) = feature_type_to_table.get(&key) else {
-                        skipped_types.fetch_add(1, Ordering::Relaxed);
-                        continue;
-                    };
-
-                    let table = &table_specs[table_idx];
-                    let buf = &mut buffers[table_idx];
-
-                    // Allocations amorties par table / archive
-                    if buf.capacity() < 64 * 1024 {
-                        buf.reserve(64 * 1024);
-                    }
-
-                    for feature in features {
-                        let geometry = match reprojector.transform_geometry(&feature.geometry) {
-                            Ok(g) => round_geometry_coords(&g, precision),
-                            Err(e) => {
-                                warn!(
-                                    "Failed to reproject {} ({} → {}): {}",
-                                    feature.id, epsg, srid, e
-                                );
-                                parse_errors.fetch_add(1, Ordering::Relaxed);
-                                continue;
-                            }
-                        };
-
-                        // Skip si le hash existe déjà (import incrémental)
-                        if table.hash_geom {
-                            if let Some(hash_set) = existing_hashes.get(&table_idx) {
-                                let hash = crate::versioning::diff::geometry_hash(&geometry);
-                                if hash_set.contains(&hash) {
-                                    skipped_existing.fetch_add(1, Ordering::Relaxed);
-                                    continue;
-                                }
-                            }
-                        }
-
-                        if let Err(e) = write_copy_row(
-                            buf,
-                            &feature,
-                            &geometry,
-                            &departement,
-                            &valid_from,
-                            &ewkt_prefix,
-                            &mut wkt_buf,
-                            table,
-                            &computed_context,
-                        ) {
-                            let msg = e.to_string();
-                            if msg.contains("Geometry not valid for PostGIS ingestion") {
-                                invalid_geometries.fetch_add(1, Ordering::Relaxed);
-                            } else {
-                                warn!(
-                                    "Failed to encode row ({} / {}): {}",
-                                    feature_type, feature.id, msg
-                                );
-                            }
-                            continue;
-                        }
-                        buffer_rows[table_idx] += 1;
-
-                        // Flush quand on atteint BATCH_SIZE rows (5000)
-                        if buffer_rows[table_idx] >= BATCH_SIZE {
-                            let rows = buffer_rows[table_idx];
-                            buffer_rows[table_idx] = 0;
-                            let data = buf.split().freeze();
-                            if rows > 0 && !data.is_empty() {
-                                if let Err(e) = senders[table_idx]
-                                    .send(crate::export::postgres::CopyChunk { data, rows })
-                                    .await
-                                {
-                                    warn!("Failed to send COPY chunk: {}", e);
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
-
-                // Flush final
-                for (idx, buf) in buffers.into_iter().enumerate() {
-                    let rows = buffer_rows[idx];
-                    if rows == 0 || buf.is_empty() {
-                        continue;
-                    }
-                    let data = buf.freeze();
-                    if let Err(e) = senders[idx]
-                        .send(crate::export::postgres::CopyChunk { data, rows })
-                        .await
-                    {
-                        warn!("Failed to send final COPY chunk: {}", e);
-                    }
+                crate::config::merge_schema(&mut lots_schema.lock().unwrap(), stats.schema);
+                imported_departements
+                    .lock()
+                    .unwrap()
+                    .extend(stats.departements);
+                if stats.failed_lots > 0 {
+                    // Lot en erreur: retiré de la staging, les entités de
+                    // l'archive restent actives
+                    kept_archives.lock().unwrap().push(archive_name.clone());
+                    discarded_lots.lock().unwrap().extend(&stats.discarded_lots);
                 }
+                parse_errors.fetch_add(
+                    stats.reproject_errors + stats.failed_lots,
+                    Ordering::Relaxed,
+                );
+                rejected_features.fetch_add(stats.rejected_features, Ordering::Relaxed);
+                file_errors.fetch_add(stats.file_errors, Ordering::Relaxed);
+                skipped_types.fetch_add(stats.skipped_types, Ordering::Relaxed);
+                invalid_geometries.fetch_add(stats.invalid_geometries, Ordering::Relaxed);
+                skipped_existing.fetch_add(stats.skipped_existing, Ordering::Relaxed);
 
                 // Enregistrer le checksum de l'archive après import réussi
-                if !checksum.is_empty() {
+                if !checksum.is_empty() && stats.failed_lots == 0 {
                     if let Err(e) = crate::export::postgres::record_archive_checksum(
-                        &pool, &schema, &archive_name, &checksum,
+                        &pool,
+                        &schema,
+                        &archive_name,
+                        &checksum,
                     )
                     .await
                     {
@@ -541,6 +631,19 @@ pub async fn cmd_import(
                     }
                 }
 
+                // Journal des retouches (remplace celui d'un import précédent)
+                if let Err(e) = crate::export::postgres::record_repairs(
+                    &pool,
+                    &schema,
+                    &archive_name,
+                    &settings.valid_from,
+                    &stats.repairs,
+                )
+                .await
+                {
+                    warn!("Failed to record repair log: {}", e);
+                }
+
                 let done = processed.fetch_add(1, Ordering::Relaxed) + 1;
                 if done % 100 == 0 {
                     info!(processed = done, "Import progress");
@@ -550,7 +653,13 @@ pub async fn cmd_import(
         .await;
 
     // Fermer les channels pour terminer les COPY
-    drop(senders);
+    let EncodeSettings {
+        tables,
+        copy_handles,
+        ..
+    } = Arc::into_inner(settings).expect("archive tasks are finished");
+    let mut table_specs = tables.into_inner().unwrap().specs.clone();
+    let copy_handles = copy_handles.into_inner().unwrap();
 
     let mut staged_by_table: HashMap<String, u64> = HashMap::new();
     let mut total_staged: u64 = 0;
@@ -562,30 +671,114 @@ pub async fn cmd_import(
 
     let copy_duration = copy_started_at.elapsed();
 
-    // Merge staging -> final (DO NOTHING sur doublons)
+    if auto_config && table_specs.is_empty() {
+        anyhow::bail!("No SCD schema found in the lots (required by --config auto)");
+    }
+    // Tables `auto` créées pendant l'import
+    let pg_tables = pg_table_configs(&table_specs, srid);
+
+    // Lots en erreur: importés en entier ou pas du tout
+    let discarded_lots = discarded_lots.lock().unwrap().clone();
+    let discarded_archives = discarded_archives.lock().unwrap().clone();
+    let discarded_rows = if discarded_lots.is_empty() && discarded_archives.is_empty() {
+        0
+    } else {
+        crate::export::postgres::discard_staged_lots(
+            &pool,
+            schema,
+            &pg_tables,
+            &discarded_lots,
+            &discarded_archives,
+        )
+        .await?
+    };
+
+    // Colonnes des attributs SCD non mappés, d'après le schéma des lots importés
+    let lots_schema = std::mem::take(&mut *lots_schema.lock().unwrap());
+    let resolved_config = if auto_config {
+        crate::config::Config::auto(&lots_schema)
+    } else {
+        let mut config = config;
+        config.add_schema_fields(&lots_schema);
+        config
+    };
+    let (resolved_specs, _) = build_import_specs(&resolved_config)?;
+    for table in table_specs.iter_mut().filter(|t| t.auto_fields) {
+        let Some(resolved) = resolved_specs.iter().find(|r| r.name == table.name) else {
+            continue;
+        };
+        let added: Vec<ColumnSpec> = resolved
+            .columns
+            .iter()
+            .filter(|c| !table.columns.iter().any(|known| known.name == c.name))
+            .cloned()
+            .collect();
+        if added.is_empty() {
+            continue;
+        }
+        table.columns.extend(added.iter().cloned());
+        crate::export::postgres::create_tables(
+            &pool,
+            schema,
+            &pg_table_configs(std::slice::from_ref(table), srid),
+        )
+        .await?;
+        crate::export::postgres::expand_auto_attributes(
+            &pool,
+            schema,
+            &table.name,
+            &column_configs(&added),
+        )
+        .await?;
+        info!(
+            table = table.name.as_str(),
+            columns = added.len(),
+            "Added SCD columns"
+        );
+    }
+
+    let scope = crate::versioning::temporal::MillesimeScope {
+        valid_from: valid_from.clone(),
+        departements: imported_departements
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect(),
+        kept_archives: kept_archives.lock().unwrap().clone(),
+    };
+
+    // Merge staging -> final (fin de vie des entités disparues ou modifiées)
     let merge_started_at = std::time::Instant::now();
-    let mut inserted_by_table: HashMap<String, u64> = HashMap::new();
+    let mut merged_by_table: HashMap<String, crate::versioning::temporal::TableMergeResult> =
+        HashMap::new();
     let mut merged_total: u64 = 0;
+    let mut ended_total: u64 = 0;
     for table in table_specs.iter() {
         let dynamic_cols = table
             .columns
             .iter()
             .map(|c| c.name.clone())
             .collect::<Vec<_>>();
-        let inserted = crate::export::postgres::merge_staging_into_table(
+        let merged = crate::export::postgres::merge_staging_into_table(
             &pool,
             schema,
             &table.name,
             &dynamic_cols,
+            table.change_detection,
+            &scope,
         )
         .await?;
-        inserted_by_table.insert(table.name.clone(), inserted);
-        merged_total += inserted;
+        merged_total += merged.inserted;
+        ended_total += merged.ended;
         info!(
-            table = table.name.as_str(),
-            inserted = inserted,
+            table = merged.table.as_str(),
+            inserted = merged.inserted,
+            replaced = merged.replaced,
+            ended = merged.ended,
             "Merged staging table"
         );
+        merged_by_table.insert(table.name.clone(), merged);
     }
 
     crate::export::postgres::drop_staging_tables(&pool, schema, &pg_tables).await?;
@@ -616,10 +809,17 @@ pub async fn cmd_import(
         );
     }
     if total_skipped_existing > 0 {
-        println!("Skipped features (already exist): {}", total_skipped_existing);
+        println!(
+            "Unchanged features (already exist): {}",
+            total_skipped_existing
+        );
     }
     println!("Rows staged: {}", total_staged);
+    if discarded_rows > 0 {
+        println!("Rows discarded (failed lots): {}", discarded_rows);
+    }
     println!("Rows inserted: {}", merged_total);
+    println!("Rows ended: {}", ended_total);
     println!("Copy duration: {:.2?}", copy_duration);
     println!("Merge duration: {:.2?}", merge_duration);
     if skip_indexes {
@@ -631,17 +831,35 @@ pub async fn cmd_import(
     println!("\nPer-table:");
     for table in table_specs.iter() {
         let staged = staged_by_table.get(&table.name).copied().unwrap_or(0);
-        let inserted = inserted_by_table.get(&table.name).copied().unwrap_or(0);
-        let duplicates = staged.saturating_sub(inserted);
+        let merged = merged_by_table
+            .get(&table.name)
+            .cloned()
+            .unwrap_or_default();
         println!(
-            "- {}: staged {}, inserted {}, duplicates {}",
-            table.name, staged, inserted, duplicates
+            "- {}: staged {}, inserted {} (new {}, changed {}), ended {}",
+            table.name,
+            staged,
+            merged.inserted,
+            merged.created(),
+            merged.replaced,
+            merged.ended
         );
     }
 
     if total_errors > 0 {
         println!("Parse errors: {}", total_errors);
     }
+    let total_rejected = rejected_features.load(Ordering::Relaxed);
+    if total_rejected > 0 {
+        println!("Rejected features (see _repair_log): {}", total_rejected);
+    }
+    let total_file_errors = file_errors.load(Ordering::Relaxed);
+    if total_file_errors > 0 {
+        println!(
+            "Non-fatal file errors (DIC, SCD, GEN): {}",
+            total_file_errors
+        );
+    }
     if total_skipped_types > 0 {
         println!(
             "Skipped feature groups (unconfigured): {}",
@@ -653,8 +871,8 @@ pub async fn cmd_import(
     }
 
     info!(
-        "Import complete: {} inserted, {} parse errors",
-        merged_total, total_errors
+        "Import complete: {} inserted, {} ended, {} parse errors",
+        merged_total, ended_total, total_errors
     );
 
     Ok(())
@@ -668,16 +886,65 @@ struct ColumnSpec {
     prefix_dep: bool,
 }
 
+impl ColumnSpec {
+    fn from_field(field: &crate::config::FieldMapping) -> Self {
+        Self {
+            name: field.target.clone(),
+            source: field.source.clone(),
+            data_type: field.data_type.clone(),
+            prefix_dep: field.prefix_dep,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct TableSpec {
     name: String,
     columns: Vec<ColumnSpec>,
     hash_geom: bool,
+    change_detection: ChangeDetection,
+    /// Attributs SCD non mappés stockés en JSON pendant l'import, puis en
+    /// colonnes d'après le schéma des lots
+    auto_fields: bool,
+    /// Identifiants construits sur l'IDU (département + commune + ...) pour
+    /// tous les types d'objets de la table
+    idu_ids: bool,
+}
+
+/// Configurations PostgreSQL des tables d'import
+fn pg_table_configs(
+    table_specs: &[TableSpec],
+    srid: u32,
+) -> Vec<crate::export::postgres::TableConfig> {
+    table_specs
+        .iter()
+        .map(|t| crate::export::postgres::TableConfig {
+            name: t.name.clone(),
+            geometry_type: "Geometry".to_string(),
+            srid,
+            columns: column_configs(&t.columns),
+        })
+        .collect()
+}
+
+/// Configurations PostgreSQL de colonnes dynamiques
+fn column_configs(columns: &[ColumnSpec]) -> Vec<crate::export::postgres::ColumnConfig> {
+    columns
+        .iter()
+        .map(|c| crate::export::postgres::ColumnConfig {
+            name: c.name.clone(),
+            pg_type: pg_type_for(&c.data_type).to_string(),
+            source: c.source.clone(),
+        })
+        .collect()
 }
 
 fn load_import_config(spec: &str) -> Result<crate::config::Config> {
     match spec {
         "full" | "light" | "bati" => crate::config::Config::from_preset(spec),
+        "auto" => anyhow::bail!(
+            "The auto config is derived from the archives' SCD and is only available for import"
+        ),
         _ => crate::config::Config::load(Path::new(spec)),
     }
 }
@@ -696,6 +963,8 @@ fn is_reserved_column(name: &str) -> bool {
             | "valid_from"
             | "valid_to"
             | "geometry_hash"
+            | "attributes_hash"
+            | "source_archive"
             | "created_at"
             | "updated_at"
     )
@@ -711,6 +980,7 @@ fn pg_type_for(data_type: &str) -> &'static str {
         "float" | "double" | "double precision" => "DOUBLE PRECISION",
         "boolean" | "bool" => "BOOLEAN",
         "date" => "DATE",
+        "update_type" => crate::export::postgres::UPDATE_TYPE,
         _ => "TEXT",
     }
 }
@@ -746,6 +1016,9 @@ fn apply_database_overrides(
     }
 }
 
+/// Types d'objets identifiés par leur IDU, qui commence par le code commune
+const IDU_FEATURE_TYPES: &[&str] = &["COMMUNE_ID", "SECTION_ID", "SUBDSECT_ID", "PARCELLE_ID"];
+
 fn build_import_specs(
     config: &crate::config::Config,
 ) -> Result<(Vec<TableSpec>, HashMap<String, usize>)> {
@@ -762,6 +1035,9 @@ fn build_import_specs(
                     name: table_cfg.table.clone(),
                     columns: Vec::new(),
                     hash_geom: table_cfg.hash_geom,
+                    change_detection: table_cfg.change_detection,
+                    auto_fields: table_cfg.auto_fields,
+                    idu_ids: true,
                 });
                 idx
             });
@@ -770,12 +1046,7 @@ fn build_import_specs(
             .fields
             .iter()
             .filter(|f| !is_reserved_column(f.target.as_str()))
-            .map(|f| ColumnSpec {
-                name: f.target.clone(),
-                source: f.source.clone(),
-                data_type: f.data_type.clone(),
-                prefix_dep: f.prefix_dep,
-            })
+            .map(ColumnSpec::from_field)
             .collect::<Vec<_>>();
 
         if tables[idx].columns.is_empty() {
@@ -787,9 +1058,18 @@ fn build_import_specs(
             );
         }
 
+        if tables[idx].change_detection != table_cfg.change_detection {
+            anyhow::bail!(
+                "Conflicting table configs for '{}': multiple feature types map to the same table with different change_detection",
+                tables[idx].name
+            );
+        }
+
         tables[idx].hash_geom = tables[idx].hash_geom || table_cfg.hash_geom;
+        tables[idx].auto_fields = tables[idx].auto_fields || table_cfg.auto_fields;
 
         let ft = normalize_feature_type(feature_type);
+        tables[idx].idu_ids &= IDU_FEATURE_TYPES.contains(&ft.as_str());
         feature_type_to_table.insert(ft.clone(), idx);
 
         // Tolérance: si on reçoit des types sans suffixe `_id`, on mappe aussi.
@@ -801,6 +1081,81 @@ fn build_import_specs(
     Ok((tables, feature_type_to_table))
 }
 
+/// Déduit le périmètre du préchargement des noms d'archives
+/// (`edigeo-<dep><commune>...`).
+///
+/// Retourne `None` si le département d'une archive est inconnu. Les préfixes
+/// de communes ne sont utilisés que si toutes les archives en fournissent un;
+/// un périmètre trop étroit ne fait que réduire les entités reconnues comme
+/// inchangées, le merge reste exact.
+fn build_preload_scope(
+    archives: &[PathBuf],
+    dep_override: Option<&str>,
+) -> Option<crate::export::postgres::PreloadScope> {
+    let fixed_dep = dep_override.filter(|d| !d.eq_ignore_ascii_case("fromfile"));
+
+    let mut departements = BTreeSet::new();
+    let mut id_prefixes = BTreeSet::new();
+    let mut all_communes = true;
+
+    for archive in archives {
+        let name_dep = derive_dep_from_archive(archive);
+        let dep = match (fixed_dep, &name_dep) {
+            (Some(fixed), _) => fixed.to_string(),
+            (None, Some(dep)) => dep.clone(),
+            (None, None) => return None,
+        };
+
+        // Code commune sur 3 caractères après un département métropolitain
+        let commune = name_dep
+            .filter(|name_dep| *name_dep == dep && dep.len() == 2)
+            .and_then(|_| archive_commune_code(archive, dep.len()));
+        match commune {
+            Some(commune) => {
+                id_prefixes.insert(format!("{}{}", dep, commune));
+            }
+            None => all_communes = false,
+        }
+
+        departements.insert(dep);
+    }
+
+    Some(crate::export::postgres::PreloadScope {
+        departements: departements.into_iter().collect(),
+        id_prefixes: if all_communes {
+            id_prefixes.into_iter().collect()
+        } else {
+            Vec::new()
+        },
+    })
+}
+
+/// Périmètre du préchargement d'une table: les préfixes de communes ne
+/// s'appliquent qu'aux identifiants construits sur l'IDU
+fn table_preload_scope(
+    scope: &crate::export::postgres::PreloadScope,
+    table: &TableSpec,
+) -> crate::export::postgres::PreloadScope {
+    crate::export::postgres::PreloadScope {
+        departements: scope.departements.clone(),
+        id_prefixes: if table.idu_ids {
+            scope.id_prefixes.clone()
+        } else {
+            Vec::new()
+        },
+    }
+}
+
+fn archive_commune_code(path: &Path, dep_len: usize) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    let rest = name.strip_prefix("edigeo-")?.get(dep_len..)?;
+    let commune = rest.get(..3)?;
+    commune
+        .bytes()
+        .all(|b| b.is_ascii_digit())
+        .then(|| commune.to_string())
+}
+
 fn derive_dep_from_archive(path: &Path) -> Option<String> {
     let name = path.file_name()?.to_str()?;
     let rest = name.strip_prefix("edigeo-")?;
@@ -862,7 +1217,9 @@ fn parse_edigeo_number(raw: &str) -> Option<f64> {
 
 /// Arrondit les coordonnées d'une géométrie à la précision spécifiée
 fn round_geometry_coords(geom: &geo::Geometry, decimals: u8) -> geo::Geometry {
-    use geo::{Coord, Geometry, LineString, MultiLineString, MultiPoint, MultiPolygon, Point, Polygon};
+    use geo::{
+        Coord, Geometry, LineString, MultiLineString, MultiPoint, MultiPolygon, Point, Polygon,
+    };
 
     let factor = 10_f64.powi(decimals as i32);
 
@@ -873,9 +1230,8 @@ fn round_geometry_coords(geom: &geo::Geometry, decimals: u8) -> geo::Geometry {
         }
     };
 
-    let round_line = |ls: &LineString| -> LineString {
-        LineString::new(ls.0.iter().map(round_coord).collect())
-    };
+    let round_line =
+        |ls: &LineString| -> LineString { LineString::new(ls.0.iter().map(round_coord).collect()) };
 
     match geom {
         Geometry::Point(p) => {
@@ -889,7 +1245,10 @@ fn round_geometry_coords(geom: &geo::Geometry, decimals: u8) -> geo::Geometry {
             Geometry::Polygon(Polygon::new(exterior, interiors))
         }
         Geometry::MultiPoint(mp) => {
-            let points: Vec<Point> = mp.0.iter().map(|p| Point::from(round_coord(&p.0))).collect();
+            let points: Vec<Point> =
+                mp.0.iter()
+                    .map(|p| Point::from(round_coord(&p.0)))
+                    .collect();
             Geometry::MultiPoint(MultiPoint::new(points))
         }
         Geometry::MultiLineString(mls) => {
@@ -897,11 +1256,16 @@ fn round_geometry_coords(geom: &geo::Geometry, decimals: u8) -> geo::Geometry {
             Geometry::MultiLineString(MultiLineString::new(lines))
         }
         Geometry::MultiPolygon(mpoly) => {
-            let polys: Vec<Polygon> = mpoly.0.iter().map(|poly| {
-                let exterior = round_line(poly.exterior());
-                let interiors: Vec<LineString> = poly.interiors().iter().map(round_line).collect();
-                Polygon::new(exterior, interiors)
-            }).collect();
+            let polys: Vec<Polygon> = mpoly
+                .0
+                .iter()
+                .map(|poly| {
+                    let exterior = round_line(poly.exterior());
+                    let interiors: Vec<LineString> =
+                        poly.interiors().iter().map(round_line).collect();
+                    Polygon::new(exterior, interiors)
+                })
+                .collect();
             Geometry::MultiPolygon(MultiPolygon::new(polys))
         }
         // Pour les autres types, on retourne tel quel
@@ -909,126 +1273,978 @@ fn round_geometry_coords(geom: &geo::Geometry, decimals: u8) -> geo::Geometry {
     }
 }
 
-/// Contexte calculé par archive (valeurs dérivées comme Node.js)
-struct ComputedContext {
-    /// IDU de la première commune de l'archive
-    commune_id: String,
-    /// IDU de la première section de l'archive
-    section_id: String,
-}
+/// Nombre de lignes par chunk COPY
+const BATCH_SIZE: u64 = 5000;
 
-fn push_csv_text_field(buf: &mut BytesMut, value: &str) {
-    buf.extend_from_slice(b"\"");
-    for b in value.as_bytes() {
-        match *b {
-            b'"' => buf.extend_from_slice(b"\"\""),
-            b'\n' | b'\r' => buf.extend_from_slice(b" "),
-            _ => buf.extend_from_slice(&[*b]),
-        }
-    }
-    buf.extend_from_slice(b"\"");
-}
+/// COPY d'une table de staging, qui retourne le nombre de lignes copiées
+type CopyHandle = tokio::task::JoinHandle<Result<u64>>;
 
-fn write_copy_row(
-    buf: &mut BytesMut,
-    feature: &edigeo::Feature,
-    geometry: &geo::Geometry,
-    departement: &str,
-    valid_from: &str,
-    ewkt_prefix: &[u8],
-    wkt_buf: &mut Vec<u8>,
+/// Démarre le COPY d'une table de staging, alimenté par le channel retourné
+fn spawn_copy(
+    pool: &deadpool_postgres::Pool,
+    schema: &str,
     table: &TableSpec,
-    computed: &ComputedContext,
-) -> Result<()> {
-    let start_len = buf.len();
+) -> (mpsc::Sender<crate::export::postgres::CopyChunk>, CopyHandle) {
+    let (tx, rx) = mpsc::channel::<crate::export::postgres::CopyChunk>(16);
+
+    let pool = pool.clone();
+    let schema = schema.to_string();
+    let table_name = format!("_staging_{}", table.name);
+    let dynamic_cols = table
+        .columns
+        .iter()
+        .map(|c| c.name.clone())
+        .collect::<Vec<_>>();
 
-    let res: Result<()> = (|| {
-        // id (préfixé avec le département pour unicité France entière)
-        let prefixed_id = format!("{}{}", departement, feature.id);
-        push_csv_text_field(buf, &prefixed_id);
-        buf.extend_from_slice(b"|");
+    let handle = tokio::spawn(async move {
+        crate::export::postgres::copy_csv_chunks(&pool, &schema, &table_name, &dynamic_cols, rx)
+            .await
+            .with_context(|| format!("COPY failed for {}.{}", schema, table_name))
+    });
+    (tx, handle)
+}
 
-        // departement
-        push_csv_text_field(buf, departement);
-        buf.extend_from_slice(b"|");
+/// Tables alimentées par l'import, indexées comme leurs buffers et leurs hash
+/// préchargés
+#[derive(Clone, Default)]
+struct ImportTables {
+    specs: Vec<TableSpec>,
+    feature_type_to_table: HashMap<String, usize>,
+    /// Un channel par table vers son COPY
+    senders: Vec<mpsc::Sender<crate::export::postgres::CopyChunk>>,
+}
+
+/// Création des tables de la configuration `auto` pendant l'import
+struct AutoTables {
+    pool: deadpool_postgres::Pool,
+    schema: String,
+    srid: u32,
+    /// Supprimer les tables existantes avant de les recréer (`--drop-table`)
+    drop_table: bool,
+}
 
-        // geometry (EWKT: SRID=...;WKT)
-        if !geometry_ok_for_postgis(geometry) {
-            anyhow::bail!("Geometry not valid for PostGIS ingestion (too few points)");
+/// Paramètres d'encodage COPY partagés par toutes les archives d'un import
+struct EncodeSettings {
+    /// Tables connues, remplacées à chaque ajout d'une table `auto`
+    tables: Mutex<Arc<ImportTables>>,
+    /// COPY de chaque table
+    copy_handles: Mutex<Vec<(String, CopyHandle)>>,
+    /// Configuration `auto`: une table par type d'objet du schéma des lots
+    auto_tables: Option<AutoTables>,
+    /// Hash des versions actives préchargés, par index de table
+    existing_hashes: HashMap<usize, HashMap<String, ExistingHashes>>,
+    /// Numéro du prochain lot encodé (colonne `staging_lot`)
+    next_lot: AtomicI64,
+    /// Date du millésime (YYYY-MM-DD)
+    valid_from: String,
+    dep_override: Option<String>,
+    srid: u32,
+    precision: u8,
+    /// Arrondi topologique des arcs par le parser (géométries déjà dans `srid`)
+    snap_rounding: bool,
+    /// Distance de fusion des sommets d'arcs (assemblage des polygones)
+    node_tolerance: f64,
+}
+
+impl EncodeSettings {
+    /// Tables de l'import pour un lot de schéma donné
+    ///
+    /// En configuration `auto`, chaque type d'objet du schéma encore sans
+    /// table en reçoit une: tables finale et de staging, COPY. Ses hash ne
+    /// sont pas préchargés (dédoublonnage côté serveur).
+    ///
+    /// Doit être appelé hors du runtime async (création bloquante).
+    fn lot_tables(&self, schema: &edigeo::Schema) -> Result<Arc<ImportTables>> {
+        let mut current = self.tables.lock().unwrap();
+        let Some(auto) = &self.auto_tables else {
+            return Ok(Arc::clone(&current));
+        };
+        let new_types: Vec<&String> = schema
+            .objects
+            .keys()
+            .filter(|t| {
+                !current
+                    .feature_type_to_table
+                    .contains_key(&normalize_feature_type(t))
+            })
+            .collect();
+        if new_types.is_empty() {
+            return Ok(Arc::clone(&current));
         }
-        wkt_buf.clear();
-        {
-            let mut writer = WktWriter::new(&mut *wkt_buf);
-            geometry
-                .process_geom(&mut writer)
-                .context("Failed to encode geometry to WKT")?;
+
+        let (specs, feature_type_to_table) =
+            build_import_specs(&crate::config::Config::auto_tables(new_types))?;
+        let mut tables = ImportTables::clone(&current);
+        let mut created = Vec::new();
+        // Index dans `tables` de chaque table de `specs`
+        let indices: Vec<usize> = specs
+            .into_iter()
+            .map(|spec| {
+                tables
+                    .specs
+                    .iter()
+                    .chain(&created)
+                    .position(|t| t.name == spec.name)
+                    .unwrap_or_else(|| {
+                        created.push(spec);
+                        tables.specs.len() + created.len() - 1
+                    })
+            })
+            .collect();
+
+        let pg_tables = pg_table_configs(&created, auto.srid);
+        tokio::runtime::Handle::current().block_on(async {
+            if auto.drop_table {
+                crate::export::postgres::drop_tables(&auto.pool, &auto.schema, &pg_tables).await?;
+            }
+            crate::export::postgres::create_tables(&auto.pool, &auto.schema, &pg_tables).await?;
+            crate::export::postgres::create_staging_tables(&auto.pool, &auto.schema, &pg_tables)
+                .await
+        })?;
+
+        // Chaque COPY garde une connexion jusqu'à la fin de l'import
+        let status = auto.pool.status();
+        auto.pool.resize(status.max_size + created.len());
+        let mut copy_handles = self.copy_handles.lock().unwrap();
+        for spec in created {
+            info!(table = spec.name.as_str(), "Created auto table");
+            let (tx, handle) = spawn_copy(&auto.pool, &auto.schema, &spec);
+            tables.senders.push(tx);
+            copy_handles.push((spec.name.clone(), handle));
+            tables.specs.push(spec);
+        }
+        for (feature_type, idx) in feature_type_to_table {
+            tables
+                .feature_type_to_table
+                .insert(feature_type, indices[idx]);
         }
-        buf.extend_from_slice(b"\"");
-        buf.extend_from_slice(ewkt_prefix);
-        buf.extend_from_slice(&wkt_buf[..]);
-        buf.extend_from_slice(b"\"");
-        buf.extend_from_slice(b"|");
 
-        // valid_from (date ISO)
-        buf.extend_from_slice(valid_from.as_bytes());
-        buf.extend_from_slice(b"|");
+        *current = Arc::new(tables);
+        Ok(Arc::clone(&current))
+    }
+}
+
+/// Bilan de l'encodage d'un lot ou d'une archive
+#[derive(Default)]
+struct ArchiveStats {
+    departements: Vec<String>,
+    reproject_errors: usize,
+    skipped_types: usize,
+    invalid_geometries: usize,
+    skipped_existing: usize,
+    /// Lots en erreur (lot illisible, VEC illisible, COPY interrompu)
+    failed_lots: usize,
+    /// Numéros des lots en erreur, dont les lignes envoyées sont à retirer
+    /// de la staging
+    discarded_lots: Vec<i64>,
+    /// Entités écartées par le parser (aucun polygone valide, sommet non
+    /// reprojetable)
+    rejected_features: usize,
+    /// Erreurs non fatales des fichiers facultatifs (DIC, SCD, GEN)
+    file_errors: usize,
+    /// Retouches de géométrie faites par le parser
+    repairs: Vec<edigeo::RepairEntry>,
+    /// Schéma des lots importés
+    schema: edigeo::Schema,
+}
+
+impl ArchiveStats {
+    fn add(&mut self, other: ArchiveStats) {
+        for departement in other.departements {
+            if !self.departements.contains(&departement) {
+                self.departements.push(departement);
+            }
+        }
+        self.reproject_errors += other.reproject_errors;
+        self.skipped_types += other.skipped_types;
+        self.invalid_geometries += other.invalid_geometries;
+        self.skipped_existing += other.skipped_existing;
+        self.failed_lots += other.failed_lots;
+        self.discarded_lots.extend(other.discarded_lots);
+        self.rejected_features += other.rejected_features;
+        self.file_errors += other.file_errors;
+        self.repairs.extend(other.repairs);
+        crate::config::merge_schema(&mut self.schema, other.schema);
+    }
+}
+
+/// Parse une archive en streaming et envoie ses lignes aux COPY
+///
+/// Une archive départementale qui contient une archive par feuille est
+/// traitée lot par lot; un lot en erreur n'interrompt pas les suivants. Les
+/// lignes d'un lot en erreur ont pu être envoyées: son numéro est retenu dans
+/// `ArchiveStats::discarded_lots` pour les retirer de la staging. Les lignes
+/// portent le chemin de leur lot (`source_archive`): nom de l'archive parcourue,
+/// suivi du chemin du lot dans l'archive quand elle en regroupe plusieurs (ex:
+/// `dep38.tar.bz2/dep38/edigeo-380010000A01.tar.bz2`).
+///
+/// Doit être appelé hors du runtime async (envois bloquants).
+fn encode_archive(
+    settings: &EncodeSettings,
+    archive_path: &Path,
+    archive_name: &str,
+) -> ArchiveStats {
+    let mut stats = ArchiveStats::default();
+    let transform = SnapTransform::new(settings.srid);
+    let options = edigeo::ParseOptions {
+        snap: settings
+            .snap_rounding
+            .then_some(edigeo::repair::snap::SnapRounding {
+                decimals: settings.precision,
+                transform: Some(&transform),
+            }),
+        tolerance: settings.node_tolerance,
+    };
+
+    let read = edigeo::for_each_lot(archive_path, |lot| {
+        let lot_path = Path::new(lot.archive());
+        let source = if lot.archive() == archive_name {
+            lot.name().to_string()
+        } else {
+            format!("{}/{}", archive_name, lot.name())
+        };
+        let mut encoder = ArchiveEncoder::new(settings, lot_path, &source);
+        let lot_number = encoder.lot;
+        let encoded = edigeo::parse_lot_by_vec(&lot, &options, |info, relations, features| {
+            encoder.push_vec(info, relations, features)
+        })
+        .map_err(anyhow::Error::from)
+        .and_then(|summary| encoder.finish(&summary));
+
+        match encoded {
+            Ok(lot_stats) => stats.add(lot_stats),
+            Err(e) => {
+                warn!(
+                    "Failed to import {} ({}): {:#}",
+                    lot.name(),
+                    archive_name,
+                    e
+                );
+                stats.failed_lots += 1;
+                stats.discarded_lots.push(lot_number);
+            }
+        }
+        std::ops::ControlFlow::Continue(())
+    });
+    if let Err(e) = read {
+        // Archive illisible ou interrompue entre deux lots
+        warn!("Failed to import {}: {:#}", archive_path.display(), e);
+        stats.failed_lots += 1;
+    }
+
+    stats
+}
+
+/// Reprojection des arcs vers le SRID cible, pour l'arrondi topologique
+struct SnapTransform {
+    srid: u32,
+    /// Reprojecteur du dernier EPSG source rencontré
+    reprojector: std::cell::RefCell<Option<(u32, SmartReprojector)>>,
+}
+
+impl SnapTransform {
+    fn new(srid: u32) -> Self {
+        Self {
+            srid,
+            reprojector: std::cell::RefCell::new(None),
+        }
+    }
+}
+
+impl edigeo::repair::snap::CoordTransform for SnapTransform {
+    fn transform(&self, source: &edigeo::Projection, x: f64, y: f64) -> Option<(f64, f64)> {
+        let mut cached = self.reprojector.borrow_mut();
+        if cached.as_ref().map(|(epsg, _)| *epsg) != Some(source.epsg) {
+            // Projection non supportée: l'échec est signalé par `ArchiveEncoder::start`
+            *cached = Some((
+                source.epsg,
+                SmartReprojector::new(source.epsg, self.srid).ok()?,
+            ));
+        }
+        let (_, reprojector) = cached.as_ref()?;
+        reprojector.transform_point(x, y).ok()
+    }
+}
+
+/// Projection et département d'une archive, connus à la première feature
+struct ArchiveState {
+    departement: String,
+    epsg: u32,
+    reprojector: SmartReprojector,
+    /// Préfixe EWKT `SRID=...;`
+    ewkt_prefix: Vec<u8>,
+}
+
+/// Commune ou section d'un lot, candidate comme parent des autres features
+struct ParentArea {
+    feature_type: String,
+    /// IDU (valeur des colonnes IDU_COMMUNE / IDU_SECTION)
+    idu: String,
+    /// Géométrie telle que transmise par le parser (repli spatial)
+    geometry: geo::Geometry,
+}
+
+/// Emprise d'une commune ou d'une section (indice dans `ParentIndex::areas`)
+type ParentEnvelope =
+    rstar::primitives::GeomWithData<rstar::primitives::Rectangle<[f64; 2]>, usize>;
+
+/// Communes et sections d'un lot, indexées par emprise pour le repli spatial
+#[derive(Default)]
+struct ParentIndex {
+    /// Parents dans l'ordre de lecture
+    areas: Vec<ParentArea>,
+    envelopes: rstar::RTree<ParentEnvelope>,
+}
+
+impl ParentIndex {
+    /// Ajoute une commune ou une section, retourne son indice
+    fn insert(&mut self, feature: &edigeo::Feature) -> usize {
+        use geo::BoundingRect;
+
+        let idx = self.areas.len();
+        if let Some(rect) = feature.geometry.bounding_rect() {
+            let envelope = rstar::primitives::Rectangle::from_corners(
+                [rect.min().x, rect.min().y],
+                [rect.max().x, rect.max().y],
+            );
+            self.envelopes.insert(ParentEnvelope::new(envelope, idx));
+        }
+        self.areas.push(ParentArea {
+            feature_type: feature.feature_type.clone(),
+            idu: feature.properties.get("IDU").cloned().unwrap_or_default(),
+            geometry: feature.geometry.clone(),
+        });
+        idx
+    }
+
+    /// Premier parent lu du type demandé dont la géométrie contient le point
+    fn containing(&self, parent_type: &str, point: &geo::Point) -> Option<&ParentArea> {
+        use geo::Contains;
+
+        self.envelopes
+            .locate_all_at_point(&[point.x(), point.y()])
+            .map(|envelope| envelope.data)
+            .filter(|&idx| {
+                let area = &self.areas[idx];
+                area.feature_type == parent_type && area.geometry.contains(point)
+            })
+            .min()
+            .map(|idx| &self.areas[idx])
+    }
+}
+
+/// Encodage COPY des features d'une archive, au fil du parsing
+///
+/// Les features arrivent VEC par VEC: la commune et la section des tables qui
+/// utilisent des valeurs calculées (IDU_COMMUNE, IDU_SECTION) sont résolues
+/// par les relations sémantiques du VEC, sinon par les emprises des communes
+/// et sections déjà lues.
+struct ArchiveEncoder<'a> {
+    settings: &'a EncodeSettings,
+    /// Tables de l'import, connues au début du lot
+    tables: Arc<ImportTables>,
+    /// Chemin ou nom du lot (département `fromFile`)
+    lot_path: &'a Path,
+    /// Chemin du lot dans l'archive parcourue (`source_archive`)
+    archive_name: &'a str,
+    /// Numéro du lot (colonne `staging_lot`)
+    lot: i64,
+    state: Option<ArchiveState>,
+    /// Erreur fatale (reprojection impossible, COPY interrompu)
+    error: Option<anyhow::Error>,
+    buffers: Vec<BytesMut>,
+    buffer_rows: Vec<u64>,
+    wkt_buf: Vec<u8>,
+    /// Communes et sections des VEC déjà lus
+    parents: ParentIndex,
+    /// Tables dont une colonne dépend du contexte calculé
+    uses_computed: Vec<bool>,
+    /// Colonnes `auto_fields` par type d'objet: attributs SCD non mappés,
+    /// typés selon le schéma du lot
+    auto_columns: HashMap<String, Vec<ColumnSpec>>,
+    skipped_types: std::collections::HashSet<String>,
+    stats: ArchiveStats,
+}
+
+impl<'a> ArchiveEncoder<'a> {
+    fn new(settings: &'a EncodeSettings, lot_path: &'a Path, archive_name: &'a str) -> Self {
+        Self {
+            settings,
+            tables: Arc::default(),
+            lot_path,
+            archive_name,
+            lot: settings.next_lot.fetch_add(1, Ordering::Relaxed),
+            state: None,
+            error: None,
+            buffers: Vec::new(),
+            buffer_rows: Vec::new(),
+            wkt_buf: Vec::with_capacity(1024),
+            parents: ParentIndex::default(),
+            uses_computed: Vec::new(),
+            auto_columns: HashMap::new(),
+            skipped_types: std::collections::HashSet::new(),
+            stats: ArchiveStats::default(),
+        }
+    }
+
+    /// Tables du lot, département et reprojection de l'archive
+    fn start(&mut self, info: &edigeo::ArchiveInfo) -> Result<()> {
+        self.tables = self.settings.lot_tables(&info.schema)?;
+        let table_count = self.tables.specs.len();
+        self.buffers = (0..table_count).map(|_| BytesMut::new()).collect();
+        self.buffer_rows = vec![0; table_count];
+        self.uses_computed = self
+            .tables
+            .specs
+            .iter()
+            .map(|t| {
+                t.columns
+                    .iter()
+                    .any(|c| matches!(c.source.as_str(), "IDU_COMMUNE" | "IDU_SECTION"))
+            })
+            .collect();
+
+        // Attributs non mappés des tables `auto_fields`, convertis selon le
+        // schéma du lot: leurs colonnes ne sont créées qu'après l'import
+        self.auto_columns = info
+            .schema
+            .objects
+            .keys()
+            .filter_map(|feature_type| {
+                let key = normalize_feature_type(feature_type);
+                let table = &self.tables.specs[*self.tables.feature_type_to_table.get(&key)?];
+                if !table.auto_fields {
+                    return None;
+                }
+                let mapped = |source: &str, target: &str| {
+                    table
+                        .columns
+                        .iter()
+                        .any(|c| c.source == source || c.name == target)
+                };
+                let columns = crate::config::schema_fields(&info.schema, feature_type, mapped)
+                    .iter()
+                    .filter(|f| !is_reserved_column(&f.target))
+                    .map(ColumnSpec::from_field)
+                    .collect();
+                Some((feature_type.clone(), columns))
+            })
+            .collect();
+
+        let departement = match &self.settings.dep_override {
+            Some(value) if value.eq_ignore_ascii_case("fromfile") => {
+                derive_dep_from_archive(self.lot_path).unwrap_or_else(|| info.departement.clone())
+            }
+            Some(value) => value.clone(),
+            None => info.departement.clone(),
+        };
+        let epsg = info.projection.epsg;
+        let srid = self.settings.srid;
+        let reprojector = SmartReprojector::new(epsg, srid)
+            .with_context(|| format!("Failed to build reprojector ({} → {})", epsg, srid))?;
+
+        self.state = Some(ArchiveState {
+            departement,
+            epsg,
+            reprojector,
+            ewkt_prefix: format!("SRID={};", srid).into_bytes(),
+        });
+        Ok(())
+    }
 
-        // geometry_hash (optionnel selon config)
-        if table.hash_geom {
-            let hash = crate::versioning::diff::geometry_hash(geometry);
-            let hash_hex = hex::encode(hash);
-            buf.extend_from_slice(b"\\x");
-            buf.extend_from_slice(hash_hex.as_bytes());
+    /// Traite les features d'un VEC transmises par le parser
+    ///
+    /// Les communes et sections du VEC sont indexées avant l'encodage: une
+    /// feature peut précéder son parent dans le VEC.
+    fn push_vec(
+        &mut self,
+        info: &edigeo::ArchiveInfo,
+        relations: &edigeo::RelationGraph,
+        features: Vec<edigeo::Feature>,
+    ) -> std::ops::ControlFlow<()> {
+        use std::ops::ControlFlow;
+
+        if self.state.is_none() {
+            if let Err(e) = self.start(info) {
+                self.error = Some(e);
+                return ControlFlow::Break(());
+            }
         }
 
-        // Colonnes dynamiques
-        for col in &table.columns {
-            buf.extend_from_slice(b"|");
-
-            // Valeurs calculées (comme Node.js "const")
-            let raw_value: &str = match col.source.as_str() {
-                "IDU_COMMUNE" => &computed.commune_id,
-                "IDU_SECTION" => &computed.section_id,
-                _ => feature
-                    .properties
-                    .get(&col.source)
-                    .map(String::as_str)
-                    .unwrap_or(""),
+        // Parents possibles des valeurs calculées (IDU_COMMUNE, IDU_SECTION),
+        // par identifiant de feature dans ce VEC
+        let vec_parents: HashMap<&str, usize> = features
+            .iter()
+            .filter(|f| matches!(f.feature_type.as_str(), "COMMUNE_id" | "SECTION_id"))
+            .map(|f| (f.id.as_str(), self.parents.insert(f)))
+            .collect();
+
+        for feature in &features {
+            let key = normalize_feature_type(&feature.feature_type);
+            let Some(&table_idx) = self.tables.feature_type_to_table.get(&key) else {
+                if !self.skipped_types.contains(&feature.feature_type) {
+                    self.skipped_types.insert(feature.feature_type.clone());
+                    self.stats.skipped_types += 1;
+                }
+                continue;
             };
 
-            // Appliquer le préfixe département si demandé (comme addDep de Node.js)
-            let final_value: std::borrow::Cow<str> = if col.prefix_dep && !raw_value.is_empty() {
-                std::borrow::Cow::Owned(format!("{}{}", departement, raw_value))
+            // Commune ou section introuvable: valeur calculée vide
+            let computed = if self.uses_computed[table_idx] {
+                let parent = |parent_type| {
+                    parent_idu(&self.parents, &vec_parents, relations, feature, parent_type)
+                };
+                ComputedContext {
+                    commune_id: parent("COMMUNE_id"),
+                    section_id: parent("SECTION_id"),
+                }
             } else {
-                std::borrow::Cow::Borrowed(raw_value)
+                ComputedContext::default()
             };
-            let raw = final_value.as_ref();
+            self.encode(table_idx, feature, &computed)?;
+        }
+        ControlFlow::Continue(())
+    }
 
-            match col.data_type.to_ascii_lowercase().as_str() {
-                "integer" | "int" | "smallint" | "bigint" => {
-                    // EDIGEO peut avoir des formats comme "+1895." → on nettoie
-                    if let Some(n) = parse_edigeo_number(raw) {
-                        buf.extend_from_slice(n.trunc().to_string().as_bytes());
-                    }
-                }
-                "float" | "double" | "double precision" => {
-                    if let Some(n) = parse_edigeo_number(raw) {
-                        buf.extend_from_slice(n.to_string().as_bytes());
-                    }
+    /// Écrit la ligne COPY d'une feature et envoie le chunk s'il est plein
+    fn encode(
+        &mut self,
+        table_idx: usize,
+        feature: &edigeo::Feature,
+        computed: &ComputedContext,
+    ) -> std::ops::ControlFlow<()> {
+        use std::ops::ControlFlow;
+
+        let Some(state) = &self.state else {
+            return ControlFlow::Continue(());
+        };
+        let table = &self.tables.specs[table_idx];
+        let auto_columns = self
+            .auto_columns
+            .get(&feature.feature_type)
+            .map_or(&[][..], Vec::as_slice);
+        let buf = &mut self.buffers[table_idx];
+
+        // Allocations amorties par table / archive
+        if buf.capacity() < 64 * 1024 {
+            buf.reserve(64 * 1024);
+        }
+
+        // Arrondi topologique: géométrie déjà reprojetée et arrondie par le parser
+        let reprojected = if self.settings.snap_rounding {
+            Ok(feature.geometry.clone())
+        } else {
+            state.reprojector.transform_geometry(&feature.geometry)
+        };
+        let geometry = match reprojected {
+            Ok(g) if self.settings.snap_rounding => g,
+            Ok(g) => round_geometry_coords(&g, self.settings.precision),
+            Err(e) => {
+                warn!(
+                    "Failed to reproject {} ({} → {}): {}",
+                    feature.id, state.epsg, self.settings.srid, e
+                );
+                self.stats.reproject_errors += 1;
+                return ControlFlow::Continue(());
+            }
+        };
+
+        let row_context = RowContext {
+            departement: &state.departement,
+            valid_from: &self.settings.valid_from,
+            source_archive: self.archive_name,
+            ewkt_prefix: &state.ewkt_prefix,
+            lot: self.lot,
+        };
+        let row = RowValues::new(
+            feature,
+            &geometry,
+            &row_context,
+            table,
+            auto_columns,
+            computed,
+        );
+
+        // Version active identique: on marque seulement l'entité comme vue
+        // (import incrémental, évite de réécrire la géométrie)
+        let unchanged = self
+            .settings
+            .existing_hashes
+            .get(&table_idx)
+            .and_then(|hashes| hashes.get(&row.id))
+            .is_some_and(|active| row.matches(active, table.change_detection));
+
+        let written = if unchanged {
+            write_seen_row(buf, &row, &row_context, table);
+            self.stats.skipped_existing += 1;
+            Ok(())
+        } else {
+            write_copy_row(buf, &row, &geometry, &row_context, &mut self.wkt_buf, table)
+        };
+
+        if let Err(e) = written {
+            let msg = e.to_string();
+            if msg.contains("Geometry not valid for PostGIS ingestion") {
+                self.stats.invalid_geometries += 1;
+            } else {
+                warn!(
+                    "Failed to encode row ({} / {}): {}",
+                    feature.feature_type, feature.id, msg
+                );
+            }
+            return ControlFlow::Continue(());
+        }
+        self.buffer_rows[table_idx] += 1;
+
+        // Flush quand on atteint BATCH_SIZE rows (5000)
+        if self.buffer_rows[table_idx] >= BATCH_SIZE {
+            if let Err(e) = self.send(table_idx) {
+                self.error = Some(e);
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Envoie le buffer d'une table à son COPY
+    fn send(&mut self, table_idx: usize) -> Result<()> {
+        let rows = std::mem::take(&mut self.buffer_rows[table_idx]);
+        let data = self.buffers[table_idx].split().freeze();
+        if rows == 0 || data.is_empty() {
+            return Ok(());
+        }
+        self.tables.senders[table_idx]
+            .blocking_send(crate::export::postgres::CopyChunk { data, rows })
+            .map_err(|e| anyhow::anyhow!("Failed to send COPY chunk: {}", e))
+    }
+
+    /// Envoie les derniers chunks
+    ///
+    /// Échoue si le lot est incomplet (erreur fatale, VEC illisible): ses
+    /// lignes déjà envoyées sont alors à retirer de la staging.
+    fn finish(mut self, summary: &edigeo::StreamSummary) -> Result<ArchiveStats> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        // Archive sans feature: le département est tout de même relu
+        if self.state.is_none() {
+            self.start(&summary.info)?;
+        }
+
+        // Entités écartées par le parser (déjà journalisées) et erreurs des
+        // fichiers facultatifs
+        for e in &summary.errors {
+            match e {
+                edigeo::EdigeoError::RepairFailed { .. } => self.stats.rejected_features += 1,
+                e => {
+                    warn!("Error in {}: {}", self.lot_path.display(), e);
+                    self.stats.file_errors += 1;
                 }
-                _ => push_csv_text_field(buf, raw),
             }
         }
+        // VEC illisible: ses entités manquent, le lot n'est pas importé
+        if summary.failed_vecs > 0 {
+            anyhow::bail!("{} VEC file(s) could not be parsed", summary.failed_vecs);
+        }
 
-        buf.extend_from_slice(b"\n");
-        Ok(())
-    })();
+        if !summary.repairs.is_empty() {
+            debug!(
+                lot = %self.lot_path.display(),
+                repairs = summary.repairs.entries.len(),
+                "Geometries repaired"
+            );
+            self.stats
+                .repairs
+                .extend(summary.repairs.entries.iter().cloned());
+        }
+
+        for table_idx in 0..self.buffers.len() {
+            self.send(table_idx)?;
+        }
+
+        let state = self.state.take().expect("archive state initialized");
+        self.stats.departements.push(state.departement);
+        self.stats.schema = summary.info.schema.clone();
+        Ok(self.stats)
+    }
+}
+
+/// Valeurs calculées d'une feature (colonnes IDU_COMMUNE / IDU_SECTION)
+#[derive(Default)]
+struct ComputedContext {
+    /// IDU de la commune de la feature
+    commune_id: String,
+    /// IDU de la section de la feature
+    section_id: String,
+}
+
+/// IDU du parent d'une feature (commune ou section)
+///
+/// Le parent est la feature du type demandé la plus proche par les relations
+/// sémantiques du VEC de la feature (`vec_parents`: parents de ce VEC, par
+/// identifiant); sans lien, le premier parent lu dont la géométrie contient
+/// un point intérieur de la feature.
+fn parent_idu(
+    parents: &ParentIndex,
+    vec_parents: &HashMap<&str, usize>,
+    relations: &edigeo::RelationGraph,
+    feature: &edigeo::Feature,
+    parent_type: &str,
+) -> String {
+    use geo::InteriorPoint;
+
+    let linked = relations
+        .nearest(&feature.id, parent_type)
+        .and_then(|id| vec_parents.get(id))
+        .map(|&idx| &parents.areas[idx])
+        .filter(|p| p.feature_type == parent_type);
+    let parent = linked.or_else(|| {
+        let point = feature.geometry.interior_point()?;
+        parents.containing(parent_type, &point)
+    });
+    parent.map(|p| p.idu.clone()).unwrap_or_default()
+}
+
+fn push_csv_text_field(buf: &mut BytesMut, value: &str) {
+    buf.extend_from_slice(b"\"");
+    for b in value.as_bytes() {
+        match *b {
+            b'"' => buf.extend_from_slice(b"\"\""),
+            b'\n' | b'\r' => buf.extend_from_slice(b" "),
+            _ => buf.extend_from_slice(&[*b]),
+        }
+    }
+    buf.extend_from_slice(b"\"");
+}
+
+/// Valeurs communes à toutes les lignes COPY d'une archive
+struct RowContext<'a> {
+    departement: &'a str,
+    /// Date du millésime (YYYY-MM-DD)
+    valid_from: &'a str,
+    /// Nom du fichier d'archive (provenance)
+    source_archive: &'a str,
+    /// Préfixe EWKT `SRID=...;`
+    ewkt_prefix: &'a [u8],
+    /// Numéro du lot (colonne `staging_lot`)
+    lot: i64,
+}
+
+/// Valeurs d'une entité calculées avant écriture COPY
+struct RowValues<'a> {
+    /// Identifiant préfixé du département
+    id: String,
+    /// Valeurs des colonnes dynamiques, dans l'ordre de `TableSpec::columns`
+    /// (chaîne vide = NULL pour les colonnes numériques)
+    columns: Vec<Cow<'a, str>>,
+    /// Valeurs non vides des colonnes `auto_fields` (objet JSON)
+    auto_attributes: Option<String>,
+    geometry_hash: Option<[u8; 32]>,
+    attributes_hash: [u8; 32],
+}
+
+impl<'a> RowValues<'a> {
+    fn new(
+        feature: &'a edigeo::Feature,
+        geometry: &geo::Geometry,
+        ctx: &RowContext,
+        table: &TableSpec,
+        auto_columns: &[ColumnSpec],
+        computed: &'a ComputedContext,
+    ) -> Self {
+        let columns: Vec<Cow<'a, str>> = table
+            .columns
+            .iter()
+            .map(|col| column_value(col, feature, ctx.departement, computed))
+            .collect();
+        let auto_values: Vec<(&str, Cow<str>)> = auto_columns
+            .iter()
+            .map(|col| {
+                let value = column_value(col, feature, ctx.departement, computed);
+                (col.name.as_str(), value)
+            })
+            .filter(|(_, value)| !value.is_empty())
+            .collect();
+
+        let attributes_hash = crate::versioning::diff::attributes_hash(
+            table
+                .columns
+                .iter()
+                .zip(&columns)
+                .map(|(col, value)| (col.name.as_str(), value.as_ref()))
+                .chain(
+                    auto_values
+                        .iter()
+                        .map(|(name, value)| (*name, value.as_ref())),
+                ),
+        );
+        let auto_attributes = (!auto_values.is_empty()).then(|| {
+            let object: serde_json::Map<String, serde_json::Value> = auto_values
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.as_ref().into()))
+                .collect();
+            serde_json::Value::Object(object).to_string()
+        });
+
+        Self {
+            // id (préfixé avec le département pour unicité France entière)
+            id: format!("{}{}", ctx.departement, feature.id),
+            columns,
+            auto_attributes,
+            geometry_hash: table
+                .hash_geom
+                .then(|| crate::versioning::diff::geometry_hash(geometry)),
+            attributes_hash,
+        }
+    }
+
+    /// La version active a les mêmes hash pour les critères de la table
+    fn matches(&self, existing: &ExistingHashes, change_detection: ChangeDetection) -> bool {
+        (!change_detection.geometry()
+            || (self.geometry_hash.is_some() && existing.geometry == self.geometry_hash))
+            && (!change_detection.attributes() || existing.attributes == Some(self.attributes_hash))
+    }
+}
 
-    if res.is_err() {
-        buf.truncate(start_len);
+/// Valeur formatée d'une colonne dynamique
+fn column_value<'a>(
+    col: &ColumnSpec,
+    feature: &'a edigeo::Feature,
+    departement: &str,
+    computed: &'a ComputedContext,
+) -> Cow<'a, str> {
+    // Valeurs calculées (comme Node.js "const")
+    let raw_value: &str = match col.source.as_str() {
+        "IDU_COMMUNE" => &computed.commune_id,
+        "IDU_SECTION" => &computed.section_id,
+        _ => feature
+            .properties
+            .get(&col.source)
+            .map(String::as_str)
+            .unwrap_or(""),
+    };
+
+    // Appliquer le préfixe département si demandé (comme addDep de Node.js)
+    let final_value: Cow<'a, str> = if col.prefix_dep && !raw_value.is_empty() {
+        Cow::Owned(format!("{}{}", departement, raw_value))
+    } else {
+        Cow::Borrowed(raw_value)
+    };
+
+    match col.data_type.to_ascii_lowercase().as_str() {
+        // EDIGEO peut avoir des formats comme "+1895." → on nettoie
+        "integer" | "int" | "smallint" | "bigint" => parse_edigeo_number(&final_value)
+            .map(|n| Cow::Owned(n.trunc().to_string()))
+            .unwrap_or(Cow::Borrowed("")),
+        "float" | "double" | "double precision" => parse_edigeo_number(&final_value)
+            .map(|n| Cow::Owned(n.to_string()))
+            .unwrap_or(Cow::Borrowed("")),
+        // AAAAMMJJ (attributs) ou ISO (dates de qualité)
+        "date" => edigeo::Date::parse(&final_value)
+            .map(|date| Cow::Owned(date.to_string()))
+            .unwrap_or(Cow::Borrowed("")),
+        // Valeurs de l'énumération, NULL pour un code non prévu par la norme
+        "update_type" => match final_value.as_ref() {
+            "creation" | "modification" | "deletion" => final_value,
+            _ => Cow::Borrowed(""),
+        },
+        _ => final_value,
+    }
+}
+
+/// Écrit les champs de hash, de provenance, les colonnes dynamiques, les
+/// attributs JSON et le numéro de lot (partie commune des lignes complètes et
+/// des marqueurs)
+fn write_row_tail(buf: &mut BytesMut, row: &RowValues, ctx: &RowContext, table: &TableSpec) {
+    // geometry_hash (optionnel selon config)
+    if let Some(hash) = &row.geometry_hash {
+        buf.extend_from_slice(b"\\x");
+        buf.extend_from_slice(hex::encode(hash).as_bytes());
+    }
+    buf.extend_from_slice(b"|");
+
+    // attributes_hash
+    buf.extend_from_slice(b"\\x");
+    buf.extend_from_slice(hex::encode(row.attributes_hash).as_bytes());
+    buf.extend_from_slice(b"|");
+
+    // source_archive
+    push_csv_text_field(buf, ctx.source_archive);
+
+    // Colonnes dynamiques
+    for (col, value) in table.columns.iter().zip(&row.columns) {
+        buf.extend_from_slice(b"|");
+        match col.data_type.to_ascii_lowercase().as_str() {
+            "integer" | "int" | "smallint" | "bigint" | "float" | "double" | "double precision"
+            | "date" | "update_type" => buf.extend_from_slice(value.as_bytes()),
+            _ => push_csv_text_field(buf, value),
+        }
+    }
+
+    // auto_attributes (JSON, NULL sans valeur)
+    buf.extend_from_slice(b"|");
+    if let Some(json) = &row.auto_attributes {
+        push_csv_text_field(buf, json);
+    }
+
+    // staging_lot
+    buf.extend_from_slice(b"|");
+    buf.extend_from_slice(ctx.lot.to_string().as_bytes());
+    buf.extend_from_slice(b"\n");
+}
+
+/// Écrit une ligne marqueur « entité vue, inchangée »: sans géométrie,
+/// elle évite la fin de vie de la version active lors du merge.
+fn write_seen_row(buf: &mut BytesMut, row: &RowValues, ctx: &RowContext, table: &TableSpec) {
+    push_csv_text_field(buf, &row.id);
+    buf.extend_from_slice(b"|");
+    push_csv_text_field(buf, ctx.departement);
+    // geometry NULL
+    buf.extend_from_slice(b"||");
+    buf.extend_from_slice(ctx.valid_from.as_bytes());
+    buf.extend_from_slice(b"|");
+    write_row_tail(buf, row, ctx, table);
+}
+
+fn write_copy_row(
+    buf: &mut BytesMut,
+    row: &RowValues,
+    geometry: &geo::Geometry,
+    ctx: &RowContext,
+    wkt_buf: &mut Vec<u8>,
+    table: &TableSpec,
+) -> Result<()> {
+    // geometry (EWKT: SRID=...;WKT)
+    if !geometry_ok_for_postgis(geometry) {
+        anyhow::bail!("Geometry not valid for PostGIS ingestion (too few points)");
     }
+    wkt_buf.clear();
+    {
+        let mut writer = WktWriter::new(&mut *wkt_buf);
+        geometry
+            .process_geom(&mut writer)
+            .context("Failed to encode geometry to WKT")?;
+    }
+
+    push_csv_text_field(buf, &row.id);
+    buf.extend_from_slice(b"|");
+
+    // departement
+    push_csv_text_field(buf, ctx.departement);
+    buf.extend_from_slice(b"|");
+
+    buf.extend_from_slice(b"\"");
+    buf.extend_from_slice(ctx.ewkt_prefix);
+    buf.extend_from_slice(&wkt_buf[..]);
+    buf.extend_from_slice(b"\"");
+    buf.extend_from_slice(b"|");
 
-    res
+    // valid_from (date ISO)
+    buf.extend_from_slice(ctx.valid_from.as_bytes());
+    buf.extend_from_slice(b"|");
+
+    write_row_tail(buf, row, ctx, table);
+    Ok(())
 }
 
 fn geometry_ok_for_postgis(geom: &geo::Geometry) -> bool {
@@ -1080,7 +2296,8 @@ pub async fn cmd_export(path: &Path, output: &Path, target_srid: Option<u32>) ->
 
     std::fs::create_dir_all(output)?;
 
-    if path.is_dir() {
+    // Répertoire ou archive départementale (une archive par feuille)
+    if path.is_dir() || edigeo::input::has_nested_lots(path)? {
         export_directory(path, output, target_srid)?;
     } else {
         export_single_archive(path, output, target_srid)?;
@@ -1089,6 +2306,209 @@ pub async fn cmd_export(path: &Path, output: &Path, target_srid: Option<u32>) ->
     Ok(())
 }
 
+/// Exécute la commande snapshot
+pub async fn cmd_snapshot(
+    date: Option<&str>,
+    schema: &str,
+    config_spec: &str,
+    materialized: bool,
+    connection: ConnectionArgs,
+) -> Result<()> {
+    if let Some(date) = date {
+        validate_date_format(date)?;
+    }
+
+    let config = load_import_config(config_spec)?;
+    let (table_specs, _) = build_import_specs(&config)?;
+    // Le SRID n'intervient pas dans la définition des vues
+    let pg_tables = pg_table_configs(&table_specs, 0);
+
+    let pool = crate::export::pool::create_pool(&connection.database_config()).await?;
+    crate::export::pool::test_connection(&pool).await?;
+
+    let mut instants = vec![crate::versioning::snapshot::SnapshotAt::Current];
+    if let Some(date) = date {
+        instants.push(crate::versioning::snapshot::SnapshotAt::Millesime(date));
+    }
+
+    for at in instants {
+        let views = crate::versioning::snapshot::create_snapshot_views(
+            &pool,
+            schema,
+            &pg_tables,
+            at,
+            materialized,
+        )
+        .await?;
+        for view in views {
+            println!("{}.{}", schema, view);
+        }
+    }
+
+    Ok(())
+}
+
+/// Exécute la commande diff
+pub async fn cmd_diff(args: DiffArgs, connection: ConnectionArgs) -> Result<()> {
+    use crate::versioning::changes::{
+        query_changes, write_changes_csv, write_changes_geojson, write_changes_table, ChangeQuery,
+        ChangeSummary,
+    };
+    use std::io::Write;
+
+    validate_date_format(&args.from)?;
+    validate_date_format(&args.to)?;
+    // Format YYYY-MM: l'ordre lexicographique est l'ordre chronologique
+    if args.from >= args.to {
+        anyhow::bail!(
+            "--from ({}) must be earlier than --to ({})",
+            args.from,
+            args.to
+        );
+    }
+
+    let config = load_import_config(&args.config)?;
+    let (table_specs, _) = build_import_specs(&config)?;
+    let mut pg_tables = pg_table_configs(&table_specs, 0);
+    if let Some(table) = &args.table {
+        pg_tables.retain(|t| &t.name == table);
+        if pg_tables.is_empty() {
+            anyhow::bail!("Table '{}' not found in config '{}'", table, args.config);
+        }
+    }
+
+    let pool = crate::export::pool::create_pool(&connection.database_config()).await?;
+    crate::export::pool::test_connection(&pool).await?;
+
+    let query = ChangeQuery {
+        from: &args.from,
+        to: &args.to,
+        departement: args.dep.as_deref(),
+        with_geometry: args.format == DiffFormat::Geojson,
+    };
+
+    let mut changes = Vec::new();
+    for table in &pg_tables {
+        let table_changes = query_changes(&pool, &args.schema, table, &query).await?;
+        info!(
+            table = table.name.as_str(),
+            changes = table_changes.len(),
+            "Changes computed"
+        );
+        changes.extend(table_changes);
+    }
+
+    let mut writer: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path)
+                .with_context(|| format!("Failed to create file: {}", path.display()))?,
+        )),
+        None => Box::new(std::io::BufWriter::new(std::io::stdout().lock())),
+    };
+
+    match args.format {
+        DiffFormat::Table => {
+            write_changes_table(&mut writer, &changes)?;
+            let summary = ChangeSummary::from_changes(&changes);
+            writeln!(writer)?;
+            writeln!(writer, "Created:             {}", summary.created)?;
+            writeln!(writer, "Deleted:             {}", summary.deleted)?;
+            writeln!(writer, "Geometry modified:   {}", summary.geometry_modified)?;
+            writeln!(
+                writer,
+                "Attributes modified: {}",
+                summary.attributes_modified
+            )?;
+            writeln!(writer, "Total:               {}", summary.total())?;
+        }
+        DiffFormat::Csv => write_changes_csv(&mut writer, &changes)?,
+        DiffFormat::Geojson => write_changes_geojson(&mut writer, &changes)?,
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Exécute la commande lineage
+pub async fn cmd_lineage(args: LineageArgs, connection: ConnectionArgs) -> Result<()> {
+    use crate::versioning::lineage::{
+        classify_overlaps, lineage_from_parse_results, query_overlaps, write_lineage, LineageKind,
+        LineageLink, LINEAGE_TABLE,
+    };
+    use std::io::Write;
+
+    let links: Vec<LineageLink> = match (&args.old_path, &args.new_path) {
+        (Some(old_path), Some(new_path)) => {
+            let old = edigeo::parse(old_path)
+                .with_context(|| format!("Failed to parse {}", old_path.display()))?;
+            let new = edigeo::parse(new_path)
+                .with_context(|| format!("Failed to parse {}", new_path.display()))?;
+            let links = lineage_from_parse_results(&old, &new);
+
+            let mut writer: Box<dyn Write> = match &args.output {
+                Some(path) => Box::new(std::io::BufWriter::new(
+                    std::fs::File::create(path)
+                        .with_context(|| format!("Failed to create file: {}", path.display()))?,
+                )),
+                None => Box::new(std::io::BufWriter::new(std::io::stdout().lock())),
+            };
+            writeln!(writer, "departement,old_id,new_id,kind,overlap_ratio")?;
+            for link in &links {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{:.4}",
+                    link.departement,
+                    link.old_id,
+                    link.new_id,
+                    link.kind.as_str(),
+                    link.overlap_ratio
+                )?;
+            }
+            writer.flush()?;
+            links
+        }
+        _ => {
+            let (Some(from), Some(to)) = (&args.from, &args.to) else {
+                anyhow::bail!("Either --from/--to or --old-path/--new-path is required");
+            };
+            validate_date_format(from)?;
+            validate_date_format(to)?;
+            if from >= to {
+                anyhow::bail!("--from ({}) must be earlier than --to ({})", from, to);
+            }
+
+            let pool = crate::export::pool::create_pool(&connection.database_config()).await?;
+            crate::export::pool::test_connection(&pool).await?;
+
+            let overlaps = query_overlaps(
+                &pool,
+                &args.schema,
+                &args.table,
+                from,
+                to,
+                args.dep.as_deref(),
+            )
+            .await?;
+            let links = classify_overlaps(&overlaps);
+            write_lineage(&pool, &args.schema, from, to, args.dep.as_deref(), &links).await?;
+            println!("{}.{}", args.schema, LINEAGE_TABLE);
+            links
+        }
+    };
+
+    for kind in [
+        LineageKind::Split,
+        LineageKind::Merge,
+        LineageKind::Renumber,
+        LineageKind::BoundaryAdjust,
+    ] {
+        let count = links.iter().filter(|l| l.kind == kind).count();
+        info!(kind = kind.as_str(), links = count, "Lineage");
+    }
+
+    Ok(())
+}
+
 /// Valide le format de date YYYY-MM
 fn validate_date_format(date: &str) -> Result<()> {
     if date.len() != 7 || date.chars().nth(4) != Some('-') {
@@ -1150,6 +2570,7 @@ fn export_single_archive(path: &Path, output: &Path, target_srid: Option<u32>) -
                         geometry: new_geom,
                         properties: f.properties.clone(),
                         feature_type: f.feature_type.clone(),
+                        quality: f.quality.clone(),
                     })
                 })
                 .collect();
@@ -1242,17 +2663,44 @@ fn export_directory(path: &Path, output: &Path, target_srid: Option<u32>) -> Res
     Ok(())
 }
 
-/// Traite une archive pour l'export
+/// Traite une archive pour l'export (un sous-dossier par lot)
 fn process_archive_for_export(
     archive_path: &Path,
     output: &Path,
     target_srid: Option<u32>,
 ) -> Result<usize> {
-    let parse_result = edigeo::parse(archive_path)
-        .with_context(|| format!("Failed to parse {}", archive_path.display()))?;
+    let mut total_features = 0;
+    let mut failure = None;
 
-    let archive_name = get_archive_basename(archive_path);
-    let archive_output = output.join(&archive_name);
+    edigeo::for_each_lot(archive_path, |lot| {
+        match export_lot(&lot, output, target_srid) {
+            Ok(count) => total_features += count,
+            Err(e) => {
+                failure = Some(e);
+                return std::ops::ControlFlow::Break(());
+            }
+        }
+        std::ops::ControlFlow::Continue(())
+    })
+    .with_context(|| format!("Failed to read {}", archive_path.display()))?;
+
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(total_features),
+    }
+}
+
+/// Exporte un lot EDIGEO dans `output/<nom de l'archive>`, suivi du nom de son
+/// THF quand l'archive regroupe plusieurs lots
+fn export_lot(lot: &edigeo::Lot, output: &Path, target_srid: Option<u32>) -> Result<usize> {
+    let parse_result =
+        edigeo::parse_lot(lot).with_context(|| format!("Failed to parse {}", lot.name()))?;
+
+    let archive_name = get_archive_basename(Path::new(lot.archive()));
+    let mut archive_output = output.join(&archive_name);
+    if let Some(stem) = lot.thf().and_then(|thf| Path::new(thf).file_stem()) {
+        archive_output.push(stem);
+    }
     std::fs::create_dir_all(&archive_output)?;
 
     // Créer le reprojector si nécessaire
@@ -1281,6 +2729,7 @@ fn process_archive_for_export(
                         geometry: new_geom,
                         properties: f.properties.clone(),
                         feature_type: f.feature_type.clone(),
+                        quality: f.quality.clone(),
                     })
                 })
                 .collect();
@@ -1311,7 +2760,7 @@ fn process_archive_for_export(
     Ok(total_features)
 }
 
-/// Extrait le nom de base d'une archive (sans .tar.bz2, .tar, .bz2)
+/// Extrait le nom de base d'une archive (sans .tar.bz2, .tar.gz, .zip, etc.)
 fn get_archive_basename(path: &Path) -> String {
     let name = path
         .file_name()
@@ -1319,33 +2768,49 @@ fn get_archive_basename(path: &Path) -> String {
         .unwrap_or("unknown");
 
     // Supprimer les extensions connues
-    let name = name
-        .strip_suffix(".tar.bz2")
-        .or_else(|| name.strip_suffix(".bz2"))
-        .or_else(|| name.strip_suffix(".tar"))
-        .unwrap_or(name);
+    let name = [
+        ".tar.bz2", ".tar.gz", ".tbz2", ".tgz", ".bz2", ".gz", ".tar", ".zip",
+    ]
+    .iter()
+    .find_map(|ext| name.strip_suffix(ext))
+    .unwrap_or(name);
 
     name.to_string()
 }
 
 /// Collecte récursivement les archives EDIGEO
+///
+/// Archives `.tar.bz2`, `.tar.gz`, `.tar` ou `.zip`, et répertoires qui
+/// contiennent directement un lot EDIGEO (fichiers décompressés).
 fn collect_archives(path: &Path) -> Result<Vec<PathBuf>> {
+    use edigeo::input::Container;
+
     let mut archives = Vec::new();
 
     if path.is_file() {
-        if path.extension().map_or(false, |ext| ext == "bz2") {
+        if Container::detect(path).is_some() {
             archives.push(path.to_path_buf());
         }
         return Ok(archives);
     }
 
+    if is_edigeo_directory(path)? {
+        archives.push(path.to_path_buf());
+        return Ok(archives);
+    }
+
     for entry in std::fs::read_dir(path)? {
         let entry = entry?;
         let entry_path = entry.path();
 
         if entry_path.is_dir() {
             archives.extend(collect_archives(&entry_path)?);
-        } else if entry_path.extension().map_or(false, |ext| ext == "bz2") {
+        } else if entry_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(Container::from_name)
+            .is_some()
+        {
             archives.push(entry_path);
         }
     }
@@ -1353,21 +2818,60 @@ fn collect_archives(path: &Path) -> Result<Vec<PathBuf>> {
     Ok(archives)
 }
 
+/// Le répertoire contient un fichier THF (lot EDIGEO décompressé)
+fn is_edigeo_directory(path: &Path) -> Result<bool> {
+    for entry in std::fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        let is_thf = entry_path.is_file()
+            && entry_path
+                .to_str()
+                .and_then(edigeo::archive::EntryKind::from_path)
+                == Some(edigeo::archive::EntryKind::Thf);
+        if is_thf {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
 /// Calcule le checksum blake3 d'un fichier
+///
+/// Pour un répertoire: noms et contenus de ses fichiers, par ordre de nom.
 fn compute_file_checksum(path: &Path) -> Result<String> {
     use std::fs::File;
     use std::io::Read;
 
-    let mut file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
     let mut hasher = blake3::Hasher::new();
+
+    let files = if path.is_dir() {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+            .with_context(|| format!("Cannot read {}", path.display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.is_file())
+            .collect();
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+
     let mut buffer = [0u8; 65536]; // 64KB buffer
+    for file_path in &files {
+        if path.is_dir() {
+            if let Some(name) = file_path.file_name() {
+                hasher.update(name.to_string_lossy().as_bytes());
+            }
+        }
 
-    loop {
-        let bytes_read = file.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
+        let mut file = File::open(file_path)
+            .with_context(|| format!("Cannot open {}", file_path.display()))?;
+        loop {
+            let bytes_read = file.read(&mut buffer)?;
+            if bytes_read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..bytes_read]);
         }
-        hasher.update(&buffer[..bytes_read]);
     }
 
     Ok(hasher.finalize().to_hex().to_string())
@@ -1423,9 +2927,483 @@ mod tests {
             "archive"
         );
         assert_eq!(get_archive_basename(Path::new("file.tar")), "file");
+        assert_eq!(get_archive_basename(Path::new("lot.tar.gz")), "lot");
+        assert_eq!(get_archive_basename(Path::new("dep38/lot.zip")), "lot");
         assert_eq!(
             get_archive_basename(Path::new("noextension")),
             "noextension"
         );
     }
+
+    #[test]
+    fn test_collect_archives_and_directory_checksum() {
+        let root = std::env::temp_dir().join("cadastre-pg-collect-test");
+        let lot = root.join("feuilles").join("edigeo-380010000A01");
+        std::fs::create_dir_all(&lot).unwrap();
+        std::fs::write(lot.join("E0000A01.THF"), b"THF").unwrap();
+        std::fs::write(lot.join("EDAB01T1.VEC"), b"VEC").unwrap();
+        std::fs::write(root.join("edigeo-380010000B01.zip"), b"").unwrap();
+        std::fs::write(root.join("edigeo-380010000C01.tar.gz"), b"").unwrap();
+        std::fs::write(root.join("notes.txt"), b"").unwrap();
+
+        let mut archives = collect_archives(&root).unwrap();
+        archives.sort();
+        assert_eq!(
+            archives,
+            vec![
+                root.join("edigeo-380010000B01.zip"),
+                root.join("edigeo-380010000C01.tar.gz"),
+                lot.clone(),
+            ]
+        );
+
+        // Checksum d'un répertoire: stable, sensible au contenu
+        let before = compute_file_checksum(&lot).unwrap();
+        assert_eq!(before, compute_file_checksum(&lot).unwrap());
+        std::fs::write(lot.join("EDAB01T1.VEC"), b"VEC2").unwrap();
+        assert_ne!(before, compute_file_checksum(&lot).unwrap());
+
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn test_build_preload_scope() {
+        let archives = vec![
+            PathBuf::from("/data/edigeo-38001000AB01.tar.bz2"),
+            PathBuf::from("/data/edigeo-38185000AC02.tar.bz2"),
+            PathBuf::from("/data/edigeo-2A004000AB01.tar.bz2"),
+        ];
+        let scope = build_preload_scope(&archives, None).unwrap();
+        assert_eq!(scope.departements, vec!["2A", "38"]);
+        assert_eq!(scope.id_prefixes, vec!["2A004", "38001", "38185"]);
+
+        // Département imposé différent du nom: pas de filtre par commune
+        let scope = build_preload_scope(&archives[..1], Some("73")).unwrap();
+        assert_eq!(scope.departements, vec!["73"]);
+        assert!(scope.id_prefixes.is_empty());
+
+        // DOM: département seul
+        let scope =
+            build_preload_scope(&[PathBuf::from("edigeo-97101000AB01.tar.bz2")], None).unwrap();
+        assert_eq!(scope.departements, vec!["971"]);
+        assert!(scope.id_prefixes.is_empty());
+
+        assert!(build_preload_scope(&[PathBuf::from("archive.tar.bz2")], None).is_none());
+    }
+
+    #[test]
+    fn test_table_preload_scope_without_idu() {
+        let config = crate::config::Config::from_preset("full").unwrap();
+        let (tables, _) = build_import_specs(&config).unwrap();
+        let table = |name: &str| tables.iter().find(|t| t.name == name).unwrap();
+        let archives = [PathBuf::from("edigeo-38001000AB01.tar.bz2")];
+        let scope = build_preload_scope(&archives, None).unwrap();
+
+        // Parcelles: id = département + IDU, filtre par commune
+        let parcelles = table_preload_scope(&scope, table("edi_parcelles"));
+        assert_eq!(parcelles.id_prefixes, vec!["38001"]);
+
+        // Bâtiments: identifiants internes du lot, département seul
+        let batiments = table_preload_scope(&scope, table("edi_batiments"));
+        assert_eq!(batiments.departements, vec!["38"]);
+        assert!(batiments.id_prefixes.is_empty());
+    }
+
+    #[test]
+    fn test_row_values_change_detection() {
+        let table = TableSpec {
+            name: "edi_parcelles".to_string(),
+            columns: vec![ColumnSpec {
+                name: "contenance".to_string(),
+                source: "SUPF".to_string(),
+                data_type: "integer".to_string(),
+                prefix_dep: false,
+            }],
+            hash_geom: true,
+            change_detection: ChangeDetection::Both,
+            auto_fields: true,
+            idu_ids: true,
+        };
+        let ctx = RowContext {
+            departement: "38",
+            valid_from: "2024-01-01",
+            source_archive: "edigeo-38001000AB01.tar.bz2",
+            ewkt_prefix: b"SRID=2154;",
+            lot: 7,
+        };
+        let computed = ComputedContext {
+            commune_id: String::new(),
+            section_id: String::new(),
+        };
+        let feature = |supf: &str| edigeo::Feature {
+            id: "001000AB0012".to_string(),
+            geometry: geo::Geometry::Point(geo::Point::new(1.0, 2.0)),
+            properties: HashMap::from([("SUPF".to_string(), supf.to_string())]),
+            feature_type: "PARCELLE_id".to_string(),
+            quality: None,
+        };
+
+        let before = feature("+1895.");
+        let after = feature("1900");
+        let old_row = RowValues::new(&before, &before.geometry, &ctx, &table, &[], &computed);
+        let new_row = RowValues::new(&after, &after.geometry, &ctx, &table, &[], &computed);
+        assert_eq!(new_row.id, "38001000AB0012");
+        assert_eq!(old_row.columns[0], "1895");
+
+        let existing = ExistingHashes {
+            geometry: old_row.geometry_hash,
+            attributes: Some(old_row.attributes_hash),
+        };
+        assert!(old_row.matches(&existing, ChangeDetection::Both));
+        assert!(!new_row.matches(&existing, ChangeDetection::Both));
+        assert!(!new_row.matches(&existing, ChangeDetection::Attributes));
+        assert!(new_row.matches(&existing, ChangeDetection::Geometry));
+
+        // Marqueur: colonnes dynamiques, attributs JSON (NULL) puis numéro
+        // de lot (`staging_lot`)
+        let mut buf = BytesMut::new();
+        write_seen_row(&mut buf, &new_row, &ctx, &table);
+        assert!(buf.ends_with(b"|1900||7\n"), "{:?}", buf);
+
+        // Attributs `auto_fields`: convertis, en JSON et dans le hash
+        let auto_columns = [
+            ColumnSpec {
+                name: "date_obs".to_string(),
+                source: "DATE_OBS".to_string(),
+                data_type: "date".to_string(),
+                prefix_dep: false,
+            },
+            ColumnSpec {
+                name: "indp".to_string(),
+                source: "INDP".to_string(),
+                data_type: "text".to_string(),
+                prefix_dep: false,
+            },
+        ];
+        let mut observed = feature("1900");
+        observed
+            .properties
+            .insert("DATE_OBS".to_string(), "20230115".to_string());
+        let auto_row = RowValues::new(
+            &observed,
+            &observed.geometry,
+            &ctx,
+            &table,
+            &auto_columns,
+            &computed,
+        );
+        // Valeur absente: omise
+        assert_eq!(
+            auto_row.auto_attributes.as_deref(),
+            Some(r#"{"date_obs":"2023-01-15"}"#)
+        );
+        assert_ne!(auto_row.attributes_hash, new_row.attributes_hash);
+        let unobserved = RowValues::new(
+            &after,
+            &after.geometry,
+            &ctx,
+            &table,
+            &auto_columns,
+            &computed,
+        );
+        assert_eq!(unobserved.auto_attributes, None);
+        assert_eq!(unobserved.attributes_hash, new_row.attributes_hash);
+
+        let mut buf = BytesMut::new();
+        write_seen_row(&mut buf, &auto_row, &ctx, &table);
+        let tail = b"|1900|\"{\"\"date_obs\"\":\"\"2023-01-15\"\"}\"|7\n";
+        assert!(buf.ends_with(tail), "{:?}", buf);
+    }
+
+    /// Carré de côté 10 dont le coin bas gauche est en (x, y)
+    fn square(x: f64, y: f64) -> geo::Geometry {
+        geo::Geometry::Polygon(geo::Polygon::new(
+            geo::LineString::from(vec![
+                (x, y),
+                (x + 10.0, y),
+                (x + 10.0, y + 10.0),
+                (x, y + 10.0),
+                (x, y),
+            ]),
+            vec![],
+        ))
+    }
+
+    /// Feature d'un type donné, avec son IDU s'il est connu
+    fn test_feature(
+        id: &str,
+        feature_type: &str,
+        idu: Option<&str>,
+        geometry: geo::Geometry,
+    ) -> edigeo::Feature {
+        edigeo::Feature {
+            id: id.to_string(),
+            geometry,
+            properties: idu
+                .map(|idu| HashMap::from([("IDU".to_string(), idu.to_string())]))
+                .unwrap_or_default(),
+            feature_type: feature_type.to_string(),
+            quality: None,
+        }
+    }
+
+    #[test]
+    fn test_parent_idu_from_relations_then_containment() {
+        let mut parents = ParentIndex::default();
+        let sections = [
+            test_feature("001000AA", "SECTION_id", Some("001000AA"), square(0.0, 0.0)),
+            test_feature(
+                "001000AB",
+                "SECTION_id",
+                Some("001000AB"),
+                square(10.0, 0.0),
+            ),
+        ];
+        let vec_parents: HashMap<&str, usize> = sections
+            .iter()
+            .map(|s| (s.id.as_str(), parents.insert(s)))
+            .collect();
+        let parcel = |id: &str, x: f64| {
+            test_feature(
+                id,
+                "PARCELLE_id",
+                None,
+                geo::Geometry::Point(geo::Point::new(x, 5.0)),
+            )
+        };
+
+        let mut relations = edigeo::RelationGraph::default();
+        relations.insert(
+            "ID_S_OBJ_Z_1_2_2",
+            ("001000AB0001", "PARCELLE_id"),
+            ("001000AB", "SECTION_id"),
+        );
+        let idu = |feature: &edigeo::Feature, parent_type: &str| {
+            parent_idu(&parents, &vec_parents, &relations, feature, parent_type)
+        };
+
+        // Le lien prime sur la géométrie
+        let linked = parcel("001000AB0001", 5.0);
+        assert_eq!(idu(&linked, "SECTION_id"), "001000AB");
+
+        // Sans lien: section qui contient la parcelle
+        let unlinked = parcel("001000AB0002", 15.0);
+        assert_eq!(idu(&unlinked, "SECTION_id"), "001000AB");
+        let outside = parcel("001000ZZ0001", 50.0);
+        assert_eq!(idu(&outside, "SECTION_id"), "");
+        assert_eq!(idu(&linked, "COMMUNE_id"), "");
+
+        // Parent relié mais absent du VEC: repli spatial
+        let other_vec = HashMap::new();
+        assert_eq!(
+            parent_idu(&parents, &other_vec, &relations, &linked, "SECTION_id"),
+            "001000AA"
+        );
+    }
+
+    #[test]
+    fn test_parent_index_containment() {
+        // Section en L: son emprise couvre le carré (10, 10) sans le contenir
+        let l_shape = geo::Geometry::Polygon(geo::Polygon::new(
+            geo::LineString::from(vec![
+                (0.0, 0.0),
+                (20.0, 0.0),
+                (20.0, 10.0),
+                (10.0, 10.0),
+                (10.0, 20.0),
+                (0.0, 20.0),
+                (0.0, 0.0),
+            ]),
+            vec![],
+        ));
+        let mut parents = ParentIndex::default();
+        for feature in [
+            test_feature("Obj_1", "COMMUNE_id", Some("38001"), square(0.0, 0.0)),
+            test_feature("Obj_2", "SECTION_id", Some("001000AA"), l_shape),
+            test_feature("Obj_3", "SECTION_id", Some("001000AB"), square(10.0, 10.0)),
+            // Recouvrement: le premier parent lu l'emporte
+            test_feature("Obj_4", "SECTION_id", Some("001000AC"), square(0.0, 0.0)),
+        ] {
+            parents.insert(&feature);
+        }
+        // Géométrie vide: pas d'emprise, jamais candidate
+        parents.insert(&test_feature(
+            "Obj_5",
+            "SECTION_id",
+            Some("001000AD"),
+            geo::Geometry::MultiPolygon(geo::MultiPolygon::new(vec![])),
+        ));
+
+        let idu = |x: f64, y: f64, parent_type: &str| {
+            parents
+                .containing(parent_type, &geo::Point::new(x, y))
+                .map(|p| p.idu.as_str())
+        };
+        assert_eq!(idu(15.0, 15.0, "SECTION_id"), Some("001000AB"));
+        assert_eq!(idu(15.0, 5.0, "SECTION_id"), Some("001000AA"));
+        assert_eq!(idu(5.0, 5.0, "SECTION_id"), Some("001000AA"));
+        assert_eq!(idu(5.0, 5.0, "COMMUNE_id"), Some("38001"));
+        assert_eq!(idu(15.0, 15.0, "COMMUNE_id"), None);
+        assert_eq!(idu(25.0, 5.0, "SECTION_id"), None);
+    }
+
+    #[test]
+    fn test_encoder_resolves_parents_per_vec() {
+        let table = TableSpec {
+            name: "edi_parcelles".to_string(),
+            columns: vec![ColumnSpec {
+                name: "idu_section".to_string(),
+                source: "IDU_SECTION".to_string(),
+                data_type: "text".to_string(),
+                prefix_dep: false,
+            }],
+            hash_geom: true,
+            change_detection: ChangeDetection::Both,
+            auto_fields: false,
+            idu_ids: true,
+        };
+        let (sender, mut receiver) = mpsc::channel(8);
+        let settings = EncodeSettings {
+            tables: Mutex::new(Arc::new(ImportTables {
+                specs: vec![table],
+                feature_type_to_table: HashMap::from([(normalize_feature_type("PARCELLE_id"), 0)]),
+                senders: vec![sender],
+            })),
+            copy_handles: Mutex::default(),
+            auto_tables: None,
+            existing_hashes: HashMap::new(),
+            next_lot: AtomicI64::new(1),
+            valid_from: "2024-01-01".to_string(),
+            dep_override: None,
+            srid: 2154,
+            precision: 2,
+            snap_rounding: false,
+            node_tolerance: 1e-6,
+        };
+        let info = edigeo::ArchiveInfo {
+            projection: edigeo::Projection::default(),
+            year: 2024,
+            departement: "38".to_string(),
+            exchange: edigeo::Exchange::new(edigeo::Date::parse("20240115").unwrap()),
+            schema: edigeo::Schema::default(),
+        };
+        let link = |relations: &mut edigeo::RelationGraph| {
+            relations.insert(
+                "ID_S_OBJ_Z_1_2_2",
+                ("Obj_P1", "PARCELLE_id"),
+                ("Obj_S", "SECTION_id"),
+            );
+        };
+        let parcel = |id: &str, x: f64| test_feature(id, "PARCELLE_id", None, square(x, 0.0));
+        let section =
+            |idu: &str, x: f64| test_feature("Obj_S", "SECTION_id", Some(idu), square(x, 0.0));
+
+        let path = Path::new("edigeo-380010000A01.tar.bz2");
+        let mut encoder = ArchiveEncoder::new(&settings, path, "edigeo-380010000A01.tar.bz2");
+        // Premier VEC: la parcelle précède sa section (identifiants sans IDU)
+        let mut relations = edigeo::RelationGraph::default();
+        link(&mut relations);
+        let first = vec![parcel("Obj_P1", 0.0), section("001000AA", 0.0)];
+        assert!(encoder.push_vec(&info, &relations, first).is_continue());
+        // Second VEC: mêmes identifiants d'enregistrement, autre section
+        let second = vec![
+            parcel("Obj_P1", 0.0),
+            parcel("Obj_P2", 20.0),
+            parcel("Obj_P3", 0.0),
+            section("001000AB", 20.0),
+        ];
+        assert!(encoder.push_vec(&info, &relations, second).is_continue());
+
+        let summary = edigeo::StreamSummary {
+            info: info.clone(),
+            features: 6,
+            general: edigeo::General::default(),
+            relations: edigeo::RelationGraph::default(),
+            errors: Vec::new(),
+            failed_vecs: 0,
+            repairs: edigeo::RepairLog::default(),
+        };
+        encoder.finish(&summary).unwrap();
+
+        let chunk = receiver.try_recv().unwrap();
+        assert_eq!(chunk.rows, 4);
+        let rows = String::from_utf8(chunk.data.to_vec()).unwrap();
+        let sections: Vec<_> = rows
+            .lines()
+            .map(|row| row.split('|').nth(7).unwrap().to_string())
+            .collect();
+        // Lien du VEC de la parcelle, sinon section qui la contient
+        assert_eq!(
+            sections,
+            vec![
+                "\"001000AA\"",
+                "\"001000AB\"",
+                "\"001000AB\"",
+                "\"001000AA\""
+            ]
+        );
+    }
+
+    #[test]
+    fn test_column_value_dates() {
+        let column = |source: &str| ColumnSpec {
+            name: "date".to_string(),
+            source: source.to_string(),
+            data_type: "date".to_string(),
+            prefix_dep: false,
+        };
+        let feature = edigeo::Feature {
+            id: "001000AB0012".to_string(),
+            geometry: geo::Geometry::Point(geo::Point::new(1.0, 2.0)),
+            properties: HashMap::from([
+                ("DATE_OBS".to_string(), "20240115".to_string()),
+                ("updateDate".to_string(), "2023-11-02".to_string()),
+                ("BAD".to_string(), "20241315".to_string()),
+            ]),
+            feature_type: "PARCELLE_id".to_string(),
+            quality: None,
+        };
+        let computed = ComputedContext::default();
+        let value = |source: &str| column_value(&column(source), &feature, "38", &computed);
+
+        assert_eq!(value("DATE_OBS"), "2024-01-15");
+        assert_eq!(value("updateDate"), "2023-11-02");
+        assert_eq!(value("BAD"), "");
+        assert_eq!(value("createDate"), "");
+
+        // Type de mise à jour: valeur de l'énumération ou NULL
+        let update_type = |raw: &str| {
+            let feature = edigeo::Feature {
+                properties: HashMap::from([("updateType".to_string(), raw.to_string())]),
+                ..feature.clone()
+            };
+            let column = ColumnSpec {
+                data_type: "update_type".to_string(),
+                ..column("updateType")
+            };
+            column_value(&column, &feature, "38", &computed).into_owned()
+        };
+        assert_eq!(update_type("modification"), "modification");
+        assert_eq!(update_type("X"), "");
+    }
+
+    #[test]
+    fn test_snap_transform() {
+        use edigeo::repair::snap::CoordTransform;
+
+        let lambert93 = edigeo::Projection::default();
+        let transform = SnapTransform::new(4326);
+        let (lon, lat) = transform
+            .transform(&lambert93, 700000.0, 6600000.0)
+            .unwrap();
+        assert!((lon - 3.0).abs() < 1e-6, "lon={}", lon);
+        assert!((lat - 46.5).abs() < 1e-6, "lat={}", lat);
+
+        // Projection source non supportée
+        let unknown = edigeo::Projection {
+            epsg: 9999,
+            name: "UNKNOWN",
+        };
+        assert!(transform.transform(&unknown, 0.0, 0.0).is_none());
+    }
 }
@@ -5,9 +5,10 @@ use std::collections::HashMap;
 use std::path::Path;
 
 use anyhow::{Context, Result};
+use edigeo::{AttributeDef, AttributeType, Schema};
 
 /// Configuration principale
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct Config {
     #[serde(flatten)]
     pub tables: HashMap<String, TableConfig>,
@@ -25,6 +26,40 @@ pub struct TableConfig {
     /// Calculer le hash de géométrie pour cette table
     #[serde(default)]
     pub hash_geom: bool,
+
+    /// Critère de changement entre deux millésimes (geometry, attributes, both)
+    #[serde(default)]
+    pub change_detection: ChangeDetection,
+
+    /// Ajouter une colonne par attribut du SCD absent de `fields`
+    /// (type PostgreSQL déduit du SCD)
+    #[serde(default)]
+    pub auto_fields: bool,
+}
+
+/// Ce qui crée une nouvelle version d'une entité d'un millésime à l'autre
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeDetection {
+    /// Seule une modification de géométrie crée une nouvelle version
+    Geometry,
+    /// Seule une modification des colonnes configurées crée une nouvelle version
+    Attributes,
+    /// Géométrie ou attributs
+    #[default]
+    Both,
+}
+
+impl ChangeDetection {
+    /// La géométrie est prise en compte
+    pub fn geometry(self) -> bool {
+        matches!(self, Self::Geometry | Self::Both)
+    }
+
+    /// Les attributs sont pris en compte
+    pub fn attributes(self) -> bool {
+        matches!(self, Self::Attributes | Self::Both)
+    }
 }
 
 /// Mapping d'un champ
@@ -49,6 +84,102 @@ fn default_type() -> String {
     "text".to_string()
 }
 
+/// Type de données d'un attribut d'après sa définition SCD
+pub fn data_type_for(attribute: &AttributeDef) -> &'static str {
+    // Au-delà de 9 chiffres, un entier peut dépasser INTEGER
+    let wide = attribute.width.is_some_and(|width| width > 9);
+    match &attribute.value_type {
+        Some(AttributeType::Integer) if wide => "bigint",
+        Some(AttributeType::Integer) => "integer",
+        Some(AttributeType::Numeric) if attribute.decimals == Some(0) => {
+            if wide {
+                "bigint"
+            } else {
+                "integer"
+            }
+        }
+        Some(AttributeType::Numeric) | Some(AttributeType::Real) => "float",
+        Some(AttributeType::Date) => "date",
+        _ => "text",
+    }
+}
+
+/// Colonnes de qualité (QAL) des features: dates de création et de mise à jour,
+/// type de mise à jour (énumération PostgreSQL `update_type`)
+fn quality_fields() -> [FieldMapping; 3] {
+    let field = |source: &str, target: &str, data_type: &str| FieldMapping {
+        source: source.to_string(),
+        target: target.to_string(),
+        data_type: data_type.to_string(),
+        prefix_dep: false,
+    };
+    [
+        field("createDate", "create_date", "date"),
+        field("updateDate", "update_date", "date"),
+        field("updateType", "update_type", "update_type"),
+    ]
+}
+
+/// Identifiant SQL dérivé d'un identifiant EDIGEO (ex: "PARCELLE_id" -> "parcelle")
+fn sql_identifier(edigeo_id: &str) -> String {
+    edigeo_id
+        .trim_end_matches("_id")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Champs `auto_fields` d'un type d'objet: un par attribut SCD dont ni la
+/// propriété ni la colonne ne sont déjà mappées (`mapped(source, target)`)
+pub fn schema_fields(
+    schema: &Schema,
+    feature_type: &str,
+    mapped: impl Fn(&str, &str) -> bool,
+) -> Vec<FieldMapping> {
+    let mut fields: Vec<FieldMapping> = Vec::new();
+    for attribute in schema.object_attributes(feature_type) {
+        // Nom de la propriété des features (ex: "IDU_id" -> "IDU")
+        let source = attribute.id.trim_end_matches("_id");
+        let target = sql_identifier(&attribute.id);
+        if mapped(source, &target)
+            || fields
+                .iter()
+                .any(|f| f.source == source || f.target == target)
+        {
+            continue;
+        }
+        fields.push(FieldMapping {
+            source: source.to_string(),
+            target,
+            data_type: data_type_for(attribute).to_string(),
+            prefix_dep: false,
+        });
+    }
+    fields
+}
+
+/// Fusionne le schéma d'un lot dans `merged`
+///
+/// Un attribut typé différemment d'un lot à l'autre (voir `data_type_for`)
+/// devient textuel: ses valeurs, converties selon le schéma de leur lot,
+/// restent toutes valides.
+pub fn merge_schema(merged: &mut Schema, lot: Schema) {
+    for (id, attribute) in &lot.attributes {
+        if let Some(known) = merged.attributes.get_mut(id) {
+            if data_type_for(known) != data_type_for(attribute) {
+                known.value_type = Some(AttributeType::Text);
+            }
+        }
+    }
+    merged.merge(lot);
+}
+
 impl Config {
     /// Charge une configuration depuis un fichier
     pub fn load(path: &Path) -> Result<Self> {
@@ -61,13 +192,71 @@ impl Config {
     /// Charge une configuration depuis un preset embarqué
     pub fn from_preset(preset: &str) -> Result<Self> {
         match preset {
-            "full" => Self::load_embedded(include_str!("presets/full.json")),
-            "light" => Self::load_embedded(include_str!("presets/light.json")),
+            "full" => Self::load_embedded(include_str!("presets/full.json"))
+                .map(Self::with_quality_fields),
+            "light" => Self::load_embedded(include_str!("presets/light.json"))
+                .map(Self::with_quality_fields),
             "bati" => Self::load_embedded(include_str!("presets/bati.json")),
             _ => anyhow::bail!("Unknown preset: {}. Use: full, light, bati", preset),
         }
     }
 
+    /// Configuration `auto`: une table `edi_<objet>` par type d'objet du SCD,
+    /// avec une colonne par attribut
+    pub fn auto(schema: &Schema) -> Self {
+        let mut config = Self::auto_tables(schema.objects.keys());
+        config.add_schema_fields(schema);
+        config
+    }
+
+    /// Tables `auto` des types d'objets donnés, avec leurs seules colonnes de
+    /// qualité (les attributs sont ajoutés par `add_schema_fields`)
+    pub fn auto_tables<'a>(feature_types: impl IntoIterator<Item = &'a String>) -> Self {
+        let tables = feature_types
+            .into_iter()
+            .map(|feature_type| {
+                let table = TableConfig {
+                    table: format!("edi_{}", sql_identifier(feature_type)),
+                    fields: Vec::new(),
+                    hash_geom: true,
+                    change_detection: ChangeDetection::default(),
+                    auto_fields: true,
+                };
+                (feature_type.clone(), table)
+            })
+            .collect();
+
+        Self { tables }.with_quality_fields()
+    }
+
+    /// Ajoute à chaque table les colonnes de qualité qu'elle n'a pas déjà
+    fn with_quality_fields(mut self) -> Self {
+        for table in self.tables.values_mut() {
+            for field in quality_fields() {
+                if !table.fields.iter().any(|f| f.target == field.target) {
+                    table.fields.push(field);
+                }
+            }
+        }
+        self
+    }
+
+    /// Complète les tables `auto_fields` avec les attributs SCD non mappés
+    pub fn add_schema_fields(&mut self, schema: &Schema) {
+        for (feature_type, table) in &mut self.tables {
+            if !table.auto_fields {
+                continue;
+            }
+            let fields = schema_fields(schema, feature_type, |source, target| {
+                table
+                    .fields
+                    .iter()
+                    .any(|f| f.source == source || f.target == target)
+            });
+            table.fields.extend(fields);
+        }
+    }
+
     fn load_embedded(json: &str) -> Result<Self> {
         serde_json::from_str(json).context("Failed to parse embedded config")
     }
@@ -77,3 +266,138 @@ impl Config {
         self.tables.get(feature_type)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use edigeo::types::ObjectType;
+
+    fn attribute(id: &str, value_type: AttributeType, width: usize) -> AttributeDef {
+        AttributeDef {
+            id: id.to_string(),
+            label: id.trim_end_matches("_id").to_string(),
+            definition: None,
+            value_type: Some(value_type),
+            width: Some(width),
+            decimals: None,
+            unit: None,
+            values: Vec::new(),
+        }
+    }
+
+    fn schema() -> Schema {
+        let mut schema = Schema::default();
+        schema.objects.insert(
+            "PARCELLE_id".to_string(),
+            ObjectType {
+                id: "PARCELLE_id".to_string(),
+                label: "PARCELLE".to_string(),
+                definition: None,
+                kind: None,
+                attributes: vec!["IDU_id".into(), "SUPF_id".into(), "DATE_OBS_id".into()],
+            },
+        );
+        for attribute in [
+            attribute("IDU_id", AttributeType::Text, 12),
+            attribute("SUPF_id", AttributeType::Integer, 10),
+            attribute("DATE_OBS_id", AttributeType::Date, 8),
+        ] {
+            schema.attributes.insert(attribute.id.clone(), attribute);
+        }
+        schema
+    }
+
+    #[test]
+    fn test_auto_config_from_schema() {
+        let config = Config::auto(&schema());
+        let parcelle = config.get_table_config("PARCELLE_id").unwrap();
+        assert_eq!(parcelle.table, "edi_parcelle");
+
+        let columns: Vec<(&str, &str, &str)> = parcelle
+            .fields
+            .iter()
+            .map(|f| (f.source.as_str(), f.target.as_str(), f.data_type.as_str()))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("createDate", "create_date", "date"),
+                ("updateDate", "update_date", "date"),
+                ("updateType", "update_type", "update_type"),
+                ("IDU", "idu", "text"),
+                ("SUPF", "supf", "bigint"),
+                ("DATE_OBS", "date_obs", "date"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_presets_store_quality() {
+        for preset in ["full", "light"] {
+            let config = Config::from_preset(preset).unwrap();
+            for table in config.tables.values() {
+                let quality: Vec<(&str, &str)> = table
+                    .fields
+                    .iter()
+                    .filter(|f| f.target.ends_with("_date") || f.target == "update_type")
+                    .map(|f| (f.target.as_str(), f.data_type.as_str()))
+                    .collect();
+                assert_eq!(
+                    quality,
+                    vec![
+                        ("create_date", "date"),
+                        ("update_date", "date"),
+                        ("update_type", "update_type"),
+                    ],
+                    "{} / {}",
+                    preset,
+                    table.table
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_auto_fields_keep_mapped_columns() {
+        let mut config = Config::load_embedded(
+            r#"{ "PARCELLE_id": { "table": "parcelles", "auto_fields": true,
+                 "fields": [{ "source": "SUPF", "target": "contenance", "data_type": "integer" }] } }"#,
+        )
+        .unwrap();
+        config.add_schema_fields(&schema());
+
+        let fields = &config.get_table_config("PARCELLE_id").unwrap().fields;
+        let targets: Vec<&str> = fields.iter().map(|f| f.target.as_str()).collect();
+        assert_eq!(targets, vec!["contenance", "idu", "date_obs"]);
+        assert_eq!(fields[0].data_type, "integer");
+    }
+
+    #[test]
+    fn test_merge_schema_conflicting_types() {
+        let mut merged = schema();
+        let mut lot = schema();
+        lot.attributes.insert(
+            "SUPF_id".to_string(),
+            attribute("SUPF_id", AttributeType::Real, 10),
+        );
+        lot.attributes.insert(
+            "DATE_OBS_id".to_string(),
+            attribute("DATE_OBS_id", AttributeType::Date, 8),
+        );
+        merge_schema(&mut merged, lot);
+
+        // Entier d'un lot, réel de l'autre: texte
+        let types: Vec<&str> = merged
+            .object_attributes("PARCELLE_id")
+            .into_iter()
+            .map(data_type_for)
+            .collect();
+        assert_eq!(types, vec!["text", "text", "date"]);
+
+        // Tables `auto` d'abord sans attributs
+        let config = Config::auto_tables(merged.objects.keys());
+        let parcelle = config.get_table_config("PARCELLE_id").unwrap();
+        assert_eq!(parcelle.fields.len(), 3);
+        assert!(parcelle.auto_fields);
+    }
+}
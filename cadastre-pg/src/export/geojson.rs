@@ -78,7 +78,7 @@ fn write_feature<W: Write>(writer: &mut W, feature: &Feature) -> Result<()> {
 }
 
 /// Échappe une chaîne pour JSON
-fn escape_json(s: &str) -> String {
+pub(crate) fn escape_json(s: &str) -> String {
     let mut result = String::with_capacity(s.len());
     for c in s.chars() {
         match c {
@@ -110,6 +110,7 @@ mod tests {
             geometry: Geometry::Point(Point::new(1.0, 2.0)),
             properties: HashMap::new(),
             feature_type: "TEST".to_string(),
+            quality: None,
         };
 
         let mut buffer = Cursor::new(Vec::new());
@@ -138,6 +139,7 @@ mod tests {
                 .into_iter()
                 .collect(),
             feature_type: "TEST".to_string(),
+            quality: None,
         }];
         let projection = Projection {
             epsg: 4326,
@@ -1,5 +1,6 @@
 //! Export vers PostgreSQL/PostGIS
 
+use std::borrow::Cow;
 use std::collections::HashMap;
 
 use anyhow::{Context, Result};
@@ -12,7 +13,13 @@ use tokio::sync::mpsc;
 use tracing::{debug, info, warn};
 use wkb::geom_to_wkb;
 
-use edigeo::{Feature, Projection};
+use edigeo::{Feature, Projection, RepairEntry};
+
+use crate::config::ChangeDetection;
+use crate::versioning::temporal::{staging_table_name, MillesimeScope, TableMergeResult};
+
+/// Type énuméré des types de mise à jour QAL, créé dans le schéma d'import
+pub const UPDATE_TYPE: &str = "update_type";
 
 /// Configuration d'une table d'export
 #[derive(Debug, Clone)]
@@ -31,6 +38,24 @@ pub struct ColumnConfig {
     pub source: String,
 }
 
+/// Hash de la version active d'une entité (préchargement incrémental)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExistingHashes {
+    pub geometry: Option<[u8; 32]>,
+    pub attributes: Option<[u8; 32]>,
+}
+
+/// Périmètre du préchargement des hash: seules les versions actives des
+/// départements (et éventuellement des communes) importés sont chargées.
+#[derive(Debug, Clone, Default)]
+pub struct PreloadScope {
+    /// Départements des archives importées
+    pub departements: Vec<String>,
+    /// Préfixes d'id (département + commune) des tables identifiées par IDU,
+    /// vide = départements entiers
+    pub id_prefixes: Vec<String>,
+}
+
 /// Chunk CSV pré-formaté pour COPY
 #[derive(Debug)]
 pub struct CopyChunk {
@@ -83,6 +108,17 @@ pub async fn create_schema(
         }
     }
 
+    // Type des colonnes de type de mise à jour
+    client
+        .batch_execute(&format!(
+            "DO $$ BEGIN \
+                CREATE TYPE {}.{} AS ENUM ('creation', 'modification', 'deletion'); \
+             EXCEPTION WHEN duplicate_object THEN NULL; END $$",
+            schema, UPDATE_TYPE
+        ))
+        .await
+        .context("Failed to create update_type enum")?;
+
     // Créer les tables
     for table in tables {
         create_table(&client, schema, table).await?;
@@ -91,6 +127,19 @@ pub async fn create_schema(
     // Créer la table des checksums d'archives
     create_archive_checksums_table(&client, schema).await?;
 
+    // Créer le journal des retouches de géométrie
+    create_repair_log_table(&client, schema).await?;
+
+    Ok(())
+}
+
+/// Crée les tables données, ou leur ajoute les colonnes qui leur manquent,
+/// dans un schéma déjà créé par `create_schema`.
+pub async fn create_tables(pool: &Pool, schema: &str, tables: &[TableConfig]) -> Result<()> {
+    let client = pool.get().await?;
+    for table in tables {
+        create_table(&client, schema, table).await?;
+    }
     Ok(())
 }
 
@@ -118,6 +167,93 @@ async fn create_archive_checksums_table(
     Ok(())
 }
 
+/// Crée le journal des retouches de géométrie faites par le parser (revue QA).
+async fn create_repair_log_table(client: &deadpool_postgres::Object, schema: &str) -> Result<()> {
+    let sql = format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {schema}._repair_log (
+            archive_name TEXT NOT NULL,
+            valid_from DATE NOT NULL,
+            entity_id TEXT NOT NULL,
+            feature_type TEXT NOT NULL,
+            action TEXT NOT NULL,
+            reason TEXT,
+            vertices BIGINT NOT NULL,
+            area_delta DOUBLE PRECISION,
+            recorded_at TIMESTAMPTZ DEFAULT NOW()
+        );
+        CREATE INDEX IF NOT EXISTS _repair_log_entity_id ON {schema}._repair_log (entity_id);
+        CREATE INDEX IF NOT EXISTS _repair_log_archive ON {schema}._repair_log (archive_name, valid_from);
+        "#,
+        schema = schema
+    );
+
+    client
+        .batch_execute(&sql)
+        .await
+        .context("Failed to create _repair_log table")?;
+
+    Ok(())
+}
+
+/// Enregistre les retouches de géométrie d'une archive pour un millésime.
+///
+/// Les entrées d'un import précédent de la même archive et du même
+/// millésime sont remplacées.
+pub async fn record_repairs(
+    pool: &Pool,
+    schema: &str,
+    archive_name: &str,
+    valid_from: &str,
+    entries: &[RepairEntry],
+) -> Result<u64> {
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+
+    tx.execute(
+        &format!(
+            "DELETE FROM {}._repair_log WHERE archive_name = $1 AND valid_from = $2::text::date",
+            schema
+        ),
+        &[&archive_name, &valid_from],
+    )
+    .await?;
+
+    let entity_ids: Vec<&str> = entries.iter().map(|e| e.entity_id.as_str()).collect();
+    let feature_types: Vec<&str> = entries.iter().map(|e| e.feature_type.as_str()).collect();
+    let actions: Vec<&str> = entries.iter().map(|e| e.action.as_str()).collect();
+    let reasons: Vec<Option<&str>> = entries.iter().map(|e| e.action.reason()).collect();
+    let vertices: Vec<i64> = entries.iter().map(|e| e.vertices as i64).collect();
+    let area_deltas: Vec<Option<f64>> = entries.iter().map(|e| e.area_delta).collect();
+
+    let inserted = tx
+        .execute(
+            &format!(
+                "INSERT INTO {}._repair_log \
+                 (archive_name, valid_from, entity_id, feature_type, action, reason, vertices, area_delta) \
+                 SELECT $1, $2::text::date, i, t, a, r, v, d \
+                 FROM UNNEST($3::text[], $4::text[], $5::text[], $6::text[], $7::int8[], $8::float8[]) \
+                 AS l(i, t, a, r, v, d)",
+                schema
+            ),
+            &[
+                &archive_name,
+                &valid_from,
+                &entity_ids,
+                &feature_types,
+                &actions,
+                &reasons,
+                &vertices,
+                &area_deltas,
+            ],
+        )
+        .await
+        .context("Failed to record repair log")?;
+
+    tx.commit().await?;
+    Ok(inserted)
+}
+
 /// Vérifie si une archive a déjà été importée (checksum identique).
 pub async fn is_archive_already_imported(
     pool: &Pool,
@@ -170,6 +306,11 @@ pub async fn record_archive_checksum(
 /// Crée les tables de staging (sans contraintes) utilisées pour COPY.
 ///
 /// Les tables sont créées dans le même schéma, avec un préfixe `_staging_`.
+/// Chaque ligne porte le numéro du lot EDIGEO qui l'a produite
+/// (`staging_lot`): les lots en erreur sont retirés avant la fusion (voir
+/// `discard_staged_lots`). Les attributs dont la colonne n'est connue
+/// qu'après lecture des lots sont stockés en JSON (`auto_attributes`, voir
+/// `expand_auto_attributes`).
 pub async fn create_staging_tables(
     pool: &Pool,
     schema: &str,
@@ -189,6 +330,17 @@ pub async fn create_staging_tables(
             .execute(&sql, &[])
             .await
             .with_context(|| format!("Failed to create staging table {}.{}", schema, staging))?;
+        client
+            .execute(
+                &format!(
+                    "ALTER TABLE {}.{} ADD COLUMN IF NOT EXISTS auto_attributes JSONB, \
+                     ADD COLUMN IF NOT EXISTS staging_lot BIGINT",
+                    schema, staging
+                ),
+                &[],
+            )
+            .await
+            .with_context(|| format!("Failed to create staging table {}.{}", schema, staging))?;
 
         // S'assurer que la table staging est vide (si réutilisée)
         client
@@ -200,36 +352,123 @@ pub async fn create_staging_tables(
     Ok(())
 }
 
-/// Fusionne la staging vers la table finale en ignorant les doublons (DO NOTHING).
+/// Reporte les attributs JSON de la staging (`auto_attributes`) dans leurs
+/// colonnes typées, ajoutées à la staging si besoin.
+///
+/// Les valeurs ont été converties à l'encodage selon le type de la colonne:
+/// le cast ne peut pas échouer. Retourne le nombre de lignes complétées.
+pub async fn expand_auto_attributes(
+    pool: &Pool,
+    schema: &str,
+    table: &str,
+    columns: &[ColumnConfig],
+) -> Result<u64> {
+    if columns.is_empty() {
+        return Ok(0);
+    }
+    let client = pool.get().await?;
+    let staging = staging_table_name(table);
+
+    let added_columns: Vec<String> = columns
+        .iter()
+        .map(|c| {
+            format!(
+                "ADD COLUMN IF NOT EXISTS {} {}",
+                c.name,
+                column_type(schema, &c.pg_type)
+            )
+        })
+        .collect();
+    client
+        .execute(
+            &format!(
+                "ALTER TABLE {}.{} {}",
+                schema,
+                staging,
+                added_columns.join(", ")
+            ),
+            &[],
+        )
+        .await
+        .with_context(|| format!("Failed to add columns to {}.{}", schema, staging))?;
+
+    let assignments: Vec<String> = columns
+        .iter()
+        .map(|c| {
+            format!(
+                "{name} = (auto_attributes ->> '{name}')::{pg_type}",
+                name = c.name,
+                pg_type = column_type(schema, &c.pg_type)
+            )
+        })
+        .collect();
+    client
+        .execute(
+            &format!(
+                "UPDATE {}.{} SET {} WHERE auto_attributes IS NOT NULL",
+                schema,
+                staging,
+                assignments.join(", ")
+            ),
+            &[],
+        )
+        .await
+        .with_context(|| format!("Failed to expand attributes of {}.{}", schema, staging))
+}
+
+/// Fusionne la staging vers la table finale avec versioning temporel.
+///
+/// Dans une seule transaction:
+/// 1. termine (`valid_to`) les entités actives des départements importés
+///    absentes de la staging,
+/// 2. termine les versions actives qui ont changé (selon `change_detection`),
+/// 3. insère une nouvelle version pour chaque entité sans version active.
+///
+/// Les lignes de staging sans géométrie sont des marqueurs d'entités inchangées:
+/// elles empêchent la fin de vie de l'entité mais ne sont jamais insérées.
 /// Applique ST_MakeValid pour corriger les géométries invalides (auto-intersections, etc.)
 pub async fn merge_staging_into_table(
     pool: &Pool,
     schema: &str,
     table: &str,
     dynamic_columns: &[String],
-) -> Result<u64> {
-    let client = pool.get().await?;
+    change_detection: ChangeDetection,
+    scope: &MillesimeScope,
+) -> Result<TableMergeResult> {
+    let mut client = pool.get().await?;
     let staging = staging_table_name(table);
 
+    // Index de jointure sur la staging (entités vues dans le millésime)
+    client
+        .batch_execute(&format!(
+            "CREATE INDEX IF NOT EXISTS {staging}_dep_id ON {schema}.{staging} (departement, id); \
+             ANALYZE {schema}.{staging}",
+            schema = schema,
+            staging = staging
+        ))
+        .await
+        .with_context(|| format!("Failed to index staging table {}.{}", schema, staging))?;
+
     // Colonnes cibles (on exclut row_id qui est généré)
-    let target_cols = vec![
-        "id",
-        "departement",
-        "geometry",
-    ];
+    let target_cols = vec!["id", "departement", "geometry"];
     let mut all_target_cols: Vec<&str> = target_cols.clone();
     let dynamic_refs: Vec<&str> = dynamic_columns.iter().map(|s| s.as_str()).collect();
     all_target_cols.extend(dynamic_refs.iter());
-    all_target_cols.extend(vec!["valid_from", "valid_to", "geometry_hash"]);
+    all_target_cols.extend(vec![
+        "valid_from",
+        "geometry_hash",
+        "attributes_hash",
+        "source_archive",
+    ]);
 
     // Colonnes sources (avec ST_MakeValid sur geometry)
     let source_cols: Vec<String> = all_target_cols
         .iter()
         .map(|&col| {
             if col == "geometry" {
-                "ST_MakeValid(geometry)".to_string()
+                "ST_MakeValid(s.geometry)".to_string()
             } else {
-                col.to_string()
+                format!("s.{}", col)
             }
         })
         .collect();
@@ -237,10 +476,30 @@ pub async fn merge_staging_into_table(
     let target_sql = all_target_cols.join(", ");
     let source_sql = source_cols.join(", ");
 
+    let tx = client.transaction().await?;
+
+    let ended = crate::versioning::temporal::mark_table_as_ended(&tx, schema, table, scope).await?;
+    let replaced = crate::versioning::temporal::mark_changed_as_ended(
+        &tx,
+        schema,
+        table,
+        dynamic_columns,
+        change_detection,
+        scope,
+    )
+    .await?;
+    crate::versioning::temporal::backfill_attributes_hash(&tx, schema, table, dynamic_columns)
+        .await?;
+
     let sql = format!(
         r#"
         INSERT INTO {schema}.{table} ({target_sql})
-        SELECT {source_sql} FROM {schema}.{staging}
+        SELECT {source_sql} FROM {schema}.{staging} s
+        WHERE s.geometry IS NOT NULL
+          AND NOT EXISTS (
+              SELECT 1 FROM {schema}.{table} t
+              WHERE t.departement = s.departement AND t.id = s.id AND t.valid_to IS NULL
+          )
         ON CONFLICT (departement, id, valid_from) DO NOTHING
         "#,
         schema = schema,
@@ -250,12 +509,53 @@ pub async fn merge_staging_into_table(
         staging = staging
     );
 
-    let inserted = client
+    let inserted = tx
         .execute(&sql, &[])
         .await
         .with_context(|| format!("Failed to merge staging into {}.{}", schema, table))?;
 
-    Ok(inserted)
+    tx.commit()
+        .await
+        .with_context(|| format!("Failed to commit merge into {}.{}", schema, table))?;
+
+    Ok(TableMergeResult {
+        table: table.to_string(),
+        inserted,
+        replaced: replaced.rows_marked as u64,
+        ended: ended.rows_marked as u64,
+    })
+}
+
+/// Retire de la staging les lignes des lots en erreur et des archives
+/// interrompues (`source_archive`, ou son premier segment pour les lots d'une
+/// archive qui en regroupe plusieurs).
+///
+/// Un lot est ainsi importé en entier ou pas du tout: ses entités actives
+/// restent en l'état. Retourne le nombre de lignes retirées.
+pub async fn discard_staged_lots(
+    pool: &Pool,
+    schema: &str,
+    tables: &[TableConfig],
+    lots: &[i64],
+    archives: &[String],
+) -> Result<u64> {
+    let client = pool.get().await?;
+    let mut discarded = 0;
+    for table in tables {
+        let staging = staging_table_name(&table.name);
+        discarded += client
+            .execute(
+                &format!(
+                    "DELETE FROM {}.{} WHERE staging_lot = ANY($1) \
+                     OR split_part(source_archive, '/', 1) = ANY($2)",
+                    schema, staging
+                ),
+                &[&lots, &archives],
+            )
+            .await
+            .with_context(|| format!("Failed to discard lots from {}.{}", schema, staging))?;
+    }
+    Ok(discarded)
 }
 
 /// Supprime les tables de staging.
@@ -343,13 +643,69 @@ pub async fn create_indexes(pool: &Pool, schema: &str, table: &str) -> Result<()
     Ok(())
 }
 
-/// Charge les geometry_hash existants d'une table pour filtrage incrémental.
-/// Retourne un HashSet des hash (32 bytes) pour lookup O(1).
+/// Estime le nombre de versions actives qu'un préchargement chargerait.
+///
+/// Si les statistiques de la table (`reltuples`) indiquent qu'elle est petite,
+/// on s'en contente; sinon on compte les lignes actives du périmètre.
+pub async fn count_preload_rows(
+    pool: &Pool,
+    schema: &str,
+    table: &str,
+    scope: &PreloadScope,
+    max_rows: u64,
+) -> Result<u64> {
+    let client = pool.get().await?;
+
+    let reltuples = client
+        .query_opt(
+            "SELECT c.reltuples::float8 FROM pg_class c \
+             JOIN pg_namespace n ON n.oid = c.relnamespace \
+             WHERE n.nspname = $1 AND c.relname = $2",
+            &[&schema, &table],
+        )
+        .await?;
+
+    let Some(row) = reltuples else {
+        return Ok(0);
+    };
+    // -1: table jamais analysée
+    let estimate: f64 = row.get(0);
+    if estimate >= 0.0 && (estimate as u64) <= max_rows {
+        return Ok(estimate as u64);
+    }
+
+    let row = client
+        .query_one(
+            &format!(
+                "SELECT COUNT(*) FROM {}.{} WHERE valid_to IS NULL AND {}",
+                schema, table, PRELOAD_SCOPE_PREDICATE
+            ),
+            &[&scope.departements, &like_patterns(&scope.id_prefixes)],
+        )
+        .await
+        .with_context(|| format!("Failed to count active rows of {}.{}", schema, table))?;
+    let count: i64 = row.get(0);
+
+    Ok(count as u64)
+}
+
+/// Filtre de périmètre du préchargement ($1: départements, $2: motifs LIKE d'id)
+const PRELOAD_SCOPE_PREDICATE: &str =
+    "departement = ANY($1) AND (cardinality($2::text[]) = 0 OR id LIKE ANY($2))";
+
+fn like_patterns(prefixes: &[String]) -> Vec<String> {
+    prefixes.iter().map(|p| format!("{}%", p)).collect()
+}
+
+/// Charge les hash (géométrie, attributs) des versions actives d'une table pour
+/// filtrage incrémental, limités au périmètre de l'import.
+/// Retourne une HashMap id → hash pour lookup O(1).
 pub async fn load_existing_hashes(
     pool: &Pool,
     schema: &str,
     table: &str,
-) -> Result<std::collections::HashSet<[u8; 32]>> {
+    scope: &PreloadScope,
+) -> Result<HashMap<String, ExistingHashes>> {
     let client = pool.get().await?;
 
     // Vérifier si la table existe
@@ -362,28 +718,34 @@ pub async fn load_existing_hashes(
         .is_some();
 
     if !table_exists {
-        return Ok(std::collections::HashSet::new());
+        return Ok(HashMap::new());
     }
 
     let rows = client
         .query(
             &format!(
-                "SELECT geometry_hash FROM {}.{} WHERE geometry_hash IS NOT NULL",
-                schema, table
+                "SELECT id, geometry_hash, attributes_hash FROM {}.{} \
+                 WHERE valid_to IS NULL AND (geometry_hash IS NOT NULL OR attributes_hash IS NOT NULL) \
+                 AND {}",
+                schema, table, PRELOAD_SCOPE_PREDICATE
             ),
-            &[],
+            &[&scope.departements, &like_patterns(&scope.id_prefixes)],
         )
         .await
         .with_context(|| format!("Failed to load hashes from {}.{}", schema, table))?;
 
-    let mut hashes = std::collections::HashSet::with_capacity(rows.len());
+    let mut hashes = HashMap::with_capacity(rows.len());
     for row in rows {
-        let hash_bytes: Vec<u8> = row.get(0);
-        if hash_bytes.len() == 32 {
-            let mut arr = [0u8; 32];
-            arr.copy_from_slice(&hash_bytes);
-            hashes.insert(arr);
-        }
+        let id: String = row.get(0);
+        let geometry: Option<Vec<u8>> = row.get(1);
+        let attributes: Option<Vec<u8>> = row.get(2);
+        hashes.insert(
+            id,
+            ExistingHashes {
+                geometry: geometry.as_deref().and_then(hash_from_bytes),
+                attributes: attributes.as_deref().and_then(hash_from_bytes),
+            },
+        );
     }
 
     info!(
@@ -395,6 +757,19 @@ pub async fn load_existing_hashes(
     Ok(hashes)
 }
 
+fn hash_from_bytes(bytes: &[u8]) -> Option<[u8; 32]> {
+    bytes.try_into().ok()
+}
+
+/// Type SQL d'une colonne, qualifié par le schéma pour les types créés à l'import
+fn column_type<'a>(schema: &str, pg_type: &'a str) -> Cow<'a, str> {
+    if pg_type == UPDATE_TYPE {
+        Cow::Owned(format!("{}.{}", schema, pg_type))
+    } else {
+        Cow::Borrowed(pg_type)
+    }
+}
+
 /// Crée une table avec versioning temporel
 async fn create_table(
     client: &deadpool_postgres::Object,
@@ -404,7 +779,7 @@ async fn create_table(
     let columns: Vec<String> = config
         .columns
         .iter()
-        .map(|c| format!("{} {}", c.name, c.pg_type))
+        .map(|c| format!("{} {}", c.name, column_type(schema, &c.pg_type)))
         .collect();
     let dynamic_columns_sql = if columns.is_empty() {
         String::new()
@@ -423,6 +798,8 @@ async fn create_table(
             valid_from DATE NOT NULL,
             valid_to DATE,
             geometry_hash BYTEA,
+            attributes_hash BYTEA,
+            source_archive TEXT,
             created_at TIMESTAMPTZ DEFAULT NOW(),
             updated_at TIMESTAMPTZ DEFAULT NOW(),
             CONSTRAINT {}_valid_dates CHECK (valid_to IS NULL OR valid_to > valid_from),
@@ -443,14 +820,35 @@ async fn create_table(
         .await
         .with_context(|| format!("Failed to create table {}.{}", schema, config.name))?;
 
+    // Tables créées par une version antérieure (sans provenance ni hash d'attributs)
+    // ou avec une configuration qui n'avait pas toutes les colonnes
+    let added_columns: String = config
+        .columns
+        .iter()
+        .map(|c| {
+            format!(
+                ", ADD COLUMN IF NOT EXISTS {} {}",
+                c.name,
+                column_type(schema, &c.pg_type)
+            )
+        })
+        .collect();
+    client
+        .execute(
+            &format!(
+                "ALTER TABLE {}.{} ADD COLUMN IF NOT EXISTS source_archive TEXT, \
+                 ADD COLUMN IF NOT EXISTS attributes_hash BYTEA{}",
+                schema, config.name, added_columns
+            ),
+            &[],
+        )
+        .await
+        .with_context(|| format!("Failed to migrate table {}.{}", schema, config.name))?;
+
     info!("Created table {}.{}", schema, config.name);
     Ok(())
 }
 
-fn staging_table_name(table: &str) -> String {
-    format!("_staging_{}", table)
-}
-
 /// Insère des features dans une table avec versioning
 pub async fn insert_features(
     pool: &Pool,
@@ -783,7 +1181,9 @@ pub async fn copy_features(
 /// Insère des lignes CSV pré-formatées via COPY (pipeline streaming).
 ///
 /// Chaque chunk doit contenir des lignes terminées par `\n` et correspondre
-/// exactement au layout de colonnes utilisé par la commande COPY.
+/// exactement au layout de colonnes utilisé par la commande COPY: colonnes
+/// fixes, colonnes dynamiques, attributs JSON (`auto_attributes`) puis numéro
+/// de lot (`staging_lot`).
 pub async fn copy_csv_chunks(
     pool: &Pool,
     schema: &str,
@@ -796,12 +1196,12 @@ pub async fn copy_csv_chunks(
 
     let copy_sql = if dynamic_columns.is_empty() {
         format!(
-            "COPY {}.{} (id, departement, geometry, valid_from, geometry_hash) FROM STDIN WITH (FORMAT csv, DELIMITER '|', QUOTE '\"', ESCAPE '\"', NULL '')",
+            "COPY {}.{} (id, departement, geometry, valid_from, geometry_hash, attributes_hash, source_archive, auto_attributes, staging_lot) FROM STDIN WITH (FORMAT csv, DELIMITER '|', QUOTE '\"', ESCAPE '\"', NULL '')",
             schema, table
         )
     } else {
         format!(
-            "COPY {}.{} (id, departement, geometry, valid_from, geometry_hash, {}) FROM STDIN WITH (FORMAT csv, DELIMITER '|', QUOTE '\"', ESCAPE '\"', NULL '')",
+            "COPY {}.{} (id, departement, geometry, valid_from, geometry_hash, attributes_hash, source_archive, {}, auto_attributes, staging_lot) FROM STDIN WITH (FORMAT csv, DELIMITER '|', QUOTE '\"', ESCAPE '\"', NULL '')",
             schema,
             table,
             dynamic_columns.join(", ")
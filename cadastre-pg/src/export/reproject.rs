@@ -108,7 +108,7 @@ impl Reprojector {
     }
 
     /// Transforme une coordonnée unique
-    fn transform_coord(&self, coord: Coord) -> Result<(f64, f64)> {
+    pub fn transform_coord(&self, coord: Coord) -> Result<(f64, f64)> {
         self.proj
             .convert((coord.x, coord.y))
             .context("Coordinate transformation failed")
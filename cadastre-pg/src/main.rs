@@ -24,7 +24,7 @@ mod export;
 mod reproject_lite;
 mod versioning;
 
-use cli::{Commands, PostgisArgs};
+use cli::{Commands, ConnectionArgs, PostgisArgs};
 
 /// Exporter les données cadastrales EDIGEO vers PostGIS ou GeoJSON
 #[derive(Parser)]
@@ -42,6 +42,10 @@ struct Cli {
     #[arg(short, long, global = true)]
     quiet: bool,
 
+    /// Grille IGN gr3df97a.txt pour les archives en Lambert NTF (défaut: $NTF_GRID)
+    #[arg(long, global = true)]
+    ntf_grid: Option<std::path::PathBuf>,
+
     /// Sous-commande (défaut: export vers PostGIS)
     #[command(subcommand)]
     command: Option<Commands>,
@@ -49,6 +53,10 @@ struct Cli {
     /// Arguments pour l'export PostGIS (commande par défaut)
     #[command(flatten)]
     postgis: Option<PostgisArgs>,
+
+    /// Connexion PostgreSQL (export PostGIS et sous-commandes base de données)
+    #[command(flatten)]
+    connection: ConnectionArgs,
 }
 
 #[tokio::main]
@@ -61,35 +69,44 @@ async fn main() -> Result<()> {
     // Configurer le logging
     init_logging(cli.verbose, cli.quiet);
 
+    if let Some(path) = &cli.ntf_grid {
+        reproject_lite::ntf::load_grid(path)?;
+    }
+
     match cli.command {
         Some(Commands::ToGeojson { path, output, srid }) => {
             info!(path = %path.display(), output = %output.display(), srid = ?srid, "Export vers GeoJSON");
             cli::cmd_export(&path, &output, srid).await?;
         }
+        Some(Commands::Snapshot {
+            date,
+            schema,
+            config,
+            materialized,
+        }) => {
+            info!(date = ?date, schema = %schema, "Vues snapshot");
+            cli::cmd_snapshot(
+                date.as_deref(),
+                &schema,
+                &config,
+                materialized,
+                cli.connection,
+            )
+            .await?;
+        }
+        Some(Commands::Diff(args)) => {
+            info!(from = %args.from, to = %args.to, schema = %args.schema, "Diff entre millésimes");
+            cli::cmd_diff(args, cli.connection).await?;
+        }
+        Some(Commands::Lineage(args)) => {
+            info!(schema = %args.schema, table = %args.table, "Filiation des parcelles");
+            cli::cmd_lineage(args, cli.connection).await?;
+        }
         None => {
             // Commande par défaut: PostGIS
             let args = cli.postgis.expect("Arguments PostGIS requis (--path et --date)");
             info!(path = %args.path.display(), date = %args.date, "Export vers PostGIS");
-            cli::cmd_import(
-                &args.path,
-                &args.date,
-                &args.schema,
-                &args.config,
-                args.drop_schema,
-                args.drop_table,
-                args.skip_indexes,
-                args.srid,
-                args.precision,
-                args.dep,
-                args.host,
-                args.database,
-                args.user,
-                args.password,
-                args.port,
-                args.ssl,
-                args.jobs,
-            )
-            .await?;
+            cli::cmd_import(args, cli.connection).await?;
         }
     }
 
@@ -0,0 +1,279 @@
+//! Registre des systèmes de coordonnées du cadastre français
+//!
+//! Chaque EPSG que le parser GEO peut retourner est décrit par ses paramètres
+//! de projection, son ellipsoïde et son datum: translation géocentrique vers
+//! WGS84 pour les datums historiques des DOM, grille IGN pour la NTF. WGS84 (EPSG:4326) et Web Mercator
+//! (EPSG:3857) complètent le registre: toute conversion passe par les
+//! coordonnées géographiques WGS84.
+
+use super::ellipsoid::Ellipsoid;
+use super::lambert::LambertConic;
+use super::mercator;
+use super::ntf;
+use super::utm::TransverseMercator;
+use super::Geographic;
+use anyhow::Result;
+
+/// Projection d'un système de coordonnées
+#[derive(Debug, Clone, Copy)]
+pub enum Projection {
+    /// Conique conforme de Lambert (Lambert 93, CC 9 zones)
+    LambertConic(LambertConic),
+    /// Transverse Mercator (UTM des DOM)
+    TransverseMercator(TransverseMercator),
+    /// Coordonnées géographiques en degrés (longitude, latitude)
+    Geographic,
+    /// Web Mercator (sphère de rayon WGS84)
+    WebMercator,
+}
+
+impl Projection {
+    /// Ellipsoïde de référence
+    fn ellipsoid(&self) -> Ellipsoid {
+        match self {
+            Projection::LambertConic(p) => p.ellipsoid,
+            Projection::TransverseMercator(p) => p.ellipsoid,
+            Projection::Geographic | Projection::WebMercator => Ellipsoid::WGS84,
+        }
+    }
+}
+
+/// Système de coordonnées projetées
+#[derive(Debug, Clone, Copy)]
+pub struct Crs {
+    /// Code EPSG
+    pub epsg: u32,
+    /// Nom usuel (ex: "RGF93 / CC46")
+    pub name: &'static str,
+    /// Projection et ellipsoïde
+    pub projection: Projection,
+    /// Datum géodésique
+    pub datum: Datum,
+}
+
+/// Datum d'un système de coordonnées, par rapport à WGS84
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Datum {
+    /// Confondu avec WGS84 (RGF93, RGFG95, RGR92, RGM04)
+    Wgs84,
+    /// Translation géocentrique vers WGS84 (mètres)
+    Translation([f64; 3]),
+    /// NTF: grille IGN gr3df97a, translation moyenne à défaut (voir `ntf`)
+    Ntf,
+}
+
+/// Codes EPSG du registre
+pub const SUPPORTED: &[u32] = &[
+    2154, 3942, 3943, 3944, 3945, 3946, 3947, 3948, 3949, 3950, 2970, 2972, 2973, 2975, 32620,
+    32622, 32738, 32740, 27571, 27572, 27573, 27574, 4326, 3857,
+];
+
+/// Système de coordonnées d'un code EPSG, `None` s'il n'est pas supporté
+pub fn lookup(epsg: u32) -> Option<Crs> {
+    let utm = |zone, south, ellipsoid| {
+        Projection::TransverseMercator(TransverseMercator::utm(zone, south, ellipsoid))
+    };
+    let (name, projection, datum) = match epsg {
+        2154 => (
+            "RGF93 / Lambert-93",
+            Projection::LambertConic(LambertConic::lambert93()),
+            Datum::Wgs84,
+        ),
+        3942..=3950 => (
+            CC_NAMES[(epsg - 3942) as usize],
+            Projection::LambertConic(LambertConic::conic_conformal(epsg - 3900)),
+            Datum::Wgs84,
+        ),
+        2970 => (
+            "Guadeloupe 1948 / UTM zone 20N",
+            utm(20, false, Ellipsoid::INTERNATIONAL_1924),
+            Datum::Translation([-467.0, -16.0, -300.0]),
+        ),
+        2972 => (
+            "RGFG95 / UTM zone 22N",
+            utm(22, false, Ellipsoid::GRS80),
+            Datum::Wgs84,
+        ),
+        2973 => (
+            "Martinique 1938 / UTM zone 20N",
+            utm(20, false, Ellipsoid::INTERNATIONAL_1924),
+            Datum::Translation([186.0, 482.0, 151.0]),
+        ),
+        2975 => (
+            "RGR92 / UTM zone 40S",
+            utm(40, true, Ellipsoid::GRS80),
+            Datum::Wgs84,
+        ),
+        32620 => (
+            "WGS 84 / UTM zone 20N",
+            utm(20, false, Ellipsoid::WGS84),
+            Datum::Wgs84,
+        ),
+        32622 => (
+            "WGS 84 / UTM zone 22N",
+            utm(22, false, Ellipsoid::WGS84),
+            Datum::Wgs84,
+        ),
+        32738 => (
+            "WGS 84 / UTM zone 38S",
+            utm(38, true, Ellipsoid::WGS84),
+            Datum::Wgs84,
+        ),
+        32740 => (
+            "WGS 84 / UTM zone 40S",
+            utm(40, true, Ellipsoid::WGS84),
+            Datum::Wgs84,
+        ),
+        27571..=27574 => (
+            NTF_NAMES[(epsg - 27571) as usize],
+            Projection::LambertConic(LambertConic::ntf_zone(epsg - 27570)?),
+            Datum::Ntf,
+        ),
+        4326 => ("WGS 84", Projection::Geographic, Datum::Wgs84),
+        3857 => (
+            "WGS 84 / Pseudo-Mercator",
+            Projection::WebMercator,
+            Datum::Wgs84,
+        ),
+        _ => return None,
+    };
+
+    Some(Crs {
+        epsg,
+        name,
+        projection,
+        datum,
+    })
+}
+
+const NTF_NAMES: [&str; 4] = [
+    "NTF (Paris) / Lambert zone I",
+    "NTF (Paris) / Lambert zone II",
+    "NTF (Paris) / Lambert zone III",
+    "NTF (Paris) / Lambert zone IV",
+];
+
+const CC_NAMES: [&str; 9] = [
+    "RGF93 / CC42",
+    "RGF93 / CC43",
+    "RGF93 / CC44",
+    "RGF93 / CC45",
+    "RGF93 / CC46",
+    "RGF93 / CC47",
+    "RGF93 / CC48",
+    "RGF93 / CC49",
+    "RGF93 / CC50",
+];
+
+impl Crs {
+    /// Coordonnées géographiques WGS84 de coordonnées exprimées dans ce système
+    pub fn unproject(&self, x: f64, y: f64) -> Result<Geographic> {
+        let geo = match &self.projection {
+            Projection::LambertConic(p) => p.unproject(x, y)?,
+            Projection::TransverseMercator(p) => p.unproject(x, y)?,
+            Projection::Geographic => Geographic::from_degrees(x, y),
+            Projection::WebMercator => mercator::web_mercator_to_geographic(x, y)?,
+        };
+
+        let [dx, dy, dz] = match self.datum {
+            Datum::Wgs84 => return Ok(geo),
+            Datum::Translation(shift) => shift,
+            Datum::Ntf => return Ok(ntf::ntf_to_rgf93(ntf::grid(), geo)),
+        };
+        // Changement de datum: translation géocentrique
+        let [gx, gy, gz] = self.projection.ellipsoid().geocentric(geo.lon, geo.lat);
+        let (lon, lat) = Ellipsoid::WGS84.geodetic([gx + dx, gy + dy, gz + dz]);
+        Ok(Geographic::new(lon, lat))
+    }
+
+    /// Coordonnées dans ce système de coordonnées géographiques WGS84
+    pub fn project(&self, geo: Geographic) -> Result<(f64, f64)> {
+        let geo = match self.datum {
+            // Translation inverse vers le datum du système
+            Datum::Translation([dx, dy, dz]) => {
+                let [gx, gy, gz] = Ellipsoid::WGS84.geocentric(geo.lon, geo.lat);
+                let (lon, lat) = self
+                    .projection
+                    .ellipsoid()
+                    .geodetic([gx - dx, gy - dy, gz - dz]);
+                Geographic::new(lon, lat)
+            }
+            Datum::Ntf => ntf::rgf93_to_ntf(ntf::grid(), geo),
+            Datum::Wgs84 => geo,
+        };
+
+        match &self.projection {
+            Projection::LambertConic(p) => p.project(geo),
+            Projection::TransverseMercator(p) => p.project(geo),
+            Projection::Geographic => Ok(geo.to_degrees()),
+            Projection::WebMercator => mercator::geographic_to_web_mercator(geo),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_covers_supported() {
+        for &epsg in SUPPORTED {
+            let crs = lookup(epsg).unwrap();
+            assert_eq!(crs.epsg, epsg);
+        }
+        assert!(lookup(9999).is_none());
+        assert!(lookup(3951).is_none());
+    }
+
+    #[test]
+    fn test_datum_shift_martinique_1938() {
+        // Fort-de-France: le datum Martinique 1938 est décalé de quelques
+        // centaines de mètres par rapport à WGS84
+        let (x, y) = (708000.0, 1615000.0);
+        let legacy = lookup(2973).unwrap().unproject(x, y).unwrap();
+        let wgs84 = lookup(32620).unwrap().unproject(x, y).unwrap();
+
+        let (lon, lat) = legacy.to_degrees();
+        assert!((lon - (-61.07)).abs() < 0.2, "lon={}", lon);
+        assert!((lat - 14.60).abs() < 0.2, "lat={}", lat);
+
+        let shift_m = ((legacy.lon - wgs84.lon) * 6378137.0 * wgs84.lat.cos())
+            .hypot((legacy.lat - wgs84.lat) * 6378137.0);
+        assert!(shift_m > 100.0 && shift_m < 1000.0, "shift={}m", shift_m);
+    }
+
+    #[test]
+    fn test_geocentric_roundtrip() {
+        let (lon, lat) = (0.1_f64, 0.8_f64);
+        let xyz = Ellipsoid::GRS80.geocentric(lon, lat);
+        let (lon2, lat2) = Ellipsoid::GRS80.geodetic(xyz);
+        assert!((lon - lon2).abs() < 1e-12);
+        assert!((lat - lat2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_datum_shift_roundtrip() {
+        for epsg in [2970, 2973] {
+            let crs = lookup(epsg).unwrap();
+            let geo = crs.unproject(650000.0, 1800000.0).unwrap();
+            let (x, y) = crs.project(geo).unwrap();
+            // Hauteur ellipsoïdale non conservée: écart millimétrique
+            assert!((x - 650000.0).abs() < 1e-2, "EPSG:{} x={}", epsg, x);
+            assert!((y - 1800000.0).abs() < 1e-2, "EPSG:{} y={}", epsg, y);
+        }
+    }
+
+    #[test]
+    fn test_ntf_lambert_ii_etendu() {
+        // Paris (Notre-Dame) en Lambert II étendu, translation moyenne sans grille
+        let crs = lookup(27572).unwrap();
+        let (lon, lat) = crs.unproject(601000.0, 2428000.0).unwrap().to_degrees();
+        assert!((lon - 2.35).abs() < 0.02, "lon={}", lon);
+        assert!((lat - 48.85).abs() < 0.02, "lat={}", lat);
+
+        let geo = Geographic::from_degrees(lon, lat);
+        let (x, y) = crs.project(geo).unwrap();
+        assert!((x - 601000.0).abs() < 1e-2, "x={}", x);
+        assert!((y - 2428000.0).abs() < 1e-2, "y={}", y);
+    }
+}
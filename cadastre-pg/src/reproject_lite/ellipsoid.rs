@@ -9,18 +9,6 @@ impl WGS84 {
 
     /// Aplatissement
     pub const F: f64 = 1.0 / 298.257223563;
-
-    /// Demi-petit axe (rayon polaire) en mètres
-    pub const B: f64 = Self::A * (1.0 - Self::F);
-
-    /// Première excentricité au carré
-    pub const E2: f64 = 2.0 * Self::F - Self::F * Self::F;
-
-    /// Première excentricité
-    pub const E: f64 = 0.0818191908426215; // sqrt(E2)
-
-    /// Deuxième excentricité au carré
-    pub const EP2: f64 = Self::E2 / (1.0 - Self::E2);
 }
 
 /// Ellipsoïde GRS80 (utilisé par Lambert 93)
@@ -30,6 +18,94 @@ pub struct GRS80;
 impl GRS80 {
     pub const A: f64 = 6378137.0;
     pub const F: f64 = 1.0 / 298.257222101;
-    pub const E2: f64 = 2.0 * Self::F - Self::F * Self::F;
-    pub const E: f64 = 0.0818191910428158; // sqrt(E2)
+}
+
+/// Ellipsoïde de Hayford 1909 (International 1924), datums DOM historiques
+pub struct International1924;
+
+impl International1924 {
+    pub const A: f64 = 6378388.0;
+    pub const F: f64 = 1.0 / 297.0;
+}
+
+/// Ellipsoïde de Clarke 1880 IGN (NTF)
+pub struct Clarke1880Ign;
+
+impl Clarke1880Ign {
+    pub const A: f64 = 6378249.2;
+    pub const B: f64 = 6356515.0;
+    pub const F: f64 = (Self::A - Self::B) / Self::A;
+}
+
+/// Paramètres d'un ellipsoïde, pour les projections paramétrées
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ellipsoid {
+    /// Demi-grand axe en mètres
+    pub a: f64,
+    /// Aplatissement
+    pub f: f64,
+}
+
+impl Ellipsoid {
+    pub const WGS84: Self = Self {
+        a: WGS84::A,
+        f: WGS84::F,
+    };
+    pub const GRS80: Self = Self {
+        a: GRS80::A,
+        f: GRS80::F,
+    };
+    pub const INTERNATIONAL_1924: Self = Self {
+        a: International1924::A,
+        f: International1924::F,
+    };
+    pub const CLARKE_1880_IGN: Self = Self {
+        a: Clarke1880Ign::A,
+        f: Clarke1880Ign::F,
+    };
+
+    /// Première excentricité au carré
+    pub fn e2(&self) -> f64 {
+        self.f * (2.0 - self.f)
+    }
+
+    /// Première excentricité
+    pub fn e(&self) -> f64 {
+        self.e2().sqrt()
+    }
+
+    /// Deuxième excentricité au carré
+    pub fn ep2(&self) -> f64 {
+        self.e2() / (1.0 - self.e2())
+    }
+
+    /// Coordonnées géocentriques (X, Y, Z) d'un point à hauteur nulle
+    pub fn geocentric(&self, lon: f64, lat: f64) -> [f64; 3] {
+        let e2 = self.e2();
+        let n = self.a / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+        [
+            n * lat.cos() * lon.cos(),
+            n * lat.cos() * lon.sin(),
+            n * (1.0 - e2) * lat.sin(),
+        ]
+    }
+
+    /// Longitude et latitude (radians) de coordonnées géocentriques
+    pub fn geodetic(&self, [x, y, z]: [f64; 3]) -> (f64, f64) {
+        let e2 = self.e2();
+        let p = (x * x + y * y).sqrt();
+        let mut lat = (z / (p * (1.0 - e2))).atan();
+
+        for _ in 0..10 {
+            let n = self.a / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+            let h = p / lat.cos() - n;
+            let new_lat = (z / (p * (1.0 - e2 * n / (n + h)))).atan();
+            if (new_lat - lat).abs() < 1e-12 {
+                lat = new_lat;
+                break;
+            }
+            lat = new_lat;
+        }
+        (y.atan2(x), lat)
+    }
 }
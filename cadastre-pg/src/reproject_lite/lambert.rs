@@ -1,38 +1,166 @@
-//! Projection Lambert 93 (EPSG:2154)
+//! Projections coniques conformes de Lambert
 //!
-//! Lambert Conformal Conic avec 2 parallèles standards
+//! Lambert Conformal Conic avec 2 parallèles standards : Lambert 93 (EPSG:2154),
+//! coniques conformes 9 zones (EPSG:3942 à 3950), et tangente avec facteur
+//! d'échelle : Lambert NTF (EPSG:27571 à 27574)
 
-use super::ellipsoid::GRS80;
+use super::ellipsoid::Ellipsoid;
 use super::Geographic;
 use anyhow::Result;
 
-/// Paramètres Lambert 93 (EPSG:2154)
-struct Lambert93 {
-    /// Longitude origine (méridien de Paris en RGF93 = Greenwich)
-    lon0: f64,
+/// Longitude du méridien de Paris (degrés, depuis Greenwich)
+const PARIS_MERIDIAN: f64 = 2.337229166667;
+
+/// Paramètres d'une projection conique conforme de Lambert
+///
+/// Sécante (2SP) quand `lat1 != lat2`, tangente au parallèle `lat1` avec le
+/// facteur d'échelle `k0` (1SP) quand `lat1 == lat2 == lat0`.
+#[derive(Debug, Clone, Copy)]
+pub struct LambertConic {
+    /// Longitude origine
+    pub lon0: f64,
     /// Latitude origine
-    lat0: f64,
+    pub lat0: f64,
     /// Premier parallèle standard
-    lat1: f64,
+    pub lat1: f64,
     /// Deuxième parallèle standard
-    lat2: f64,
+    pub lat2: f64,
+    /// Facteur d'échelle sur le premier parallèle (1 pour une sécante)
+    pub k0: f64,
     /// False easting
-    x0: f64,
+    pub x0: f64,
     /// False northing
-    y0: f64,
+    pub y0: f64,
+    /// Ellipsoïde de référence
+    pub ellipsoid: Ellipsoid,
 }
 
-impl Default for Lambert93 {
-    fn default() -> Self {
+impl LambertConic {
+    /// Lambert 93 (EPSG:2154)
+    pub fn lambert93() -> Self {
+        Self {
+            lon0: 3.0_f64.to_radians(),  // 3°E
+            lat0: 46.5_f64.to_radians(), // 46.5°N
+            lat1: 44.0_f64.to_radians(), // 44°N
+            lat2: 49.0_f64.to_radians(), // 49°N
+            k0: 1.0,
+            x0: 700000.0,  // False easting
+            y0: 6600000.0, // False northing
+            ellipsoid: Ellipsoid::GRS80,
+        }
+    }
+
+    /// Conique conforme 9 zones CC42 à CC50 (EPSG:3942 à 3950)
+    ///
+    /// Zone centrée sur la latitude `zone`, parallèles à ±0.75°.
+    pub fn conic_conformal(zone: u32) -> Self {
+        let lat0 = zone as f64;
         Self {
-            lon0: 3.0_f64.to_radians(),          // 3°E
-            lat0: 46.5_f64.to_radians(),         // 46.5°N
-            lat1: 44.0_f64.to_radians(),         // 44°N
-            lat2: 49.0_f64.to_radians(),         // 49°N
-            x0: 700000.0,                        // False easting
-            y0: 6600000.0,                       // False northing
+            lon0: 3.0_f64.to_radians(),
+            lat0: lat0.to_radians(),
+            lat1: (lat0 - 0.75).to_radians(),
+            lat2: (lat0 + 0.75).to_radians(),
+            k0: 1.0,
+            x0: 1700000.0,
+            y0: 1200000.0 + (zone as f64 - 42.0) * 1000000.0,
+            ellipsoid: Ellipsoid::GRS80,
         }
     }
+
+    /// Lambert zones I à IV « carto » en NTF (EPSG:27571 à 27574)
+    ///
+    /// La zone II est le Lambert II étendu. Longitudes comptées depuis le
+    /// méridien de Greenwich (méridien de Paris: 2°20'14.025"E). Les zones
+    /// sont définies par l'IGN en tangente (latitude origine en grades et
+    /// facteur d'échelle): les parallèles sécants arrondis au millième de
+    /// seconde décalent les coordonnées d'environ un centimètre.
+    pub fn ntf_zone(zone: u32) -> Option<Self> {
+        let (grades, k0, x0, y0) = match zone {
+            1 => (55.0, 0.99987734, 600000.0, 1200000.0),
+            2 => (52.0, 0.99987742, 600000.0, 2200000.0),
+            3 => (49.0, 0.99987750, 600000.0, 3200000.0),
+            4 => (46.85, 0.99994471, 234.358, 4185861.369),
+            _ => return None,
+        };
+        let lat0 = f64::to_radians(grades * 0.9);
+        Some(Self {
+            lon0: PARIS_MERIDIAN.to_radians(),
+            lat0,
+            lat1: lat0,
+            lat2: lat0,
+            k0,
+            x0,
+            y0,
+            ellipsoid: Ellipsoid::CLARKE_1880_IGN,
+        })
+    }
+
+    /// Constantes de la projection: exposant n, constante C et rayon à l'origine
+    fn constants(&self) -> (f64, f64, f64) {
+        let e = self.ellipsoid.e();
+        let e2 = self.ellipsoid.e2();
+        let a = self.ellipsoid.a;
+
+        let n1 = grande_normale(self.lat1, a, e2);
+        let n2 = grande_normale(self.lat2, a, e2);
+
+        let iso_lat1 = isometric_latitude(self.lat1, e);
+        let iso_lat2 = isometric_latitude(self.lat2, e);
+        let iso_lat0 = isometric_latitude(self.lat0, e);
+
+        // Exposant de la projection
+        let n = if self.lat1 == self.lat2 {
+            self.lat1.sin()
+        } else {
+            let n = (n1 * self.lat1.cos()).ln() - (n2 * self.lat2.cos()).ln();
+            n / (iso_lat2 - iso_lat1)
+        };
+
+        // Constante C
+        let c = self.k0 * (n1 * self.lat1.cos() / n) * (n * iso_lat1).exp();
+
+        // Rayon à l'origine
+        let r0 = c * (-n * iso_lat0).exp();
+
+        (n, c, r0)
+    }
+
+    /// Convertit des coordonnées projetées en coordonnées géographiques
+    pub fn unproject(&self, x: f64, y: f64) -> Result<Geographic> {
+        let (n, c, r0) = self.constants();
+
+        // Coordonnées centrées
+        let dx = x - self.x0;
+        let dy = y - self.y0;
+
+        // Rayon et angle
+        let r = (dx.powi(2) + (r0 - dy).powi(2)).sqrt();
+        let r = if n < 0.0 { -r } else { r };
+
+        let gamma = (dx / (r0 - dy)).atan();
+
+        // Latitude isométrique
+        let iso_lat = -(r / c).ln() / n;
+
+        // Latitude géographique
+        let lat = latitude_from_isometric(iso_lat, self.ellipsoid.e());
+
+        // Longitude
+        let lon = self.lon0 + gamma / n;
+
+        Ok(Geographic::new(lon, lat))
+    }
+
+    /// Convertit des coordonnées géographiques en coordonnées projetées
+    pub fn project(&self, geo: Geographic) -> Result<(f64, f64)> {
+        let (n, c, r0) = self.constants();
+
+        // Rayon et angle du parallèle et du méridien
+        let r = c * (-n * isometric_latitude(geo.lat, self.ellipsoid.e())).exp();
+        let gamma = n * (geo.lon - self.lon0);
+
+        Ok((self.x0 + r * gamma.sin(), self.y0 + r0 - r * gamma.cos()))
+    }
 }
 
 /// Calcule la latitude isométrique
@@ -64,53 +192,6 @@ fn grande_normale(lat: f64, a: f64, e2: f64) -> f64 {
     a / (1.0 - e2 * lat.sin().powi(2)).sqrt()
 }
 
-/// Convertit Lambert 93 vers coordonnées géographiques WGS84
-pub fn lambert93_to_geographic(x: f64, y: f64) -> Result<Geographic> {
-    let params = Lambert93::default();
-    let e = GRS80::E;
-    let e2 = GRS80::E2;
-    let a = GRS80::A;
-
-    // Calcul des constantes de la projection
-    let n1 = grande_normale(params.lat1, a, e2);
-    let n2 = grande_normale(params.lat2, a, e2);
-
-    let iso_lat1 = isometric_latitude(params.lat1, e);
-    let iso_lat2 = isometric_latitude(params.lat2, e);
-    let iso_lat0 = isometric_latitude(params.lat0, e);
-
-    // Exposant de la projection
-    let n = (n1 * params.lat1.cos()).ln() - (n2 * params.lat2.cos()).ln();
-    let n = n / (iso_lat2 - iso_lat1);
-
-    // Constante C
-    let c = (n1 * params.lat1.cos() / n) * (n * iso_lat1).exp();
-
-    // Rayon à l'origine
-    let r0 = c * (-n * iso_lat0).exp();
-
-    // Coordonnées centrées
-    let dx = x - params.x0;
-    let dy = y - params.y0;
-
-    // Rayon et angle
-    let r = (dx.powi(2) + (r0 - dy).powi(2)).sqrt();
-    let r = if n < 0.0 { -r } else { r };
-
-    let gamma = (dx / (r0 - dy)).atan();
-
-    // Latitude isométrique
-    let iso_lat = -(r / c).ln() / n;
-
-    // Latitude géographique
-    let lat = latitude_from_isometric(iso_lat, e);
-
-    // Longitude
-    let lon = params.lon0 + gamma / n;
-
-    Ok(Geographic::new(lon, lat))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -118,7 +199,9 @@ mod tests {
     #[test]
     fn test_paris() {
         // Tour Eiffel approximativement
-        let geo = lambert93_to_geographic(648237.0, 6862107.0).unwrap();
+        let geo = LambertConic::lambert93()
+            .unproject(648237.0, 6862107.0)
+            .unwrap();
         let (lon, lat) = geo.to_degrees();
 
         // Tour Eiffel: 2.2945°E, 48.8584°N
@@ -129,11 +212,168 @@ mod tests {
     #[test]
     fn test_marseille() {
         // Vieux-Port approximativement
-        let geo = lambert93_to_geographic(893193.0, 6245829.0).unwrap();
+        let geo = LambertConic::lambert93()
+            .unproject(893193.0, 6245829.0)
+            .unwrap();
         let (lon, lat) = geo.to_degrees();
 
         // Marseille: 5.37°E, 43.30°N
         assert!((lon - 5.37).abs() < 0.1, "lon={}", lon);
         assert!((lat - 43.30).abs() < 0.1, "lat={}", lat);
     }
+
+    #[test]
+    fn test_conic_conformal_origins() {
+        for zone in 42..=50 {
+            let cc = LambertConic::conic_conformal(zone);
+            let (lon, lat) = cc.unproject(cc.x0, cc.y0).unwrap().to_degrees();
+            assert!((lon - 3.0).abs() < 1e-9, "CC{}: lon={}", zone, lon);
+            assert!((lat - zone as f64).abs() < 1e-9, "CC{}: lat={}", zone, lat);
+        }
+    }
+
+    #[test]
+    fn test_lyon_cc46() {
+        // Lyon (place Bellecour) en CC46
+        let geo = LambertConic::conic_conformal(46)
+            .unproject(1842297.0, 5174470.0)
+            .unwrap();
+        let (lon, lat) = geo.to_degrees();
+
+        // Lyon: 4.83°E, 45.76°N
+        assert!((lon - 4.83).abs() < 0.05, "lon={}", lon);
+        assert!((lat - 45.76).abs() < 0.05, "lat={}", lat);
+    }
+
+    #[test]
+    fn test_forward_origins() {
+        // Origines publiées par l'IGN: (3°E, 46.5°N) en Lambert 93, (3°E, 44°N) en CC44
+        let l93 = LambertConic::lambert93();
+        let (x, y) = l93.project(Geographic::from_degrees(3.0, 46.5)).unwrap();
+        assert!((x - 700000.0).abs() < 1e-6 && (y - 6600000.0).abs() < 1e-6);
+
+        let cc44 = LambertConic::conic_conformal(44);
+        let (x, y) = cc44.project(Geographic::from_degrees(3.0, 44.0)).unwrap();
+        assert!((x - 1700000.0).abs() < 1e-6 && (y - 3200000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let l93 = LambertConic::lambert93();
+        for &(x, y) in &[
+            (648237.0, 6862107.0),
+            (893193.0, 6245829.0),
+            (1200000.0, 7100000.0),
+        ] {
+            let geo = l93.unproject(x, y).unwrap();
+            let (x2, y2) = l93.project(geo).unwrap();
+            assert!((x - x2).abs() < 1e-4, "x={} x2={}", x, x2);
+            assert!((y - y2).abs() < 1e-4, "y={} y2={}", y, y2);
+        }
+    }
+
+    #[test]
+    fn test_ntf_zone_origins() {
+        // Lambert II étendu: origine au méridien de Paris, 46.8°N (52 grades)
+        let zone2 = LambertConic::ntf_zone(2).unwrap();
+        let (lon, lat) = zone2.unproject(600000.0, 2200000.0).unwrap().to_degrees();
+        assert!((lon - PARIS_MERIDIAN).abs() < 1e-9, "lon={}", lon);
+        assert!((lat - 46.8).abs() < 1e-9, "lat={}", lat);
+        assert!(LambertConic::ntf_zone(5).is_none());
+    }
+
+    #[test]
+    fn test_ign_constants() {
+        // Constantes publiées par l'IGN (NT/G 71 et fiches des projections):
+        // exposant n, constante C et ordonnée du pôle Ys = Y0 + R0
+        let zones = [
+            (
+                LambertConic::lambert93(),
+                0.7256077650,
+                11754255.426,
+                12655612.050,
+            ),
+            (
+                LambertConic::ntf_zone(1).unwrap(),
+                0.7604059656,
+                11603796.98,
+                6657616.674,
+            ),
+            (
+                LambertConic::ntf_zone(2).unwrap(),
+                0.7289686274,
+                11745793.39,
+                8199695.768,
+            ),
+            (
+                LambertConic::ntf_zone(3).unwrap(),
+                0.6959127966,
+                11947992.52,
+                9791905.085,
+            ),
+            (
+                LambertConic::ntf_zone(4).unwrap(),
+                0.6712679322,
+                12136281.99,
+                11239161.542,
+            ),
+        ];
+        for (proj, n_ref, c_ref, ys_ref) in zones {
+            let (n, c, r0) = proj.constants();
+            assert!((n - n_ref).abs() < 1e-9, "n={} attendu {}", n, n_ref);
+            assert!((c - c_ref).abs() < 1e-2, "C={} attendu {}", c, c_ref);
+            let ys = proj.y0 + r0;
+            assert!((ys - ys_ref).abs() < 1e-2, "Ys={} attendu {}", ys, ys_ref);
+        }
+    }
+
+    #[test]
+    fn test_ign_alg0003_alg0004() {
+        // Jeu d'essai des algorithmes ALG0003/ALG0004 de la note IGN NT/G 71:
+        // Lambert I (Ys = 5 657 616.674, soit le Lambert I « carto » moins
+        // 1 000 000 m), λ = 0.145512099 rad et φ = 0.872664626 rad depuis
+        // Greenwich, X = 1 029 705.083 m, Y = 272 723.849 m
+        let zone1 = LambertConic::ntf_zone(1).unwrap();
+        let geo = Geographic::new(0.145512099, 0.872664626);
+        let (x, y) = zone1.project(geo).unwrap();
+        assert!((x - 1029705.083).abs() < 1e-2, "x={}", x);
+        assert!((y - 1000000.0 - 272723.849).abs() < 1e-2, "y={}", y);
+
+        let back = zone1.unproject(1029705.083, 1272723.849).unwrap();
+        // 1e-9 rad ≈ 6 mm au sol
+        assert!((back.lon - 0.145512099).abs() < 1e-9, "lon={}", back.lon);
+        assert!((back.lat - 0.872664626).abs() < 1e-9, "lat={}", back.lat);
+    }
+
+    #[test]
+    fn test_epsg_guidance_note_lcc_2sp() {
+        // Exemple de la note EPSG 7-2 (NAD27 / Texas South Central, pieds US):
+        // φ = 28°30'N, λ = 96°W → E = 2 963 503.91 ftUS, N = 254 759.80 ftUS
+        const US_FOOT: f64 = 1200.0 / 3937.0;
+        let texas = LambertConic {
+            lon0: (-99.0_f64).to_radians(),
+            lat0: (27.0_f64 + 50.0 / 60.0).to_radians(),
+            lat1: (28.0_f64 + 23.0 / 60.0).to_radians(),
+            lat2: (30.0_f64 + 17.0 / 60.0).to_radians(),
+            k0: 1.0,
+            x0: 2000000.0 * US_FOOT,
+            y0: 0.0,
+            ellipsoid: Ellipsoid {
+                a: 6378206.4,
+                f: 1.0 / 294.9786982,
+            },
+        };
+        let (x, y) = texas
+            .project(Geographic::from_degrees(-96.0, 28.5))
+            .unwrap();
+        assert!((x - 2963503.91 * US_FOOT).abs() < 1e-2, "x={}", x / US_FOOT);
+        assert!((y - 254759.80 * US_FOOT).abs() < 1e-2, "y={}", y / US_FOOT);
+
+        let (lon, lat) = texas
+            .unproject(2963503.91 * US_FOOT, 254759.80 * US_FOOT)
+            .unwrap()
+            .to_degrees();
+        assert!((lon + 96.0).abs() < 1e-7, "lon={}", lon);
+        assert!((lat - 28.5).abs() < 1e-7, "lat={}", lat);
+    }
 }
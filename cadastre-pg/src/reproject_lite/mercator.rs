@@ -25,7 +25,6 @@ pub fn geographic_to_web_mercator(geo: Geographic) -> Result<(f64, f64)> {
 }
 
 /// Convertit Web Mercator vers coordonnées géographiques
-#[allow(dead_code)]
 pub fn web_mercator_to_geographic(x: f64, y: f64) -> Result<Geographic> {
     let r = WGS84::A;
 
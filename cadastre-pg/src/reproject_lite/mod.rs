@@ -1,19 +1,22 @@
 //! Reprojection légère en Rust pur (sans dépendances externes)
 //!
-//! Supporte les projections du cadastre français :
+//! Supporte toutes les projections du cadastre français (voir `crs`) :
 //! - Lambert 93 (EPSG:2154) - Métropole
-//! - UTM 20N (EPSG:32620) - Martinique, Guadeloupe
-//! - UTM 22N (EPSG:32622) - Guyane
-//! - UTM 40S (EPSG:32740) - Réunion
+//! - Coniques conformes CC42 à CC50 (EPSG:3942 à 3950) - Métropole
+//! - UTM 20N (EPSG:2970, 2973, 32620) - Guadeloupe, Martinique
+//! - UTM 22N (EPSG:2972, 32622) - Guyane
+//! - UTM 40S (EPSG:2975, 32740) - Réunion
 //! - UTM 38S (EPSG:32738) - Mayotte
+//! - Lambert I à IV et II étendu NTF (EPSG:27571 à 27574) - données anciennes
 //!
-//! Cibles supportées :
-//! - WGS84 (EPSG:4326)
-//! - Web Mercator (EPSG:3857)
+//! Ainsi que WGS84 (EPSG:4326) et Web Mercator (EPSG:3857). Chacun de ces
+//! systèmes peut servir de source comme de cible.
 
+pub mod crs;
 mod ellipsoid;
 mod lambert;
 mod mercator;
+pub mod ntf;
 mod smart;
 mod utm;
 
@@ -53,42 +56,57 @@ impl Geographic {
 }
 
 /// Reprojection légère pour le cadastre français
+///
+/// Toute conversion passe par les coordonnées géographiques WGS84: source →
+/// WGS84 (projection inverse) puis WGS84 → cible (projection directe).
 pub struct ReprojectorLite {
-    source_epsg: u32,
-    target_epsg: u32,
+    source: crs::Crs,
+    target: crs::Crs,
 }
 
 impl ReprojectorLite {
     /// Crée un nouveau reprojector
     pub fn new(source_epsg: u32, target_epsg: u32) -> Result<Self> {
+        if source_epsg == target_epsg {
+            bail!("EPSG:{} : source et cible identiques", source_epsg);
+        }
         // Vérifier que les EPSG sont supportés
-        if !Self::is_supported_source(source_epsg) {
-            bail!(
-                "EPSG:{} non supporté. Sources supportées: 2154, 32620, 32622, 32738, 32740",
+        let (Some(source), Some(target)) = (crs::lookup(source_epsg), crs::lookup(target_epsg))
+        else {
+            let unsupported = if Self::is_supported_source(source_epsg) {
+                target_epsg
+            } else {
                 source_epsg
-            );
-        }
-        if !Self::is_supported_target(target_epsg) {
+            };
             bail!(
-                "EPSG:{} non supporté. Cibles supportées: 4326, 3857",
-                target_epsg
+                "EPSG:{} non supporté. Projections supportées: {}",
+                unsupported,
+                crs::SUPPORTED
+                    .iter()
+                    .filter_map(|&epsg| crs::lookup(epsg))
+                    .map(|crs| format!("{} ({})", crs.epsg, crs.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
             );
+        };
+        // La NTF n'est reprojetée au centimètre qu'avec la grille IGN
+        for crs in [&source, &target] {
+            if crs.datum == crs::Datum::Ntf {
+                ntf::require_grid(crs)?;
+            }
         }
 
-        Ok(Self {
-            source_epsg,
-            target_epsg,
-        })
+        Ok(Self { source, target })
     }
 
     /// Vérifie si l'EPSG source est supporté
     pub fn is_supported_source(epsg: u32) -> bool {
-        matches!(epsg, 2154 | 32620 | 32622 | 32738 | 32740)
+        crs::lookup(epsg).is_some()
     }
 
     /// Vérifie si l'EPSG cible est supporté
     pub fn is_supported_target(epsg: u32) -> bool {
-        matches!(epsg, 4326 | 3857)
+        crs::lookup(epsg).is_some()
     }
 
     /// Vérifie si la reprojection est supportée
@@ -99,34 +117,10 @@ impl ReprojectorLite {
     /// Transforme un point (x, y) de la source vers la cible
     pub fn transform_point(&self, x: f64, y: f64) -> Result<(f64, f64)> {
         // Étape 1: Source → Géographique (WGS84)
-        let geo = self.source_to_geographic(x, y)?;
+        let geo = self.source.unproject(x, y)?;
 
         // Étape 2: Géographique → Cible
-        self.geographic_to_target(geo)
-    }
-
-    /// Convertit les coordonnées source en géographique (WGS84)
-    fn source_to_geographic(&self, x: f64, y: f64) -> Result<Geographic> {
-        match self.source_epsg {
-            2154 => lambert::lambert93_to_geographic(x, y),
-            32620 => utm::utm_to_geographic(x, y, 20, false),
-            32622 => utm::utm_to_geographic(x, y, 22, false),
-            32738 => utm::utm_to_geographic(x, y, 38, true),
-            32740 => utm::utm_to_geographic(x, y, 40, true),
-            _ => bail!("EPSG:{} non supporté", self.source_epsg),
-        }
-    }
-
-    /// Convertit les coordonnées géographiques vers la cible
-    fn geographic_to_target(&self, geo: Geographic) -> Result<(f64, f64)> {
-        match self.target_epsg {
-            4326 => {
-                let (lon, lat) = geo.to_degrees();
-                Ok((lon, lat))
-            }
-            3857 => mercator::geographic_to_web_mercator(geo),
-            _ => bail!("EPSG:{} non supporté", self.target_epsg),
-        }
+        self.target.project(geo)
     }
 
     /// Transforme une géométrie
@@ -261,9 +255,56 @@ mod tests {
         assert!((lat - 14.6).abs() < 0.5, "lat={}", lat);
     }
 
+    #[test]
+    fn test_all_parser_projections_supported() {
+        // Projections retournées par le parser GEO d'edigeo
+        for epsg in [
+            2154, 3942, 3943, 3944, 3945, 3946, 3947, 3948, 3949, 3950, 2970, 2972, 2973, 2975,
+            32738,
+        ] {
+            assert!(ReprojectorLite::is_supported(epsg, 4326), "EPSG:{}", epsg);
+        }
+    }
+
+    #[test]
+    fn test_cc_to_wgs84() {
+        // Origine de la zone CC49: 3°E, 49°N
+        let reproj = ReprojectorLite::new(3949, 4326).unwrap();
+        let (lon, lat) = reproj.transform_point(1700000.0, 8200000.0).unwrap();
+
+        assert!((lon - 3.0).abs() < 1e-9, "lon={}", lon);
+        assert!((lat - 49.0).abs() < 1e-9, "lat={}", lat);
+    }
+
+    #[test]
+    fn test_projected_targets() {
+        // Paris: Lambert 93 → WGS84 → Lambert 93, et Lambert 93 → CC49 → Lambert 93
+        let (x, y) = (652381.0, 6862047.0);
+        for epsg in [4326, 3857, 3949] {
+            let to = ReprojectorLite::new(2154, epsg).unwrap();
+            let back = ReprojectorLite::new(epsg, 2154).unwrap();
+            let (tx, ty) = to.transform_point(x, y).unwrap();
+            let (x2, y2) = back.transform_point(tx, ty).unwrap();
+            assert!((x - x2).abs() < 1e-3, "EPSG:{} x2={}", epsg, x2);
+            assert!((y - y2).abs() < 1e-3, "EPSG:{} y2={}", epsg, y2);
+        }
+
+        // Origine de Lambert 93 depuis WGS84
+        let reproj = ReprojectorLite::new(4326, 2154).unwrap();
+        let (x, y) = reproj.transform_point(3.0, 46.5).unwrap();
+        assert!((x - 700000.0).abs() < 1e-6, "x={}", x);
+        assert!((y - 6600000.0).abs() < 1e-6, "y={}", y);
+
+        // DOM vers Lambert 93 (hors zone, mais calculable)
+        assert!(ReprojectorLite::new(2975, 2154).is_ok());
+    }
+
     #[test]
     fn test_unsupported_epsg() {
         assert!(ReprojectorLite::new(4326, 4326).is_err());
         assert!(ReprojectorLite::new(2154, 2154).is_err());
+        let err = ReprojectorLite::new(2154, 9999).err().unwrap().to_string();
+        assert!(err.contains("EPSG:9999"), "{}", err);
+        assert!(err.contains("2154 (RGF93 / Lambert-93)"), "{}", err);
     }
 }
@@ -0,0 +1,278 @@
+//! Transformation NTF → RGF93 (grille IGN gr3df97a)
+//!
+//! La grille donne, tous les 0.1°, la translation géocentrique de la NTF vers
+//! le RGF93 (précision centimétrique). Elle est lue depuis le fichier texte
+//! `gr3df97a.txt` passé à [`load_grid`] (option `--ntf-grid`) ou, à défaut,
+//! désigné par la variable d'environnement `NTF_GRID`. La reprojection d'un
+//! système NTF exige la grille (voir [`require_grid`]); hors de son emprise,
+//! la translation moyenne (Helmert) est utilisée (précision métrique).
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use anyhow::{bail, Context, Result};
+use tracing::{info, warn};
+
+use super::crs::Crs;
+use super::ellipsoid::Ellipsoid;
+use super::Geographic;
+
+/// Translation moyenne NTF → RGF93 (mètres)
+pub const HELMERT: [f64; 3] = [-168.0, -60.0, 320.0];
+
+/// Variable d'environnement du chemin de la grille
+pub const GRID_ENV: &str = "NTF_GRID";
+
+/// Grille de translations NTF → RGF93, indexée en coordonnées RGF93 (degrés)
+#[derive(Debug)]
+pub struct Grid {
+    lon_min: f64,
+    lat_min: f64,
+    step_lon: f64,
+    step_lat: f64,
+    cols: usize,
+    rows: usize,
+    /// Translations des nœuds, ligne par ligne (latitude croissante)
+    nodes: Vec<Option<[f64; 3]>>,
+}
+
+impl Grid {
+    /// Lit un fichier au format IGN (`gr3df97a.txt`)
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read NTF grid {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid NTF grid {}", path.display()))
+    }
+
+    /// Parse le contenu d'une grille IGN
+    ///
+    /// L'en-tête `GR3D1` donne l'emprise et le pas; chaque nœud est une ligne
+    /// `numéro longitude latitude tx ty tz précision [feuille]`.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut grid: Option<Self> = None;
+
+        for line in content.lines() {
+            let mut fields = line.split_whitespace();
+            let Some(first) = fields.next() else {
+                continue;
+            };
+            let numbers: Vec<f64> = fields.filter_map(|f| f.parse().ok()).collect();
+
+            if first == "GR3D1" {
+                let [lon_min, lon_max, lat_min, lat_max, step_lon, step_lat] = numbers[..] else {
+                    bail!("malformed GR3D1 header: {:?}", line);
+                };
+                let cols = ((lon_max - lon_min) / step_lon).round() as usize + 1;
+                let rows = ((lat_max - lat_min) / step_lat).round() as usize + 1;
+                grid = Some(Self {
+                    lon_min,
+                    lat_min,
+                    step_lon,
+                    step_lat,
+                    cols,
+                    rows,
+                    nodes: vec![None; cols * rows],
+                });
+                continue;
+            }
+            if first.starts_with("GR3D") {
+                continue;
+            }
+
+            let Some(grid) = grid.as_mut() else {
+                bail!("node before GR3D1 header");
+            };
+            let [lon, lat, tx, ty, tz, ..] = numbers[..] else {
+                bail!("malformed node: {:?}", line);
+            };
+            let col = ((lon - grid.lon_min) / grid.step_lon).round();
+            let row = ((lat - grid.lat_min) / grid.step_lat).round();
+            if col < 0.0 || row < 0.0 || col as usize >= grid.cols || row as usize >= grid.rows {
+                bail!("node outside of the grid: {:?}", line);
+            }
+            grid.nodes[row as usize * grid.cols + col as usize] = Some([tx, ty, tz]);
+        }
+
+        grid.context("missing GR3D1 header")
+    }
+
+    /// Translation interpolée (bilinéaire) en un point RGF93 (degrés),
+    /// `None` hors de la grille
+    pub fn translation(&self, lon: f64, lat: f64) -> Option<[f64; 3]> {
+        let x = (lon - self.lon_min) / self.step_lon;
+        let y = (lat - self.lat_min) / self.step_lat;
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        // Le dernier nœud de chaque axe est interpolé avec son voisin
+        let col = (x.floor() as usize).min(self.cols.checked_sub(2)?);
+        let row = (y.floor() as usize).min(self.rows.checked_sub(2)?);
+        let (fx, fy) = (x - col as f64, y - row as f64);
+        // Tolérance d'arrondi sur les bords est et nord
+        if fx > 1.0 + 1e-9 || fy > 1.0 + 1e-9 {
+            return None;
+        }
+        let (fx, fy) = (fx.min(1.0), fy.min(1.0));
+
+        let node = |c: usize, r: usize| self.nodes[r * self.cols + c];
+        let (t00, t10) = (node(col, row)?, node(col + 1, row)?);
+        let (t01, t11) = (node(col, row + 1)?, node(col + 1, row + 1)?);
+
+        Some(std::array::from_fn(|i| {
+            (1.0 - fx) * (1.0 - fy) * t00[i]
+                + fx * (1.0 - fy) * t10[i]
+                + (1.0 - fx) * fy * t01[i]
+                + fx * fy * t11[i]
+        }))
+    }
+}
+
+/// Grille du processus, chargée une seule fois
+static GRID: OnceLock<Option<Grid>> = OnceLock::new();
+
+/// Charge la grille du processus depuis `path` (prioritaire sur `NTF_GRID`)
+pub fn load_grid(path: &Path) -> Result<&'static Grid> {
+    let loaded = Grid::load(path)?;
+    if GRID.set(Some(loaded)).is_err() {
+        bail!(
+            "NTF grid already initialized, cannot load {}",
+            path.display()
+        );
+    }
+    info!("NTF grid loaded from {}", path.display());
+    Ok(grid().expect("NTF grid just loaded"))
+}
+
+/// Grille du processus, ou erreur explicite si elle est absente
+///
+/// La grille couvre la France métropolitaine: un point hors de son emprise est
+/// converti avec la translation moyenne [`HELMERT`] (précision métrique), sans
+/// avertissement.
+pub fn require_grid(crs: &Crs) -> Result<&'static Grid> {
+    grid().with_context(|| {
+        format!(
+            "EPSG:{} ({}) requires the IGN grid gr3df97a.txt for the NTF → RGF93 \
+             conversion: pass --ntf-grid <path> or set {}",
+            crs.epsg, crs.name, GRID_ENV
+        )
+    })
+}
+
+/// Grille du processus, lue depuis `NTF_GRID` si aucune n'a été chargée
+pub fn grid() -> Option<&'static Grid> {
+    GRID.get_or_init(|| {
+        let path = std::env::var_os(GRID_ENV)?;
+        match Grid::load(Path::new(&path)) {
+            Ok(grid) => {
+                info!("NTF grid loaded from {}", Path::new(&path).display());
+                Some(grid)
+            }
+            Err(e) => {
+                warn!("{:#}; ignoring {}", e, GRID_ENV);
+                None
+            }
+        }
+    })
+    .as_ref()
+}
+
+/// Translation NTF → RGF93 en un point RGF93 (radians)
+fn translation_at(grid: Option<&Grid>, rgf93: Geographic) -> [f64; 3] {
+    let (lon, lat) = rgf93.to_degrees();
+    grid.and_then(|g| g.translation(lon, lat))
+        .unwrap_or(HELMERT)
+}
+
+/// Convertit des coordonnées géographiques NTF en RGF93 (≈ WGS84)
+pub fn ntf_to_rgf93(grid: Option<&Grid>, ntf: Geographic) -> Geographic {
+    let [x, y, z] = Ellipsoid::CLARKE_1880_IGN.geocentric(ntf.lon, ntf.lat);
+
+    // La grille est indexée en RGF93: position approchée par la translation moyenne
+    let [dx, dy, dz] = HELMERT;
+    let (lon, lat) = Ellipsoid::GRS80.geodetic([x + dx, y + dy, z + dz]);
+    let [dx, dy, dz] = translation_at(grid, Geographic::new(lon, lat));
+
+    let (lon, lat) = Ellipsoid::GRS80.geodetic([x + dx, y + dy, z + dz]);
+    Geographic::new(lon, lat)
+}
+
+/// Convertit des coordonnées géographiques RGF93 (≈ WGS84) en NTF
+pub fn rgf93_to_ntf(grid: Option<&Grid>, rgf93: Geographic) -> Geographic {
+    let [dx, dy, dz] = translation_at(grid, rgf93);
+    let [x, y, z] = Ellipsoid::GRS80.geocentric(rgf93.lon, rgf93.lat);
+    let (lon, lat) = Ellipsoid::CLARKE_1880_IGN.geodetic([x - dx, y - dy, z - dz]);
+    Geographic::new(lon, lat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Grille 2 × 2 nœuds autour de Paris
+    const GRID: &str = "GR3D  002024 024 20370201\n\
+GR3D1    2.0000    2.1000   48.8000   48.9000    0.1000    0.1000\n\
+GR3D2 INTERPOLATION BILINEAIRE\n\
+GR3D3 PREC CM 01:5 02:10 03:20 04:50 99>100\n\
+00001    2.000000000   48.800000000  -168.000  -60.000  320.000  01  -0158\n\
+00002    2.000000000   48.900000000  -168.000  -60.000  322.000  01  -0158\n\
+00003    2.100000000   48.800000000  -170.000  -60.000  320.000  01  -0158\n\
+00004    2.100000000   48.900000000  -170.000  -60.000  322.000  01  -0158\n";
+
+    #[test]
+    fn test_grid_interpolation() {
+        let grid = Grid::parse(GRID).unwrap();
+
+        let [tx, ty, tz] = grid.translation(2.05, 48.875).unwrap();
+        assert!((tx - -169.0).abs() < 1e-9, "tx={}", tx);
+        assert!((ty - -60.0).abs() < 1e-9, "ty={}", ty);
+        assert!((tz - 321.5).abs() < 1e-9, "tz={}", tz);
+
+        // Nœuds et bords exacts
+        assert_eq!(grid.translation(2.1, 48.9), Some([-170.0, -60.0, 322.0]));
+        assert!(grid.translation(2.2, 48.85).is_none());
+        assert!(grid.translation(1.9, 48.85).is_none());
+    }
+
+    #[test]
+    fn test_grid_errors() {
+        assert!(Grid::parse("00001 2.0 48.8 -168 -60 320 01\n").is_err());
+        assert!(Grid::parse(&GRID.replace("GR3D1    2.0000", "GR3D1")).is_err());
+    }
+
+    #[test]
+    fn test_ntf_roundtrip() {
+        let grid = Grid::parse(GRID).unwrap();
+        let rgf93 = Geographic::from_degrees(2.05, 48.85);
+
+        for grid in [Some(&grid), None] {
+            let ntf = rgf93_to_ntf(grid, rgf93);
+            // Décalage NTF / RGF93 de l'ordre de 100 m à Paris
+            let shift = (ntf.lon - rgf93.lon)
+                .hypot(ntf.lat - rgf93.lat)
+                .to_degrees();
+            assert!(shift > 1e-4 && shift < 1e-2, "shift={}°", shift);
+
+            let back = ntf_to_rgf93(grid, ntf);
+            // Moins d'un millimètre (1e-8° ≈ 1 mm)
+            assert!((back.lon - rgf93.lon).to_degrees().abs() < 1e-8);
+            assert!((back.lat - rgf93.lat).to_degrees().abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn test_require_grid_error() {
+        if std::env::var_os(GRID_ENV).is_some() {
+            return;
+        }
+        let crs = super::super::crs::lookup(27572).unwrap();
+        let err = require_grid(&crs).unwrap_err().to_string();
+        assert!(
+            err.contains("EPSG:27572") && err.contains("--ntf-grid"),
+            "{}",
+            err
+        );
+        assert!(err.contains("Lambert zone II"), "{}", err);
+        assert!(super::super::ReprojectorLite::new(27572, 4326).is_err());
+        assert!(super::super::ReprojectorLite::new(2154, 27572).is_err());
+    }
+}
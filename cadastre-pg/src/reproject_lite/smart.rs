@@ -11,7 +11,7 @@ use geo::Geometry;
 /// Essaie d'abord reproject_lite (pure Rust), puis fallback sur proj si disponible.
 pub enum SmartReprojector {
     /// Reprojection légère (pure Rust)
-    Lite(ReprojectorLite),
+    Lite(Box<ReprojectorLite>),
     /// Reprojection via PROJ (si feature activée)
     #[cfg(feature = "reproject")]
     Proj(crate::export::reproject::Reprojector),
@@ -30,7 +30,7 @@ impl SmartReprojector {
         // Essayer reproject_lite d'abord
         if ReprojectorLite::is_supported(source_epsg, target_epsg) {
             let lite = ReprojectorLite::new(source_epsg, target_epsg)?;
-            return Ok(Self::Lite(lite));
+            return Ok(Self::Lite(Box::new(lite)));
         }
 
         // Fallback sur proj si disponible
@@ -45,8 +45,8 @@ impl SmartReprojector {
         bail!(
             "Reprojection EPSG:{} → EPSG:{} non supportée.\n\
              Projections supportées (reproject_lite) :\n\
-             - Sources: 2154 (Lambert 93), 32620/32622/32738/32740 (UTM DOM)\n\
-             - Cibles: 4326 (WGS84), 3857 (Web Mercator)\n\
+             2154 (Lambert 93), 3942-3950 (CC 9 zones), 2970/2972/2973/2975 et\n\
+             32620/32622/32738/32740 (UTM DOM), 4326 (WGS84), 3857 (Web Mercator)\n\
              Pour d'autres projections, compilez avec: cargo build --features reproject",
             source_epsg,
             target_epsg
@@ -63,6 +63,16 @@ impl SmartReprojector {
         }
     }
 
+    /// Transforme un point (x, y)
+    pub fn transform_point(&self, x: f64, y: f64) -> Result<(f64, f64)> {
+        match self {
+            Self::Identity => Ok((x, y)),
+            Self::Lite(lite) => lite.transform_point(x, y),
+            #[cfg(feature = "reproject")]
+            Self::Proj(proj) => proj.transform_coord(geo::Coord { x, y }),
+        }
+    }
+
     /// Retourne une description du reprojector utilisé
     pub fn description(&self) -> &'static str {
         match self {
@@ -90,6 +100,12 @@ mod tests {
         assert!(matches!(r, SmartReprojector::Lite(_)));
     }
 
+    #[test]
+    fn test_wgs84_to_lambert93() {
+        let r = SmartReprojector::new(4326, 2154).unwrap();
+        assert!(matches!(r, SmartReprojector::Lite(_)));
+    }
+
     #[test]
     fn test_lambert93_to_3857() {
         let r = SmartReprojector::new(2154, 3857).unwrap();
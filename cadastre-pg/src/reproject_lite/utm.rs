@@ -1,76 +1,138 @@
-//! Projection UTM (Universal Transverse Mercator)
+//! Projection Transverse Mercator (UTM)
 //!
-//! Zones supportées:
-//! - Zone 20N (EPSG:32620) - Martinique, Guadeloupe
-//! - Zone 22N (EPSG:32622) - Guyane
-//! - Zone 38S (EPSG:32738) - Mayotte
-//! - Zone 40S (EPSG:32740) - Réunion
+//! Zones utilisées par le cadastre des DOM:
+//! - Zone 20N - Martinique, Guadeloupe
+//! - Zone 22N - Guyane
+//! - Zone 38S - Mayotte
+//! - Zone 40S - Réunion
 
-use super::ellipsoid::WGS84;
+use super::ellipsoid::Ellipsoid;
 use super::Geographic;
 use anyhow::Result;
 
-/// Convertit UTM vers coordonnées géographiques WGS84
-pub fn utm_to_geographic(x: f64, y: f64, zone: u32, south: bool) -> Result<Geographic> {
-    let a = WGS84::A;
-    let e2 = WGS84::E2;
-    let e = WGS84::E;
-    let ep2 = WGS84::EP2;
-
-    // Paramètres UTM
-    let k0 = 0.9996; // Facteur d'échelle
-    let x0 = 500000.0; // False easting
-    let y0 = if south { 10000000.0 } else { 0.0 }; // False northing
-
-    // Longitude centrale de la zone
-    let lon0 = ((zone as f64 - 1.0) * 6.0 - 180.0 + 3.0).to_radians();
-
-    // Coordonnées réduites
-    let x = x - x0;
-    let y = y - y0;
-
-    // Calcul du footprint latitude
-    let m = y / k0;
-    let mu = m / (a * (1.0 - e2 / 4.0 - 3.0 * e2.powi(2) / 64.0 - 5.0 * e2.powi(3) / 256.0));
-
-    // Coefficients pour la série
-    let e1 = (1.0 - (1.0 - e2).sqrt()) / (1.0 + (1.0 - e2).sqrt());
-
-    let phi1 = mu
-        + (3.0 * e1 / 2.0 - 27.0 * e1.powi(3) / 32.0) * (2.0 * mu).sin()
-        + (21.0 * e1.powi(2) / 16.0 - 55.0 * e1.powi(4) / 32.0) * (4.0 * mu).sin()
-        + (151.0 * e1.powi(3) / 96.0) * (6.0 * mu).sin()
-        + (1097.0 * e1.powi(4) / 512.0) * (8.0 * mu).sin();
-
-    // Calculs intermédiaires
-    let sin_phi1 = phi1.sin();
-    let cos_phi1 = phi1.cos();
-    let tan_phi1 = phi1.tan();
-
-    let n1 = a / (1.0 - e2 * sin_phi1.powi(2)).sqrt();
-    let t1 = tan_phi1.powi(2);
-    let c1 = ep2 * cos_phi1.powi(2);
-    let r1 = a * (1.0 - e2) / (1.0 - e2 * sin_phi1.powi(2)).powf(1.5);
-    let d = x / (n1 * k0);
-
-    // Latitude
-    let lat = phi1
-        - (n1 * tan_phi1 / r1)
-            * (d.powi(2) / 2.0
-                - (5.0 + 3.0 * t1 + 10.0 * c1 - 4.0 * c1.powi(2) - 9.0 * ep2) * d.powi(4) / 24.0
-                + (61.0 + 90.0 * t1 + 298.0 * c1 + 45.0 * t1.powi(2) - 252.0 * ep2 - 3.0 * c1.powi(2))
-                    * d.powi(6)
-                    / 720.0);
-
-    // Longitude
-    let lon = lon0
-        + (d - (1.0 + 2.0 * t1 + c1) * d.powi(3) / 6.0
-            + (5.0 - 2.0 * c1 + 28.0 * t1 - 3.0 * c1.powi(2) + 8.0 * ep2 + 24.0 * t1.powi(2))
-                * d.powi(5)
-                / 120.0)
-            / cos_phi1;
-
-    Ok(Geographic::new(lon, lat))
+/// Paramètres d'une projection Transverse Mercator
+#[derive(Debug, Clone, Copy)]
+pub struct TransverseMercator {
+    /// Longitude du méridien central
+    pub lon0: f64,
+    /// Facteur d'échelle sur le méridien central
+    pub k0: f64,
+    /// False easting
+    pub x0: f64,
+    /// False northing
+    pub y0: f64,
+    /// Ellipsoïde de référence
+    pub ellipsoid: Ellipsoid,
+}
+
+impl TransverseMercator {
+    /// Zone UTM sur l'ellipsoïde donné
+    pub fn utm(zone: u32, south: bool, ellipsoid: Ellipsoid) -> Self {
+        Self {
+            // Longitude centrale de la zone
+            lon0: ((zone as f64 - 1.0) * 6.0 - 180.0 + 3.0).to_radians(),
+            k0: 0.9996,
+            x0: 500000.0,
+            y0: if south { 10000000.0 } else { 0.0 },
+            ellipsoid,
+        }
+    }
+
+    /// Convertit des coordonnées projetées en coordonnées géographiques
+    pub fn unproject(&self, x: f64, y: f64) -> Result<Geographic> {
+        let a = self.ellipsoid.a;
+        let e2 = self.ellipsoid.e2();
+        let ep2 = self.ellipsoid.ep2();
+        let k0 = self.k0;
+        let lon0 = self.lon0;
+
+        // Coordonnées réduites
+        let x = x - self.x0;
+        let y = y - self.y0;
+
+        // Calcul du footprint latitude
+        let m = y / k0;
+        let mu = m / (a * (1.0 - e2 / 4.0 - 3.0 * e2.powi(2) / 64.0 - 5.0 * e2.powi(3) / 256.0));
+
+        // Coefficients pour la série
+        let e1 = (1.0 - (1.0 - e2).sqrt()) / (1.0 + (1.0 - e2).sqrt());
+
+        let phi1 = mu
+            + (3.0 * e1 / 2.0 - 27.0 * e1.powi(3) / 32.0) * (2.0 * mu).sin()
+            + (21.0 * e1.powi(2) / 16.0 - 55.0 * e1.powi(4) / 32.0) * (4.0 * mu).sin()
+            + (151.0 * e1.powi(3) / 96.0) * (6.0 * mu).sin()
+            + (1097.0 * e1.powi(4) / 512.0) * (8.0 * mu).sin();
+
+        // Calculs intermédiaires
+        let sin_phi1 = phi1.sin();
+        let cos_phi1 = phi1.cos();
+        let tan_phi1 = phi1.tan();
+
+        let n1 = a / (1.0 - e2 * sin_phi1.powi(2)).sqrt();
+        let t1 = tan_phi1.powi(2);
+        let c1 = ep2 * cos_phi1.powi(2);
+        let r1 = a * (1.0 - e2) / (1.0 - e2 * sin_phi1.powi(2)).powf(1.5);
+        let d = x / (n1 * k0);
+
+        // Latitude
+        let lat = phi1
+            - (n1 * tan_phi1 / r1)
+                * (d.powi(2) / 2.0
+                    - (5.0 + 3.0 * t1 + 10.0 * c1 - 4.0 * c1.powi(2) - 9.0 * ep2) * d.powi(4)
+                        / 24.0
+                    + (61.0 + 90.0 * t1 + 298.0 * c1 + 45.0 * t1.powi(2)
+                        - 252.0 * ep2
+                        - 3.0 * c1.powi(2))
+                        * d.powi(6)
+                        / 720.0);
+
+        // Longitude
+        let lon = lon0
+            + (d - (1.0 + 2.0 * t1 + c1) * d.powi(3) / 6.0
+                + (5.0 - 2.0 * c1 + 28.0 * t1 - 3.0 * c1.powi(2) + 8.0 * ep2 + 24.0 * t1.powi(2))
+                    * d.powi(5)
+                    / 120.0)
+                / cos_phi1;
+
+        Ok(Geographic::new(lon, lat))
+    }
+
+    /// Convertit des coordonnées géographiques en coordonnées projetées
+    pub fn project(&self, geo: Geographic) -> Result<(f64, f64)> {
+        let a = self.ellipsoid.a;
+        let e2 = self.ellipsoid.e2();
+        let ep2 = self.ellipsoid.ep2();
+        let (e4, e6) = (e2 * e2, e2 * e2 * e2);
+
+        let sin_lat = geo.lat.sin();
+        let cos_lat = geo.lat.cos();
+        let tan_lat = geo.lat.tan();
+
+        let n = a / (1.0 - e2 * sin_lat.powi(2)).sqrt();
+        let t = tan_lat.powi(2);
+        let c = ep2 * cos_lat.powi(2);
+        let d = (geo.lon - self.lon0) * cos_lat;
+
+        // Longueur de l'arc de méridien depuis l'équateur
+        let m = a
+            * ((1.0 - e2 / 4.0 - 3.0 * e4 / 64.0 - 5.0 * e6 / 256.0) * geo.lat
+                - (3.0 * e2 / 8.0 + 3.0 * e4 / 32.0 + 45.0 * e6 / 1024.0) * (2.0 * geo.lat).sin()
+                + (15.0 * e4 / 256.0 + 45.0 * e6 / 1024.0) * (4.0 * geo.lat).sin()
+                - (35.0 * e6 / 3072.0) * (6.0 * geo.lat).sin());
+
+        let x = self.k0
+            * n
+            * (d + (1.0 - t + c) * d.powi(3) / 6.0
+                + (5.0 - 18.0 * t + t.powi(2) + 72.0 * c - 58.0 * ep2) * d.powi(5) / 120.0);
+        let y = self.k0
+            * (m + n
+                * tan_lat
+                * (d.powi(2) / 2.0
+                    + (5.0 - t + 9.0 * c + 4.0 * c.powi(2)) * d.powi(4) / 24.0
+                    + (61.0 - 58.0 * t + t.powi(2) + 600.0 * c - 330.0 * ep2) * d.powi(6) / 720.0));
+
+        Ok((self.x0 + x, self.y0 + y))
+    }
 }
 
 #[cfg(test)]
@@ -81,7 +143,9 @@ mod tests {
     fn test_martinique() {
         // Fort-de-France approximativement
         // UTM Zone 20N: 708000, 1615000
-        let geo = utm_to_geographic(708000.0, 1615000.0, 20, false).unwrap();
+        let geo = TransverseMercator::utm(20, false, Ellipsoid::WGS84)
+            .unproject(708000.0, 1615000.0)
+            .unwrap();
         let (lon, lat) = geo.to_degrees();
 
         // Fort-de-France: -61.07°E, 14.60°N
@@ -93,7 +157,9 @@ mod tests {
     fn test_reunion() {
         // Saint-Denis approximativement
         // UTM Zone 40S: 338000, 7691000
-        let geo = utm_to_geographic(338000.0, 7691000.0, 40, true).unwrap();
+        let geo = TransverseMercator::utm(40, true, Ellipsoid::WGS84)
+            .unproject(338000.0, 7691000.0)
+            .unwrap();
         let (lon, lat) = geo.to_degrees();
 
         // Saint-Denis: 55.45°E, -20.88°S
@@ -105,11 +171,79 @@ mod tests {
     fn test_guyane() {
         // Cayenne approximativement
         // UTM Zone 22N: 352000, 546000
-        let geo = utm_to_geographic(352000.0, 546000.0, 22, false).unwrap();
+        let geo = TransverseMercator::utm(22, false, Ellipsoid::WGS84)
+            .unproject(352000.0, 546000.0)
+            .unwrap();
         let (lon, lat) = geo.to_degrees();
 
         // Cayenne: -52.33°E, 4.93°N
         assert!((lon - (-52.33)).abs() < 0.2, "lon={}", lon);
         assert!((lat - 4.93).abs() < 0.2, "lat={}", lat);
     }
+
+    #[test]
+    fn test_forward_central_meridian() {
+        // Méridien central de la zone 20 (63°W) sur l'équateur
+        let utm = TransverseMercator::utm(20, false, Ellipsoid::WGS84);
+        let (x, y) = utm.project(Geographic::from_degrees(-63.0, 0.0)).unwrap();
+        assert!((x - 500000.0).abs() < 1e-6 && y.abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let zones = [
+            (
+                TransverseMercator::utm(20, false, Ellipsoid::WGS84),
+                708000.0,
+                1615000.0,
+            ),
+            (
+                TransverseMercator::utm(22, false, Ellipsoid::GRS80),
+                352000.0,
+                546000.0,
+            ),
+            (
+                TransverseMercator::utm(40, true, Ellipsoid::GRS80),
+                338000.0,
+                7691000.0,
+            ),
+            (
+                TransverseMercator::utm(38, true, Ellipsoid::WGS84),
+                515000.0,
+                8580000.0,
+            ),
+        ];
+        for (utm, x, y) in zones {
+            let geo = utm.unproject(x, y).unwrap();
+            let (x2, y2) = utm.project(geo).unwrap();
+            assert!((x - x2).abs() < 1e-3, "x={} x2={}", x, x2);
+            assert!((y - y2).abs() < 1e-3, "y={} y2={}", y, y2);
+        }
+    }
+
+    #[test]
+    fn test_epsg_guidance_note_tm() {
+        // Exemple de la note EPSG 7-2 (OSGB 1936 / British National Grid):
+        // φ = 50°30'N, λ = 0°30'E → E = 577 274.99 m, N = 69 740.50 m
+        let mut osgb = TransverseMercator {
+            lon0: (-2.0_f64).to_radians(),
+            k0: 0.9996012717,
+            x0: 400000.0,
+            y0: 0.0,
+            ellipsoid: Ellipsoid {
+                a: 6377563.396,
+                f: 1.0 / 299.3249646,
+            },
+        };
+        // Latitude origine 49°N: le false northing absorbe l'arc de méridien
+        let (_, m0) = osgb.project(Geographic::from_degrees(-2.0, 49.0)).unwrap();
+        osgb.y0 = -100000.0 - m0;
+        let (x, y) = osgb.project(Geographic::from_degrees(0.5, 50.5)).unwrap();
+        assert!((x - 577274.99).abs() < 1e-2, "x={}", x);
+        assert!((y - 69740.50).abs() < 1e-2, "y={}", y);
+
+        let (lon, lat) = osgb.unproject(577274.99, 69740.50).unwrap().to_degrees();
+        assert!((lon - 0.5).abs() < 1e-7, "lon={}", lon);
+        assert!((lat - 50.5).abs() < 1e-7, "lat={}", lat);
+    }
 }
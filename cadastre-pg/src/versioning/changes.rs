@@ -0,0 +1,402 @@
+//! Flux de changements entre deux millésimes
+//!
+//! Compare les versions valides à deux dates et classe chaque entité:
+//! créée, supprimée, géométrie modifiée ou attributs modifiés.
+
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use deadpool_postgres::Pool;
+
+use crate::export::geojson::escape_json;
+use crate::export::postgres::TableConfig;
+
+/// Nature d'un changement entre deux millésimes
+///
+/// Pendant de [`super::upsert::UpsertResult`] vu depuis l'historique:
+/// `Created` correspond à `Inserted`, les modifications à `Updated`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Entité absente au premier millésime
+    Created,
+    /// Entité absente au second millésime
+    Deleted,
+    /// Géométrie modifiée (les attributs peuvent aussi avoir changé)
+    GeometryModified,
+    /// Attributs modifiés, géométrie identique
+    AttributesModified,
+}
+
+impl ChangeKind {
+    /// Libellé utilisé en SQL et dans les sorties
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Deleted => "deleted",
+            ChangeKind::GeometryModified => "geometry_modified",
+            ChangeKind::AttributesModified => "attributes_modified",
+        }
+    }
+
+    fn from_sql(value: &str) -> Option<Self> {
+        match value {
+            "created" => Some(ChangeKind::Created),
+            "deleted" => Some(ChangeKind::Deleted),
+            "geometry_modified" => Some(ChangeKind::GeometryModified),
+            "attributes_modified" => Some(ChangeKind::AttributesModified),
+            _ => None,
+        }
+    }
+}
+
+/// Changement d'une entité
+#[derive(Debug, Clone)]
+pub struct EntityChange {
+    pub table: String,
+    pub departement: String,
+    pub id: String,
+    pub kind: ChangeKind,
+    /// Géométrie GeoJSON (version la plus récente), si demandée
+    pub geometry: Option<String>,
+    /// SRID de la géométrie, si demandée
+    pub srid: Option<i32>,
+}
+
+/// Paramètres d'une comparaison de millésimes
+#[derive(Debug, Clone, Copy)]
+pub struct ChangeQuery<'a> {
+    /// Premier millésime (YYYY-MM)
+    pub from: &'a str,
+    /// Second millésime (YYYY-MM)
+    pub to: &'a str,
+    /// Limiter à un département
+    pub departement: Option<&'a str>,
+    /// Récupérer les géométries (sortie GeoJSON)
+    pub with_geometry: bool,
+}
+
+/// Compteurs par nature de changement
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ChangeSummary {
+    pub created: usize,
+    pub deleted: usize,
+    pub geometry_modified: usize,
+    pub attributes_modified: usize,
+}
+
+impl ChangeSummary {
+    /// Compte une liste de changements
+    pub fn from_changes(changes: &[EntityChange]) -> Self {
+        let mut summary = Self::default();
+        for change in changes {
+            match change.kind {
+                ChangeKind::Created => summary.created += 1,
+                ChangeKind::Deleted => summary.deleted += 1,
+                ChangeKind::GeometryModified => summary.geometry_modified += 1,
+                ChangeKind::AttributesModified => summary.attributes_modified += 1,
+            }
+        }
+        summary
+    }
+
+    /// Nombre total de changements
+    pub fn total(&self) -> usize {
+        self.created + self.deleted + self.geometry_modified + self.attributes_modified
+    }
+}
+
+/// Requête de comparaison entre les versions valides à $1 et à $2
+/// ($3: département optionnel)
+fn changes_sql(schema: &str, table: &TableConfig, with_geometry: bool) -> String {
+    let geometry_changed = "CASE WHEN a.geometry_hash IS NOT NULL AND b.geometry_hash IS NOT NULL \
+         THEN a.geometry_hash <> b.geometry_hash \
+         ELSE NOT ST_OrderingEquals(a.geometry, b.geometry) END";
+
+    let attributes_changed = if table.columns.is_empty() {
+        "false".to_string()
+    } else {
+        let a_cols: Vec<String> = table
+            .columns
+            .iter()
+            .map(|c| format!("a.{}", c.name))
+            .collect();
+        let b_cols: Vec<String> = table
+            .columns
+            .iter()
+            .map(|c| format!("b.{}", c.name))
+            .collect();
+        format!(
+            "({}) IS DISTINCT FROM ({})",
+            a_cols.join(", "),
+            b_cols.join(", ")
+        )
+    };
+
+    let geometry_cols = if with_geometry {
+        ", ST_AsGeoJSON(COALESCE(b.geometry, a.geometry)), ST_SRID(COALESCE(b.geometry, a.geometry))"
+    } else {
+        ""
+    };
+
+    format!(
+        r#"
+        WITH a AS (
+            SELECT * FROM {schema}.{table}
+            WHERE valid_from <= $1::text::date AND (valid_to IS NULL OR valid_to > $1::text::date)
+              AND ($3::text IS NULL OR departement = $3)
+        ), b AS (
+            SELECT * FROM {schema}.{table}
+            WHERE valid_from <= $2::text::date AND (valid_to IS NULL OR valid_to > $2::text::date)
+              AND ($3::text IS NULL OR departement = $3)
+        )
+        SELECT COALESCE(a.departement, b.departement)::text,
+               COALESCE(a.id, b.id),
+               CASE
+                   WHEN a.row_id IS NULL THEN 'created'
+                   WHEN b.row_id IS NULL THEN 'deleted'
+                   WHEN {geometry_changed} THEN 'geometry_modified'
+                   ELSE 'attributes_modified'
+               END{geometry_cols}
+        FROM a FULL OUTER JOIN b ON a.departement = b.departement AND a.id = b.id
+        WHERE a.row_id IS NULL
+           OR b.row_id IS NULL
+           OR (a.row_id <> b.row_id AND ({geometry_changed} OR {attributes_changed}))
+        ORDER BY 1, 2
+        "#,
+        schema = schema,
+        table = table.name,
+        geometry_changed = geometry_changed,
+        attributes_changed = attributes_changed,
+        geometry_cols = geometry_cols
+    )
+}
+
+/// Liste les changements d'une table entre deux millésimes
+pub async fn query_changes(
+    pool: &Pool,
+    schema: &str,
+    table: &TableConfig,
+    query: &ChangeQuery<'_>,
+) -> Result<Vec<EntityChange>> {
+    let client = pool.get().await?;
+    let from = format!("{}-01", query.from);
+    let to = format!("{}-01", query.to);
+
+    let rows = client
+        .query(
+            &changes_sql(schema, table, query.with_geometry),
+            &[&from, &to, &query.departement],
+        )
+        .await
+        .with_context(|| format!("Failed to compare millésimes in {}.{}", schema, table.name))?;
+
+    let mut changes = Vec::with_capacity(rows.len());
+    for row in rows {
+        let kind: String = row.get(2);
+        let kind = ChangeKind::from_sql(&kind)
+            .with_context(|| format!("Unexpected change kind: {}", kind))?;
+        changes.push(EntityChange {
+            table: table.name.clone(),
+            departement: row.get(0),
+            id: row.get(1),
+            kind,
+            geometry: if query.with_geometry {
+                row.get(3)
+            } else {
+                None
+            },
+            srid: if query.with_geometry {
+                row.get(4)
+            } else {
+                None
+            },
+        });
+    }
+
+    Ok(changes)
+}
+
+/// Écrit les changements en tableau texte aligné
+pub fn write_changes_table<W: Write>(writer: &mut W, changes: &[EntityChange]) -> Result<()> {
+    let table_width = changes
+        .iter()
+        .map(|c| c.table.len())
+        .max()
+        .unwrap_or(0)
+        .max("table".len());
+    let id_width = changes
+        .iter()
+        .map(|c| c.id.len())
+        .max()
+        .unwrap_or(0)
+        .max("id".len());
+
+    writeln!(
+        writer,
+        "{:<table_width$}  {:<3}  {:<id_width$}  kind",
+        "table", "dep", "id"
+    )?;
+    for change in changes {
+        writeln!(
+            writer,
+            "{:<table_width$}  {:<3}  {:<id_width$}  {}",
+            change.table,
+            change.departement,
+            change.id,
+            change.kind.as_str()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Écrit les changements en CSV (`table,departement,id,kind`)
+pub fn write_changes_csv<W: Write>(writer: &mut W, changes: &[EntityChange]) -> Result<()> {
+    writeln!(writer, "table,departement,id,kind")?;
+    for change in changes {
+        writeln!(
+            writer,
+            "{},{},{},{}",
+            csv_field(&change.table),
+            csv_field(&change.departement),
+            csv_field(&change.id),
+            change.kind.as_str()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Écrit les changements en GeoJSON (FeatureCollection)
+pub fn write_changes_geojson<W: Write>(writer: &mut W, changes: &[EntityChange]) -> Result<()> {
+    write!(writer, r#"{{"type":"FeatureCollection""#)?;
+    if let Some(srid) = changes.iter().find_map(|c| c.srid) {
+        write!(
+            writer,
+            r#","crs":{{"type":"name","properties":{{"name":"urn:ogc:def:crs:EPSG::{}"}}}}"#,
+            srid
+        )?;
+    }
+    write!(writer, r#","features":["#)?;
+
+    for (i, change) in changes.iter().enumerate() {
+        if i > 0 {
+            write!(writer, ",")?;
+        }
+        write!(
+            writer,
+            r#"{{"type":"Feature","id":"{}","geometry":{},"properties":{{"table":"{}","departement":"{}","kind":"{}"}}}}"#,
+            escape_json(&change.id),
+            change.geometry.as_deref().unwrap_or("null"),
+            escape_json(&change.table),
+            escape_json(&change.departement),
+            change.kind.as_str()
+        )?;
+    }
+
+    write!(writer, "]}}")?;
+    Ok(())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::postgres::ColumnConfig;
+
+    fn change(id: &str, kind: ChangeKind) -> EntityChange {
+        EntityChange {
+            table: "edi_parcelles".to_string(),
+            departement: "38".to_string(),
+            id: id.to_string(),
+            kind,
+            geometry: None,
+            srid: None,
+        }
+    }
+
+    #[test]
+    fn test_changes_sql_compares_configured_columns() {
+        let table = TableConfig {
+            name: "edi_parcelles".to_string(),
+            geometry_type: "Geometry".to_string(),
+            srid: 4326,
+            columns: vec![
+                ColumnConfig {
+                    name: "numero".to_string(),
+                    pg_type: "TEXT".to_string(),
+                    source: "TEX".to_string(),
+                },
+                ColumnConfig {
+                    name: "contenance".to_string(),
+                    pg_type: "INTEGER".to_string(),
+                    source: "SUPF".to_string(),
+                },
+            ],
+        };
+
+        let sql = changes_sql("cadastre", &table, false);
+        assert!(sql.contains("FROM cadastre.edi_parcelles"));
+        assert!(sql.contains("(a.numero, a.contenance) IS DISTINCT FROM (b.numero, b.contenance)"));
+        assert!(!sql.contains("ST_AsGeoJSON"));
+
+        let sql = changes_sql("cadastre", &table, true);
+        assert!(sql.contains("ST_AsGeoJSON"));
+    }
+
+    #[test]
+    fn test_change_kind_roundtrip() {
+        for kind in [
+            ChangeKind::Created,
+            ChangeKind::Deleted,
+            ChangeKind::GeometryModified,
+            ChangeKind::AttributesModified,
+        ] {
+            assert_eq!(ChangeKind::from_sql(kind.as_str()), Some(kind));
+        }
+    }
+
+    #[test]
+    fn test_change_summary() {
+        let changes = vec![
+            change("38A", ChangeKind::Created),
+            change("38B", ChangeKind::Created),
+            change("38C", ChangeKind::AttributesModified),
+        ];
+        let summary = ChangeSummary::from_changes(&changes);
+        assert_eq!(summary.created, 2);
+        assert_eq!(summary.attributes_modified, 1);
+        assert_eq!(summary.total(), 3);
+    }
+
+    #[test]
+    fn test_write_changes_csv() {
+        let mut out = Vec::new();
+        write_changes_csv(&mut out, &[change("38A", ChangeKind::Deleted)]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "table,departement,id,kind\nedi_parcelles,38,38A,deleted\n"
+        );
+    }
+
+    #[test]
+    fn test_write_changes_geojson() {
+        let mut c = change("38A", ChangeKind::GeometryModified);
+        c.geometry = Some(r#"{"type":"Point","coordinates":[5.7,45.1]}"#.to_string());
+        c.srid = Some(4326);
+
+        let mut out = Vec::new();
+        write_changes_geojson(&mut out, &[c]).unwrap();
+        let json = String::from_utf8(out).unwrap();
+        assert!(json.starts_with(r#"{"type":"FeatureCollection","crs""#));
+        assert!(json.contains(r#""geometry":{"type":"Point","coordinates":[5.7,45.1]}"#));
+        assert!(json.contains(r#""kind":"geometry_modified""#));
+    }
+}
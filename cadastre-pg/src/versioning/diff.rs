@@ -1,7 +1,8 @@
-//! Calcul de hash pour comparaison de géométries
+//! Calcul de hash pour comparaison de géométries et d'attributs
 //!
 //! Le hash est normalisé pour être indépendant de l'ordre de départ des anneaux
 //! (un polygone qui commence à un vertex différent aura le même hash).
+//! Le hash d'attributs est indépendant de l'ordre des champs.
 
 use blake3::Hasher;
 use geo::{Coord, Geometry, LineString};
@@ -68,6 +69,29 @@ pub fn geometry_hash(geom: &Geometry) -> [u8; 32] {
     *hasher.finalize().as_bytes()
 }
 
+/// Calcule un hash stable des attributs d'une entité
+///
+/// Les paires (colonne, valeur) sont triées par nom de colonne: l'ordre des
+/// champs dans la configuration n'influence pas le hash.
+pub fn attributes_hash<'a, I>(attributes: I) -> [u8; 32]
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let mut pairs: Vec<(&'a str, &'a str)> = attributes.into_iter().collect();
+    pairs.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+    let mut hasher = Hasher::new();
+    for (name, value) in pairs {
+        // Longueurs en préfixe: ("ab", "c") et ("a", "bc") ne collisionnent pas
+        hasher.update(&(name.len() as u64).to_le_bytes());
+        hasher.update(name.as_bytes());
+        hasher.update(&(value.len() as u64).to_le_bytes());
+        hasher.update(value.as_bytes());
+    }
+
+    *hasher.finalize().as_bytes()
+}
+
 /// Hash un anneau (ring) de polygone en le normalisant
 /// pour commencer au vertex lexicographiquement le plus petit.
 fn hash_ring_normalized(hasher: &mut Hasher, ring: &LineString) {
@@ -198,4 +222,25 @@ mod tests {
         assert_eq!(hash1, hash2, "Same polygon starting at different vertex should have same hash");
         assert_eq!(hash1, hash3, "Same polygon starting at different vertex should have same hash");
     }
+
+    #[test]
+    fn test_attributes_hash_order_independent() {
+        let a = attributes_hash([("numero", "12"), ("contenance", "340")]);
+        let b = attributes_hash([("contenance", "340"), ("numero", "12")]);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_attributes_hash_detects_value_change() {
+        let a = attributes_hash([("numero", "12"), ("contenance", "340")]);
+        let b = attributes_hash([("numero", "12"), ("contenance", "341")]);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_attributes_hash_field_boundaries() {
+        let a = attributes_hash([("ab", "c")]);
+        let b = attributes_hash([("a", "bc")]);
+        assert_ne!(a, b);
+    }
 }
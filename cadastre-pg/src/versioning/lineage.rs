@@ -0,0 +1,598 @@
+//! Filiation des parcelles entre deux millésimes
+//!
+//! Quand une parcelle est divisée ou fusionnée, EDIGEO supprime simplement
+//! l'ancien IDU et en crée de nouveaux. La filiation est reconstruite en
+//! rapprochant, section par section, les parcelles disparues et apparues
+//! selon leur recouvrement:
+//! - `split`: une ancienne parcelle répartie sur plusieurs nouvelles
+//! - `merge`: plusieurs anciennes parcelles réunies en une nouvelle
+//! - `renumber`: même emprise, nouvel identifiant
+//! - `boundary_adjust`: limites redessinées (1 pour 1 ou plusieurs pour plusieurs)
+//!
+//! Les recouvrements sont calculés par PostGIS (millésimes déjà importés) ou
+//! directement sur deux `edigeo::ParseResult`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use anyhow::{Context, Result};
+use deadpool_postgres::Pool;
+use geo::{Area, BooleanOps, BoundingRect, Geometry, Intersects, MultiPolygon, Rect};
+use tracing::{info, warn};
+
+/// Type de feature EDIGEO des parcelles
+const PARCELLE_FEATURE_TYPE: &str = "PARCELLE_id";
+
+/// Table de filiation (dans le schéma des tables versionnées)
+pub const LINEAGE_TABLE: &str = "parcelle_lineage";
+
+/// Part minimale de l'ancienne ou de la nouvelle parcelle couverte par
+/// l'intersection (en deçà: simple contact ou artefact de numérisation)
+const MIN_OVERLAP_RATIO: f64 = 0.05;
+
+/// Intersection / union minimale pour qu'un 1 pour 1 soit une renumérotation
+const RENUMBER_MIN_IOU: f64 = 0.98;
+
+/// Nature d'un lien de filiation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineageKind {
+    Split,
+    Merge,
+    Renumber,
+    BoundaryAdjust,
+}
+
+impl LineageKind {
+    /// Libellé stocké dans la colonne `kind`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineageKind::Split => "split",
+            LineageKind::Merge => "merge",
+            LineageKind::Renumber => "renumber",
+            LineageKind::BoundaryAdjust => "boundary_adjust",
+        }
+    }
+}
+
+/// Recouvrement entre une parcelle disparue et une parcelle apparue
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParcelOverlap {
+    pub departement: String,
+    pub old_id: String,
+    pub new_id: String,
+    pub old_area: f64,
+    pub new_area: f64,
+    pub intersection_area: f64,
+}
+
+impl ParcelOverlap {
+    /// Le recouvrement dépasse le simple contact
+    fn is_significant(&self) -> bool {
+        ratio(self.intersection_area, self.old_area) >= MIN_OVERLAP_RATIO
+            || ratio(self.intersection_area, self.new_area) >= MIN_OVERLAP_RATIO
+    }
+
+    /// Intersection / union
+    fn iou(&self) -> f64 {
+        ratio(
+            self.intersection_area,
+            self.old_area + self.new_area - self.intersection_area,
+        )
+    }
+}
+
+/// Lien de filiation entre une ancienne et une nouvelle parcelle
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineageLink {
+    pub departement: String,
+    pub old_id: String,
+    pub new_id: String,
+    pub kind: LineageKind,
+    /// Part de l'ancienne parcelle reprise par la nouvelle (intersection / surface ancienne)
+    pub overlap_ratio: f64,
+}
+
+fn ratio(part: f64, whole: f64) -> f64 {
+    if whole > 0.0 {
+        part / whole
+    } else {
+        0.0
+    }
+}
+
+/// Section d'un identifiant de parcelle (identifiant sans le numéro, 4 derniers caractères)
+fn section_key(id: &str) -> &str {
+    id.get(..id.len().saturating_sub(4)).unwrap_or(id)
+}
+
+/// Racine d'un élément (union-find avec compression de chemin)
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Classe les recouvrements en liens de filiation
+///
+/// Les recouvrements significatifs forment un graphe biparti (anciennes /
+/// nouvelles parcelles); chaque composante connexe reçoit un type selon sa
+/// cardinalité.
+pub fn classify_overlaps(overlaps: &[ParcelOverlap]) -> Vec<LineageLink> {
+    let links: Vec<&ParcelOverlap> = overlaps.iter().filter(|o| o.is_significant()).collect();
+
+    // Union-find sur les parcelles (anciennes et nouvelles distinguées)
+    let mut nodes: HashMap<(bool, &str, &str), usize> = HashMap::new();
+    let mut parent: Vec<usize> = Vec::new();
+    let mut edges = Vec::with_capacity(links.len());
+    for link in &links {
+        let mut node = |key| {
+            let next = nodes.len();
+            *nodes.entry(key).or_insert_with(|| {
+                parent.push(next);
+                next
+            })
+        };
+        let old = node((false, link.departement.as_str(), link.old_id.as_str()));
+        let new = node((true, link.departement.as_str(), link.new_id.as_str()));
+        let (old_root, new_root) = (find(&mut parent, old), find(&mut parent, new));
+        if old_root != new_root {
+            parent[old_root] = new_root;
+        }
+        edges.push(old);
+    }
+
+    // Cardinalité de chaque composante
+    let mut components: HashMap<usize, (BTreeSet<&str>, BTreeSet<&str>)> = HashMap::new();
+    for (link, &old) in links.iter().zip(&edges) {
+        let root = find(&mut parent, old);
+        let entry = components.entry(root).or_default();
+        entry.0.insert(&link.old_id);
+        entry.1.insert(&link.new_id);
+    }
+
+    let mut result: Vec<LineageLink> = links
+        .iter()
+        .zip(&edges)
+        .map(|(link, &old)| {
+            let (olds, news) = &components[&find(&mut parent, old)];
+            let kind = match (olds.len(), news.len()) {
+                (1, 1) if link.iou() >= RENUMBER_MIN_IOU => LineageKind::Renumber,
+                (1, 1) => LineageKind::BoundaryAdjust,
+                (1, _) => LineageKind::Split,
+                (_, 1) => LineageKind::Merge,
+                _ => LineageKind::BoundaryAdjust,
+            };
+            LineageLink {
+                departement: link.departement.clone(),
+                old_id: link.old_id.clone(),
+                new_id: link.new_id.clone(),
+                kind,
+                overlap_ratio: ratio(link.intersection_area, link.old_area),
+            }
+        })
+        .collect();
+
+    result.sort_by(|a, b| {
+        (&a.departement, &a.old_id, &a.new_id).cmp(&(&b.departement, &b.old_id, &b.new_id))
+    });
+    result
+}
+
+/// Parcelle indexée pour le calcul des recouvrements
+struct Parcel<'a> {
+    id: String,
+    geometry: MultiPolygon,
+    bbox: Rect,
+    area: f64,
+    section: &'a str,
+    /// Raison de l'invalidité OGC: intersection non calculée
+    invalid: Option<String>,
+}
+
+fn parcels(result: &edigeo::ParseResult) -> BTreeMap<String, Parcel<'_>> {
+    let mut parcels = BTreeMap::new();
+    for feature in result
+        .features
+        .get(PARCELLE_FEATURE_TYPE)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+    {
+        let geometry = match &feature.geometry {
+            Geometry::Polygon(p) => MultiPolygon::new(vec![p.clone()]),
+            Geometry::MultiPolygon(mp) => mp.clone(),
+            _ => continue,
+        };
+        let Some(bbox) = geometry.bounding_rect() else {
+            continue;
+        };
+        let id = format!("{}{}", result.departement, feature.id);
+        parcels.insert(
+            id.clone(),
+            Parcel {
+                id,
+                area: geometry.unsigned_area(),
+                geometry,
+                bbox,
+                section: section_key(&feature.id),
+                invalid: edigeo::repair::valid::check(&feature.geometry).err(),
+            },
+        );
+    }
+    parcels
+}
+
+/// Recouvrements entre parcelles disparues et apparues de deux millésimes parsés
+///
+/// Les identifiants sont préfixés du département, comme dans les tables PostGIS.
+pub fn overlaps_from_parse_results(
+    old: &edigeo::ParseResult,
+    new: &edigeo::ParseResult,
+) -> Vec<ParcelOverlap> {
+    let old_parcels = parcels(old);
+    let new_parcels = parcels(new);
+
+    let mut created_by_section: HashMap<&str, Vec<&Parcel>> = HashMap::new();
+    for parcel in new_parcels.values() {
+        if !old_parcels.contains_key(&parcel.id) {
+            created_by_section
+                .entry(parcel.section)
+                .or_default()
+                .push(parcel);
+        }
+    }
+
+    let mut overlaps = Vec::new();
+    for gone in old_parcels.values() {
+        if new_parcels.contains_key(&gone.id) {
+            continue;
+        }
+        let Some(candidates) = created_by_section.get(gone.section) else {
+            continue;
+        };
+        for born in candidates {
+            if !gone.bbox.intersects(&born.bbox) {
+                continue;
+            }
+            // Les opérations booléennes de geo supposent des géométries valides
+            if let Some((id, reason)) = [gone, *born]
+                .into_iter()
+                .find_map(|p| Some((&p.id, p.invalid.as_ref()?)))
+            {
+                warn!(
+                    "Skipping parcels {} and {}: {} is invalid ({})",
+                    gone.id, born.id, id, reason
+                );
+                continue;
+            }
+            let intersection_area = gone.geometry.intersection(&born.geometry).unsigned_area();
+            if intersection_area > 0.0 {
+                overlaps.push(ParcelOverlap {
+                    departement: new.departement.clone(),
+                    old_id: gone.id.clone(),
+                    new_id: born.id.clone(),
+                    old_area: gone.area,
+                    new_area: born.area,
+                    intersection_area,
+                });
+            }
+        }
+    }
+
+    overlaps
+}
+
+/// Filiation entre deux millésimes parsés
+pub fn lineage_from_parse_results(
+    old: &edigeo::ParseResult,
+    new: &edigeo::ParseResult,
+) -> Vec<LineageLink> {
+    classify_overlaps(&overlaps_from_parse_results(old, new))
+}
+
+/// Requête des recouvrements entre les versions valides à $1 et à $2
+/// ($3: département optionnel)
+fn overlaps_sql(schema: &str, table: &str) -> String {
+    format!(
+        r#"
+        WITH a AS (
+            SELECT id, departement, geometry FROM {schema}.{table}
+            WHERE valid_from <= $1::text::date AND (valid_to IS NULL OR valid_to > $1::text::date)
+              AND ($3::text IS NULL OR departement = $3)
+        ), b AS (
+            SELECT id, departement, geometry FROM {schema}.{table}
+            WHERE valid_from <= $2::text::date AND (valid_to IS NULL OR valid_to > $2::text::date)
+              AND ($3::text IS NULL OR departement = $3)
+        ), gone AS (
+            SELECT * FROM a
+            WHERE NOT EXISTS (SELECT 1 FROM b WHERE b.departement = a.departement AND b.id = a.id)
+        ), born AS (
+            SELECT * FROM b
+            WHERE NOT EXISTS (SELECT 1 FROM a WHERE a.departement = b.departement AND a.id = b.id)
+        )
+        SELECT g.departement::text, g.id, n.id,
+               ST_Area(g.geometry), ST_Area(n.geometry),
+               ST_Area(ST_Intersection(ST_MakeValid(g.geometry), ST_MakeValid(n.geometry)))
+        FROM gone g
+        JOIN born n
+          ON n.departement = g.departement
+         AND left(n.id, length(n.id) - 4) = left(g.id, length(g.id) - 4)
+         AND ST_Intersects(g.geometry, n.geometry)
+        "#,
+        schema = schema,
+        table = table
+    )
+}
+
+/// Recouvrements entre parcelles disparues et apparues de deux millésimes importés
+pub async fn query_overlaps(
+    pool: &Pool,
+    schema: &str,
+    table: &str,
+    from: &str,
+    to: &str,
+    departement: Option<&str>,
+) -> Result<Vec<ParcelOverlap>> {
+    let client = pool.get().await?;
+    let from = format!("{}-01", from);
+    let to = format!("{}-01", to);
+
+    let rows = client
+        .query(&overlaps_sql(schema, table), &[&from, &to, &departement])
+        .await
+        .with_context(|| format!("Failed to compute overlaps in {}.{}", schema, table))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| ParcelOverlap {
+            departement: row.get(0),
+            old_id: row.get(1),
+            new_id: row.get(2),
+            old_area: row.get(3),
+            new_area: row.get(4),
+            intersection_area: row.get(5),
+        })
+        .collect())
+}
+
+/// Écrit les liens dans `<schema>.parcelle_lineage`
+///
+/// Les liens déjà calculés pour la même paire de millésimes (et le même
+/// département si précisé) sont remplacés.
+pub async fn write_lineage(
+    pool: &Pool,
+    schema: &str,
+    from: &str,
+    to: &str,
+    departement: Option<&str>,
+    links: &[LineageLink],
+) -> Result<u64> {
+    let mut client = pool.get().await?;
+    let from = format!("{}-01", from);
+    let to = format!("{}-01", to);
+
+    client
+        .batch_execute(&format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {schema}.{table} (
+                departement VARCHAR(3) NOT NULL,
+                old_id TEXT NOT NULL,
+                new_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                overlap_ratio DOUBLE PRECISION NOT NULL,
+                from_millesime DATE NOT NULL,
+                to_millesime DATE NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS {table}_old_id ON {schema}.{table} (old_id);
+            CREATE INDEX IF NOT EXISTS {table}_new_id ON {schema}.{table} (new_id);
+            "#,
+            schema = schema,
+            table = LINEAGE_TABLE
+        ))
+        .await
+        .with_context(|| format!("Failed to create {}.{}", schema, LINEAGE_TABLE))?;
+
+    let tx = client.transaction().await?;
+
+    tx.execute(
+        &format!(
+            "DELETE FROM {}.{} WHERE from_millesime = $1::text::date AND to_millesime = $2::text::date \
+             AND ($3::text IS NULL OR departement = $3)",
+            schema, LINEAGE_TABLE
+        ),
+        &[&from, &to, &departement],
+    )
+    .await?;
+
+    let departements: Vec<&str> = links.iter().map(|l| l.departement.as_str()).collect();
+    let old_ids: Vec<&str> = links.iter().map(|l| l.old_id.as_str()).collect();
+    let new_ids: Vec<&str> = links.iter().map(|l| l.new_id.as_str()).collect();
+    let kinds: Vec<&str> = links.iter().map(|l| l.kind.as_str()).collect();
+    let ratios: Vec<f64> = links.iter().map(|l| l.overlap_ratio).collect();
+
+    let inserted = tx
+        .execute(
+            &format!(
+                "INSERT INTO {}.{} \
+                 (departement, old_id, new_id, kind, overlap_ratio, from_millesime, to_millesime) \
+                 SELECT d, o, n, k, r, $6::text::date, $7::text::date \
+                 FROM UNNEST($1::text[], $2::text[], $3::text[], $4::text[], $5::float8[]) AS l(d, o, n, k, r)",
+                schema, LINEAGE_TABLE
+            ),
+            &[&departements, &old_ids, &new_ids, &kinds, &ratios, &from, &to],
+        )
+        .await
+        .with_context(|| format!("Failed to insert into {}.{}", schema, LINEAGE_TABLE))?;
+
+    tx.commit().await?;
+
+    info!(links = inserted, "Lineage written");
+    Ok(inserted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use edigeo::{Date, Exchange, Feature, ParseResult, Projection};
+    use geo::polygon;
+
+    fn overlap(old: &str, new: &str, old_area: f64, new_area: f64, inter: f64) -> ParcelOverlap {
+        ParcelOverlap {
+            departement: "38".to_string(),
+            old_id: old.to_string(),
+            new_id: new.to_string(),
+            old_area,
+            new_area,
+            intersection_area: inter,
+        }
+    }
+
+    fn kinds(links: &[LineageLink]) -> Vec<(&str, &str, LineageKind)> {
+        links
+            .iter()
+            .map(|l| (l.old_id.as_str(), l.new_id.as_str(), l.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_classify_split_and_merge() {
+        let links = classify_overlaps(&[
+            // A divisée en B et C
+            overlap("A", "B", 100.0, 60.0, 60.0),
+            overlap("A", "C", 100.0, 40.0, 40.0),
+            // D et E réunies en F
+            overlap("D", "F", 30.0, 50.0, 30.0),
+            overlap("E", "F", 20.0, 50.0, 20.0),
+        ]);
+
+        assert_eq!(
+            kinds(&links),
+            vec![
+                ("A", "B", LineageKind::Split),
+                ("A", "C", LineageKind::Split),
+                ("D", "F", LineageKind::Merge),
+                ("E", "F", LineageKind::Merge),
+            ]
+        );
+        assert!((links[0].overlap_ratio - 0.6).abs() < 1e-9);
+        assert!((links[2].overlap_ratio - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_classify_one_to_one() {
+        let links = classify_overlaps(&[
+            overlap("A", "B", 100.0, 100.0, 99.5),
+            overlap("C", "D", 100.0, 120.0, 95.0),
+        ]);
+
+        assert_eq!(
+            kinds(&links),
+            vec![
+                ("A", "B", LineageKind::Renumber),
+                ("C", "D", LineageKind::BoundaryAdjust),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_classify_ignores_slivers() {
+        // Contact le long d'une limite: ne lie pas A à C
+        let links = classify_overlaps(&[
+            overlap("A", "B", 100.0, 100.0, 100.0),
+            overlap("A", "C", 100.0, 100.0, 0.5),
+        ]);
+
+        assert_eq!(kinds(&links), vec![("A", "B", LineageKind::Renumber)]);
+    }
+
+    #[test]
+    fn test_classify_many_to_many() {
+        let links = classify_overlaps(&[
+            overlap("A", "C", 100.0, 100.0, 70.0),
+            overlap("A", "D", 100.0, 100.0, 30.0),
+            overlap("B", "D", 100.0, 100.0, 70.0),
+        ]);
+
+        assert!(links.iter().all(|l| l.kind == LineageKind::BoundaryAdjust));
+    }
+
+    #[test]
+    fn test_section_key() {
+        assert_eq!(section_key("380010000A0123"), "380010000A");
+        assert_eq!(section_key("12"), "");
+    }
+
+    fn parse_result(parcels: Vec<(&str, geo::Polygon)>) -> ParseResult {
+        let features = parcels
+            .into_iter()
+            .map(|(id, polygon)| Feature {
+                id: id.to_string(),
+                geometry: Geometry::Polygon(polygon),
+                properties: HashMap::new(),
+                feature_type: PARCELLE_FEATURE_TYPE.to_string(),
+                quality: None,
+            })
+            .collect();
+
+        ParseResult {
+            features: [(PARCELLE_FEATURE_TYPE.to_string(), features)]
+                .into_iter()
+                .collect(),
+            projection: Projection {
+                epsg: 2154,
+                name: "",
+            },
+            year: 2024,
+            departement: "38".to_string(),
+            exchange: Exchange::new(Date::new(2024, 1, 15).unwrap()),
+            schema: Default::default(),
+            general: Default::default(),
+            relations: Default::default(),
+            errors: Vec::new(),
+            repairs: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_lineage_from_parse_results() {
+        let square = |x0: f64, x1: f64| polygon![(x: x0, y: 0.0), (x: x1, y: 0.0), (x: x1, y: 10.0), (x: x0, y: 10.0)];
+
+        let old = parse_result(vec![
+            ("0010000A0001", square(0.0, 10.0)),
+            ("0010000A0002", square(10.0, 20.0)),
+        ]);
+        let new = parse_result(vec![
+            ("0010000A0002", square(10.0, 20.0)),
+            ("0010000A0010", square(0.0, 4.0)),
+            ("0010000A0011", square(4.0, 10.0)),
+        ]);
+
+        let links = lineage_from_parse_results(&old, &new);
+        assert_eq!(
+            kinds(&links),
+            vec![
+                ("380010000A0001", "380010000A0010", LineageKind::Split),
+                ("380010000A0001", "380010000A0011", LineageKind::Split),
+            ]
+        );
+        assert!((links[0].overlap_ratio - 0.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_lineage_skips_invalid_parcels() {
+        let square = |x0: f64, x1: f64| polygon![(x: x0, y: 0.0), (x: x1, y: 0.0), (x: x1, y: 10.0), (x: x0, y: 10.0)];
+        // Nœud papillon: auto-intersection
+        let bowtie =
+            polygon![(x: 0.0, y: 0.0), (x: 4.0, y: 10.0), (x: 4.0, y: 0.0), (x: 0.0, y: 10.0)];
+
+        let old = parse_result(vec![("0010000A0001", square(0.0, 10.0))]);
+        let new = parse_result(vec![
+            ("0010000A0010", bowtie),
+            ("0010000A0011", square(4.0, 10.0)),
+        ]);
+
+        let overlaps = overlaps_from_parse_results(&old, &new);
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].new_id, "380010000A0011");
+    }
+}
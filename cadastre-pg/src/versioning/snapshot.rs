@@ -0,0 +1,203 @@
+//! Vues instantanées (snapshots) des tables versionnées
+//!
+//! Pour chaque table de la configuration:
+//! - `<table>_<yyyy>_<mm>`: entités valides au premier jour du millésime
+//! - `<table>_current`: versions actives (`valid_to IS NULL`)
+//!
+//! Les vues peuvent être matérialisées; une vue existante est alors rafraîchie
+//! si sa définition (gardée en commentaire) n'a pas changé, recréée sinon.
+
+use anyhow::{Context, Result};
+use deadpool_postgres::Pool;
+use tracing::info;
+
+use crate::export::postgres::TableConfig;
+
+/// Instant d'une vue snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotAt<'a> {
+    /// Millésime au format YYYY-MM
+    Millesime(&'a str),
+    /// Versions actives
+    Current,
+}
+
+impl SnapshotAt<'_> {
+    /// Suffixe du nom de vue (`2024_01` ou `current`)
+    pub fn suffix(&self) -> String {
+        match self {
+            SnapshotAt::Millesime(date) => date.replace('-', "_"),
+            SnapshotAt::Current => "current".to_string(),
+        }
+    }
+
+    /// Prédicat temporel SQL
+    fn predicate(&self) -> String {
+        match self {
+            SnapshotAt::Millesime(date) => format!(
+                "valid_from <= DATE '{date}-01' AND (valid_to IS NULL OR valid_to > DATE '{date}-01')",
+                date = date
+            ),
+            SnapshotAt::Current => "valid_to IS NULL".to_string(),
+        }
+    }
+}
+
+/// Nom de la vue snapshot d'une table
+pub fn snapshot_view_name(table: &str, at: SnapshotAt) -> String {
+    format!("{}_{}", table, at.suffix())
+}
+
+/// Requête SELECT d'une vue snapshot, avec les colonnes de la configuration
+fn snapshot_select(schema: &str, table: &TableConfig, at: SnapshotAt) -> String {
+    let mut columns = vec!["id", "departement", "geometry"];
+    columns.extend(table.columns.iter().map(|c| c.name.as_str()));
+    columns.extend(["valid_from", "valid_to"]);
+
+    format!(
+        "SELECT {} FROM {}.{} WHERE {}",
+        columns.join(", "),
+        schema,
+        table.name,
+        at.predicate()
+    )
+}
+
+/// Recrée une vue matérialisée et ses index, définition en commentaire
+fn materialized_view_sql(schema: &str, view: &str, select: &str) -> String {
+    format!(
+        "DROP MATERIALIZED VIEW IF EXISTS {schema}.{view}; \
+         CREATE MATERIALIZED VIEW {schema}.{view} AS {select}; \
+         CREATE INDEX {view}_id ON {schema}.{view} (departement, id); \
+         CREATE INDEX {view}_geom ON {schema}.{view} USING GIST (geometry); \
+         COMMENT ON MATERIALIZED VIEW {schema}.{view} IS '{definition}'",
+        schema = schema,
+        view = view,
+        select = select,
+        definition = select.replace('\'', "''")
+    )
+}
+
+/// Crée (ou rafraîchit) les vues snapshot de toutes les tables
+///
+/// # Returns
+/// Les noms des vues créées ou rafraîchies
+pub async fn create_snapshot_views(
+    pool: &Pool,
+    schema: &str,
+    tables: &[TableConfig],
+    at: SnapshotAt<'_>,
+    materialized: bool,
+) -> Result<Vec<String>> {
+    let client = pool.get().await?;
+    let mut views = Vec::with_capacity(tables.len());
+
+    for table in tables {
+        let view = snapshot_view_name(&table.name, at);
+        let select = snapshot_select(schema, table, at);
+
+        if materialized {
+            // Définition de la vue existante (NULL: créée sans commentaire)
+            let definition: Option<Option<String>> = client
+                .query_opt(
+                    "SELECT obj_description(format('%I.%I', schemaname, matviewname)::regclass, 'pg_class') \
+                     FROM pg_matviews WHERE schemaname = $1 AND matviewname = $2",
+                    &[&schema, &view],
+                )
+                .await?
+                .map(|row| row.get(0));
+
+            if definition.flatten().as_deref() == Some(select.as_str()) {
+                client
+                    .execute(
+                        &format!("REFRESH MATERIALIZED VIEW {}.{}", schema, view),
+                        &[],
+                    )
+                    .await
+                    .with_context(|| format!("Failed to refresh {}.{}", schema, view))?;
+            } else {
+                // Une seule requête simple: suppression et création atomiques
+                client
+                    .batch_execute(&materialized_view_sql(schema, &view, &select))
+                    .await
+                    .with_context(|| format!("Failed to create {}.{}", schema, view))?;
+            }
+        } else {
+            client
+                .execute(
+                    &format!("CREATE OR REPLACE VIEW {}.{} AS {}", schema, view, select),
+                    &[],
+                )
+                .await
+                .with_context(|| format!("Failed to create view {}.{}", schema, view))?;
+        }
+
+        info!(
+            view = view.as_str(),
+            materialized = materialized,
+            "Snapshot view ready"
+        );
+        views.push(view);
+    }
+
+    Ok(views)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::postgres::ColumnConfig;
+
+    fn parcelles() -> TableConfig {
+        TableConfig {
+            name: "edi_parcelles".to_string(),
+            geometry_type: "Geometry".to_string(),
+            srid: 4326,
+            columns: vec![ColumnConfig {
+                name: "numero".to_string(),
+                pg_type: "TEXT".to_string(),
+                source: "TEX".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_snapshot_view_name() {
+        assert_eq!(
+            snapshot_view_name("edi_parcelles", SnapshotAt::Millesime("2024-01")),
+            "edi_parcelles_2024_01"
+        );
+        assert_eq!(
+            snapshot_view_name("edi_parcelles", SnapshotAt::Current),
+            "edi_parcelles_current"
+        );
+    }
+
+    #[test]
+    fn test_snapshot_select_millesime() {
+        let sql = snapshot_select("cadastre", &parcelles(), SnapshotAt::Millesime("2024-01"));
+        assert_eq!(
+            sql,
+            "SELECT id, departement, geometry, numero, valid_from, valid_to \
+             FROM cadastre.edi_parcelles \
+             WHERE valid_from <= DATE '2024-01-01' \
+             AND (valid_to IS NULL OR valid_to > DATE '2024-01-01')"
+        );
+    }
+
+    #[test]
+    fn test_snapshot_select_current() {
+        let sql = snapshot_select("cadastre", &parcelles(), SnapshotAt::Current);
+        assert!(sql.ends_with("WHERE valid_to IS NULL"));
+    }
+
+    #[test]
+    fn test_materialized_view_sql_recreates_view() {
+        let select = snapshot_select("cadastre", &parcelles(), SnapshotAt::Millesime("2024-01"));
+        let sql = materialized_view_sql("cadastre", "edi_parcelles_2024_01", &select);
+        assert!(sql.starts_with("DROP MATERIALIZED VIEW IF EXISTS cadastre.edi_parcelles_2024_01;"));
+        assert!(sql.contains("CREATE MATERIALIZED VIEW cadastre.edi_parcelles_2024_01 AS SELECT"));
+        assert!(sql.ends_with(&format!("IS '{}'", select.replace('\'', "''"))));
+        assert!(sql.contains("DATE ''2024-01-01''"));
+    }
+}
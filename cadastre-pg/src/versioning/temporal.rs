@@ -1,7 +1,8 @@
 //! Gestion du versioning temporel des entités cadastrales
 //!
 //! Ce module gère le marquage des entités pour le versioning temporel:
-//! - Marquage des entités existantes comme potentiellement terminées
+//! - Fin de vie (`valid_to`) des entités absentes du nouveau millésime
+//! - Fin de vie des versions dont la géométrie ou les attributs ont changé
 //! - Réactivation des entités trouvées dans le nouveau millésime
 //! - Conservation de l'historique des versions
 
@@ -9,6 +10,8 @@ use anyhow::{Context, Result};
 use deadpool_postgres::Transaction;
 use tracing::info;
 
+use crate::config::ChangeDetection;
+
 /// Tables cadastrales avec versioning temporel
 pub const CADASTRE_TABLES: &[&str] = &["parcelles", "sections", "communes", "batiments"];
 
@@ -24,76 +27,75 @@ pub struct TableMarkingResult {
     pub rows_marked: usize,
 }
 
-/// Rapport de marquage pour toutes les tables
-#[derive(Debug, Default)]
-pub struct MarkingReport {
-    /// Résultats par table
-    pub tables: Vec<TableMarkingResult>,
+/// Périmètre d'un import de millésime
+///
+/// Seules les entités des départements importés peuvent être terminées, et
+/// jamais celles provenant d'une archive qui n'a pas été relue (checksum
+/// inchangé ou archive en erreur).
+#[derive(Debug, Clone, Default)]
+pub struct MillesimeScope {
+    /// Date du millésime (format YYYY-MM-DD)
+    pub valid_from: String,
+    /// Départements effectivement importés
+    pub departements: Vec<String>,
+    /// Archives non relues dont les entités sont conservées telles quelles
+    pub kept_archives: Vec<String>,
 }
 
-impl MarkingReport {
-    /// Retourne le nombre total d'entités marquées
-    pub fn total_marked(&self) -> usize {
-        self.tables.iter().map(|t| t.rows_marked).sum()
-    }
-
-    /// Vérifie si des entités ont été marquées
-    pub fn has_marked_entities(&self) -> bool {
-        self.total_marked() > 0
-    }
+/// Résultat de la fusion staging → table finale pour une table
+#[derive(Debug, Clone, Default)]
+pub struct TableMergeResult {
+    /// Nom de la table
+    pub table: String,
+    /// Nouvelles versions insérées (entités nouvelles ou modifiées)
+    pub inserted: u64,
+    /// Versions fermées car l'entité a changé (rouvertes par l'insertion)
+    pub replaced: u64,
+    /// Versions fermées car l'entité a disparu du millésime
+    pub ended: u64,
 }
 
-/// Marque toutes les entités actives comme potentiellement terminées
-///
-/// Cette fonction est appelée au début d'un import de millésime.
-/// Elle met `valid_to` à la date du millésime pour toutes les entités
-/// qui ont actuellement `valid_to IS NULL`.
-///
-/// Après l'import, les entités qui n'ont pas été réactivées ou mises à jour
-/// conserveront cette date `valid_to`, indiquant qu'elles ont disparu.
-///
-/// # Arguments
-/// * `tx` - Transaction PostgreSQL en cours
-/// * `schema` - Schéma de la base de données
-/// * `millesime_date` - Date du millésime (format YYYY-MM-DD)
-///
-/// # Returns
-/// Un rapport indiquant combien d'entités ont été marquées par table
-pub async fn mark_all_as_ended(
-    tx: &Transaction<'_>,
-    schema: &str,
-    millesime_date: &str,
-) -> Result<MarkingReport> {
-    let mut report = MarkingReport::default();
-
-    for table in CADASTRE_TABLES {
-        let result = mark_table_as_ended(tx, schema, table, millesime_date).await?;
-        report.tables.push(result);
+impl TableMergeResult {
+    /// Nombre d'entités apparues dans ce millésime
+    pub fn created(&self) -> u64 {
+        self.inserted.saturating_sub(self.replaced)
     }
-
-    info!(
-        total_marked = report.total_marked(),
-        millesime = millesime_date,
-        "Completed marking all entities as potentially ended"
-    );
-
-    Ok(report)
 }
 
-/// Marque les entités d'une table spécifique comme potentiellement terminées
+/// Termine les entités d'une table absentes de sa staging
 pub async fn mark_table_as_ended(
     tx: &Transaction<'_>,
     schema: &str,
     table: &str,
-    millesime_date: &str,
+    scope: &MillesimeScope,
 ) -> Result<TableMarkingResult> {
+    // Une ligne sans provenance (importée avant `source_archive`) n'est terminée
+    // que si aucune archive n'a été conservée: `NULL <> ALL('{}')` vaut vrai,
+    // `NULL <> ALL('{a}')` vaut NULL. Un lot d'archive départementale porte le
+    // chemin `archive/lot`: l'archive est son premier segment.
     let query = format!(
-        "UPDATE {}.{} SET valid_to = $1 WHERE valid_to IS NULL",
-        schema, table
+        r#"
+        UPDATE {schema}.{table} t
+        SET valid_to = $1::text::date, updated_at = NOW()
+        WHERE t.valid_to IS NULL
+          AND t.valid_from < $1::text::date
+          AND t.departement = ANY($2)
+          AND COALESCE(split_part(t.source_archive, '/', 1) <> ALL($3), false)
+          AND NOT EXISTS (
+              SELECT 1 FROM {schema}.{staging} s
+              WHERE s.departement = t.departement AND s.id = t.id
+          )
+        "#,
+        schema = schema,
+        table = table,
+        staging = staging_table_name(table)
     );
 
     let rows_affected = tx
-        .execute(&query, &[&millesime_date])
+        .execute(
+            &query,
+            &[&scope.valid_from, &scope.departements, &scope.kept_archives],
+        )
         .await
         .with_context(|| format!("Failed to mark {} as ended", table))?;
 
@@ -101,16 +103,134 @@ pub async fn mark_table_as_ended(
         table = table,
         schema = schema,
         rows = rows_affected,
-        millesime = millesime_date,
-        "Marked entities as potentially ended"
+        millesime = scope.valid_from.as_str(),
+        "Marked vanished entities as ended"
+    );
+
+    Ok(TableMarkingResult {
+        table: table.to_string(),
+        rows_marked: rows_affected as usize,
+    })
+}
+
+/// Termine les versions actives dont la géométrie ou les attributs diffèrent
+/// de la staging, selon le critère `change_detection` de la table
+///
+/// Les lignes de staging sans géométrie sont des marqueurs « vu, inchangé »
+/// (écrits quand les hash préchargés correspondent) et sont ignorées ici.
+/// La nouvelle version est insérée ensuite par la fusion.
+pub async fn mark_changed_as_ended(
+    tx: &Transaction<'_>,
+    schema: &str,
+    table: &str,
+    dynamic_columns: &[String],
+    change_detection: ChangeDetection,
+    scope: &MillesimeScope,
+) -> Result<TableMarkingResult> {
+    let query = format!(
+        r#"
+        UPDATE {schema}.{table} t
+        SET valid_to = $1::text::date, updated_at = NOW()
+        FROM {schema}.{staging} s
+        WHERE t.valid_to IS NULL
+          AND t.valid_from < $1::text::date
+          AND s.departement = t.departement
+          AND s.id = t.id
+          AND s.geometry IS NOT NULL
+          AND ({predicate})
+        "#,
+        schema = schema,
+        table = table,
+        staging = staging_table_name(table),
+        predicate = change_predicate(dynamic_columns, change_detection)
     );
 
+    let rows_affected = tx
+        .execute(&query, &[&scope.valid_from])
+        .await
+        .with_context(|| format!("Failed to mark changed entities of {} as ended", table))?;
+
     Ok(TableMarkingResult {
         table: table.to_string(),
         rows_marked: rows_affected as usize,
     })
 }
 
+/// Renseigne `attributes_hash` sur les versions actives qui n'en ont pas
+/// (importées avant l'ajout de la colonne) lorsque leurs attributs sont
+/// identiques à la staging, pour que le préchargement puisse les reconnaître
+/// au millésime suivant.
+pub async fn backfill_attributes_hash(
+    tx: &Transaction<'_>,
+    schema: &str,
+    table: &str,
+    dynamic_columns: &[String],
+) -> Result<u64> {
+    let query = format!(
+        r#"
+        UPDATE {schema}.{table} t
+        SET attributes_hash = s.attributes_hash
+        FROM {schema}.{staging} s
+        WHERE t.valid_to IS NULL
+          AND t.attributes_hash IS NULL
+          AND s.departement = t.departement
+          AND s.id = t.id
+          AND s.geometry IS NOT NULL
+          AND NOT ({attributes_changed})
+        "#,
+        schema = schema,
+        table = table,
+        staging = staging_table_name(table),
+        attributes_changed = attributes_predicate(dynamic_columns)
+    );
+
+    tx.execute(&query, &[])
+        .await
+        .with_context(|| format!("Failed to backfill attributes_hash of {}", table))
+}
+
+/// Prédicat SQL de changement entre une version active `t` et la staging `s`
+fn change_predicate(dynamic_columns: &[String], change_detection: ChangeDetection) -> String {
+    let mut parts = Vec::new();
+
+    if change_detection.geometry() {
+        // Sans hash de géométrie (`hash_geom: false`), on compare les géométries.
+        parts.push(
+            "s.geometry_hash IS DISTINCT FROM t.geometry_hash \
+             OR (s.geometry_hash IS NULL AND NOT ST_OrderingEquals(ST_MakeValid(s.geometry), t.geometry))"
+                .to_string(),
+        );
+    }
+    if change_detection.attributes() {
+        parts.push(attributes_predicate(dynamic_columns));
+    }
+
+    parts.join(" OR ")
+}
+
+/// Prédicat SQL de changement des colonnes configurées
+///
+/// Les colonnes sont comparées directement (et non `attributes_hash`) pour
+/// rester exact sur les versions importées avant l'ajout du hash.
+fn attributes_predicate(dynamic_columns: &[String]) -> String {
+    if dynamic_columns.is_empty() {
+        return "false".to_string();
+    }
+
+    let t_cols: Vec<String> = dynamic_columns.iter().map(|c| format!("t.{}", c)).collect();
+    let s_cols: Vec<String> = dynamic_columns.iter().map(|c| format!("s.{}", c)).collect();
+    format!(
+        "({}) IS DISTINCT FROM ({})",
+        t_cols.join(", "),
+        s_cols.join(", ")
+    )
+}
+
+/// Nom de la table de staging associée à une table finale
+pub fn staging_table_name(table: &str) -> String {
+    format!("_staging_{}", table)
+}
+
 /// Réactive une entité existante (remet valid_to à NULL)
 ///
 /// Appelée lorsqu'une entité existe toujours dans le nouveau millésime
@@ -144,7 +264,7 @@ pub async fn count_ended_entities(
 
     for table in CADASTRE_TABLES {
         let query = format!(
-            "SELECT COUNT(*) FROM {}.{} WHERE valid_to = $1",
+            "SELECT COUNT(*) FROM {}.{} WHERE valid_to = $1::text::date",
             schema, table
         );
 
@@ -186,29 +306,44 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_marking_report_total() {
-        let report = MarkingReport {
-            tables: vec![
-                TableMarkingResult {
-                    table: "parcelles".to_string(),
-                    rows_marked: 100,
-                },
-                TableMarkingResult {
-                    table: "sections".to_string(),
-                    rows_marked: 50,
-                },
-            ],
-        };
+    fn test_change_predicate_with_columns() {
+        let columns = ["numero".to_string(), "contenance".to_string()];
+        let predicate = change_predicate(&columns, ChangeDetection::Both);
+        assert!(predicate.contains("s.geometry_hash IS DISTINCT FROM t.geometry_hash"));
+        assert!(predicate
+            .contains("(t.numero, t.contenance) IS DISTINCT FROM (s.numero, s.contenance)"));
+    }
+
+    #[test]
+    fn test_change_predicate_without_columns() {
+        let predicate = change_predicate(&[], ChangeDetection::Both);
+        assert!(!predicate.contains("t.numero"));
+        assert!(predicate.contains("ST_OrderingEquals"));
+    }
 
-        assert_eq!(report.total_marked(), 150);
-        assert!(report.has_marked_entities());
+    #[test]
+    fn test_change_predicate_geometry_only() {
+        let predicate = change_predicate(&["numero".to_string()], ChangeDetection::Geometry);
+        assert!(predicate.contains("geometry_hash"));
+        assert!(!predicate.contains("t.numero"));
     }
 
     #[test]
-    fn test_empty_marking_report() {
-        let report = MarkingReport::default();
-        assert_eq!(report.total_marked(), 0);
-        assert!(!report.has_marked_entities());
+    fn test_change_predicate_attributes_only() {
+        let predicate = change_predicate(&["numero".to_string()], ChangeDetection::Attributes);
+        assert_eq!(predicate, "(t.numero) IS DISTINCT FROM (s.numero)");
+        assert_eq!(change_predicate(&[], ChangeDetection::Attributes), "false");
+    }
+
+    #[test]
+    fn test_merge_result_created() {
+        let result = TableMergeResult {
+            table: "edi_parcelles".to_string(),
+            inserted: 12,
+            replaced: 5,
+            ended: 3,
+        };
+        assert_eq!(result.created(), 7);
     }
 
     #[test]
@@ -412,3 +412,117 @@ async fn test_entity_upsert() {
     let updated_hash: Vec<u8> = updated.get(0);
     assert_eq!(updated_hash, hash2);
 }
+
+/// Test de la fusion d'un millésime avec fin de vie des entités
+#[tokio::test]
+#[ignore = "Requires PostgreSQL database"]
+async fn test_millesime_merge_versioning() {
+    use cadastre_pg::config::ChangeDetection;
+    use cadastre_pg::export::postgres::{
+        create_schema, create_staging_tables, merge_staging_into_table, ColumnConfig, TableConfig,
+    };
+    use cadastre_pg::versioning::temporal::MillesimeScope;
+
+    let pool = create_test_pool().await.expect("Failed to create pool");
+    let schema = "cadastre_merge";
+    let tables = vec![TableConfig {
+        name: "parcelles".to_string(),
+        geometry_type: "Geometry".to_string(),
+        srid: 2154,
+        columns: vec![ColumnConfig {
+            name: "numero".to_string(),
+            pg_type: "TEXT".to_string(),
+            source: "TEX".to_string(),
+        }],
+    }];
+    create_schema(&pool, schema, &tables, true)
+        .await
+        .expect("Failed to create schema");
+
+    let client = pool.get().await.expect("Failed to get client");
+
+    // Millésime 2024: A inchangée, B modifiée, C disparue, D dans une archive ignorée
+    client
+        .batch_execute(
+            r#"
+            INSERT INTO cadastre_merge.parcelles
+                (id, departement, geometry, numero, valid_from, geometry_hash, source_archive)
+            VALUES
+                ('38A', '38', 'SRID=2154;POINT(0 0)', '1', '2024-01-01', '\x01', 'a.tar.bz2'),
+                ('38B', '38', 'SRID=2154;POINT(1 1)', '2', '2024-01-01', '\x02', 'a.tar.bz2'),
+                ('38C', '38', 'SRID=2154;POINT(2 2)', '3', '2024-01-01', '\x03', 'a.tar.bz2'),
+                ('38D', '38', 'SRID=2154;POINT(3 3)', '4', '2024-01-01', '\x04', 'k.tar.bz2'),
+                ('01A', '01', 'SRID=2154;POINT(4 4)', '5', '2024-01-01', '\x05', 'z.tar.bz2'),
+                ('38F', '38', 'SRID=2154;POINT(6 6)', '7', '2024-01-01', '\x07', 'a.tar.bz2');
+            "#,
+        )
+        .await
+        .expect("Failed to insert 2024");
+
+    create_staging_tables(&pool, schema, &tables)
+        .await
+        .expect("Failed to create staging");
+
+    // Millésime 2025: marqueur pour A, nouvelle géométrie pour B, nouvelle entité E,
+    // même géométrie mais numéro modifié pour F
+    client
+        .batch_execute(
+            r#"
+            INSERT INTO cadastre_merge._staging_parcelles
+                (id, departement, geometry, numero, valid_from, geometry_hash, source_archive)
+            VALUES
+                ('38A', '38', NULL, NULL, '2025-01-01', '\x01', 'a.tar.bz2'),
+                ('38B', '38', 'SRID=2154;POINT(1 2)', '2', '2025-01-01', '\x22', 'a.tar.bz2'),
+                ('38E', '38', 'SRID=2154;POINT(5 5)', '6', '2025-01-01', '\x06', 'a.tar.bz2'),
+                ('38F', '38', 'SRID=2154;POINT(6 6)', '8', '2025-01-01', '\x07', 'a.tar.bz2');
+            "#,
+        )
+        .await
+        .expect("Failed to fill staging");
+
+    let scope = MillesimeScope {
+        valid_from: "2025-01-01".to_string(),
+        departements: vec!["38".to_string()],
+        kept_archives: vec!["k.tar.bz2".to_string()],
+    };
+    let columns = ["numero".to_string()];
+    let merged = merge_staging_into_table(
+        &pool,
+        schema,
+        "parcelles",
+        &columns,
+        ChangeDetection::Both,
+        &scope,
+    )
+    .await
+    .expect("Failed to merge");
+
+    assert_eq!(merged.inserted, 3);
+    assert_eq!(merged.replaced, 2);
+    assert_eq!(merged.ended, 1);
+
+    let active: Vec<String> = client
+        .query(
+            "SELECT id FROM cadastre_merge.parcelles WHERE valid_to IS NULL ORDER BY id",
+            &[],
+        )
+        .await
+        .expect("Failed to query")
+        .iter()
+        .map(|r| r.get(0))
+        .collect();
+    assert_eq!(active, vec!["01A", "38A", "38B", "38D", "38E", "38F"]);
+
+    // Rejouer le même millésime ne change rien
+    let replay = merge_staging_into_table(
+        &pool,
+        schema,
+        "parcelles",
+        &columns,
+        ChangeDetection::Both,
+        &scope,
+    )
+    .await
+    .expect("Failed to replay merge");
+    assert_eq!(replay.inserted + replay.replaced + replay.ended, 0);
+}
@@ -15,13 +15,165 @@ fn find_fixtures() -> Vec<std::path::PathBuf> {
         .filter_map(|e| e.ok())
     {
         let path = entry.path();
-        if path.extension().map_or(false, |ext| ext == "bz2") {
+        if path.extension().is_some_and(|ext| ext == "bz2") {
             archives.push(path.to_path_buf());
         }
     }
     archives
 }
 
+/// VEC synthétique: grille de `n` × `n` parcelles (arcs partagés, attributs en Latin-9)
+fn synthetic_vec(n: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut record = |kind: &str, fields: &[(&str, String)]| {
+        out.extend_from_slice(format!("RTYSA03:{}\r\n", kind).as_bytes());
+        for (code, value) in fields {
+            out.extend_from_slice(format!("{}{:02}:", code, value.chars().count()).as_bytes());
+            out.extend_from_slice(value.as_bytes());
+            out.extend_from_slice(b"\r\n");
+        }
+    };
+    let reference = |rty: &str, rid: &str| format!("EDAB01;SeSD;{};{}", rty, rid);
+    let coord = |i: usize, j: usize| {
+        format!(
+            "+{:.2};+{:.2};",
+            880000.0 + 10.0 * i as f64,
+            6660000.0 + 10.0 * j as f64
+        )
+    };
+
+    // Arcs horizontaux H_i_j et verticaux V_i_j
+    for i in 0..=n {
+        for j in 0..=n {
+            if i < n {
+                record(
+                    "PAR",
+                    &[
+                        ("RIDSA", format!("H_{}_{}", i, j)),
+                        ("SCPCP", reference("ARC", "ID_S_OBJ_ARC")),
+                        ("CORCC", coord(i, j)),
+                        ("CORCC", coord(i + 1, j)),
+                    ],
+                );
+            }
+            if j < n {
+                record(
+                    "PAR",
+                    &[
+                        ("RIDSA", format!("V_{}_{}", i, j)),
+                        ("SCPCP", reference("ARC", "ID_S_OBJ_ARC")),
+                        ("CORCC", coord(i, j)),
+                        ("CORCC", coord(i, j + 1)),
+                    ],
+                );
+            }
+        }
+    }
+    for i in 0..n {
+        for j in 0..n {
+            let face = format!("F_{}_{}", i, j);
+            let fea = format!("P_{}_{}", i, j);
+            record(
+                "PFE",
+                &[
+                    ("RIDSA", face.clone()),
+                    ("SCPCP", reference("FAC", "ID_S_OBJ_FAC")),
+                ],
+            );
+            record(
+                "FEA",
+                &[
+                    ("RIDSA", fea.clone()),
+                    ("SCPCP", reference("OBJ", "PARCELLE_id")),
+                    ("ATPCP", reference("ATT", "IDU_id")),
+                    ("ATVST", format!("380010000A{:04}", i * n + j)),
+                    ("ATPCP", reference("ATT", "TEX_id")),
+                    ("ATVST", "Lieu-dit des Pr\u{e9}s".to_string()),
+                ],
+            );
+            record(
+                "LNK",
+                &[
+                    ("RIDSA", format!("L_{}_{}", i, j)),
+                    ("SCPCP", reference("REL", "ID_S_RCO_FAC_SEMANT")),
+                    ("FTPCP", reference("FEA", &fea)),
+                    ("FTPCP", reference("PFE", &face)),
+                ],
+            );
+            for (k, arc) in [
+                format!("H_{}_{}", i, j),
+                format!("V_{}_{}", i + 1, j),
+                format!("H_{}_{}", i, j + 1),
+                format!("V_{}_{}", i, j),
+            ]
+            .iter()
+            .enumerate()
+            {
+                record(
+                    "LNK",
+                    &[
+                        ("RIDSA", format!("C_{}_{}_{}", i, j, k)),
+                        ("SCPCP", reference("REL", "ID_S_RCO_FAC_DROITE")),
+                        ("FTPCP", reference("PFE", &face)),
+                        ("FTPCP", reference("PAR", arc)),
+                    ],
+                );
+            }
+        }
+    }
+    // Latin-9: un octet par caractère accentué
+    let (latin9, _, _) = encoding_rs::ISO_8859_15.encode(std::str::from_utf8(&out).unwrap());
+    latin9.into_owned()
+}
+
+fn bench_parse_vec(c: &mut Criterion) {
+    let data = synthetic_vec(100);
+
+    let mut group = c.benchmark_group("parse_vec");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("grid_100x100", |b| {
+        b.iter(|| {
+            let parsed =
+                edigeo::parser::vec::parse(black_box(&data), encoding_rs::ISO_8859_15).unwrap();
+            black_box(parsed.fea.len())
+        })
+    });
+    group.finish();
+}
+
+fn bench_decompress_bz2(c: &mut Criterion) {
+    use std::io::{Read, Write};
+
+    let data = synthetic_vec(100);
+    let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::best());
+    encoder.write_all(&data).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let mut group = c.benchmark_group("decompress_bz2");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.sample_size(10);
+    group.bench_function("sequential", |b| {
+        b.iter(|| {
+            let mut out = Vec::new();
+            bzip2::read::BzDecoder::new(black_box(compressed.as_slice()))
+                .read_to_end(&mut out)
+                .unwrap();
+            black_box(out.len())
+        })
+    });
+    #[cfg(feature = "parallel")]
+    group.bench_function("parallel", |b| {
+        b.iter(|| {
+            let mut out = Vec::new();
+            edigeo::ParBzDecoder::new(black_box(compressed.as_slice()))
+                .read_to_end(&mut out)
+                .unwrap();
+            black_box(out.len())
+        })
+    });
+    group.finish();
+}
+
 fn bench_parse_single(c: &mut Criterion) {
     let fixtures = find_fixtures();
     if fixtures.is_empty() {
@@ -116,6 +268,8 @@ fn bench_parse_parallel(c: &mut Criterion) {
 
 criterion_group!(
     benches,
+    bench_parse_vec,
+    bench_decompress_bz2,
     bench_parse_single,
     bench_parse_batch,
     bench_parse_parallel
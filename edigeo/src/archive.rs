@@ -1,11 +1,9 @@
-//! Extraction des archives EDIGEO (.tar.bz2)
+//! Extraction des archives EDIGEO (.tar.bz2, .tar.gz, .tar, .zip ou répertoire)
 
-use bzip2::read::BzDecoder;
-use std::collections::HashMap;
-use std::io::Read;
+use std::ops::ControlFlow;
 use std::path::Path;
-use tar::Archive;
 
+use crate::input::Lot;
 use crate::EdigeoError;
 
 /// Contenu extrait d'une archive EDIGEO
@@ -23,77 +21,124 @@ pub struct EdigeoArchive {
     /// Contenu des fichiers VEC (données vectorielles)
     /// Il peut y en avoir plusieurs (un par feuille)
     pub vec: Vec<Vec<u8>>,
+
+    /// Contenu du fichier DIC (dictionnaire), vide si absent
+    pub dic: Vec<u8>,
+
+    /// Contenu du fichier SCD (schéma conceptuel), vide si absent
+    pub scd: Vec<u8>,
+
+    /// Contenu du fichier GEN (informations générales), vide si absent
+    pub gen: Vec<u8>,
+}
+
+/// Type d'un fichier EDIGEO utile au parsing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    /// Métadonnées du lot
+    Thf,
+    /// Projection
+    Geo,
+    /// Qualité
+    Qal,
+    /// Données vectorielles
+    Vec,
+    /// Dictionnaire
+    Dic,
+    /// Schéma conceptuel
+    Scd,
+    /// Informations générales
+    Gen,
+}
+
+impl EntryKind {
+    /// Type d'après l'extension du fichier (insensible à la casse)
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = path.rsplit('.').next().unwrap_or("");
+        if extension.eq_ignore_ascii_case("THF") {
+            Some(EntryKind::Thf)
+        } else if extension.eq_ignore_ascii_case("GEO") {
+            Some(EntryKind::Geo)
+        } else if extension.eq_ignore_ascii_case("QAL") {
+            Some(EntryKind::Qal)
+        } else if extension.eq_ignore_ascii_case("VEC") {
+            Some(EntryKind::Vec)
+        } else if extension.eq_ignore_ascii_case("DIC") {
+            Some(EntryKind::Dic)
+        } else if extension.eq_ignore_ascii_case("SCD") {
+            Some(EntryKind::Scd)
+        } else if extension.eq_ignore_ascii_case("GEN") {
+            Some(EntryKind::Gen)
+        } else {
+            // Ignorer les autres fichiers
+            None
+        }
+    }
+}
+
+/// Parcourt les fichiers EDIGEO d'une archive au fil de la décompression
+///
+/// Chaque fichier utile est lu puis transmis à `on_entry` avant de passer au
+/// suivant: un seul fichier est en mémoire à la fois. Le parcours s'arrête si
+/// `on_entry` retourne `ControlFlow::Break`. Voir `input::Lot` pour les
+/// formats acceptés.
+pub fn for_each_entry<F>(path: &Path, on_entry: F) -> Result<(), EdigeoError>
+where
+    F: FnMut(EntryKind, Vec<u8>) -> ControlFlow<()>,
+{
+    Lot::open(path)?.for_each_entry(on_entry)
 }
 
 /// Extrait une archive EDIGEO en mémoire
 ///
 /// # Arguments
 ///
-/// * `path` - Chemin vers l'archive .tar.bz2
+/// * `path` - Chemin vers l'archive ou le répertoire du lot
 ///
 /// # Returns
 ///
 /// Les contenus des fichiers THF, GEO, QAL et VEC
 pub fn extract(path: &Path) -> Result<EdigeoArchive, EdigeoError> {
-    let file = std::fs::File::open(path)?;
-    let decoder = BzDecoder::new(file);
-    let mut archive = Archive::new(decoder);
-
-    let mut files: HashMap<String, Vec<u8>> = HashMap::new();
-
-    for entry in archive.entries()? {
-        let mut entry = entry?;
-        let path = entry.path()?.to_string_lossy().to_uppercase();
-
-        // Extraire l'extension
-        let extension = path.rsplit('.').next().unwrap_or("").to_uppercase();
-
-        // Lire le contenu en mémoire
-        let mut content = Vec::new();
-        entry.read_to_end(&mut content)?;
-
-        // Stocker selon le type
-        match extension.as_str() {
-            "THF" | "GEO" | "QAL" => {
-                files.insert(extension, content);
-            }
-            "VEC" => {
-                // Plusieurs fichiers VEC possibles, on les préfixe
-                let key = format!(
-                    "VEC_{}",
-                    files.keys().filter(|k| k.starts_with("VEC")).count()
-                );
-                files.insert(key, content);
-            }
-            _ => {
-                // Ignorer les autres fichiers (DIC, SCD, GEN, etc.)
-            }
+    let mut thf = None;
+    let mut geo = None;
+    let mut qal = None;
+    let mut vec = Vec::new();
+    let mut dic = Vec::new();
+    let mut scd = Vec::new();
+    let mut gen = Vec::new();
+
+    for_each_entry(path, |kind, content| {
+        match kind {
+            EntryKind::Thf => thf = Some(content),
+            EntryKind::Geo => geo = Some(content),
+            EntryKind::Qal => qal = Some(content),
+            // Plusieurs fichiers VEC possibles (un par thème)
+            EntryKind::Vec => vec.push(content),
+            EntryKind::Dic => dic = content,
+            EntryKind::Scd => scd = content,
+            EntryKind::Gen => gen = content,
         }
-    }
+        ControlFlow::Continue(())
+    })?;
 
     // Vérifier la présence des fichiers obligatoires
-    let thf = files
-        .remove("THF")
-        .ok_or_else(|| EdigeoError::MissingFile("THF".into()))?;
-
-    let geo = files
-        .remove("GEO")
-        .ok_or_else(|| EdigeoError::MissingFile("GEO".into()))?;
-
-    let qal = files.remove("QAL").unwrap_or_default(); // QAL peut être absent
-
-    // Collecter tous les VEC
-    let vec: Vec<Vec<u8>> = files
-        .into_iter()
-        .filter(|(k, _)| k.starts_with("VEC"))
-        .map(|(_, v)| v)
-        .collect();
+    let thf = thf.ok_or_else(|| EdigeoError::MissingFile("THF".into()))?;
+    let geo = geo.ok_or_else(|| EdigeoError::MissingFile("GEO".into()))?;
+    let qal = qal.unwrap_or_default(); // QAL peut être absent
 
     if vec.is_empty() {
         return Err(EdigeoError::MissingFile("VEC".into()));
     }
 
-    Ok(EdigeoArchive { thf, geo, qal, vec })
+    Ok(EdigeoArchive {
+        thf,
+        geo,
+        qal,
+        vec,
+        dic,
+        scd,
+        gen,
+    })
 }
 
 #[cfg(test)]
@@ -105,4 +150,18 @@ mod tests {
         let result = extract(Path::new("nonexistent.tar.bz2"));
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_entry_kind_from_path() {
+        assert_eq!(EntryKind::from_path("E0000A01.THF"), Some(EntryKind::Thf));
+        assert_eq!(
+            EntryKind::from_path("lot/edab01t1.vec"),
+            Some(EntryKind::Vec)
+        );
+        assert_eq!(EntryKind::from_path("EDAB01SE.QAL"), Some(EntryKind::Qal));
+        assert_eq!(EntryKind::from_path("EDAB01SE.DIC"), Some(EntryKind::Dic));
+        assert_eq!(EntryKind::from_path("EDAB01SE.scd"), Some(EntryKind::Scd));
+        assert_eq!(EntryKind::from_path("EDAB01SE.GEN"), Some(EntryKind::Gen));
+        assert_eq!(EntryKind::from_path("LISEZMOI.txt"), None);
+    }
 }
@@ -0,0 +1,601 @@
+//! Décompression bzip2 par blocs en parallèle (feature `parallel`)
+//!
+//! Un flux bzip2 est une suite de blocs indépendants, alignés au bit près et
+//! précédés d'un nombre magique de 48 bits (π en BCD), suivie d'un marqueur de
+//! fin (√π) et du CRC combiné des blocs. Le flux compressé est lu par
+//! morceaux: dès qu'une fenêtre d'un bloc par thread est complète, chaque bloc
+//! est recopié dans un flux d'un seul bloc puis décompressé par libbzip2 sur
+//! le pool rayon; les blocs décompressés sont restitués dans l'ordre.
+//!
+//! Le nombre magique peut apparaître par hasard dans les données compressées.
+//! Le début d'un bloc suit la fin d'un bloc décompressé (CRC vérifié): seule
+//! sa fin peut être un faux marqueur, et un bloc indécodable est prolongé
+//! jusqu'au marqueur suivant. Le CRC combiné est vérifié en fin de flux. Comme
+//! `BzDecoder`, seul le premier flux est lu.
+
+use std::collections::VecDeque;
+use std::io::{self, Chain, Cursor, Read};
+
+use bzip2::read::BzDecoder;
+use memchr::memmem;
+use rayon::prelude::*;
+use tracing::debug;
+
+/// Début de bloc (π)
+const BLOCK_MAGIC: u64 = 0x3141_5926_5359;
+
+/// Fin de flux (√π)
+const EOS_MAGIC: u64 = 0x1772_4538_5090;
+
+/// Taille des lectures du flux compressé
+const CHUNK: usize = 1 << 20;
+
+/// Taille compressée maximale d'un bloc: 900 000 symboles d'au plus 20 bits,
+/// plus les tables de Huffman
+const MAX_BLOCK_BYTES: usize = 900_000 * 20 / 8 + (1 << 16);
+
+/// Bloc compressé: bornes en bits dans le tampon d'entrée, à partir du nombre
+/// magique, et CRC des données décompressées
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Block {
+    start: usize,
+    end: usize,
+    crc: u32,
+}
+
+/// Marqueur du flux: position en bits dans le tampon d'entrée, fin de flux
+type Marker = (usize, bool);
+
+/// Décodeur bzip2 multi-thread
+///
+/// Le flux compressé est lu au fil de la décompression: seuls les blocs de la
+/// fenêtre en cours (un bloc par thread du pool rayon courant) sont en
+/// mémoire.
+pub struct ParBzDecoder<R: Read> {
+    /// Flux compressé, `None` une fois passé au décodeur séquentiel
+    reader: Option<R>,
+    /// Taille des lectures de `reader`
+    chunk: usize,
+    /// Octets compressés lus, à partir de l'octet du prochain bloc
+    input: Vec<u8>,
+    /// Fin de `reader` atteinte
+    eof: bool,
+    /// En-tête lu
+    started: bool,
+    /// Marqueur du prochain bloc (ou de la fin de flux)
+    cursor: Marker,
+    /// Marqueurs trouvés après `cursor`, dans l'ordre du flux
+    markers: VecDeque<Marker>,
+    /// Position en bits avant laquelle les marqueurs ont été cherchés
+    scanned: usize,
+    /// CRC combiné des blocs décompressés
+    combined: u32,
+    /// Fin du premier flux atteinte
+    finished: bool,
+    /// Blocs décompressés pas encore lus
+    decoded: VecDeque<Vec<u8>>,
+    /// Position dans le premier bloc décompressé
+    offset: usize,
+    /// Décodeur séquentiel, si le flux ne commence pas par un bloc
+    sequential: Option<BzDecoder<Chain<Cursor<Vec<u8>>, R>>>,
+}
+
+impl<R: Read> ParBzDecoder<R> {
+    /// Décodeur du flux bzip2 lu dans `reader`
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: Some(reader),
+            chunk: CHUNK,
+            input: Vec::new(),
+            eof: false,
+            started: false,
+            cursor: (0, false),
+            markers: VecDeque::new(),
+            scanned: 0,
+            combined: 0,
+            finished: false,
+            decoded: VecDeque::new(),
+            offset: 0,
+            sequential: None,
+        }
+    }
+
+    /// Lit un morceau du flux compressé et y cherche les marqueurs
+    fn fill(&mut self) -> io::Result<()> {
+        let Some(reader) = self.reader.as_mut() else {
+            return Ok(());
+        };
+        let mut filled = self.input.len();
+        self.input.resize(filled + self.chunk, 0);
+        while filled < self.input.len() {
+            match reader.read(&mut self.input[filled..]) {
+                Ok(0) => {
+                    self.eof = true;
+                    break;
+                }
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.input.truncate(filled);
+                    return Err(e);
+                }
+            }
+        }
+        self.input.truncate(filled);
+
+        // Marqueurs commençant après la dernière recherche, désormais entiers
+        let from = self.scanned / 8;
+        let region = &self.input[from..];
+        let mut found: Vec<Marker> = [(BLOCK_MAGIC, false), (EOS_MAGIC, true)]
+            .into_iter()
+            .flat_map(|(magic, end_of_stream)| {
+                find_magic(region, magic)
+                    .into_iter()
+                    .map(move |pos| (from * 8 + pos, end_of_stream))
+            })
+            .filter(|&(pos, _)| pos >= self.scanned)
+            .collect();
+        found.sort_unstable();
+        self.markers.extend(found);
+        self.scanned = (self.input.len() * 8).saturating_sub(47);
+        Ok(())
+    }
+
+    /// Lit l'en-tête et le premier marqueur
+    ///
+    /// Un flux qui ne commence pas par un bloc (en-tête invalide, marqueur
+    /// absent) est confié à `BzDecoder`, qui en signale l'erreur.
+    fn start(&mut self) -> io::Result<()> {
+        self.started = true;
+        while self.input.len() < 10 && !self.eof {
+            self.fill()?;
+        }
+        let header_ok = self.input.len() >= 4
+            && &self.input[..3] == b"BZh"
+            && (b'1'..=b'9').contains(&self.input[3]);
+        match self.markers.front() {
+            Some(&(32, _)) if header_ok => {
+                self.cursor = self.markers.pop_front().expect("first marker");
+            }
+            _ => {
+                debug!("bzip2 blocks not found, decompressing sequentially");
+                let input = Cursor::new(std::mem::take(&mut self.input));
+                let reader = self.reader.take().expect("reader not yet consumed");
+                self.sequential = Some(BzDecoder::new(input.chain(reader)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Décompresse la fenêtre de blocs suivante
+    fn decode_window(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.start()?;
+            if self.sequential.is_some() {
+                return Ok(());
+            }
+        }
+
+        // Fin de flux: CRC combiné des blocs
+        if self.cursor.1 {
+            while self.input.len() * 8 < self.cursor.0 + 80 && !self.eof {
+                self.fill()?;
+            }
+            if self.input.len() * 8 < self.cursor.0 + 80 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            if bits(&self.input, self.cursor.0 + 48, 32) as u32 != self.combined {
+                return Err(invalid_data("bzip2 stream CRC mismatch"));
+            }
+            self.finished = true;
+            // Le reste de l'entrée (flux suivants) n'est pas lu
+            self.input = Vec::new();
+            return Ok(());
+        }
+
+        // Blocs complets: jusqu'à la fin de flux, un bloc par thread
+        let threads = rayon::current_num_threads().max(1);
+        let complete = loop {
+            let complete = match self.markers.iter().position(|&(_, eos)| eos) {
+                Some(eos) => eos + 1,
+                None => self.markers.len(),
+            };
+            if complete >= threads || complete < self.markers.len() || self.eof {
+                break complete;
+            }
+            let last = self.markers.back().map_or(self.cursor.0, |m| m.0);
+            if self.input.len() - last / 8 > MAX_BLOCK_BYTES {
+                return Err(invalid_data("bzip2 block exceeds the maximum block size"));
+            }
+            self.fill()?;
+        };
+        if complete == 0 {
+            // Flux tronqué
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let window = complete.min(threads);
+
+        let mut blocks = Vec::with_capacity(window);
+        let mut start = self.cursor.0;
+        for &(end, _) in self.markers.iter().take(window) {
+            let crc = bits(&self.input, start + 48, 32) as u32;
+            blocks.push(Block { start, end, crc });
+            start = end;
+        }
+        let data = &self.input;
+        let decoded: Vec<io::Result<Vec<u8>>> = blocks
+            .par_iter()
+            .map(|block| decode_block(data, block))
+            .collect();
+
+        for (block, decoded) in blocks.iter().zip(decoded) {
+            match decoded {
+                Ok(decoded) => {
+                    self.combined = self.combined.rotate_left(1) ^ block.crc;
+                    self.decoded.push_back(decoded);
+                    self.cursor = self.markers.pop_front().expect("block end marker");
+                }
+                // Aucun marqueur au-delà: le bloc est invalide
+                Err(e) if self.eof && self.markers.len() == 1 => return Err(e),
+                Err(e) => {
+                    // Faux nombre magique: le bloc continue jusqu'au marqueur
+                    // suivant, les blocs suivants sont repris
+                    debug!(
+                        "bzip2 block at bit {} not decodable ({}), extending it",
+                        block.start, e
+                    );
+                    self.markers.pop_front();
+                    break;
+                }
+            }
+        }
+
+        // Octets entièrement consommés
+        let consumed = self.cursor.0 / 8;
+        if consumed > 0 {
+            self.input.drain(..consumed);
+            let shift = consumed * 8;
+            self.cursor.0 -= shift;
+            self.scanned -= shift;
+            for marker in &mut self.markers {
+                marker.0 -= shift;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for ParBzDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some(sequential) = &mut self.sequential {
+                return sequential.read(buf);
+            }
+            if let Some(front) = self.decoded.front() {
+                if self.offset < front.len() {
+                    let n = (front.len() - self.offset).min(buf.len());
+                    buf[..n].copy_from_slice(&front[self.offset..self.offset + n]);
+                    self.offset += n;
+                    return Ok(n);
+                }
+                self.decoded.pop_front();
+                self.offset = 0;
+                continue;
+            }
+            if self.finished {
+                return Ok(0);
+            }
+            self.decode_window()?;
+        }
+    }
+}
+
+/// Erreur de données du flux compressé
+fn invalid_data(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+/// Positions en bits d'un nombre magique de 48 bits
+///
+/// Pour chacun des 8 décalages possibles, les 5 octets entièrement déterminés
+/// par le nombre magique sont cherchés, puis les 48 bits vérifiés.
+fn find_magic(data: &[u8], magic: u64) -> Vec<usize> {
+    let mut positions = Vec::new();
+    for shift in 0..8 {
+        // Nombre magique à `shift` bits du début d'un champ de 7 octets
+        let field = (magic << (8 - shift)).to_be_bytes();
+        for hit in memmem::find_iter(data, &field[2..7]) {
+            let Some(pos) = (hit * 8 + shift).checked_sub(8) else {
+                continue;
+            };
+            if pos + 48 <= data.len() * 8 && bits(data, pos, 48) == magic {
+                positions.push(pos);
+            }
+        }
+    }
+    positions
+}
+
+/// `n` bits (1 à 56) à partir de la position `pos`, grand-boutiste
+fn bits(data: &[u8], pos: usize, n: u32) -> u64 {
+    let byte = pos / 8;
+    let mut word = [0u8; 8];
+    let available = data.len().saturating_sub(byte).min(8);
+    word[..available].copy_from_slice(&data[byte..byte + available]);
+    (u64::from_be_bytes(word) << (pos % 8)) >> (64 - n)
+}
+
+/// Décompresse un bloc, recopié dans un flux d'un seul bloc
+fn decode_block(data: &[u8], block: &Block) -> io::Result<Vec<u8>> {
+    let mut stream = BitWriter::default();
+    // Niveau 9: taille de bloc maximale, quel que soit le niveau d'origine
+    stream.bytes.extend_from_slice(b"BZh9");
+    let mut pos = block.start;
+    while pos < block.end {
+        let n = (block.end - pos).min(32);
+        stream.push(bits(data, pos, n as u32), n as u32);
+        pos += n;
+    }
+    // Fin de flux: le CRC combiné d'un seul bloc est celui du bloc
+    stream.push(EOS_MAGIC, 48);
+    stream.push(u64::from(block.crc), 32);
+
+    let mut out = Vec::new();
+    BzDecoder::new(stream.finish().as_slice()).read_to_end(&mut out)?;
+    Ok(out)
+}
+
+/// Écriture d'un flux de bits, grand-boutiste
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    /// Bits en attente, alignés à droite
+    pending: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    /// Ajoute les `n` bits de poids faible de `value` (au plus 56)
+    fn push(&mut self, value: u64, n: u32) {
+        self.pending = (self.pending << n) | value;
+        self.count += n;
+        while self.count >= 8 {
+            self.count -= 8;
+            self.bytes.push((self.pending >> self.count) as u8);
+        }
+        self.pending &= (1 << self.count) - 1;
+    }
+
+    /// Complète le dernier octet par des zéros
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push((self.pending << (8 - self.count)) as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bzip2::write::BzEncoder;
+    use bzip2::Compression;
+    use std::io::Write;
+
+    /// Texte peu compressible de `len` octets
+    fn sample(len: usize) -> Vec<u8> {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                b"EDIGEO;0123456789\r\n"[(state % 19) as usize]
+            })
+            .collect()
+    }
+
+    fn compress(data: &[u8], level: u32) -> Vec<u8> {
+        let mut encoder = BzEncoder::new(Vec::new(), Compression::new(level));
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn decompress(decoder: &mut impl Read) -> Vec<u8> {
+        let mut out = Vec::new();
+        // Petits tampons: les lectures chevauchent les blocs
+        let mut buf = [0u8; 4093];
+        loop {
+            let n = decoder.read(&mut buf).unwrap();
+            if n == 0 {
+                return out;
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+    }
+
+    /// Blocs du premier flux, d'après les marqueurs du flux entier
+    fn blocks(data: &[u8]) -> Vec<Block> {
+        let mut markers: Vec<Marker> = find_magic(data, BLOCK_MAGIC)
+            .into_iter()
+            .map(|pos| (pos, false))
+            .chain(
+                find_magic(data, EOS_MAGIC)
+                    .into_iter()
+                    .map(|pos| (pos, true)),
+            )
+            .collect();
+        markers.sort_unstable();
+        let end = markers.iter().position(|&(_, eos)| eos).unwrap();
+        markers[..=end]
+            .windows(2)
+            .map(|pair| Block {
+                start: pair[0].0,
+                end: pair[1].0,
+                crc: bits(data, pair[0].0 + 48, 32) as u32,
+            })
+            .collect()
+    }
+
+    /// Lecteur qui rend 1 à 7 octets par lecture
+    struct Trickle<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = (self.pos % 7 + 1)
+                .min(buf.len())
+                .min(self.data.len() - self.pos);
+            buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    fn pool(threads: usize) -> rayon::ThreadPool {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parallel_decoding_matches_input() {
+        // Niveau 1: blocs d'environ 100 000 octets
+        let data = sample(1_000_000);
+        let compressed = compress(&data, 1);
+
+        let blocks = blocks(&compressed);
+        assert!(blocks.len() >= 10, "{} blocks", blocks.len());
+        let concatenated: Vec<u8> = blocks
+            .iter()
+            .flat_map(|block| decode_block(&compressed, block).unwrap())
+            .collect();
+        assert_eq!(concatenated, data);
+
+        let mut decoder = ParBzDecoder::new(compressed.as_slice());
+        assert_eq!(decompress(&mut decoder), data);
+        assert!(decoder.sequential.is_none());
+    }
+
+    #[test]
+    fn test_unaligned_blocks_across_reads() {
+        let data = sample(1_000_000);
+        let compressed = compress(&data, 1);
+        let blocks = blocks(&compressed);
+        assert!(blocks.iter().any(|block| block.start % 8 != 0));
+
+        // Premier morceau coupé au milieu du nombre magique du deuxième bloc,
+        // puis petits morceaux: marqueurs à cheval sur deux lectures
+        for chunk in [blocks[1].start / 8 + 3, 4096] {
+            for threads in [1, 3] {
+                let mut decoder = ParBzDecoder::new(Trickle {
+                    data: &compressed,
+                    pos: 0,
+                });
+                decoder.chunk = chunk;
+                let out = pool(threads).install(|| decompress(&mut decoder));
+                assert_eq!(out, data, "chunk {chunk}, {threads} threads");
+                assert!(decoder.sequential.is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn test_bounded_window() {
+        let data = sample(1_000_000);
+        let compressed = compress(&data, 1);
+
+        // Un thread: un bloc décompressé à la fois, lu morceau par morceau
+        let mut decoder = ParBzDecoder::new(Trickle {
+            data: &compressed,
+            pos: 0,
+        });
+        decoder.chunk = 16 * 1024;
+        let mut buf = [0u8; 1000];
+        pool(1).install(|| decoder.read_exact(&mut buf)).unwrap();
+        assert_eq!(&buf[..], &data[..1000]);
+        let read = decoder.reader.as_ref().unwrap().pos;
+        assert!(
+            read < compressed.len() / 3,
+            "{read} of {}",
+            compressed.len()
+        );
+        assert!(decoder.input.len() < compressed.len() / 3);
+    }
+
+    #[test]
+    fn test_empty_and_trailing_data() {
+        let compressed = compress(b"", 9);
+        assert!(blocks(&compressed).is_empty());
+        assert!(decompress(&mut ParBzDecoder::new(compressed.as_slice())).is_empty());
+
+        // Flux suivants ignorés, comme avec BzDecoder, y compris quand le
+        // premier flux s'étend sur plusieurs fenêtres et morceaux
+        let data = sample(600_000);
+        for (first, expected) in [
+            (compress(b"lot 1", 9), b"lot 1".to_vec()),
+            (compress(&data, 1), data.clone()),
+        ] {
+            let mut compressed = first;
+            compressed.extend(compress(b"lot 2", 9));
+            let mut decoder = ParBzDecoder::new(compressed.as_slice());
+            decoder.chunk = 8192;
+            let out = pool(2).install(|| decompress(&mut decoder));
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn test_false_magic() {
+        let data = sample(300_000);
+        let compressed = compress(&data, 1);
+        let blocks = blocks(&compressed);
+
+        // Faux marqueur dans le deuxième bloc: le bloc indécodable est
+        // prolongé jusqu'au marqueur suivant
+        for threads in [1, 3] {
+            let mut decoder = ParBzDecoder::new(compressed.as_slice());
+            decoder.start().unwrap();
+            let fake = blocks[1].start + 100;
+            let index = decoder.markers.partition_point(|&(pos, _)| pos < fake);
+            decoder.markers.insert(index, (fake, false));
+            let out = pool(threads).install(|| decompress(&mut decoder));
+            assert_eq!(out, data, "{threads} threads");
+        }
+    }
+
+    #[test]
+    fn test_invalid_streams() {
+        let data = sample(300_000);
+        let compressed = compress(&data, 1);
+        let read = |input: &[u8]| {
+            let mut decoder = ParBzDecoder::new(input);
+            decoder
+                .read_to_end(&mut Vec::new())
+                .map(|_| decoder.sequential.is_some())
+        };
+
+        // Flux tronqué
+        let error = read(&compressed[..compressed.len() / 2]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        // Bloc corrompu
+        let mut corrupted = compressed.clone();
+        let middle = (blocks(&compressed)[1].start / 8 + blocks(&compressed)[1].end / 8) / 2;
+        corrupted[middle] ^= 0x55;
+        assert!(read(&corrupted).is_err());
+
+        // CRC combiné faux
+        let mut corrupted = compressed.clone();
+        *corrupted.last_mut().unwrap() ^= 0xff;
+        let error = read(&corrupted).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // Pas de bloc: décompression séquentielle, qui signale l'erreur
+        assert!(read(b"BZh9 pas un flux").is_err());
+        assert!(!read(&compress(&data[..1000], 9)).unwrap());
+    }
+}
@@ -0,0 +1,1039 @@
+//! Entrées EDIGEO: répertoires, archives et archives imbriquées
+//!
+//! Un lot EDIGEO (un fichier THF et ses fichiers GEO, QAL, VEC) peut être livré:
+//! - dans un répertoire (fichiers côte à côte)
+//! - dans une archive `.tar`, `.tar.gz`, `.tar.bz2` ou `.zip`
+//! - dans une archive départementale qui contient une archive par feuille
+//!
+//! `for_each_lot` détecte le conteneur et transmet chaque lot; `Lot::for_each_entry`
+//! lit ensuite ses fichiers au fil de la décompression. Un conteneur qui regroupe
+//! plusieurs fichiers THF donne un lot par THF, limité aux fichiers qu'il déclare.
+//!
+//! Les THF sont repérés sans relire le conteneur: répertoires, archives zip et
+//! tar non compressées sont indexés d'après leurs en-têtes, une archive
+//! imbriquée est décompressée une seule fois en mémoire.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read, Seek};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[cfg(not(feature = "parallel"))]
+use bzip2::read::BzDecoder;
+use flate2::read::MultiGzDecoder;
+use tar::Archive;
+use tracing::warn;
+
+use crate::archive::EntryKind;
+#[cfg(feature = "parallel")]
+use crate::bz2::ParBzDecoder;
+use crate::{parser, EdigeoError};
+
+/// Format d'un conteneur EDIGEO
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    Directory,
+    Tar,
+    TarGz,
+    TarBz2,
+    Zip,
+}
+
+impl Container {
+    /// Format d'après le nom de fichier (insensible à la casse)
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        if name.ends_with(".bz2") || name.ends_with(".tbz2") || name.ends_with(".tbz") {
+            Some(Container::TarBz2)
+        } else if name.ends_with(".gz") || name.ends_with(".tgz") {
+            Some(Container::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(Container::Tar)
+        } else if name.ends_with(".zip") {
+            Some(Container::Zip)
+        } else {
+            None
+        }
+    }
+
+    /// Format d'après la signature des premiers octets
+    pub fn from_magic(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"BZh") {
+            Some(Container::TarBz2)
+        } else if bytes.starts_with(&[0x1f, 0x8b]) {
+            Some(Container::TarGz)
+        } else if bytes.starts_with(b"PK\x03\x04") {
+            Some(Container::Zip)
+        } else if bytes.get(257..262) == Some(b"ustar") {
+            Some(Container::Tar)
+        } else {
+            None
+        }
+    }
+
+    /// Archive tar compressée
+    pub fn is_compressed(self) -> bool {
+        matches!(self, Container::TarGz | Container::TarBz2)
+    }
+
+    /// Format d'un chemin: répertoire, extension, puis signature
+    pub fn detect(path: &Path) -> Option<Self> {
+        if path.is_dir() {
+            return Some(Container::Directory);
+        }
+        if let Some(container) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(Self::from_name)
+        {
+            return Some(container);
+        }
+
+        let mut header = [0u8; 512];
+        let mut file = File::open(path).ok()?;
+        let mut len = 0;
+        while len < header.len() {
+            match file.read(&mut header[len..]) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => len += n,
+            }
+        }
+        Self::from_magic(&header[..len])
+    }
+}
+
+#[derive(Debug, Clone)]
+enum LotSource {
+    /// Répertoire ou fichier d'archive
+    Path(PathBuf),
+    /// Archive imbriquée, lue en mémoire (partagée par ses lots): zip, ou tar
+    /// décompressée
+    Memory(Arc<Vec<u8>>),
+    /// Fichiers d'un lot lus au parcours de son archive (chemin, contenu)
+    Files(Arc<Vec<(String, Vec<u8>)>>),
+}
+
+/// Lot EDIGEO: un fichier THF et les fichiers GEO, QAL, VEC associés
+#[derive(Debug)]
+pub struct Lot {
+    name: String,
+    archive: String,
+    container: Container,
+    source: LotSource,
+    /// Chemin du THF quand le conteneur en regroupe plusieurs
+    thf: Option<String>,
+    /// Fichiers déclarés par ce THF (voir `member_key`); `None`: tout le conteneur
+    members: Option<HashSet<String>>,
+}
+
+impl Lot {
+    /// Lot contenu directement dans un répertoire ou une archive
+    ///
+    /// Tous les fichiers EDIGEO du conteneur sont lus: `for_each_lot` sépare
+    /// les lots d'un conteneur qui contient plusieurs THF.
+    pub fn open(path: &Path) -> Result<Self, EdigeoError> {
+        let container = Container::detect(path).ok_or_else(|| {
+            EdigeoError::InvalidArchive(format!("Unsupported input: {}", path.display()))
+        })?;
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Self::whole(
+            name,
+            container,
+            LotSource::Path(path.to_path_buf()),
+        ))
+    }
+
+    fn whole(name: String, container: Container, source: LotSource) -> Self {
+        Self {
+            archive: name.clone(),
+            name,
+            container,
+            source,
+            thf: None,
+            members: None,
+        }
+    }
+
+    /// Un lot par fichier THF du conteneur
+    ///
+    /// Avec un seul THF (cas courant), le lot reste le conteneur entier.
+    fn split(self, thfs: Vec<ThfEntry>) -> Vec<Lot> {
+        if thfs.len() < 2 {
+            return vec![self];
+        }
+        thfs.into_iter()
+            .map(|(path, content)| Lot {
+                name: format!("{}/{}", self.archive, path),
+                archive: self.archive.clone(),
+                container: self.container,
+                source: self.source.clone(),
+                members: Some(thf_members(&path, &content)),
+                thf: Some(path),
+            })
+            .collect()
+    }
+
+    /// Chemin du lot: nom du répertoire ou de l'archive qui le contient (ex:
+    /// `edigeo-380010000A01.tar.bz2`), suivi du chemin de son THF quand ce
+    /// conteneur regroupe plusieurs lots (ex: `feuilles/E0000A01.THF`)
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Nom du répertoire ou de l'archive qui contient le lot
+    pub fn archive(&self) -> &str {
+        &self.archive
+    }
+
+    /// Chemin du THF dans le conteneur, quand celui-ci regroupe plusieurs lots
+    pub fn thf(&self) -> Option<&str> {
+        self.thf.as_deref()
+    }
+
+    /// Format du conteneur du lot
+    pub fn container(&self) -> Container {
+        self.container
+    }
+
+    /// Le fichier appartient au lot
+    fn contains(&self, path: &str) -> bool {
+        self.members
+            .as_ref()
+            .is_none_or(|members| members.contains(&member_key(path)))
+    }
+
+    /// Parcourt les fichiers EDIGEO du lot au fil de la décompression
+    ///
+    /// Chaque fichier utile est lu puis transmis à `on_entry` avant de passer au
+    /// suivant. Le parcours s'arrête si `on_entry` retourne `ControlFlow::Break`.
+    pub fn for_each_entry<F>(&self, mut on_entry: F) -> Result<(), EdigeoError>
+    where
+        F: FnMut(EntryKind, Vec<u8>) -> ControlFlow<()>,
+    {
+        let mut on_entry = |path: &str, kind, read: &mut dyn FnMut() -> io::Result<Vec<u8>>| {
+            if !self.contains(path) {
+                return Ok(ControlFlow::Continue(()));
+            }
+            Ok(on_entry(kind, read()?))
+        };
+        match (&self.source, self.container) {
+            (LotSource::Path(path), Container::Directory) => {
+                for_each_directory_entry(path, &mut on_entry)
+            }
+            (LotSource::Path(path), Container::Zip) => {
+                for_each_zip_entry(BufReader::new(File::open(path)?), &mut on_entry)
+            }
+            // Fichiers hors du lot sautés sans être lus
+            (LotSource::Path(path), Container::Tar) => for_each_tar_entry(
+                Archive::new(File::open(path)?).entries_with_seek()?,
+                &mut on_entry,
+            ),
+            (LotSource::Path(path), container) => {
+                let mut archive = Archive::new(decoder(File::open(path)?, container)?);
+                for_each_tar_entry(archive.entries()?, &mut on_entry)
+            }
+            (LotSource::Memory(data), Container::Zip) => {
+                for_each_zip_entry(Cursor::new(data.as_slice()), &mut on_entry)
+            }
+            (LotSource::Memory(data), _) => {
+                let mut archive = Archive::new(Cursor::new(data.as_slice()));
+                for_each_tar_entry(archive.entries_with_seek()?, &mut on_entry)
+            }
+            (LotSource::Files(files), _) => {
+                for (path, content) in files.iter() {
+                    let Some(kind) = EntryKind::from_path(path) else {
+                        continue;
+                    };
+                    if on_entry(path, kind, &mut || Ok(content.clone()))?.is_break() {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Fichier THF d'un conteneur (chemin, contenu)
+type ThfEntry = (String, Vec<u8>);
+
+/// Fichiers EDIGEO d'un conteneur: chemins dans l'ordre du conteneur, et THF
+#[derive(Debug, Default)]
+struct Index {
+    paths: Vec<String>,
+    thfs: Vec<ThfEntry>,
+}
+
+/// Rappel pour chaque fichier EDIGEO (chemin, type, lecture du contenu)
+type EntryCallback<'a> = dyn FnMut(
+        &str,
+        EntryKind,
+        &mut dyn FnMut() -> io::Result<Vec<u8>>,
+    ) -> Result<ControlFlow<()>, EdigeoError>
+    + 'a;
+
+/// Clé d'un fichier du conteneur: répertoire et nom sans extension, en majuscules
+fn member_key(path: &str) -> String {
+    let path = Path::new(path);
+    let dir = path.parent().unwrap_or(Path::new("")).to_string_lossy();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    format!("{}/{}", dir, stem.to_ascii_uppercase())
+}
+
+/// Clés du THF et des fichiers qu'il déclare, dans son répertoire
+///
+/// Un THF illisible ne garde que lui-même: l'erreur est signalée à la lecture
+/// du lot.
+fn thf_members(path: &str, content: &[u8]) -> HashSet<String> {
+    let mut members = HashSet::from([member_key(path)]);
+    if let Ok(thf) = parser::thf::parse(content) {
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        for file in thf.exchange.lots.iter().flat_map(|lot| &lot.files) {
+            members.insert(member_key(&dir.join(file).to_string_lossy()));
+        }
+    }
+    members
+}
+
+/// Parcourt les lots EDIGEO d'une entrée
+///
+/// Une archive qui contient directement des fichiers EDIGEO donne un lot par
+/// fichier THF; une archive qui contient d'autres archives (archive
+/// départementale) donne les lots de chaque archive interne, lue en mémoire
+/// l'une après l'autre. Le type est déterminé par la première entrée utile de
+/// l'archive.
+///
+/// Les THF d'une archive tar ne sont connus qu'à sa fin. Une archive tar non
+/// compressée est indexée d'après ses en-têtes; une archive compressée est
+/// parcourue une première fois (en-têtes et THF), puis ses lots sont lus:
+/// avec un seul THF, le lot est lu au fil de la décompression; avec plusieurs,
+/// tous les lots sont lus en un second parcours (voir `split_stream`).
+///
+/// # Errors
+///
+/// Retourne `EdigeoError::MissingFile` si l'entrée ne contient aucun lot.
+pub fn for_each_lot<F>(path: &Path, mut on_lot: F) -> Result<(), EdigeoError>
+where
+    F: FnMut(Lot) -> ControlFlow<()>,
+{
+    let lot = Lot::open(path)?;
+    let mut found = false;
+    let mut index = Index::default();
+    let mut on_nested = |name, data| nested_lots(name, data, &mut found, &mut on_lot);
+
+    let layout = match lot.container {
+        Container::Directory => {
+            for (kind, file) in directory_files(path)? {
+                if kind == EntryKind::Thf {
+                    index.thfs.push((file_name(&file), std::fs::read(&file)?));
+                }
+                index.paths.push(file_name(&file));
+            }
+            Layout::Lot
+        }
+        Container::Zip => scan_zip(
+            BufReader::new(File::open(path)?),
+            Some(&mut index),
+            &mut on_nested,
+        )?,
+        Container::Tar => {
+            let mut archive = Archive::new(File::open(path)?);
+            scan_tar(
+                archive.entries_with_seek()?,
+                Some(&mut index),
+                &mut on_nested,
+            )?
+        }
+        container => {
+            let mut archive = Archive::new(decoder(File::open(path)?, container)?);
+            scan_tar(archive.entries()?, Some(&mut index), &mut on_nested)?
+        }
+    };
+
+    match layout {
+        Layout::Lot if index.thfs.len() > 1 && lot.container.is_compressed() => {
+            split_stream(path, lot, index, &mut on_lot)
+        }
+        Layout::Lot => {
+            for lot in lot.split(index.thfs) {
+                if on_lot(lot).is_break() {
+                    break;
+                }
+            }
+            Ok(())
+        }
+        Layout::Nested if found => Ok(()),
+        _ => Err(EdigeoError::MissingFile("THF".into())),
+    }
+}
+
+/// Le chemin contient des archives imbriquées (un lot par archive interne)
+pub fn has_nested_lots(path: &Path) -> Result<bool, EdigeoError> {
+    let layout = match Lot::open(path)?.container {
+        Container::Directory => Layout::Lot,
+        Container::Zip => scan_zip(BufReader::new(File::open(path)?), None, &mut |_, _| {
+            Ok(ControlFlow::Break(()))
+        })?,
+        container => {
+            let mut archive = Archive::new(decoder(File::open(path)?, container)?);
+            scan_tar(archive.entries()?, None, &mut |_, _| {
+                Ok(ControlFlow::Break(()))
+            })?
+        }
+    };
+    Ok(layout == Layout::Nested)
+}
+
+/// Lots d'une archive compressée qui regroupe plusieurs THF, lus en un seul
+/// parcours
+///
+/// Les fichiers d'un lot sont gardés en mémoire jusqu'au dernier d'entre eux
+/// dans l'archive (d'après `index`), puis le lot est transmis: les lots rangés
+/// chacun dans leur répertoire sont en mémoire l'un après l'autre.
+fn split_stream<F>(path: &Path, lot: Lot, index: Index, on_lot: &mut F) -> Result<(), EdigeoError>
+where
+    F: FnMut(Lot) -> ControlFlow<()>,
+{
+    let container = lot.container;
+    let mut lots: Vec<Option<Lot>> = lot.split(index.thfs).into_iter().map(Some).collect();
+    // Position du dernier fichier de chaque lot
+    let last: Vec<Option<usize>> = lots
+        .iter()
+        .flatten()
+        .map(|lot| index.paths.iter().rposition(|path| lot.contains(path)))
+        .collect();
+    let mut files: Vec<Vec<(String, Vec<u8>)>> = vec![Vec::new(); lots.len()];
+    let mut flow = ControlFlow::Continue(());
+    let mut position = 0;
+
+    let mut archive = Archive::new(decoder(File::open(path)?, container)?);
+    for_each_tar_entry(archive.entries()?, &mut |path, _, read| {
+        let owners: Vec<usize> = (0..lots.len())
+            .filter(|&i| lots[i].as_ref().is_some_and(|lot| lot.contains(path)))
+            .collect();
+        if let Some((&owner, others)) = owners.split_last() {
+            let content = read()?;
+            for &i in others {
+                files[i].push((path.to_string(), content.clone()));
+            }
+            files[owner].push((path.to_string(), content));
+        }
+        for i in 0..lots.len() {
+            if last[i] != Some(position) {
+                continue;
+            }
+            if let Some(mut lot) = lots[i].take() {
+                lot.source = LotSource::Files(Arc::new(std::mem::take(&mut files[i])));
+                flow = on_lot(lot);
+                if flow.is_break() {
+                    return Ok(flow);
+                }
+            }
+        }
+        position += 1;
+        Ok(ControlFlow::Continue(()))
+    })?;
+
+    // Archive modifiée depuis l'index: lots restants avec les fichiers lus
+    for (lot, files) in lots.into_iter().zip(files) {
+        let Some(mut lot) = lot else {
+            continue;
+        };
+        if flow.is_break() {
+            break;
+        }
+        lot.source = LotSource::Files(Arc::new(files));
+        flow = on_lot(lot);
+    }
+    Ok(())
+}
+
+/// Organisation d'une archive, d'après sa première entrée utile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    /// Fichiers EDIGEO à la racine: un lot par THF
+    Lot,
+    /// Archives internes
+    Nested,
+    /// Aucune entrée utile
+    Empty,
+}
+
+/// Rappel pour chaque archive interne (nom, contenu)
+type NestedCallback<'a> = dyn FnMut(String, Vec<u8>) -> Result<ControlFlow<()>, EdigeoError> + 'a;
+
+/// Transmet les lots d'une archive interne (elle-même éventuellement imbriquée)
+fn nested_lots<F>(
+    name: String,
+    data: Vec<u8>,
+    found: &mut bool,
+    on_lot: &mut F,
+) -> Result<ControlFlow<()>, EdigeoError>
+where
+    F: FnMut(Lot) -> ControlFlow<()>,
+{
+    let Some(container) = Container::from_name(&name).or_else(|| Container::from_magic(&data))
+    else {
+        return Ok(ControlFlow::Continue(()));
+    };
+
+    // Archive tar décompressée une seule fois: ses lots la relisent en mémoire
+    let data = match container {
+        Container::Zip | Container::Tar => data,
+        _ => {
+            let mut tar = Vec::new();
+            decoder(data.as_slice(), container)?.read_to_end(&mut tar)?;
+            tar
+        }
+    };
+
+    let mut flow = ControlFlow::Continue(());
+    let mut index = Index::default();
+    let mut on_nested = |inner, bytes| {
+        flow = nested_lots(inner, bytes, found, on_lot)?;
+        Ok(flow)
+    };
+    let layout = match container {
+        Container::Zip => scan_zip(Cursor::new(&data), Some(&mut index), &mut on_nested)?,
+        _ => {
+            let mut archive = Archive::new(Cursor::new(data.as_slice()));
+            scan_tar(
+                archive.entries_with_seek()?,
+                Some(&mut index),
+                &mut on_nested,
+            )?
+        }
+    };
+
+    if layout == Layout::Lot {
+        *found = true;
+        let lot = Lot::whole(name, container, LotSource::Memory(Arc::new(data)));
+        for lot in lot.split(index.thfs) {
+            flow = on_lot(lot);
+            if flow.is_break() {
+                break;
+            }
+        }
+    }
+    Ok(flow)
+}
+
+/// Décompresseur d'une archive tar
+fn decoder<'a, R: Read + 'a>(reader: R, container: Container) -> io::Result<Box<dyn Read + 'a>> {
+    #[cfg(test)]
+    if container.is_compressed() {
+        tests::DECOMPRESSIONS.with(|count| count.set(count.get() + 1));
+    }
+    Ok(match container {
+        // Blocs décompressés en parallèle, par fenêtres
+        #[cfg(feature = "parallel")]
+        Container::TarBz2 => Box::new(ParBzDecoder::new(reader)),
+        #[cfg(not(feature = "parallel"))]
+        Container::TarBz2 => Box::new(BzDecoder::new(reader)),
+        Container::TarGz => Box::new(MultiGzDecoder::new(reader)),
+        _ => Box::new(reader),
+    })
+}
+
+/// Parcourt les entrées d'une archive tar
+///
+/// Fichiers EDIGEO: l'archive est un lot. Sans `index`, le parcours s'arrête à
+/// la première entrée; sinon il continue jusqu'à la fin pour indexer les
+/// fichiers et lire les THF.
+/// Archive interne: lue puis transmise à `on_nested`, et ainsi de suite.
+fn scan_tar<R: Read>(
+    entries: tar::Entries<'_, R>,
+    mut index: Option<&mut Index>,
+    on_nested: &mut NestedCallback,
+) -> Result<Layout, EdigeoError> {
+    let mut layout = Layout::Empty;
+
+    for entry in entries {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+
+        if let Some(kind) = EntryKind::from_path(&name) {
+            if layout == Layout::Nested {
+                warn!("Ignoring {} next to nested archives", name);
+                continue;
+            }
+            layout = Layout::Lot;
+            let Some(index) = index.as_deref_mut() else {
+                return Ok(Layout::Lot);
+            };
+            if kind == EntryKind::Thf {
+                let mut data = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut data)?;
+                index.thfs.push((name.clone(), data));
+            }
+            index.paths.push(name);
+            continue;
+        }
+        if Container::from_name(&name).is_none() || entry.header().entry_type().is_dir() {
+            continue;
+        }
+        if layout == Layout::Lot {
+            warn!("Ignoring {} next to EDIGEO files", name);
+            continue;
+        }
+
+        layout = Layout::Nested;
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data)?;
+        if on_nested(name, data)?.is_break() {
+            break;
+        }
+    }
+
+    Ok(layout)
+}
+
+/// Équivalent de `scan_tar` pour une archive zip (noms lus dans le répertoire central)
+fn scan_zip<R: Read + Seek>(
+    reader: R,
+    index: Option<&mut Index>,
+    on_nested: &mut NestedCallback,
+) -> Result<Layout, EdigeoError> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(zip_error)?;
+
+    if archive
+        .file_names()
+        .any(|name| EntryKind::from_path(name).is_some())
+    {
+        if let Some(index) = index {
+            // Seuls les THF sont lus
+            for i in 0..archive.len() {
+                let Some(name) = archive.name_for_index(i).map(str::to_string) else {
+                    continue;
+                };
+                match EntryKind::from_path(&name) {
+                    Some(EntryKind::Thf) => {
+                        let mut file = archive.by_index(i).map_err(zip_error)?;
+                        let mut data = Vec::with_capacity(file.size() as usize);
+                        file.read_to_end(&mut data)?;
+                        index.thfs.push((name.clone(), data));
+                    }
+                    Some(_) => {}
+                    None => continue,
+                }
+                index.paths.push(name);
+            }
+        }
+        return Ok(Layout::Lot);
+    }
+
+    let mut layout = Layout::Empty;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(zip_error)?;
+        let name = file.name().to_string();
+        if file.is_dir() || Container::from_name(&name).is_none() {
+            continue;
+        }
+
+        layout = Layout::Nested;
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data)?;
+        drop(file);
+        if on_nested(name, data)?.is_break() {
+            break;
+        }
+    }
+
+    Ok(layout)
+}
+
+fn for_each_tar_entry<R: Read>(
+    entries: tar::Entries<'_, R>,
+    on_entry: &mut EntryCallback,
+) -> Result<(), EdigeoError> {
+    for entry in entries {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let Some(kind) = EntryKind::from_path(&path) else {
+            continue;
+        };
+
+        let flow = on_entry(&path, kind, &mut || {
+            let mut content = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut content)?;
+            Ok(content)
+        })?;
+        if flow.is_break() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn for_each_zip_entry<R: Read + Seek>(
+    reader: R,
+    on_entry: &mut EntryCallback,
+) -> Result<(), EdigeoError> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(zip_error)?;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(zip_error)?;
+        let path = file.name().to_string();
+        let Some(kind) = EntryKind::from_path(&path) else {
+            continue;
+        };
+
+        let flow = on_entry(&path, kind, &mut || {
+            let mut content = Vec::with_capacity(file.size() as usize);
+            file.read_to_end(&mut content)?;
+            Ok(content)
+        })?;
+        if flow.is_break() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Fichiers EDIGEO d'un répertoire, par ordre de nom
+fn directory_files(path: &Path) -> io::Result<Vec<(EntryKind, PathBuf)>> {
+    let mut files: Vec<(EntryKind, PathBuf)> = std::fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file())
+        .filter_map(|p| Some((EntryKind::from_path(p.to_str()?)?, p)))
+        .collect();
+    files.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(files)
+}
+
+/// Nom d'un fichier de répertoire, chemin d'un fichier dans le lot
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn for_each_directory_entry(path: &Path, on_entry: &mut EntryCallback) -> Result<(), EdigeoError> {
+    for (kind, file) in directory_files(path)? {
+        if on_entry(&file_name(&file), kind, &mut || std::fs::read(&file))?.is_break() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn zip_error(e: zip::result::ZipError) -> EdigeoError {
+    match e {
+        zip::result::ZipError::Io(e) => EdigeoError::Io(e),
+        e => EdigeoError::InvalidArchive(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::io::Write;
+
+    thread_local! {
+        /// Décompressions d'archives tar (voir `decoder`) du thread de test
+        pub(super) static DECOMPRESSIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Décompressions nécessaires pour lire tous les lots d'une entrée
+    fn decompressions(path: &Path) -> usize {
+        DECOMPRESSIONS.with(|count| count.set(0));
+        lot_names(path);
+        DECOMPRESSIONS.with(Cell::get)
+    }
+
+    fn tar_bytes(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn bz2(data: &[u8]) -> Vec<u8> {
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::fast());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn gz(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn zip_bytes(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in entries {
+            writer
+                .start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    const LOT: [(&str, &[u8]); 2] = [("E0000A01.THF", b"THF"), ("EDAB01T1.VEC", b"VEC")];
+
+    fn temp_file(name: &str, data: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    fn lot_names(path: &Path) -> Vec<String> {
+        let mut names = Vec::new();
+        for_each_lot(path, |lot| {
+            let mut kinds = Vec::new();
+            lot.for_each_entry(|kind, _| {
+                kinds.push(kind);
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+            assert_eq!(kinds, vec![EntryKind::Thf, EntryKind::Vec]);
+            names.push(lot.name().to_string());
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        names
+    }
+
+    #[test]
+    fn test_container_detection() {
+        assert_eq!(Container::from_name("a.tar.bz2"), Some(Container::TarBz2));
+        assert_eq!(Container::from_name("a.TGZ"), Some(Container::TarGz));
+        assert_eq!(Container::from_name("a.tar"), Some(Container::Tar));
+        assert_eq!(Container::from_name("a.zip"), Some(Container::Zip));
+        assert_eq!(Container::from_name("a.THF"), None);
+        assert_eq!(Container::from_magic(b"BZh91AY"), Some(Container::TarBz2));
+        assert_eq!(
+            Container::from_magic(&tar_bytes(&LOT)),
+            Some(Container::Tar)
+        );
+    }
+
+    #[test]
+    fn test_single_lot_containers() {
+        let tar = tar_bytes(&LOT);
+        let inputs = [
+            ("edigeo-lot-test.tar", tar.clone()),
+            ("edigeo-lot-test.tar.gz", gz(&tar)),
+            ("edigeo-lot-test.tar.bz2", bz2(&tar)),
+            ("edigeo-lot-test.zip", zip_bytes(&LOT)),
+            // Sans extension: détection par signature
+            ("edigeo-lot-test-noext", gz(&tar)),
+        ];
+
+        for (name, data) in inputs {
+            let path = temp_file(name, &data);
+            assert_eq!(lot_names(&path), vec![name.to_string()], "{}", name);
+            assert!(!has_nested_lots(&path).unwrap());
+            std::fs::remove_file(path).ok();
+        }
+    }
+
+    #[test]
+    fn test_directory_lot() {
+        let dir = std::env::temp_dir().join("edigeo-lot-test-dir");
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, data) in LOT {
+            std::fs::write(dir.join(name), data).unwrap();
+        }
+
+        assert_eq!(lot_names(&dir), vec!["edigeo-lot-test-dir".to_string()]);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_nested_lots() {
+        let feuille = bz2(&tar_bytes(&LOT));
+        let departement = bz2(&tar_bytes(&[
+            ("dep38/edigeo-380010000A01.tar.bz2", &feuille),
+            ("dep38/edigeo-380010000B01.zip", &zip_bytes(&LOT)),
+            ("dep38/LISEZMOI.txt", b"..."),
+        ]));
+        let path = temp_file("edigeo-nested-test.tar.bz2", &departement);
+
+        assert!(has_nested_lots(&path).unwrap());
+        assert_eq!(
+            lot_names(&path),
+            vec![
+                "dep38/edigeo-380010000A01.tar.bz2".to_string(),
+                "dep38/edigeo-380010000B01.zip".to_string(),
+            ]
+        );
+        std::fs::remove_file(path).ok();
+    }
+
+    /// THF qui déclare un seul fichier VEC
+    fn thf(vec: &str) -> Vec<u8> {
+        format!(
+            "CSET 03:IRV\r\nRTYSA03:GTS\r\nRIDSA07:SUPPORT\r\nTDASD08:20260115\r\n\
+RTYSA03:GTL\r\nRIDSA04:LOT1\r\nGDNSA{:02}:{}\r\n",
+            vec.len(),
+            vec
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_directory_several_lots() {
+        let dir = std::env::temp_dir().join("edigeo-lots-test-dir");
+        std::fs::create_dir_all(&dir).unwrap();
+        let files: [(&str, &[u8]); 4] = [
+            ("E0000A01.THF", &thf("EDAB01T1")),
+            ("EDAB01T1.VEC", b"VEC"),
+            ("E0000B01.THF", &thf("EDAB02T1")),
+            ("EDAB02T1.VEC", b"VEC"),
+        ];
+        for (name, data) in files {
+            std::fs::write(dir.join(name), data).unwrap();
+        }
+
+        assert_eq!(
+            lot_names(&dir),
+            vec![
+                "edigeo-lots-test-dir/E0000A01.THF".to_string(),
+                "edigeo-lots-test-dir/E0000B01.THF".to_string(),
+            ]
+        );
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_nested_several_lots() {
+        let feuilles = tar_bytes(&[
+            ("feuilles/E0000A01.THF", &thf("EDAB01T1")),
+            ("feuilles/E0000B01.THF", &thf("edab02t1")),
+            ("feuilles/EDAB02T1.VEC", b"VEC"),
+            ("feuilles/EDAB01T1.VEC", b"VEC"),
+        ]);
+        let departement = bz2(&tar_bytes(&[
+            ("dep38/edigeo-38001.tar", &feuilles),
+            ("dep38/edigeo-38002.zip", &zip_bytes(&LOT)),
+        ]));
+        let path = temp_file("edigeo-nested-lots-test.tar.bz2", &departement);
+
+        let mut lots = Vec::new();
+        for_each_lot(&path, |lot| {
+            lots.push((
+                lot.name().to_string(),
+                lot.archive().to_string(),
+                lot.thf().map(str::to_string),
+            ));
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(
+            lots,
+            vec![
+                (
+                    "dep38/edigeo-38001.tar/feuilles/E0000A01.THF".to_string(),
+                    "dep38/edigeo-38001.tar".to_string(),
+                    Some("feuilles/E0000A01.THF".to_string()),
+                ),
+                (
+                    "dep38/edigeo-38001.tar/feuilles/E0000B01.THF".to_string(),
+                    "dep38/edigeo-38001.tar".to_string(),
+                    Some("feuilles/E0000B01.THF".to_string()),
+                ),
+                (
+                    "dep38/edigeo-38002.zip".to_string(),
+                    "dep38/edigeo-38002.zip".to_string(),
+                    None,
+                ),
+            ]
+        );
+
+        // Chaque lot ne lit que son THF et les fichiers qu'il déclare
+        assert_eq!(
+            lot_names(&path),
+            vec![
+                "dep38/edigeo-38001.tar/feuilles/E0000A01.THF".to_string(),
+                "dep38/edigeo-38001.tar/feuilles/E0000B01.THF".to_string(),
+                "dep38/edigeo-38002.zip".to_string(),
+            ]
+        );
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_no_lot() {
+        let path = temp_file("edigeo-empty-test.tar", &tar_bytes(&[("a.txt", b"")]));
+        assert!(matches!(
+            for_each_lot(&path, |_| ControlFlow::Continue(())),
+            Err(EdigeoError::MissingFile(_))
+        ));
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_compressed_several_lots() {
+        // Lots rangés chacun dans leur répertoire
+        let tar = tar_bytes(&[
+            ("38001/E0000A01.THF", &thf("EDAB01T1")),
+            ("38001/EDAB01T1.VEC", b"VEC"),
+            ("38002/E0000B01.THF", &thf("EDAB02T1")),
+            ("38002/EDAB02T1.VEC", b"VEC"),
+        ]);
+        for (name, data) in [
+            ("edigeo-lots-test.tar.bz2", bz2(&tar)),
+            ("edigeo-lots-test.tar", tar.clone()),
+        ] {
+            let path = temp_file(name, &data);
+            assert_eq!(
+                lot_names(&path),
+                vec![
+                    format!("{}/38001/E0000A01.THF", name),
+                    format!("{}/38002/E0000B01.THF", name),
+                ]
+            );
+            std::fs::remove_file(path).ok();
+        }
+    }
+
+    #[test]
+    fn test_reader_passes() {
+        let tar = tar_bytes(&LOT);
+        let several = tar_bytes(&[
+            ("38001/E0000A01.THF", &thf("EDAB01T1")),
+            ("38001/EDAB01T1.VEC", b"VEC"),
+            ("38002/E0000B01.THF", &thf("EDAB02T1")),
+            ("38002/EDAB02T1.VEC", b"VEC"),
+        ]);
+        let feuille = bz2(&tar);
+        let departement = bz2(&tar_bytes(&[
+            ("dep38/edigeo-380010000A01.tar.bz2", &feuille),
+            ("dep38/edigeo-380010000B01.tar.gz", &gz(&tar)),
+            ("dep38/edigeo-38003.tar.bz2", &bz2(&several)),
+        ]));
+
+        let inputs = [
+            // Index d'après les en-têtes, sans décompression
+            ("edigeo-passes-test.tar", tar.clone(), 0),
+            ("edigeo-passes-several-test.tar", several.clone(), 0),
+            // Index puis lecture du lot, ou de tous les lots en un parcours
+            ("edigeo-passes-test.tar.bz2", feuille.clone(), 2),
+            ("edigeo-passes-several-test.tar.bz2", bz2(&several), 2),
+            // Archive départementale et chaque archive interne: une fois
+            ("edigeo-passes-dep-test.tar.bz2", departement, 4),
+        ];
+        for (name, data, expected) in inputs {
+            let path = temp_file(name, &data);
+            assert_eq!(decompressions(&path), expected, "{}", name);
+            std::fs::remove_file(path).ok();
+        }
+    }
+}
@@ -6,6 +6,7 @@
 //!
 //! - Parsing SIMD optimisé avec `memchr` et `simdutf8`
 //! - Support de tous les fichiers EDIGEO (THF, GEO, QAL, VEC)
+//! - Tokenizer sans perte des enregistrements (`tokenize`, `tokenize_bytes`)
 //! - Réparation automatique des géométries invalides
 //! - Types `geo` pour l'interopérabilité avec l'écosystème Rust géospatial
 //!
@@ -23,18 +24,51 @@
 //!     println!("{}: {} features", table_name, features.len());
 //! }
 //! ```
+//!
+//! Pour limiter la mémoire, `parse_with` transmet les features au fil de la
+//! décompression:
+//!
+//! ```rust,ignore
+//! use std::ops::ControlFlow;
+//!
+//! let summary = edigeo::parse_with(Path::new("archive.tar.bz2"), |info, feature| {
+//!     println!("{} ({}): {}", feature.id, info.projection.epsg, feature.feature_type);
+//!     ControlFlow::Continue(())
+//! })?;
+//! println!("{} features", summary.features);
+//! ```
 
 pub mod archive;
+#[cfg(feature = "parallel")]
+mod bz2;
 pub mod error;
+pub mod input;
+#[cfg(feature = "parallel")]
+mod parallel;
 pub mod parser;
 pub mod repair;
 pub mod types;
 
+#[cfg(feature = "parallel")]
+pub use bz2::ParBzDecoder;
 pub use error::EdigeoError;
-pub use types::{Feature, ParseResult, Projection};
+pub use input::{for_each_lot, Lot};
+#[cfg(feature = "parallel")]
+pub use parallel::{parse_lot_parallel, parse_parallel};
+pub use parser::token::{tokenize, tokenize_bytes, Token};
+pub use types::{
+    ArchiveInfo, AttributeDef, AttributeType, Date, Exchange, ExchangeLot, Feature, General,
+    ParseOptions, ParseResult, Projection, Quality, RelationGraph, RepairAction, RepairEntry,
+    RepairLog, Schema, StreamSummary, UpdateType,
+};
 
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::ControlFlow;
 use std::path::Path;
 
+use types::ThfData;
+
 /// Extrait le code département depuis le nom de fichier EDIGEO
 /// Format attendu: EDIGEO-CCXXXXX.tar.bz2 où CC est le département
 /// Supporte les départements avec 2 chiffres (01-19), 2A, 2B pour la Corse
@@ -77,6 +111,581 @@ pub fn extract_departement(path: &Path) -> Option<String> {
     None
 }
 
+/// Parse une archive EDIGEO (.tar.bz2) et retourne les features géographiques.
+///
+/// # Arguments
+///
+/// * `archive_path` - Chemin vers l'archive .tar.bz2
+///
+/// # Returns
+///
+/// Un `ParseResult` contenant les features groupées par type, la projection source,
+/// l'année du millésime, le code département, et les erreurs non fatales rencontrées.
+///
+/// # Errors
+///
+/// Retourne `EdigeoError` si l'archive est illisible ou si aucun fichier THF n'est trouvé.
+pub fn parse(archive_path: &Path) -> Result<ParseResult, EdigeoError> {
+    parse_lot(&Lot::open(archive_path)?)
+}
+
+/// Parse un lot EDIGEO (voir `for_each_lot` pour les archives imbriquées).
+pub fn parse_lot(lot: &Lot) -> Result<ParseResult, EdigeoError> {
+    let mut features: HashMap<String, Vec<Feature>> = HashMap::new();
+
+    let summary = parse_lot_by_vec(lot, &ParseOptions::default(), |_, _, vec_features| {
+        group_by_type(&mut features, vec_features);
+        ControlFlow::Continue(())
+    })?;
+
+    Ok(parse_result(features, summary))
+}
+
+/// Ajoute des features à leur groupe de type
+fn group_by_type(groups: &mut HashMap<String, Vec<Feature>>, features: Vec<Feature>) {
+    for feature in features {
+        groups
+            .entry(feature.feature_type.clone())
+            .or_default()
+            .push(feature);
+    }
+}
+
+/// Résultat d'un lot dont les features ont été groupées par type
+fn parse_result(features: HashMap<String, Vec<Feature>>, summary: StreamSummary) -> ParseResult {
+    ParseResult {
+        features,
+        projection: summary.info.projection,
+        year: summary.info.year,
+        departement: summary.info.departement,
+        exchange: summary.info.exchange,
+        schema: summary.info.schema,
+        general: summary.general,
+        relations: summary.relations,
+        errors: summary.errors,
+        repairs: summary.repairs,
+    }
+}
+
+/// Parse une archive EDIGEO en streaming et transmet chaque feature à `on_feature`.
+///
+/// Les fichiers VEC sont parsés au fil de la décompression: seul le fichier en
+/// cours et ses features sont en mémoire. Les VEC rencontrés avant les
+/// métadonnées (THF, GEO, QAL) et le schéma (DIC, SCD) sont conservés
+/// jusqu'à leur lecture.
+///
+/// `on_feature` reçoit les métadonnées de l'archive avec chaque feature; le
+/// parsing s'arrête s'il retourne `ControlFlow::Break`.
+///
+/// # Errors
+///
+/// Retourne `EdigeoError` si l'archive est illisible ou s'il manque un fichier
+/// obligatoire. Ces erreurs surviennent avant la première feature, sauf une
+/// erreur d'I/O en cours de décompression.
+pub fn parse_with<F>(archive_path: &Path, on_feature: F) -> Result<StreamSummary, EdigeoError>
+where
+    F: FnMut(&ArchiveInfo, Feature) -> ControlFlow<()>,
+{
+    parse_lot_with(&Lot::open(archive_path)?, on_feature)
+}
+
+/// Équivalent de `parse_with` pour un lot EDIGEO.
+///
+/// Le département est déduit du nom du lot (archive ou répertoire), sinon de
+/// la commune décrite dans le fichier THF.
+pub fn parse_lot_with<F>(lot: &Lot, on_feature: F) -> Result<StreamSummary, EdigeoError>
+where
+    F: FnMut(&ArchiveInfo, Feature) -> ControlFlow<()>,
+{
+    parse_lot_with_options(lot, &ParseOptions::default(), on_feature)
+}
+
+/// Équivalent de `parse_lot_with` avec des options de construction des géométries.
+///
+/// Avec `options.snap`, les arcs de chaque VEC sont transformés puis arrondis
+/// avant l'assemblage des polygones (voir `repair::snap`): les features sont
+/// alors exprimées dans le système cible de la transformation, et non plus
+/// dans `ArchiveInfo::projection`.
+pub fn parse_lot_with_options<F>(
+    lot: &Lot,
+    options: &ParseOptions,
+    mut on_feature: F,
+) -> Result<StreamSummary, EdigeoError>
+where
+    F: FnMut(&ArchiveInfo, Feature) -> ControlFlow<()>,
+{
+    let mut features = 0usize;
+    let mut summary = parse_lot_by_vec(lot, options, |info, _, vec_features| {
+        for feature in vec_features {
+            features += 1;
+            on_feature(info, feature)?;
+        }
+        ControlFlow::Continue(())
+    })?;
+    summary.features = features;
+    Ok(summary)
+}
+
+/// Équivalent de `parse_lot_with_options` qui transmet les features VEC par VEC.
+///
+/// `on_vec` reçoit les features d'un fichier VEC avec le graphe de leurs
+/// relations, résolu dans ce seul VEC: un identifiant de feature sans IDU n'y
+/// désigne qu'une feature. `StreamSummary::relations` fusionne ces graphes.
+pub fn parse_lot_by_vec<F>(
+    lot: &Lot,
+    options: &ParseOptions,
+    mut on_vec: F,
+) -> Result<StreamSummary, EdigeoError>
+where
+    F: FnMut(&ArchiveInfo, &RelationGraph, Vec<Feature>) -> ControlFlow<()>,
+{
+    let mut errors = Vec::new();
+    let mut relations = RelationGraph::default();
+    let mut failed_vecs = 0usize;
+    let mut repairs = RepairLog::default();
+    let mut features = 0usize;
+
+    let files = read_lot(lot, &mut errors, |metadata, errors, vec_data| {
+        let mut context = VecContext {
+            projection: metadata.info.projection,
+            options,
+            quality: metadata.quality,
+            relations: &mut relations,
+            errors,
+            failed_vecs: &mut failed_vecs,
+            repairs: &mut repairs,
+        };
+        parse_vec(
+            &vec_data,
+            metadata.thf,
+            &mut context,
+            |vec_relations, vec_features| {
+                features += vec_features.len();
+                on_vec(metadata.info, vec_relations, vec_features)
+            },
+        )
+    })?;
+    let general = parse_general(files.gen.as_deref(), &files.thf, &mut errors);
+
+    Ok(StreamSummary {
+        info: files.info,
+        features,
+        general,
+        relations,
+        errors,
+        failed_vecs,
+        repairs,
+    })
+}
+
+/// Métadonnées d'un lot transmises avec chacun de ses VEC (voir `read_lot`)
+struct LotMetadata<'a> {
+    info: &'a ArchiveInfo,
+    thf: &'a ThfData,
+    quality: &'a HashMap<String, Quality>,
+}
+
+/// Fichiers d'un lot hors VEC, lus par `read_lot`
+struct LotFiles {
+    info: ArchiveInfo,
+    thf: ThfData,
+    /// Dates de qualité, pour les VEC traités après la lecture
+    #[cfg(feature = "parallel")]
+    quality: HashMap<String, Quality>,
+    /// Fichier GEN, parsé en fin de lot
+    gen: Option<Vec<u8>>,
+}
+
+/// Lit les fichiers d'un lot et transmet le contenu de chaque VEC à `on_vec`
+///
+/// Les VEC attendent l'encodage (THF), la projection (GEO), les dates (QAL)
+/// et le schéma (DIC, SCD): ceux rencontrés avant sont conservés jusqu'à leur
+/// lecture, ou jusqu'à la fin du lot s'il manque QAL, DIC ou SCD. La lecture
+/// s'arrête si `on_vec` retourne `ControlFlow::Break`. Les erreurs non
+/// fatales (schéma) sont ajoutées à `errors` avant celles des VEC.
+///
+/// # Errors
+///
+/// Retourne `EdigeoError` si le lot est illisible, si un fichier THF, GEO ou
+/// QAL est invalide, ou s'il manque un fichier obligatoire.
+fn read_lot<F>(
+    lot: &Lot,
+    errors: &mut Vec<EdigeoError>,
+    mut on_vec: F,
+) -> Result<LotFiles, EdigeoError>
+where
+    F: FnMut(&LotMetadata, &mut Vec<EdigeoError>, Vec<u8>) -> ControlFlow<()>,
+{
+    let departement = extract_departement(Path::new(lot.archive()));
+
+    let mut thf: Option<ThfData> = None;
+    let mut projection: Option<Projection> = None;
+    let mut quality: Option<HashMap<String, Quality>> = None;
+    let mut pending_vec: Vec<Vec<u8>> = Vec::new();
+    // DIC et SCD (petits fichiers) sont parsés avec les métadonnées, GEN en
+    // fin d'archive
+    let mut dic: Option<Vec<u8>> = None;
+    let mut scd: Option<Vec<u8>> = None;
+    let mut gen: Option<Vec<u8>> = None;
+    let mut vec_count = 0usize;
+
+    let mut info: Option<ArchiveInfo> = None;
+    let mut stopped = false;
+    let mut failure: Option<EdigeoError> = None;
+
+    lot.for_each_entry(|kind, content| {
+        let parsed = match kind {
+            archive::EntryKind::Thf => parser::thf::parse(&content).map(|t| thf = Some(t)),
+            archive::EntryKind::Geo => parser::geo::parse(&content).map(|p| projection = Some(p)),
+            archive::EntryKind::Qal => parser::qal::parse(&content).map(|q| quality = Some(q)),
+            archive::EntryKind::Vec => {
+                vec_count += 1;
+                pending_vec.push(content);
+                Ok(())
+            }
+            archive::EntryKind::Dic => {
+                dic = Some(content);
+                Ok(())
+            }
+            archive::EntryKind::Scd => {
+                scd = Some(content);
+                Ok(())
+            }
+            archive::EntryKind::Gen => {
+                gen = Some(content);
+                Ok(())
+            }
+        };
+        if let Err(e) = parsed {
+            failure = Some(e);
+            return ControlFlow::Break(());
+        }
+
+        let (Some(thf), Some(projection), Some(quality), Some(_), Some(_)) =
+            (&thf, projection, &quality, &dic, &scd)
+        else {
+            return ControlFlow::Continue(());
+        };
+        let info = info.get_or_insert_with(|| {
+            let schema = parse_schema(dic.as_deref(), scd.as_deref(), thf, errors);
+            archive_info(departement.as_deref(), projection, thf, schema)
+        });
+
+        let metadata = LotMetadata { info, thf, quality };
+        for vec_data in pending_vec.drain(..) {
+            if on_vec(&metadata, errors, vec_data).is_break() {
+                stopped = true;
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
+    })?;
+
+    if let Some(e) = failure {
+        return Err(e);
+    }
+
+    // Vérifier la présence des fichiers obligatoires
+    let thf = thf.ok_or_else(|| EdigeoError::MissingFile("THF".into()))?;
+    let projection = projection.ok_or_else(|| EdigeoError::MissingFile("GEO".into()))?;
+    if vec_count == 0 {
+        return Err(EdigeoError::MissingFile("VEC".into()));
+    }
+
+    // QAL, DIC ou SCD absent: les VEC restants sont parsés sans dates de
+    // qualité ou avec un schéma incomplet
+    let info = info.unwrap_or_else(|| {
+        let schema = parse_schema(dic.as_deref(), scd.as_deref(), &thf, errors);
+        archive_info(departement.as_deref(), projection, &thf, schema)
+    });
+    let quality = quality.unwrap_or_default();
+    if !stopped {
+        let metadata = LotMetadata {
+            info: &info,
+            thf: &thf,
+            quality: &quality,
+        };
+        for vec_data in pending_vec {
+            if on_vec(&metadata, errors, vec_data).is_break() {
+                break;
+            }
+        }
+    }
+
+    Ok(LotFiles {
+        info,
+        thf,
+        #[cfg(feature = "parallel")]
+        quality,
+        gen,
+    })
+}
+
+/// Métadonnées d'un lot, le département du nom du lot prévalant sur le THF
+fn archive_info(
+    departement: Option<&str>,
+    projection: Projection,
+    thf: &ThfData,
+    schema: Schema,
+) -> ArchiveInfo {
+    ArchiveInfo {
+        projection,
+        year: thf.exchange.production_date.year,
+        departement: departement
+            .or(thf.exchange.departement())
+            .unwrap_or("00")
+            .to_string(),
+        exchange: thf.exchange.clone(),
+        schema,
+    }
+}
+
+/// Lit le schéma conceptuel d'une entrée EDIGEO sans parser les VEC.
+///
+/// La lecture d'un lot s'arrête dès que ses fichiers THF, DIC et SCD ont été
+/// lus. Les schémas des lots d'une archive départementale sont fusionnés.
+///
+/// # Errors
+///
+/// Retourne `EdigeoError` si l'entrée est illisible, s'il manque le fichier
+/// THF d'un lot ou si un fichier SCD est invalide.
+pub fn read_schema(path: &Path) -> Result<Schema, EdigeoError> {
+    let mut schema = Schema::default();
+    let mut failure: Option<EdigeoError> = None;
+
+    input::for_each_lot(path, |lot| match read_lot_schema(&lot) {
+        Ok(lot_schema) => {
+            schema.merge(lot_schema);
+            ControlFlow::Continue(())
+        }
+        Err(e) => {
+            failure = Some(e);
+            ControlFlow::Break(())
+        }
+    })?;
+
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(schema),
+    }
+}
+
+/// Schéma conceptuel d'un lot (voir `read_schema`)
+pub fn read_lot_schema(lot: &Lot) -> Result<Schema, EdigeoError> {
+    let mut thf: Option<ThfData> = None;
+    let mut dic: Option<Vec<u8>> = None;
+    let mut scd: Option<Vec<u8>> = None;
+    let mut failure: Option<EdigeoError> = None;
+
+    lot.for_each_entry(|kind, content| {
+        match kind {
+            archive::EntryKind::Thf => match parser::thf::parse(&content) {
+                Ok(t) => thf = Some(t),
+                Err(e) => {
+                    failure = Some(e);
+                    return ControlFlow::Break(());
+                }
+            },
+            archive::EntryKind::Dic => dic = Some(content),
+            archive::EntryKind::Scd => scd = Some(content),
+            _ => {}
+        }
+        if thf.is_some() && dic.is_some() && scd.is_some() {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })?;
+
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    let thf = thf.ok_or_else(|| EdigeoError::MissingFile("THF".into()))?;
+
+    let mut errors = Vec::new();
+    let schema = parse_schema(dic.as_deref(), scd.as_deref(), &thf, &mut errors);
+    match errors.into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(schema),
+    }
+}
+
+/// Parse le dictionnaire et le schéma conceptuel
+///
+/// Ces fichiers sont facultatifs; leurs erreurs de parsing sont non fatales.
+fn parse_schema(
+    dic: Option<&[u8]>,
+    scd: Option<&[u8]>,
+    thf: &ThfData,
+    errors: &mut Vec<EdigeoError>,
+) -> Schema {
+    let decode = |data| decode_with_encoding(data, thf.encoding);
+
+    let dictionary = non_fatal(dic.map(|data| parser::dic::parse(&decode(data))), errors);
+    non_fatal(
+        scd.map(|data| parser::scd::parse(&decode(data), &dictionary)),
+        errors,
+    )
+}
+
+/// Parse les informations générales (fichier GEN facultatif, erreur non fatale)
+fn parse_general(gen: Option<&[u8]>, thf: &ThfData, errors: &mut Vec<EdigeoError>) -> General {
+    non_fatal(
+        gen.map(|data| parser::gen::parse(&decode_with_encoding(data, thf.encoding))),
+        errors,
+    )
+}
+
+/// Résultat d'un fichier facultatif: valeur par défaut si absent ou en erreur
+fn non_fatal<T: Default>(
+    result: Option<Result<T, EdigeoError>>,
+    errors: &mut Vec<EdigeoError>,
+) -> T {
+    match result {
+        Some(Ok(value)) => value,
+        Some(Err(e)) => {
+            errors.push(e);
+            T::default()
+        }
+        None => T::default(),
+    }
+}
+
+/// Contexte de construction des VEC d'un lot: options, et relations, erreurs
+/// non fatales et retouches accumulées
+struct VecContext<'a> {
+    projection: Projection,
+    options: &'a ParseOptions<'a>,
+    quality: &'a HashMap<String, Quality>,
+    /// Relations des VEC déjà parsés
+    relations: &'a mut RelationGraph,
+    errors: &'a mut Vec<EdigeoError>,
+    /// VEC illisibles
+    failed_vecs: &'a mut usize,
+    repairs: &'a mut RepairLog,
+}
+
+/// Parse un fichier VEC et transmet ses features avec leurs relations
+///
+/// Les erreurs de parsing d'un VEC sont non fatales: elles sont comptées
+/// dans `context.failed_vecs`.
+fn parse_vec<F>(data: &[u8], thf: &ThfData, context: &mut VecContext, on_vec: F) -> ControlFlow<()>
+where
+    F: FnOnce(&RelationGraph, Vec<Feature>) -> ControlFlow<()>,
+{
+    // Seules les valeurs d'attributs sont décodées
+    let mut parsed_vec = match parser::vec::parse(data, thf.encoding) {
+        Ok(parsed_vec) => parsed_vec,
+        Err(e) => {
+            context.fail(e);
+            return ControlFlow::Continue(());
+        }
+    };
+
+    reject_unreadable(&mut parsed_vec, context.errors, context.repairs);
+
+    // Arrondi topologique: un sommet non transformable écarte ses entités
+    let tolerance = snap_vec(
+        &mut parsed_vec,
+        &context.projection,
+        context.options,
+        context.errors,
+        context.repairs,
+    );
+
+    let relations = repair::build_relations(&parsed_vec);
+
+    // Construire les géométries depuis les entités parsées
+    let features = repair::build_geometries(
+        &parsed_vec,
+        context.quality,
+        tolerance,
+        context.errors,
+        context.repairs,
+    );
+    context.deliver(relations, features, on_vec)
+}
+
+impl VecContext<'_> {
+    /// VEC illisible: erreur non fatale, ses features manquent au lot
+    fn fail(&mut self, error: EdigeoError) {
+        self.errors.push(error);
+        *self.failed_vecs += 1;
+    }
+
+    /// Transmet les features construites d'un VEC, puis retient ses relations
+    fn deliver<F>(
+        &mut self,
+        relations: RelationGraph,
+        features: Result<Vec<Feature>, EdigeoError>,
+        on_vec: F,
+    ) -> ControlFlow<()>
+    where
+        F: FnOnce(&RelationGraph, Vec<Feature>) -> ControlFlow<()>,
+    {
+        let flow = match features {
+            Ok(features) => on_vec(&relations, features),
+            Err(e) => {
+                self.fail(e);
+                ControlFlow::Continue(())
+            }
+        };
+        self.relations.extend(relations);
+        flow
+    }
+}
+
+/// Écarte les entités qui utilisent un enregistrement illisible du VEC
+///
+/// Les erreurs des enregistrements écartés au parsing sont non fatales: seules
+/// ces entités manquent au lot.
+fn reject_unreadable(
+    parsed_vec: &mut parser::vec::ParsedVec,
+    errors: &mut Vec<EdigeoError>,
+    repairs: &mut RepairLog,
+) {
+    errors.append(&mut parsed_vec.errors);
+    let rejected = std::mem::take(&mut parsed_vec.rejected);
+    repair::reject_primitives(parsed_vec, &rejected, "unreadable record", errors, repairs);
+}
+
+/// Arrondi topologique d'un VEC si demandé, retourne la tolérance d'assemblage
+///
+/// Les entités qui utilisent un sommet non transformable sont écartées (voir
+/// `repair::reject_primitives`).
+fn snap_vec(
+    parsed_vec: &mut parser::vec::ParsedVec,
+    projection: &Projection,
+    options: &ParseOptions,
+    errors: &mut Vec<EdigeoError>,
+    repairs: &mut RepairLog,
+) -> f64 {
+    let Some(snap) = &options.snap else {
+        return options.tolerance;
+    };
+    let removed = snap.apply(parsed_vec, projection);
+    repair::reject_primitives(
+        parsed_vec,
+        &removed,
+        "coordinate transform failed",
+        errors,
+        repairs,
+    );
+    // Sommets sur la grille: deux nœuds distincts sont distants d'au moins un pas
+    options
+        .tolerance
+        .min(0.5 * 10_f64.powi(-i32::from(snap.decimals)))
+}
+
+/// Décode les bytes avec l'encodage détecté (sans copie si déjà valides)
+fn decode_with_encoding<'a>(
+    data: &'a [u8],
+    encoding: &'static encoding_rs::Encoding,
+) -> Cow<'a, str> {
+    let (decoded, _, _) = encoding.decode(data);
+    decoded
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -101,74 +710,345 @@ mod tests {
         );
         assert_eq!(extract_departement(Path::new("fichier-invalide.txt")), None);
     }
-}
 
-/// Parse une archive EDIGEO (.tar.bz2) et retourne les features géographiques.
-///
-/// # Arguments
-///
-/// * `archive_path` - Chemin vers l'archive .tar.bz2
-///
-/// # Returns
-///
-/// Un `ParseResult` contenant les features groupées par type, la projection source,
-/// l'année du millésime, le code département, et les erreurs non fatales rencontrées.
-///
-/// # Errors
-///
-/// Retourne `EdigeoError` si l'archive est illisible ou si aucun fichier THF n'est trouvé.
-pub fn parse(archive_path: &Path) -> Result<ParseResult, EdigeoError> {
-    // 1. Extraire le département depuis le nom de fichier
-    let departement = extract_departement(archive_path).unwrap_or_else(|| "00".to_string());
+    const VEC: &str = "RTYSA03:PNO\r\nRIDSA07:Noeud_1\r\nCORCC23:+881824.53;+6663821.17;\r\n\
+        RTYSA03:PNO\r\nRIDSA07:Noeud_2\r\nCORCC23:+881830.00;+6663830.00;\r\n\
+        RTYSA03:FEA\r\nRIDSA05:Obj_1\r\nSCPCP24:EDAB01;SeSD;OBJ;BORNE_id\r\n\
+        ATPCP22:EDAB01;SeSD;ATT;IDU_id\r\nATVST03:001\r\n\
+        RTYSA03:FEA\r\nRIDSA05:Obj_2\r\nSCPCP24:EDAB01;SeSD;OBJ;BORNE_id\r\n\
+        RTYSA03:LNK\r\nRIDSA05:Lnk_1\r\nSCPCP30:EDAB01;SeSD;REL;ID_S_RCO_Noeud\r\n\
+        FTPCP21:EDAB01;SeSD;FEA;Obj_1\r\nFTPCP23:EDAB01;SeSD;PNO;Noeud_1\r\n\
+        RTYSA03:LNK\r\nRIDSA05:Lnk_2\r\nSCPCP30:EDAB01;SeSD;REL;ID_S_RCO_Noeud\r\n\
+        FTPCP21:EDAB01;SeSD;FEA;Obj_2\r\nFTPCP23:EDAB01;SeSD;PNO;Noeud_2\r\n\
+        RTYSA03:LNK\r\nRIDSA05:Lnk_3\r\nSCPCP32:EDAB01;SeSD;REL;ID_S_OBJ_Z_1_2_2\r\n\
+        FTPCP21:EDAB01;SeSD;FEA;Obj_2\r\nFTPCP21:EDAB01;SeSD;FEA;Obj_1\r\n";
 
-    // 2. Extraire l'archive
-    let archive_data = archive::extract(archive_path)?;
+    // Dictionnaire en Latin-9 (CSET IRV)
+    const DIC: &[u8] = b"RTYSA03:DID\r\nRIDSA08:BORNE_id\r\nLABSA05:BORNE\r\n\
+        DEFTA25:Borne limite de propri\xe9t\xe9\r\n";
+    const SCD: &[u8] = b"RTYSA03:OBJ\r\nRIDSA08:BORNE_id\r\nDIPCP24:EDAB01;SeDI;DID;BORNE_id\r\n\
+        KNDSA03:PCT\r\nAAPCP22:EDAB01;SeSD;ATT;IDU_id\r\n\
+        RTYSA03:ATT\r\nRIDSA06:IDU_id\r\nTYPSA01:T\r\nCANSN02:14\r\n";
+    const THF: &[u8] = b"CSET 03:IRV\r\nRTYSA03:GTS\r\nAUTSA05:DGFiP\r\nTDASD08:20240115\r\n\
+        RTYSA03:GTL\r\nRIDSA04:LOT1\r\nLONSA12:380010000S01\r\nGDNSA08:EDAB01T1\r\n";
+    const GEN: &[u8] =
+        b"RTYSA03:DEG\r\nCM1CC23:+881000.00;+6663000.00;\r\nCM2CC23:+882000.00;+6664000.00;\r\n";
 
-    // 3. Parser les métadonnées
-    let thf = parser::thf::parse(&archive_data.thf)?;
-    let projection = parser::geo::parse(&archive_data.geo)?;
-    let quality = parser::qal::parse(&archive_data.qal)?;
+    /// Archive .tar.bz2 minimale, VEC placé avant les métadonnées
+    fn write_archive(name: &str) -> std::path::PathBuf {
+        write_archive_with(name, &[("EDAB01T1.VEC", VEC.as_bytes())])
+    }
 
-    // 4. Parser les VEC et construire les géométries
-    // Note: Le parallélisme est géré au niveau des archives (--jobs), pas ici
-    let mut all_features = std::collections::HashMap::new();
-    let mut errors = Vec::new();
+    /// Métadonnées et schéma d'un lot
+    const METADATA: [(&str, &[u8]); 6] = [
+        ("EDAB01SE.SCD", SCD),
+        ("E0000A01.THF", THF),
+        ("EDAB01SE.GEO", b"RELSACC:LAMB93\r\n"),
+        ("EDAB01SE.QAL", b""),
+        ("EDAB01SE.DIC", DIC),
+        ("EDAB01SE.GEN", GEN),
+    ];
 
-    for vec_data in &archive_data.vec {
-        // Décoder avec le bon encodage
-        let decoded = decode_with_encoding(vec_data, thf.encoding);
-
-        match parser::vec::parse(&decoded) {
-            Ok(parsed_vec) => {
-                // Construire les géométries depuis les entités parsées
-                match repair::build_geometries(&parsed_vec, &quality) {
-                    Ok(features) => {
-                        for feature in features {
-                            let feature_type = feature.feature_type.clone();
-                            all_features
-                                .entry(feature_type)
-                                .or_insert_with(Vec::new)
-                                .push(feature);
-                        }
-                    }
-                    Err(e) => errors.push(e),
-                }
-            }
-            Err(e) => errors.push(e),
+    /// Archive .tar.bz2 minimale avec les VEC donnés, avant les métadonnées
+    fn write_archive_with(name: &str, vecs: &[(&str, &[u8])]) -> std::path::PathBuf {
+        let entries: Vec<_> = vecs.iter().chain(&METADATA).copied().collect();
+        write_entries(name, &entries)
+    }
+
+    /// Archive .tar.bz2 des fichiers donnés, dans l'ordre
+    fn write_entries(name: &str, entries: &[(&str, &[u8])]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(name);
+        let file = std::fs::File::create(&path).unwrap();
+        let encoder = bzip2::write::BzEncoder::new(file, bzip2::Compression::fast());
+        let mut builder = tar::Builder::new(encoder);
+
+        for &(name, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, data).unwrap();
         }
+        builder.into_inner().unwrap().finish().unwrap();
+        path
     }
 
-    Ok(ParseResult {
-        features: all_features,
-        projection,
-        year: thf.year,
-        departement,
-        errors,
-    })
-}
+    #[test]
+    fn test_parse_with_streams_features() {
+        let path = write_archive("edigeo-380010000S01.tar.bz2");
 
-/// Décode les bytes avec l'encodage détecté
-fn decode_with_encoding(data: &[u8], encoding: &'static encoding_rs::Encoding) -> String {
-    let (decoded, _, _) = encoding.decode(data);
-    decoded.into_owned()
+        let mut ids = Vec::new();
+        let summary = parse_with(&path, |info, feature| {
+            assert_eq!(info.projection.epsg, 2154);
+            // Schéma connu dès la première feature
+            assert!(info.schema.object(&feature.feature_type).is_some());
+            ids.push(feature.id);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+
+        ids.sort();
+        assert_eq!(ids, vec!["001", "Obj_2"]);
+        assert_eq!(summary.features, 2);
+        assert_eq!(summary.info.year, 2024);
+        assert_eq!(summary.info.departement, "38");
+        assert_eq!(summary.info.exchange.producer.as_deref(), Some("DGFiP"));
+        assert_eq!(summary.info.exchange.commune.as_deref(), Some("38001"));
+        assert_eq!(summary.info.exchange.lots[0].files, vec!["EDAB01T1"]);
+
+        // `parse` regroupe les mêmes features par type
+        let result = parse(&path).unwrap();
+        assert_eq!(result.features["BORNE_id"].len(), 2);
+
+        // Schéma et emprise lus dans les DIC, SCD et GEN
+        let borne = result.schema.object("BORNE_id").unwrap();
+        assert_eq!(borne.label, "BORNE");
+        assert_eq!(
+            borne.definition.as_deref(),
+            Some("Borne limite de propriété")
+        );
+        assert_eq!(borne.kind, Some(types::GeometryKind::Point));
+        assert_eq!(
+            result.schema.attribute("IDU").unwrap().value_type,
+            Some(AttributeType::Text)
+        );
+        assert_eq!(result.general.extent.unwrap().width(), 1000.0);
+
+        // Lien FEA → FEA, par identifiant de feature (IDU si disponible)
+        let neighbors: Vec<_> = result.relations.neighbors("Obj_2").collect();
+        assert_eq!(neighbors, vec![("ID_S_OBJ_Z_1_2_2", "001")]);
+        assert_eq!(result.relations.nearest("Obj_2", "BORNE_id"), Some("Obj_2"));
+        assert_eq!(result.relations.nearest("Obj_2", "SECTION_id"), None);
+
+        // Schéma seul, sans parser les VEC
+        let schema = read_schema(&path).unwrap();
+        assert_eq!(schema.object_attributes("BORNE_id")[0].id, "IDU_id");
+
+        // Arrêt anticipé
+        let mut seen = 0;
+        let summary = parse_with(&path, |_, _| {
+            seen += 1;
+            ControlFlow::Break(())
+        })
+        .unwrap();
+        assert_eq!((seen, summary.features), (1, 1));
+
+        std::fs::remove_file(path).ok();
+    }
+
+    /// Recalcule les longueurs déclarées d'un VEC dont les valeurs ont été modifiées
+    fn relength(vec: &str) -> String {
+        vec.split_inclusive("\r\n")
+            .map(|line| {
+                let value = line[8..].trim_end_matches("\r\n");
+                format!("{}{:02}:{}\r\n", &line[..5], value.len(), value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_relations_resolved_per_vec() {
+        // Mêmes identifiants d'enregistrement dans les deux VEC, IDU distincts
+        let second = relength(&VEC.replace("ATVST03:001", "ATVST03:002"));
+        let path = write_archive_with(
+            "edigeo-380010000S03.tar.bz2",
+            &[
+                ("EDAB01T1.VEC", VEC.as_bytes()),
+                ("EDAB01T2.VEC", second.as_bytes()),
+            ],
+        );
+
+        let lot = Lot::open(&path).unwrap();
+        let mut linked = Vec::new();
+        let summary = parse_lot_by_vec(&lot, &ParseOptions::default(), |_, relations, features| {
+            assert_eq!(features.len(), 2);
+            let neighbors: Vec<_> = relations.neighbors("Obj_2").map(|(_, id)| id).collect();
+            linked.push(neighbors.join(","));
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+
+        // Obj_2 de chaque VEC est relié à l'Obj_1 de son VEC
+        assert_eq!(linked, vec!["001", "002"]);
+        assert_eq!(summary.features, 4);
+        let mut merged: Vec<_> = summary.relations.neighbors("Obj_2").collect();
+        merged.sort();
+        assert_eq!(
+            merged,
+            vec![("ID_S_OBJ_Z_1_2_2", "001"), ("ID_S_OBJ_Z_1_2_2", "002")]
+        );
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_corrupt_record_rejects_its_features() {
+        // Longueur déclarée inexacte sur le nœud de Obj_2, au milieu du VEC
+        let vec = VEC.replace(
+            "CORCC23:+881830.00;+6663830.00;",
+            "CORCC24:+881830.00;+6663830.00;",
+        );
+        let path = write_archive_with(
+            "edigeo-380010000S06.tar.bz2",
+            &[("EDAB01T1.VEC", vec.as_bytes())],
+        );
+
+        let mut ids = Vec::new();
+        let summary = parse_with(&path, |_, feature| {
+            ids.push(feature.id);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+
+        // Seule la feature du nœud illisible manque, le VEC n'est pas en échec
+        assert_eq!(ids, vec!["001"]);
+        assert_eq!(summary.failed_vecs, 0);
+        let errors: Vec<_> = summary.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors[0].contains("record Noeud_2 skipped"), "{:?}", errors);
+        assert!(errors[1].contains("Obj_2"), "{:?}", errors);
+        assert_eq!(summary.repairs.entries.len(), 1);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    /// Second VEC aux identifiants renommés (longueurs déclarées recalculées)
+    #[cfg(feature = "parallel")]
+    fn renamed_vec() -> String {
+        relength(
+            &VEC.replace("Obj_", "Obj_T2_")
+                .replace("Noeud_", "Noeud_T2_")
+                .replace("ATVST03:001", "ATVST03:002"),
+        )
+    }
+
+    /// Vérifie que `parse_lot_parallel` rend le même résultat que le
+    /// streaming (avec les mêmes options), sur plusieurs exécutions
+    #[cfg(feature = "parallel")]
+    fn assert_parallel_matches(path: &Path, options: &ParseOptions) -> ParseResult {
+        let ids = |result: &ParseResult| -> Vec<(String, String)> {
+            let mut types: Vec<_> = result.features.keys().cloned().collect();
+            types.sort();
+            types
+                .iter()
+                .flat_map(|t| result.features[t].iter())
+                .map(|f| (f.id.clone(), format!("{:?}", f.geometry)))
+                .collect()
+        };
+        // Voisins de chaque feature, triés (ordre des HashMap du graphe)
+        let neighbors = |result: &ParseResult| -> Vec<Vec<(String, String)>> {
+            ids(result)
+                .iter()
+                .map(|(id, _)| {
+                    let mut linked: Vec<_> = result
+                        .relations
+                        .neighbors(id)
+                        .map(|(r, n)| (r.to_string(), n.to_string()))
+                        .collect();
+                    linked.sort();
+                    linked
+                })
+                .collect()
+        };
+
+        let lot = Lot::open(path).unwrap();
+        let mut features = HashMap::new();
+        let summary = parse_lot_by_vec(&lot, options, |_, _, vec_features| {
+            group_by_type(&mut features, vec_features);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        let sequential = parse_result(features, summary);
+
+        for _ in 0..3 {
+            let parallel = parse_lot_parallel(&lot, options).unwrap();
+            assert_eq!(ids(&parallel), ids(&sequential));
+            assert_eq!(neighbors(&parallel), neighbors(&sequential));
+            assert_eq!(
+                format!("{:?}", parallel.errors),
+                format!("{:?}", sequential.errors)
+            );
+            assert_eq!(parallel.repairs.entries, sequential.repairs.entries);
+            assert_eq!(parallel.year, sequential.year);
+            assert_eq!(parallel.exchange, sequential.exchange);
+            assert_eq!(parallel.general.extent, sequential.general.extent);
+            assert_eq!(
+                parallel.schema.objects.keys().collect::<Vec<_>>(),
+                sequential.schema.objects.keys().collect::<Vec<_>>()
+            );
+        }
+        sequential
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parse_parallel_matches_sequential() {
+        let second = renamed_vec();
+        let path = write_archive_with(
+            "edigeo-380010000S02.tar.bz2",
+            &[
+                ("EDAB01T1.VEC", VEC.as_bytes()),
+                ("EDAB01T2.VEC", second.as_bytes()),
+            ],
+        );
+
+        let result = assert_parallel_matches(&path, &ParseOptions::default());
+        let ids: Vec<_> = result.features["BORNE_id"].iter().map(|f| &f.id).collect();
+        assert_eq!(ids, vec!["001", "Obj_2", "002", "Obj_T2_2"]);
+        assert_eq!(
+            result.relations.nearest("Obj_T2_2", "BORNE_id"),
+            Some("Obj_T2_2")
+        );
+
+        // Arrondi topologique
+        let snap = ParseOptions {
+            snap: Some(repair::snap::SnapRounding {
+                decimals: 1,
+                transform: None,
+            }),
+            ..ParseOptions::default()
+        };
+        assert_parallel_matches(&path, &snap);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parse_parallel_matches_sequential_with_errors() {
+        // VEC illisible (identifiant en Latin-1) entre deux VEC valides, le
+        // second avec un enregistrement illisible
+        let second = renamed_vec().replace("CORCC23:+881830.00", "CORCC24:+881830.00");
+        let path = write_archive_with(
+            "edigeo-380010000S04.tar.bz2",
+            &[
+                ("EDAB01T1.VEC", VEC.as_bytes()),
+                ("EDAB01T3.VEC", b"RTYSA03:PNO\r\nRIDSA07:Noeud_\xe9\r\n"),
+                ("EDAB01T2.VEC", second.as_bytes()),
+            ],
+        );
+        let result = assert_parallel_matches(&path, &ParseOptions::default());
+        assert_eq!(result.features["BORNE_id"].len(), 3);
+        assert_eq!(result.errors.len(), 3);
+        std::fs::remove_file(path).ok();
+
+        // Sans QAL ni DIC, SCD invalide: VEC parsés en fin de lot, erreur du
+        // schéma avant celle du VEC
+        let entries: [(&str, &[u8]); 5] = [
+            ("EDAB01T1.VEC", VEC.as_bytes()),
+            ("EDAB01SE.SCD", b"RTYSA03:OBJ\r\nRIDSA09:BORNE_id\r\n"),
+            ("EDAB01T3.VEC", b"RTYSA03:PNO\r\nRIDSA08:Noeud_1\r\n"),
+            ("E0000A01.THF", THF),
+            ("EDAB01SE.GEO", b"RELSACC:LAMB93\r\n"),
+        ];
+        let path = write_entries("edigeo-380010000S05.tar.bz2", &entries);
+        let result = assert_parallel_matches(&path, &ParseOptions::default());
+        assert_eq!(result.features["BORNE_id"].len(), 2);
+        assert!(result.general.extent.is_none());
+        let errors: Vec<_> = result.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("RIDSA09"), "{:?}", errors);
+        assert!(errors[1].contains("RIDSA08"), "{:?}", errors);
+        std::fs::remove_file(path).ok();
+    }
 }
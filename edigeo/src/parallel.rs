@@ -0,0 +1,137 @@
+//! Parsing d'un lot en parallèle (feature `parallel`)
+//!
+//! Les VEC d'un lot sont lus en entier, parsés en parallèle, puis leurs
+//! géométries construites par lots de liens sur le pool rayon courant. Les
+//! résultats sont fusionnés dans l'ordre des VEC de l'archive: le
+//! `ParseResult` est le même d'une exécution à l'autre, et le même qu'avec
+//! `parse_lot`.
+
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::path::Path;
+
+use rayon::prelude::*;
+
+use crate::{
+    group_by_type, parse_general, parse_result, parser, read_lot, reject_unreadable, repair,
+    snap_vec, EdigeoError, Feature, Lot, ParseOptions, ParseResult, RelationGraph, RepairLog,
+    StreamSummary, VecContext,
+};
+
+/// Parse une archive EDIGEO en parallélisant le traitement de ses VEC.
+///
+/// # Errors
+///
+/// Retourne `EdigeoError` si l'archive est illisible ou s'il manque un fichier
+/// obligatoire.
+pub fn parse_parallel(
+    archive_path: &Path,
+    options: &ParseOptions,
+) -> Result<ParseResult, EdigeoError> {
+    parse_lot_parallel(&Lot::open(archive_path)?, options)
+}
+
+/// Équivalent parallèle de `parse_lot` (avec options, voir
+/// `parse_lot_with_options`).
+///
+/// Contrairement au parsing en streaming, tous les VEC du lot sont en mémoire
+/// en même temps. L'arrondi topologique (`options.snap`) reste séquentiel: sa
+/// transformation n'a pas à être partagée entre threads.
+pub fn parse_lot_parallel(lot: &Lot, options: &ParseOptions) -> Result<ParseResult, EdigeoError> {
+    let mut errors = Vec::new();
+    let mut vecs: Vec<Vec<u8>> = Vec::new();
+    let files = read_lot(lot, &mut errors, |_, _, vec_data| {
+        vecs.push(vec_data);
+        ControlFlow::Continue(())
+    })?;
+
+    // Parsing des VEC, résultats dans l'ordre de l'archive
+    let encoding = files.thf.encoding;
+    let parsed: Vec<_> = vecs
+        .par_iter()
+        .map(|data| parser::vec::parse(data, encoding))
+        .collect();
+
+    // Arrondi topologique, dans l'ordre
+    let prepared: Vec<Result<_, EdigeoError>> = parsed
+        .into_iter()
+        .map(|parsed| {
+            let mut parsed_vec = parsed?;
+            let mut errors = Vec::new();
+            let mut repairs = RepairLog::default();
+            reject_unreadable(&mut parsed_vec, &mut errors, &mut repairs);
+            let tolerance = snap_vec(
+                &mut parsed_vec,
+                &files.info.projection,
+                options,
+                &mut errors,
+                &mut repairs,
+            );
+            Ok((parsed_vec, tolerance, errors, repairs))
+        })
+        .collect();
+
+    // Relations et géométries de chaque VEC, par lots de liens
+    let built: Vec<Result<_, EdigeoError>> = prepared
+        .into_par_iter()
+        .map(|prepared| {
+            let (parsed_vec, tolerance, mut errors, mut repairs) = prepared?;
+            let relations = repair::build_relations(&parsed_vec);
+            let features = repair::build_geometries_parallel(
+                &parsed_vec,
+                &files.quality,
+                tolerance,
+                &mut errors,
+                &mut repairs,
+            );
+            Ok((features, relations, errors, repairs))
+        })
+        .collect();
+
+    // Assemblage dans l'ordre des VEC, comme en streaming
+    let mut features: HashMap<String, Vec<Feature>> = HashMap::new();
+    let mut relations = RelationGraph::default();
+    let mut failed_vecs = 0usize;
+    let mut repairs = RepairLog::default();
+    let mut feature_count = 0usize;
+    let mut context = VecContext {
+        projection: files.info.projection,
+        options,
+        quality: &files.quality,
+        relations: &mut relations,
+        errors: &mut errors,
+        failed_vecs: &mut failed_vecs,
+        repairs: &mut repairs,
+    };
+    for result in built {
+        let (vec_features, vec_relations, vec_errors, vec_repairs) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                context.fail(e);
+                continue;
+            }
+        };
+        context.errors.extend(vec_errors);
+        context.repairs.entries.extend(vec_repairs.entries);
+        let _ = context.deliver(vec_relations, vec_features, |_, vec_features| {
+            feature_count += vec_features.len();
+            group_by_type(&mut features, vec_features);
+            ControlFlow::Continue(())
+        });
+    }
+
+    let general = parse_general(files.gen.as_deref(), &files.thf, &mut errors);
+
+    Ok(parse_result(
+        features,
+        StreamSummary {
+            info: files.info,
+            features: feature_count,
+            general,
+            relations,
+            errors,
+            failed_vecs,
+            repairs,
+        },
+    ))
+}
@@ -0,0 +1,65 @@
+//! Parser pour les fichiers DIC (dictionnaire)
+
+use crate::parser::records;
+use crate::types::{AttributeType, Definition, Dictionary};
+use crate::EdigeoError;
+
+/// Parse un fichier DIC décodé
+///
+/// Les enregistrements DID (objets), DIA (attributs) et DIR (relations) sont
+/// indexés par identifiant; les autres sont ignorés.
+pub fn parse(content: &str) -> Result<Dictionary, EdigeoError> {
+    let mut dictionary = Dictionary::default();
+
+    for record in records(content)? {
+        let target = match record.kind.as_ref() {
+            "DID" => &mut dictionary.objects,
+            "DIA" => &mut dictionary.attributes,
+            "DIR" => &mut dictionary.relations,
+            _ => continue,
+        };
+        let Some(id) = record.id() else {
+            continue;
+        };
+
+        let definition = Definition {
+            label: record.get("LAB").unwrap_or(id).to_string(),
+            definition: record.get("DEF").map(str::to_string),
+            origin: record.get("ORI").map(str::to_string),
+            category: record.get("CAT").map(str::to_string),
+            value_type: record.get("TYP").map(AttributeType::from_code),
+        };
+        target.insert(id.to_string(), definition);
+    }
+
+    Ok(dictionary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIC: &str =
+        "RTYSA03:DID\r\nRIDSA11:PARCELLE_id\r\nLABSA08:PARCELLE\r\nTEXT 06:8859-1\r\n\
+DEFTA19:Parcelle cadastrale\r\nORISA05:DGFiP\r\nCATSA00:\r\n\
+RTYSA03:DIA\r\nRIDSA06:IDU_id\r\nLABSA03:IDU\r\nTEXT 06:8859-1\r\n\
+DEFTA26:Identifiant de la parcelle\r\nTYPSA01:T\r\n\
+RTYSA03:DIR\r\nRIDSA08:ID_S_RCO\r\nLABSA03:RCO\r\n";
+
+    #[test]
+    fn test_parse_dic() {
+        let dictionary = parse(DIC).unwrap();
+
+        let parcelle = &dictionary.objects["PARCELLE_id"];
+        assert_eq!(parcelle.label, "PARCELLE");
+        assert_eq!(parcelle.definition.as_deref(), Some("Parcelle cadastrale"));
+        assert_eq!(parcelle.origin.as_deref(), Some("DGFiP"));
+        assert_eq!(parcelle.category, None);
+
+        let idu = &dictionary.attributes["IDU_id"];
+        assert_eq!(idu.label, "IDU");
+        assert_eq!(idu.value_type, Some(AttributeType::Text));
+
+        assert_eq!(dictionary.relations["ID_S_RCO"].label, "RCO");
+    }
+}
@@ -0,0 +1,69 @@
+//! Parser pour les fichiers GEN (informations générales)
+
+use geo::{coord, Rect};
+
+use crate::parser::records;
+use crate::types::General;
+use crate::EdigeoError;
+
+/// Parse un fichier GEN décodé
+///
+/// L'emprise est lue dans les coins CM1 (minimum) et CM2 (maximum) des
+/// données géographiques (enregistrement DEG, sinon premier enregistrement
+/// qui en porte).
+pub fn parse(content: &str) -> Result<General, EdigeoError> {
+    let mut general = General::default();
+
+    for record in records(content)? {
+        let corners = record
+            .get("CM1")
+            .and_then(parse_corner)
+            .zip(record.get("CM2").and_then(parse_corner));
+        let Some((min, max)) = corners else {
+            continue;
+        };
+
+        if record.kind == "DEG" || general.extent.is_none() {
+            general.extent = Some(Rect::new(
+                coord! { x: min.0, y: min.1 },
+                coord! { x: max.0, y: max.1 },
+            ));
+        }
+        if record.kind == "DEG" {
+            break;
+        }
+    }
+
+    Ok(general)
+}
+
+/// Coordonnées au format `+X;+Y;`
+fn parse_corner(value: &str) -> Option<(f64, f64)> {
+    let mut parts = value.split(';').map(|p| p.trim().trim_start_matches('+'));
+    let x = parts.next()?.parse().ok()?;
+    let y = parts.next()?.parse().ok()?;
+    Some((x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gen_extent() {
+        let content = "RTYSA03:GSE\r\nRIDSA06:SeSD01\r\n\
+RTYSA03:DEG\r\nRIDSA06:DEG_id\r\nCM1CC23:+881000.00;+6663000.00;\r\nCM2CC23:+882500.50;+6664200.00;\r\n";
+        let general = parse(content).unwrap();
+        let extent = general.extent.unwrap();
+        assert_eq!(extent.min(), coord! { x: 881000.0, y: 6663000.0 });
+        assert_eq!(extent.max(), coord! { x: 882500.5, y: 6664200.0 });
+    }
+
+    #[test]
+    fn test_parse_gen_without_extent() {
+        assert!(parse("RTYSA03:GSE\r\nRIDSA06:SeSD01\r\n")
+            .unwrap()
+            .extent
+            .is_none());
+    }
+}
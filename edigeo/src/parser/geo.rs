@@ -22,6 +22,12 @@ const PROJECTIONS: &[(&str, u32)] = &[
     ("RGFG95UTM22", 2972),
     ("RGR92UTM", 2975),
     ("RGM04", 32738),
+    // NTF (méridien de Paris), Lambert zones I à IV et II étendu
+    ("LAMB1C", 27571),
+    ("LAMB2C", 27572),
+    ("LAMBE", 27572),
+    ("LAMB3C", 27573),
+    ("LAMB4C", 27574),
 ];
 
 /// Parse un fichier GEO pour extraire la projection
@@ -94,6 +100,12 @@ mod tests {
         assert_eq!(result.epsg, 3946);
     }
 
+    #[test]
+    fn test_parse_ntf_lambert() {
+        assert_eq!(parse(b"RELSACC:LAMBE\r\n").unwrap().epsg, 27572);
+        assert_eq!(parse(b"RELSACC:LAMB4C\r\n").unwrap().epsg, 27574);
+    }
+
     #[test]
     fn test_parse_unknown_projection_returns_error() {
         let data = b"RELSACC:UNKNOWN_PROJ\r\n";
@@ -1,6 +1,80 @@
 //! Parsers pour les différents fichiers EDIGEO
 
+pub mod dic;
+pub mod gen;
 pub mod geo;
 pub mod qal;
+pub mod scd;
 pub mod thf;
+pub mod token;
 pub mod vec;
+
+use std::borrow::Cow;
+
+use crate::EdigeoError;
+use token::tokenize;
+
+/// Enregistrement d'un fichier EDIGEO (champ `RTY` et champs suivants)
+pub(crate) struct Record<'a> {
+    /// Type d'enregistrement (ex: "OBJ", "DIA")
+    pub kind: Cow<'a, str>,
+
+    /// Champs dans l'ordre du fichier: code sur 3 caractères (ex: "RID") et
+    /// valeur sans espaces de bord
+    pub fields: Vec<(&'a str, Cow<'a, str>)>,
+}
+
+impl Record<'_> {
+    /// Première valeur non vide d'un champ
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, value)| *field == name && !value.is_empty())
+            .map(|(_, value)| value.as_ref())
+    }
+
+    /// Identifiant de l'enregistrement (RID)
+    pub fn id(&self) -> Option<&str> {
+        self.get("RID")
+    }
+}
+
+/// Type et identifiant pointés par une référence `SID;GID;RTY;RID`
+pub(crate) fn reference(value: &str) -> Option<(&str, &str)> {
+    let mut parts = value.split(';');
+    let rty = parts.nth(2)?.trim();
+    let rid = parts.next()?.trim();
+    (!rid.is_empty()).then_some((rty, rid))
+}
+
+/// Découpe un fichier décodé en enregistrements (voir `tokenize`)
+///
+/// Les champs qui précèdent le premier `RTY` (en-tête du fichier) sont ignorés.
+///
+/// # Errors
+///
+/// Retourne la première erreur du tokenizer (descripteur mal formé, longueur
+/// déclarée inexacte, suite orpheline).
+pub(crate) fn records(content: &str) -> Result<Vec<Record<'_>>, EdigeoError> {
+    let mut records: Vec<Record> = Vec::new();
+    for token in tokenize(content) {
+        let token = token?;
+        let value = trim(token.value);
+        if token.code == "RTY" {
+            records.push(Record {
+                kind: value,
+                fields: Vec::new(),
+            });
+        } else if let Some(record) = records.last_mut() {
+            record.fields.push((token.code, value));
+        }
+    }
+    Ok(records)
+}
+
+fn trim(value: Cow<'_, str>) -> Cow<'_, str> {
+    match value {
+        Cow::Borrowed(value) => Cow::Borrowed(value.trim()),
+        Cow::Owned(value) => Cow::Owned(value.trim().to_string()),
+    }
+}
@@ -2,10 +2,15 @@
 
 use std::collections::HashMap;
 
-use crate::types::Quality;
+use crate::parser::records;
+use crate::types::{Date, Quality, UpdateType};
 use crate::EdigeoError;
 
 /// Parse un fichier QAL pour extraire les informations de qualité
+///
+/// Chaque bloc QUP (actualité) donne la qualité de l'objet qui le référence.
+/// Les dates invalides sont ignorées; les champs non typés sont conservés
+/// bruts dans `Quality::other`.
 pub fn parse(data: &[u8]) -> Result<HashMap<String, Quality>, EdigeoError> {
     if data.is_empty() {
         return Ok(HashMap::new());
@@ -14,55 +19,30 @@ pub fn parse(data: &[u8]) -> Result<HashMap<String, Quality>, EdigeoError> {
     let content = String::from_utf8_lossy(data);
     let mut qualities = HashMap::new();
 
-    // Splitter par blocs RTYSA03:QUP
-    let blocks: Vec<&str> = content.split("RTYSA03:").collect();
-
-    for block in blocks.iter().skip(1) {
-        // Vérifier que c'est un bloc QUP (actualité)
-        if !block.starts_with("QUP") {
-            continue;
-        }
-
-        let lines: Vec<&str> = block.lines().collect();
-        if lines.len() < 2 {
+    for record in records(&content)? {
+        if record.kind != "QUP" {
             continue;
         }
-
-        // Extraire l'ID de l'objet (ligne 2: RIDSA:xxx)
-        let id = lines
-            .get(1)
-            .and_then(|line| line.split(':').nth(1))
-            .map(|s| s.trim().to_string());
-
-        let Some(object_id) = id else {
+        let Some(object_id) = record.id() else {
             continue;
         };
 
         let mut quality = Quality::default();
-
-        for line in &lines[2..] {
-            if line.is_empty() {
-                continue;
-            }
-
-            let parts: Vec<&str> = line.splitn(2, ':').collect();
-            if parts.len() != 2 {
-                continue;
-            }
-
-            let (key, value) = (parts[0], parts[1].trim());
-
-            // Parser les différents champs
-            if key.starts_with("ODA") {
-                quality.create_date = Some(value.to_string());
-            } else if key.starts_with("UDA") {
-                quality.update_date = Some(value.to_string());
-            } else if key.starts_with("UTY") {
-                quality.update_type = Some(value.to_string());
+        for (code, value) in &record.fields {
+            match *code {
+                "RID" => {}
+                "ODA" => quality.create_date = Date::parse(value),
+                "UDA" => quality.update_date = Date::parse(value),
+                "UTY" if !value.is_empty() => {
+                    quality.update_type = Some(UpdateType::from_code(value))
+                }
+                _ => {
+                    quality.other.insert(code.to_string(), value.to_string());
+                }
             }
         }
 
-        qualities.insert(object_id, quality);
+        qualities.insert(object_id.to_string(), quality);
     }
 
     Ok(qualities)
@@ -74,12 +54,24 @@ mod tests {
 
     #[test]
     fn test_parse_qal() {
-        let data = b"RTYSA03:QUP\r\nRIDSA:Objet_123\r\nODASA:20260115\r\nUDASA:20260120\r\n";
+        let data = b"RTYSA03:QUP\r\nRIDSA09:Objet_123\r\nODASA08:20260115\r\nUDASA08:20260120\r\n\
+UTYSA01:M\r\nEDASA08:20260101\r\n";
         let result = parse(data).unwrap();
 
         assert!(result.contains_key("Objet_123"));
         let quality = result.get("Objet_123").unwrap();
-        assert_eq!(quality.create_date.as_deref(), Some("20260115"));
-        assert_eq!(quality.update_date.as_deref(), Some("20260120"));
+        assert_eq!(quality.create_date, Date::new(2026, 1, 15));
+        assert_eq!(quality.update_date.unwrap().to_string(), "2026-01-20");
+        assert_eq!(quality.update_type, Some(UpdateType::Modification));
+        assert_eq!(quality.other["EDA"], "20260101");
+    }
+
+    #[test]
+    fn test_parse_qal_invalid_date() {
+        let data = b"RTYSA03:QUP\r\nRIDSA07:Objet_1\r\nODASA08:20260230\r\nUDASA00:\r\n";
+        let quality = &parse(data).unwrap()["Objet_1"];
+        assert_eq!(quality.create_date, None);
+        assert_eq!(quality.update_date, None);
+        assert_eq!(quality.update_type, None);
     }
 }
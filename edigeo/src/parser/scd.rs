@@ -0,0 +1,201 @@
+//! Parser pour les fichiers SCD (schéma conceptuel)
+
+use crate::parser::{records, reference, Record};
+use crate::types::{
+    AttributeDef, AttributeType, AttributeValue, Definition, Dictionary, GeometryKind, ObjectType,
+    RelationDef, Schema,
+};
+use crate::EdigeoError;
+
+/// Parse un fichier SCD décodé
+///
+/// Les libellés, définitions et types sont complétés par le dictionnaire
+/// (référence DIP de chaque enregistrement). Sans dictionnaire, le libellé
+/// est l'identifiant sans son suffixe `_id`.
+pub fn parse(content: &str, dictionary: &Dictionary) -> Result<Schema, EdigeoError> {
+    let mut schema = Schema::default();
+
+    for record in records(content)? {
+        let Some(id) = record.id() else {
+            continue;
+        };
+
+        match record.kind.as_ref() {
+            "OBJ" => {
+                let definition = definition(&record, id, |rid| dictionary.objects.get(rid));
+                let object = ObjectType {
+                    id: id.to_string(),
+                    label: definition.label,
+                    definition: definition.definition,
+                    kind: record.get("KND").and_then(GeometryKind::from_code),
+                    attributes: referenced(&record, "AAP", &["ATT"]),
+                };
+                schema.objects.insert(object.id.clone(), object);
+            }
+            "ATT" => {
+                let definition = definition(&record, id, |rid| dictionary.attributes.get(rid));
+                let attribute = AttributeDef {
+                    id: id.to_string(),
+                    label: definition.label,
+                    definition: definition.definition,
+                    value_type: record
+                        .get("TYP")
+                        .map(AttributeType::from_code)
+                        .or(definition.value_type),
+                    width: record.get("CAN").and_then(|v| v.parse().ok()),
+                    decimals: record.get("CAD").and_then(|v| v.parse().ok()),
+                    unit: record.get("UNI").map(str::to_string),
+                    values: values(&record),
+                };
+                schema.attributes.insert(attribute.id.clone(), attribute);
+            }
+            "REL" => {
+                let definition = definition(&record, id, |rid| dictionary.relations.get(rid));
+                let relation = RelationDef {
+                    id: id.to_string(),
+                    label: definition.label,
+                    definition: definition.definition,
+                    kind: record.get("KND").map(str::to_string),
+                    members: record
+                        .fields
+                        .iter()
+                        .filter(|(name, _)| !matches!(*name, "DIP" | "AAP" | "QAP"))
+                        .filter_map(|(_, value)| reference(value))
+                        .filter(|(rty, _)| matches!(*rty, "OBJ" | "PGE"))
+                        .map(|(_, rid)| rid.to_string())
+                        .collect(),
+                };
+                schema.relations.insert(relation.id.clone(), relation);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(schema)
+}
+
+/// Définition du dictionnaire pointée par le champ DIP
+fn definition<'d>(
+    record: &Record,
+    id: &str,
+    lookup: impl Fn(&str) -> Option<&'d Definition>,
+) -> Definition {
+    record
+        .get("DIP")
+        .and_then(reference)
+        .and_then(|(_, rid)| lookup(rid))
+        .cloned()
+        .unwrap_or_else(|| Definition {
+            label: id.trim_end_matches("_id").to_string(),
+            ..Definition::default()
+        })
+}
+
+/// Identifiants pointés par un champ répété
+fn referenced(record: &Record, field: &str, types: &[&str]) -> Vec<String> {
+    record
+        .fields
+        .iter()
+        .filter(|(name, _)| *name == field)
+        .filter_map(|(_, value)| reference(value))
+        .filter(|(rty, _)| types.contains(rty))
+        .map(|(_, rid)| rid.to_string())
+        .collect()
+}
+
+/// Valeurs énumérées: code (AVV) suivi de son libellé (AVD)
+fn values(record: &Record) -> Vec<AttributeValue> {
+    let mut values: Vec<AttributeValue> = Vec::new();
+    for (name, value) in &record.fields {
+        match *name {
+            "AVV" => values.push(AttributeValue {
+                code: value.to_string(),
+                label: None,
+            }),
+            "AVD" if !value.is_empty() => {
+                if let Some(last) = values.last_mut() {
+                    last.label = Some(value.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCD: &str =
+        "RTYSA03:OBJ\r\nRIDSA11:BATIMENT_id\r\nDIPCP27:EDAB01;SeDI;DID;BATIMENT_id\r\n\
+KNDSA03:ARE\r\nAAPCP22:EDAB01;SeSD;ATT;DUR_id\r\nAAPCP22:EDAB01;SeSD;ATT;TEX_id\r\n\
+RTYSA03:ATT\r\nRIDSA06:DUR_id\r\nTYPSA01:A\r\nUNISA00:\r\nAVCSP01:2\r\n\
+AVVSA02:01\r\nAVDTA08:Bâti dur\r\nAVVSA02:02\r\nAVDTA10:Bâti léger\r\n\
+DIPCP22:EDAB01;SeDI;DIA;DUR_id\r\nCANSN01:2\r\n\
+RTYSA03:ATT\r\nRIDSA06:TEX_id\r\nCANSN02:80\r\n\
+RTYSA03:REL\r\nRIDSA16:ID_S_RCO_FAC_ARC\r\nKNDSA02:IC\r\n\
+ANSCP27:EDAB01;SeSD;OBJ;BATIMENT_id\r\nANSCP19:EDAB01;SeSD;PGE;FAC\r\n";
+
+    fn dictionary() -> Dictionary {
+        let mut dictionary = Dictionary::default();
+        dictionary.objects.insert(
+            "BATIMENT_id".into(),
+            Definition {
+                label: "BATIMENT".into(),
+                definition: Some("Construction".into()),
+                ..Definition::default()
+            },
+        );
+        dictionary.attributes.insert(
+            "DUR_id".into(),
+            Definition {
+                label: "DUR".into(),
+                value_type: Some(AttributeType::Integer),
+                ..Definition::default()
+            },
+        );
+        dictionary
+    }
+
+    #[test]
+    fn test_parse_scd_objects() {
+        let schema = parse(SCD, &dictionary()).unwrap();
+
+        let batiment = schema.object("BATIMENT_id").unwrap();
+        assert_eq!(batiment.label, "BATIMENT");
+        assert_eq!(batiment.definition.as_deref(), Some("Construction"));
+        assert_eq!(batiment.kind, Some(GeometryKind::Area));
+        assert_eq!(batiment.attributes, vec!["DUR_id", "TEX_id"]);
+        assert_eq!(schema.object_attributes("BATIMENT_id").len(), 2);
+    }
+
+    #[test]
+    fn test_parse_scd_attributes() {
+        let schema = parse(SCD, &dictionary()).unwrap();
+
+        // Le type du SCD prime sur celui du dictionnaire
+        let dur = schema.attribute("DUR").unwrap();
+        assert_eq!(dur.label, "DUR");
+        assert_eq!(dur.value_type, Some(AttributeType::Alphanumeric));
+        assert_eq!(dur.width, Some(2));
+        assert_eq!(dur.unit, None);
+        assert_eq!(dur.value_label("02"), Some("Bâti léger"));
+        assert_eq!(dur.values.len(), 2);
+
+        // Sans dictionnaire: libellé déduit de l'identifiant
+        let tex = schema.attribute("TEX_id").unwrap();
+        assert_eq!(tex.label, "TEX");
+        assert_eq!(tex.value_type, None);
+        assert_eq!(tex.width, Some(80));
+    }
+
+    #[test]
+    fn test_parse_scd_relations() {
+        let schema = parse(SCD, &Dictionary::default()).unwrap();
+
+        let relation = &schema.relations["ID_S_RCO_FAC_ARC"];
+        assert_eq!(relation.kind.as_deref(), Some("IC"));
+        assert_eq!(relation.members, vec!["BATIMENT_id", "FAC"]);
+    }
+}
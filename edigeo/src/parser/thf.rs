@@ -1,17 +1,93 @@
-//! Parser pour les fichiers THF (métadonnées)
+//! Parser pour les fichiers THF (métadonnées d'échange)
 
 use encoding_rs::Encoding;
 use memchr::memmem;
 
-use crate::types::ThfData;
+use crate::parser::records;
+use crate::types::{Date, Exchange, ExchangeLot, ThfData};
 use crate::EdigeoError;
 
-/// Parse un fichier THF pour extraire l'encodage et l'année
+/// Champs d'un lot (GTL) qui nomment ses fichiers: GEN, GEO, QAL, DIC, SCD, VEC
+const FILE_FIELDS: [&str; 6] = ["GNN", "GON", "QAN", "DIN", "SCN", "GDN"];
+
+/// Parse un fichier THF: encodage, support (GTS) et lots (GTL)
+///
+/// La date de production (TDA) est obligatoire: elle donne le millésime.
+/// La commune et la feuille sont lues dans le premier identifiant de
+/// feuille (ex: `380010000S01`) trouvé dans les noms et commentaires.
 pub fn parse(data: &[u8]) -> Result<ThfData, EdigeoError> {
     let encoding = parse_encoding(data)?;
-    let year = parse_year(data)?;
+    let (content, _) = encoding.decode_without_bom_handling(data);
+
+    let mut support = None;
+    let mut lots = Vec::new();
+    for record in records(&content)? {
+        match record.kind.as_ref() {
+            "GTS" => support = Some(record),
+            "GTL" => lots.push(ExchangeLot {
+                id: record.id().unwrap_or_default().to_string(),
+                name: record.get("LON").map(str::to_string),
+                description: record.get("INF").map(str::to_string),
+                files: record
+                    .fields
+                    .iter()
+                    .filter(|(code, value)| FILE_FIELDS.contains(code) && !value.is_empty())
+                    .map(|(_, value)| value.to_string())
+                    .collect(),
+            }),
+            _ => {}
+        }
+    }
 
-    Ok(ThfData { encoding, year })
+    let support = support
+        .ok_or_else(|| EdigeoError::parse_error("THF", "missing support descriptor (GTS)"))?;
+    let production_date = support
+        .get("TDA")
+        .ok_or_else(|| EdigeoError::parse_error("THF", "missing production date (TDA)"))?;
+    let production_date = Date::parse(production_date).ok_or_else(|| {
+        EdigeoError::parse_error(
+            "THF",
+            format!("invalid production date {:?}", production_date),
+        )
+    })?;
+
+    let text = |code| support.get(code).map(str::to_string);
+    let mut exchange = Exchange {
+        producer: text("AUT"),
+        address: text("ADR"),
+        version: text("VER"),
+        version_date: support.get("VDA").and_then(Date::parse),
+        description: text("INF"),
+        lots,
+        ..Exchange::new(production_date)
+    };
+
+    let labels = exchange
+        .lots
+        .iter()
+        .flat_map(|lot| [lot.name.as_deref(), lot.description.as_deref()])
+        .chain([exchange.description.as_deref()])
+        .flatten();
+    exchange.sheet = labels
+        .flat_map(|label| label.split(|c: char| !c.is_ascii_alphanumeric()))
+        .find(|word| is_sheet_id(word))
+        .map(str::to_string);
+    exchange.commune = exchange.sheet.as_ref().map(|sheet| sheet[..5].to_string());
+
+    Ok(ThfData { encoding, exchange })
+}
+
+/// Identifiant de feuille: département et commune (INSEE), préfixe,
+/// section et numéro de feuille (ex: `380010000S01`, `2A0040000A01`)
+fn is_sheet_id(word: &str) -> bool {
+    let b = word.as_bytes();
+    b.len() == 12
+        && (b[..2].iter().all(u8::is_ascii_digit) || matches!(&b[..2], b"2A" | b"2B"))
+        && b[2..8].iter().all(u8::is_ascii_digit)
+        && b[8..10]
+            .iter()
+            .all(|c| c.is_ascii_digit() || c.is_ascii_uppercase())
+        && b[10..].iter().all(u8::is_ascii_digit)
 }
 
 /// Extrait l'encodage depuis le champ CSET
@@ -60,32 +136,6 @@ fn cset_to_encoding(cset: &str) -> &'static Encoding {
     }
 }
 
-/// Extrait l'année depuis le champ TDASD
-fn parse_year(data: &[u8]) -> Result<u16, EdigeoError> {
-    // Recherche SIMD du pattern "TDASD"
-    let finder = memmem::Finder::new(b"TDASD");
-
-    if let Some(pos) = finder.find(data) {
-        let start = pos + 5; // Après "TDASD"
-        if let Some(colon_offset) = data[start..].iter().position(|&b| b == b':') {
-            let value_start = start + colon_offset + 1;
-
-            // Les 4 premiers caractères sont l'année
-            if value_start + 4 <= data.len() {
-                let year_str =
-                    std::str::from_utf8(&data[value_start..value_start + 4]).unwrap_or("2020");
-
-                if let Ok(year) = year_str.parse::<u16>() {
-                    return Ok(year);
-                }
-            }
-        }
-    }
-
-    // Par défaut: année courante
-    Ok(2020)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -120,11 +170,51 @@ mod tests {
         assert_eq!(result.name(), "ISO-8859-15");
     }
 
+    // Latin-9 (CSET IRV)
+    const THF: &[u8] = b"BOMT 12:E0000A01.THF\r\nCSET 03:IRV\r\n\
+RTYSA03:GTS\r\nRIDSA07:SUPPORT\r\nAUTSA05:DGFiP\r\nVERSA01:1\r\nVDASD08:19921201\r\n\
+TDASD08:20260115\r\nINFSA17:Fichier d'\xe9change\r\n\
+RTYSA03:GTL\r\nRIDSA04:LOT1\r\nLONSA12:380010000S01\r\nGNNSA08:EDAB01SE\r\n\
+GONSA08:EDAB01SE\r\nQANSA08:EDAB01SE\r\nGDCSN01:2\r\nGDNSA08:EDAB01T1\r\nGDNSA08:EDAB01T2\r\n";
+
+    #[test]
+    fn test_parse_exchange() {
+        let thf = parse(THF).unwrap();
+        let exchange = thf.exchange;
+
+        assert_eq!(exchange.production_date, Date::new(2026, 1, 15).unwrap());
+        assert_eq!(exchange.producer.as_deref(), Some("DGFiP"));
+        assert_eq!(exchange.version_date, Date::new(1992, 12, 1));
+        assert_eq!(exchange.description.as_deref(), Some("Fichier d'échange"));
+        assert_eq!(exchange.lots.len(), 1);
+        assert_eq!(
+            exchange.lots[0].files,
+            vec!["EDAB01SE", "EDAB01SE", "EDAB01SE", "EDAB01T1", "EDAB01T2"]
+        );
+        assert_eq!(exchange.sheet.as_deref(), Some("380010000S01"));
+        assert_eq!(exchange.commune.as_deref(), Some("38001"));
+        assert_eq!(exchange.departement(), Some("38"));
+    }
+
+    #[test]
+    fn test_parse_missing_production_date() {
+        let thf = String::from_utf8_lossy(THF);
+        let without_date = thf.replace("TDASD08:20260115\r\n", "");
+        assert!(parse(without_date.as_bytes()).is_err());
+
+        let invalid_date = thf.replace("20260115", "20261315");
+        assert!(parse(invalid_date.as_bytes()).is_err());
+
+        assert!(parse(b"CSET 03:IRV\r\n").is_err());
+    }
+
     #[test]
-    fn test_parse_year() {
-        let data = b"TDASDP:20260115\r\n";
-        let result = parse_year(data).unwrap();
-        assert_eq!(result, 2026);
+    fn test_sheet_id() {
+        assert!(is_sheet_id("380010000S01"));
+        assert!(is_sheet_id("2A0040000A01"));
+        assert!(is_sheet_id("97101000AB01"));
+        assert!(!is_sheet_id("EDAB01SE"));
+        assert!(!is_sheet_id("38001000ab01"));
     }
 
     #[test]
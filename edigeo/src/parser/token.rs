@@ -0,0 +1,329 @@
+//! Tokenizer des enregistrements EDIGEO
+//!
+//! Chaque ligne d'un fichier EDIGEO est un enregistrement précédé de son
+//! descripteur: `RIDSA07:Noeud_1` se lit code `RID`, nature `S`, format `A`,
+//! longueur déclarée `07`, valeur `Noeud_1`.
+//!
+//! Le tokenizer lit un fichier décodé (`tokenize`) ou ses octets bruts
+//! (`tokenize_bytes`, valeurs non décodées).
+
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+
+use crate::EdigeoError;
+
+/// Nature des enregistrements de suite: la valeur prolonge celle de
+/// l'enregistrement précédent de même code (valeurs de plus de 99 caractères)
+pub const CONTINUATION: char = 'N';
+
+/// Contenu d'un fichier EDIGEO: texte décodé (`str`) ou octets bruts (`[u8]`)
+pub trait Text: ToOwned<Owned: fmt::Debug> + fmt::Debug + PartialEq + Eq {
+    /// Octets du contenu
+    fn bytes(&self) -> &[u8];
+
+    /// Partie du contenu (bornes sur des octets ASCII ou en fin de ligne)
+    fn slice(&self, range: Range<usize>) -> &Self;
+
+    /// Longueur en caractères
+    fn chars(&self) -> usize;
+
+    /// Ajoute une valeur de suite
+    fn append(owned: &mut Self::Owned, value: &Self);
+}
+
+impl Text for str {
+    fn bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+
+    fn slice(&self, range: Range<usize>) -> &Self {
+        &self[range]
+    }
+
+    fn chars(&self) -> usize {
+        if self.is_ascii() {
+            self.len()
+        } else {
+            self.chars().count()
+        }
+    }
+
+    fn append(owned: &mut String, value: &Self) {
+        owned.push_str(value);
+    }
+}
+
+/// Octets non décodés: les encodages EDIGEO (jeux ISO 8859) codent un
+/// caractère par octet
+impl Text for [u8] {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn slice(&self, range: Range<usize>) -> &Self {
+        &self[range]
+    }
+
+    fn chars(&self) -> usize {
+        self.len()
+    }
+
+    fn append(owned: &mut Vec<u8>, value: &Self) {
+        owned.extend_from_slice(value);
+    }
+}
+
+/// Enregistrement d'un fichier EDIGEO, valeur brute comprise
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'a, T: Text + ?Sized = str> {
+    /// Code du champ sur 3 caractères (ex: "RID", "COR")
+    pub code: &'a str,
+
+    /// Nature de la valeur (ex: 'S' simple, 'C' composée)
+    pub nature: char,
+
+    /// Format de la valeur (ex: 'A' alphanumérique, 'P' pointeur, 'C' coordonnées)
+    pub format: char,
+
+    /// Longueur déclarée en caractères (somme des suites éventuelles)
+    pub length: usize,
+
+    /// Valeur telle qu'elle figure dans le fichier, suites concaténées
+    pub value: Cow<'a, T>,
+
+    /// Numéro de la ligne de l'enregistrement (à partir de 1)
+    pub line: usize,
+}
+
+/// Itérateur sur les enregistrements d'un fichier (voir `tokenize`)
+pub struct Tokenizer<'a, T: Text + ?Sized = str> {
+    content: &'a T,
+    /// Début de la prochaine ligne
+    pos: usize,
+    /// Numéro de la prochaine ligne (à partir de 1)
+    line: usize,
+    /// Ligne du dernier enregistrement lu
+    last: Range<usize>,
+}
+
+/// Découpe un fichier EDIGEO décodé en enregistrements
+///
+/// Les lignes vides sont ignorées. Un descripteur mal formé, une longueur
+/// déclarée différente de celle de la valeur ou une suite orpheline donnent
+/// une erreur pour l'enregistrement concerné; l'itération continue ensuite.
+pub fn tokenize(content: &str) -> Tokenizer<'_> {
+    Tokenizer {
+        content,
+        pos: 0,
+        line: 1,
+        last: 0..0,
+    }
+}
+
+/// Équivalent de `tokenize` sur les octets d'un fichier, sans décodage
+///
+/// La longueur déclarée est comparée au nombre d'octets de la valeur.
+pub fn tokenize_bytes(data: &[u8]) -> Tokenizer<'_, [u8]> {
+    Tokenizer {
+        content: data,
+        pos: 0,
+        line: 1,
+        last: 0..0,
+    }
+}
+
+impl<'a, T: Text + ?Sized> Tokenizer<'a, T> {
+    /// Ligne du dernier enregistrement lu (sans ses suites), y compris d'un
+    /// enregistrement en erreur: son code situe l'erreur dans le fichier
+    pub fn last_line(&self) -> &'a T {
+        self.content.slice(self.last.clone())
+    }
+
+    /// Prochaine ligne non vide (numéro, bornes sans fin de ligne)
+    fn next_line(&mut self) -> Option<(usize, Range<usize>)> {
+        let bytes = self.content.bytes();
+        while self.pos < bytes.len() {
+            let start = self.pos;
+            let mut end = memchr::memchr(b'\n', &bytes[start..]).map_or(bytes.len(), |i| start + i);
+            self.pos = end + 1;
+            let line = self.line;
+            self.line += 1;
+            if end > start && bytes[end - 1] == b'\r' {
+                end -= 1;
+            }
+            if end > start {
+                return Some((line, start..end));
+            }
+        }
+        None
+    }
+}
+
+impl<'a, T: Text + ?Sized> Iterator for Tokenizer<'a, T> {
+    type Item = Result<Token<'a, T>, EdigeoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (line_number, range) = self.next_line()?;
+        self.last = range.clone();
+
+        let mut token = match parse_line(self.content.slice(range), line_number) {
+            Ok(token) => token,
+            Err(e) => return Some(Err(e)),
+        };
+        if token.nature == CONTINUATION {
+            return Some(Err(token_error(
+                line_number,
+                format!("continuation of {} without a preceding record", token.code),
+            )));
+        }
+
+        // Enregistrements de suite: même code, nature `N` (ligne suivante)
+        loop {
+            let next = self.content.bytes().get(self.pos..self.pos + 4);
+            let continues = next.is_some_and(|header| {
+                header[3] == CONTINUATION as u8 && &header[..3] == token.code.as_bytes()
+            });
+            if !continues {
+                break;
+            }
+            let (pos, line) = (self.pos, self.line);
+            let Some((line_number, range)) = self.next_line() else {
+                break;
+            };
+            match parse_line(self.content.slice(range), line_number) {
+                Ok(next) => {
+                    T::append(token.value.to_mut(), &next.value);
+                    token.length += next.length;
+                }
+                Err(_) => {
+                    // Ligne relue au prochain appel
+                    (self.pos, self.line) = (pos, line);
+                    break;
+                }
+            }
+        }
+
+        Some(Ok(token))
+    }
+}
+
+/// Parse une ligne `CCCNFLL:valeur` et vérifie sa longueur déclarée
+fn parse_line<T: Text + ?Sized>(line: &T, line_number: usize) -> Result<Token<'_, T>, EdigeoError> {
+    let bytes = line.bytes();
+    let header = bytes
+        .get(..8)
+        .filter(|header| header.is_ascii() && header[7] == b':')
+        .ok_or_else(|| {
+            let line = String::from_utf8_lossy(bytes);
+            token_error(line_number, format!("malformed descriptor: {:?}", line))
+        })?;
+    // En-tête ASCII
+    let descriptor = std::str::from_utf8(header).unwrap_or_default();
+
+    let length = Some(&descriptor[5..7])
+        .filter(|digits| digits.bytes().all(|b| b.is_ascii_digit()))
+        .and_then(|digits| digits.parse::<usize>().ok())
+        .ok_or_else(|| token_error(line_number, format!("invalid length in {:?}", descriptor)))?;
+
+    let value = line.slice(8..bytes.len());
+    let actual = value.chars();
+    if actual != length {
+        return Err(token_error(
+            line_number,
+            format!(
+                "{} declares {} characters, value has {}",
+                &descriptor[..7],
+                length,
+                actual
+            ),
+        ));
+    }
+
+    Ok(Token {
+        code: &descriptor[..3],
+        nature: header[3] as char,
+        format: header[4] as char,
+        length,
+        value: Cow::Borrowed(value),
+        line: line_number,
+    })
+}
+
+fn token_error(line_number: usize, reason: String) -> EdigeoError {
+    EdigeoError::parse_error(format!("line {}", line_number), reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_descriptors() {
+        let content = "RTYSA03:PNO\r\nRIDSA07:Noeud_1\r\n\r\nCORCC23:+881824.53;+6663821.17;\r\n\
+TEXT 06:8859-1\r\nCM1CC00:\r\n";
+        let tokens: Vec<Token> = tokenize(content).map(Result::unwrap).collect();
+
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(
+            tokens[1],
+            Token {
+                code: "RID",
+                nature: 'S',
+                format: 'A',
+                length: 7,
+                value: Cow::Borrowed("Noeud_1"),
+                line: 2,
+            }
+        );
+        assert_eq!(tokens[2].format, 'C');
+        assert_eq!(tokens[2].line, 4);
+        assert_eq!((tokens[3].code, tokens[3].format), ("TEX", ' '));
+        assert_eq!(tokens[4].value, "");
+    }
+
+    #[test]
+    fn test_tokenize_keeps_raw_values() {
+        // Latin-1 décodé: la longueur compte les caractères
+        let tokens: Vec<Token> = tokenize("ATVST09:Bâti dur \r\n")
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(tokens[0].value, "Bâti dur ");
+    }
+
+    #[test]
+    fn test_tokenize_continuation() {
+        let content = "DEFST05:Parce\r\nDEFNT05:lle c\r\nDEFNT07:adastre\r\nRIDSA02:P1\r\n";
+        let tokens: Vec<Token> = tokenize(content).map(Result::unwrap).collect();
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].value, "Parcelle cadastre");
+        assert_eq!(tokens[0].length, 17);
+        assert_eq!(tokens[1].line, 4);
+    }
+
+    #[test]
+    fn test_tokenize_bytes() {
+        // Latin-9 non décodé: la longueur compte les octets
+        let content = b"ATVST09:B\xe2ti dur \r\nDEFST05:Parce\r\nDEFNT04:lle \r\nRIDSA02:P1";
+        let tokens: Vec<Token<[u8]>> = tokenize_bytes(content).map(Result::unwrap).collect();
+
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[0].value, Cow::Borrowed(b"B\xe2ti dur ".as_slice()));
+        assert_eq!(tokens[1].value, Cow::<[u8]>::Owned(b"Parcelle ".to_vec()));
+        assert_eq!((tokens[1].length, tokens[2].line), (9, 4));
+        assert_eq!(tokens[2].code, "RID");
+    }
+
+    #[test]
+    fn test_tokenize_errors() {
+        let content = "RIDSA08:Noeud_1\r\nbroken\r\nDEFNT02:ab\r\nRIDSA02:P1\r\n";
+        let results: Vec<_> = tokenize(content).collect();
+
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_err());
+        assert!(results[1].is_err());
+        assert!(results[2].is_err());
+        assert_eq!(results[3].as_ref().unwrap().value, "P1");
+    }
+}
@@ -1,124 +1,217 @@
 //! Parser pour les fichiers VEC (données vectorielles)
-
+//!
+//! Le fichier est découpé en enregistrements sur ses octets, sans être décodé
+//! (`tokenize_bytes`): identifiants, références et coordonnées sont en ASCII
+//! (un identifiant illisible met le VEC en erreur).
+//! Les identifiants sont internés une fois par fichier (`Id`), les faces
+//! désignent leurs arcs par identifiant, et seules les valeurs d'attributs
+//! passent par l'encodage du lot (sans copie si elles sont en ASCII).
+
+use std::borrow::Cow;
 use std::collections::HashMap;
 
+use encoding_rs::Encoding;
 use memchr::memmem;
 
+use crate::parser::token::tokenize_bytes;
 use crate::EdigeoError;
 
+/// Identifiant interné d'un fichier VEC (voir `Interner`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Id(u32);
+
+/// Identifiants d'un fichier VEC (enregistrements et cibles des références)
+#[derive(Debug, Default)]
+pub struct Interner<'a> {
+    ids: HashMap<&'a str, Id>,
+    names: Vec<&'a str>,
+}
+
+impl<'a> Interner<'a> {
+    /// Identifiant d'un nom, créé au besoin
+    pub fn intern(&mut self, name: &'a str) -> Id {
+        *self.ids.entry(name).or_insert_with(|| {
+            self.names.push(name);
+            Id((self.names.len() - 1) as u32)
+        })
+    }
+
+    /// Identifiant d'un nom déjà rencontré
+    pub fn get(&self, name: &str) -> Option<Id> {
+        self.ids.get(name).copied()
+    }
+
+    /// Nom d'un identifiant
+    pub fn resolve(&self, id: Id) -> &'a str {
+        self.names[id.0 as usize]
+    }
+}
+
 /// Référence vers une autre entité
-#[derive(Debug, Clone, Default)]
-pub struct Reference {
-    pub sid: String,
-    pub gid: String,
-    pub rty: String,
-    pub rid: String,
+#[derive(Debug, Clone, Copy)]
+pub struct Reference<'a> {
+    pub sid: &'a str,
+    pub gid: &'a str,
+    pub rty: &'a str,
+    pub rid: Id,
 }
 
 /// Point (noeud)
 #[derive(Debug, Clone)]
-pub struct Point {
-    pub id: String,
+pub struct Point<'a> {
+    pub id: Id,
     pub coords: Vec<(f64, f64)>,
-    pub scp: Option<Reference>,
+    pub scp: Option<Reference<'a>>,
 }
 
 /// Arc (segment de ligne)
 #[derive(Debug, Clone)]
-pub struct Arc {
-    pub id: String,
+pub struct Arc<'a> {
+    pub id: Id,
     pub coords: Vec<(f64, f64)>,
-    pub scp: Option<Reference>,
+    pub scp: Option<Reference<'a>>,
+}
+
+/// Côté d'un arc, dans le sens de ses sommets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
 }
 
 /// Face (surface)
 #[derive(Debug, Clone)]
-pub struct Face {
-    pub id: String,
-    pub scp: Option<Reference>,
-    pub arcs: Vec<Arc>,
+pub struct Face<'a> {
+    pub id: Id,
+    pub scp: Option<Reference<'a>>,
+    /// Arcs de la face (clés de `ParsedVec::par`), dans l'ordre du fichier, et
+    /// côté de l'arc où se trouve la face (`ID_S_RCO_FAC_GAUCHE` ou `_DROITE`,
+    /// `None` si la relation ne le précise pas)
+    pub arcs: Vec<(Id, Option<Side>)>,
 }
 
 /// Feature (objet métier)
 #[derive(Debug, Clone)]
-pub struct Feature {
-    pub id: String,
-    pub scp: Option<Reference>,
-    pub attributes: HashMap<String, String>,
-    pub qap: Option<String>,
+pub struct Feature<'a> {
+    pub id: Id,
+    pub scp: Option<Reference<'a>>,
+    /// Attributs dans l'ordre du fichier (nom, valeur décodée)
+    pub attributes: Vec<(&'a str, Cow<'a, str>)>,
+    pub qap: Option<&'a str>,
+}
+
+impl Feature<'_> {
+    /// Valeur d'un attribut (la dernière si l'attribut est répété)
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .rev()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.as_ref())
+    }
 }
 
 /// Link (relation)
 #[derive(Debug, Clone)]
-pub struct Link {
-    pub id: String,
-    pub scp: Option<Reference>,
-    pub ftp: Vec<Reference>,
+pub struct Link<'a> {
+    pub id: Id,
+    pub scp: Option<Reference<'a>>,
+    pub ftp: Vec<Reference<'a>>,
 }
 
-/// Résultat du parsing d'un fichier VEC
+/// Résultat du parsing d'un fichier VEC, adossé à son contenu
 #[derive(Debug, Default)]
-pub struct ParsedVec {
-    pub pno: HashMap<String, Point>,
-    pub par: HashMap<String, Arc>,
-    pub pfe: HashMap<String, Face>,
-    pub fea: HashMap<String, Feature>,
-    pub lnk: HashMap<String, Link>,
+pub struct ParsedVec<'a> {
+    pub ids: Interner<'a>,
+    pub pno: HashMap<Id, Point<'a>>,
+    pub par: HashMap<Id, Arc<'a>>,
+    pub pfe: HashMap<Id, Face<'a>>,
+    pub fea: HashMap<Id, Feature<'a>>,
+    /// Liens dans l'ordre du fichier
+    pub lnk: Vec<Link<'a>>,
+    /// Coordonnées d'origine (transformées) des arcs et nœuds déplacés par
+    /// l'arrondi topologique
+    pub unsnapped: HashMap<Id, Vec<(f64, f64)>>,
+    /// Enregistrements écartés (descripteur invalide, voir `parse`), et faces
+    /// qui utilisent un arc écarté
+    pub rejected: Vec<Id>,
+    /// Erreurs non fatales des enregistrements écartés
+    pub errors: Vec<EdigeoError>,
 }
 
-/// Parse un fichier VEC décodé
-pub fn parse(content: &str) -> Result<ParsedVec, EdigeoError> {
-    let mut result = ParsedVec::default();
-
-    // Itérer sur les blocs RTYSA03: sans collecter (lazy)
-    for block in content.split("RTYSA03:").skip(1) {
-        // Parser le bloc directement avec un itérateur de lignes
-        let mut lines_iter = block.lines();
-
-        let Some(first_line) = lines_iter.next() else {
-            continue;
-        };
-        let block_type = first_line.trim();
+impl<'a> ParsedVec<'a> {
+    /// Nom d'un identifiant
+    pub fn name(&self, id: Id) -> &'a str {
+        self.ids.resolve(id)
+    }
 
-        // L'ID est sur la ligne 2, format RIDSA..:id
-        let id = extract_id_from_iter(lines_iter.clone());
-        if id.is_empty() {
-            continue;
-        }
+    /// Liens sémantiques entre deux features: (relation, FEA, FEA)
+    ///
+    /// Les FEA sont désignées par leur identifiant dans ce VEC (clés de `fea`).
+    pub fn feature_links(&self) -> impl Iterator<Item = (&'a str, Id, Id)> + '_ {
+        self.lnk.iter().filter_map(|lnk| {
+            let relation = lnk.scp.as_ref().filter(|scp| scp.rty == "REL")?;
+            let [from, to] = lnk.ftp.as_slice() else {
+                return None;
+            };
+            (from.rty == "FEA" && to.rty == "FEA")
+                .then(|| (self.name(relation.rid), from.rid, to.rid))
+        })
+    }
+}
 
-        // Collecter les lignes seulement pour le parsing du bloc
-        // (nécessaire car les parsers ont besoin d'un accès aléatoire)
-        let lines: Vec<&str> = std::iter::once(first_line).chain(lines_iter).collect();
+/// Champs d'un enregistrement: code et valeur sans espaces de bord
+type Fields<'a> = [(&'a str, Cow<'a, [u8]>)];
+
+/// Parse un fichier VEC (valeurs d'attributs dans `encoding`)
+///
+/// Un enregistrement dont une ligne est invalide pour le tokenizer
+/// (descripteur mal formé, longueur déclarée inexacte, suite orpheline) est
+/// écarté et la lecture reprend à la ligne suivante: l'erreur est consignée
+/// dans `ParsedVec::errors` et l'identifiant de l'enregistrement dans
+/// `ParsedVec::rejected`.
+///
+/// # Errors
+///
+/// Retourne l'erreur d'un identifiant illisible (voir `text`).
+pub fn parse<'a>(
+    data: &'a [u8],
+    encoding: &'static Encoding,
+) -> Result<ParsedVec<'a>, EdigeoError> {
+    let mut result = ParsedVec::default();
 
-        match block_type {
-            "PNO" => {
-                if let Ok(pno) = parse_pno(&lines, &id) {
-                    result.pno.insert(id, pno);
-                }
-            }
-            "PAR" => {
-                if let Ok(par) = parse_par(&lines, &id) {
-                    result.par.insert(id, par);
+    let mut record = Record::default();
+    let mut tokens = tokenize_bytes(data);
+    while let Some(token) = tokens.next() {
+        let token = match token {
+            Ok(token) => token,
+            Err(e) => {
+                // La ligne en erreur appartient à l'enregistrement en cours,
+                // ou en commence un nouveau
+                let line = tokens.last_line();
+                if line.starts_with(b"RTY") {
+                    record.finish(encoding, &mut result)?;
+                } else if line.starts_with(b"RID") {
+                    record.id = line
+                        .get(8..)
+                        .and_then(|id| std::str::from_utf8(id.trim_ascii()).ok());
                 }
+                record.error.get_or_insert(e);
+                continue;
             }
-            "PFE" => {
-                if let Ok(pfe) = parse_pfe(&lines, &id) {
-                    result.pfe.insert(id, pfe);
-                }
-            }
-            "FEA" => {
-                if let Ok(fea) = parse_fea(&lines, &id) {
-                    result.fea.insert(id, fea);
-                }
-            }
-            "LNK" => {
-                if let Ok(lnk) = parse_lnk(&lines, &id) {
-                    result.lnk.insert(id, lnk);
-                }
-            }
-            _ => {}
+        };
+        let value = match token.value {
+            Cow::Borrowed(value) => Cow::Borrowed(value.trim_ascii()),
+            Cow::Owned(value) => Cow::Owned(value.trim_ascii().to_vec()),
+        };
+        if token.code == "RTY" {
+            record.finish(encoding, &mut result)?;
+            record.kind = Some(value);
+        } else {
+            record.fields.push((token.code, value));
         }
     }
+    record.finish(encoding, &mut result)?;
 
     // Associer les arcs aux faces via les LNK
     associate_arcs_to_faces(&mut result);
@@ -126,220 +219,245 @@ pub fn parse(content: &str) -> Result<ParsedVec, EdigeoError> {
     Ok(result)
 }
 
-/// Extrait l'ID d'un bloc (format RIDSA..:id)
-fn extract_id(lines: &[&str]) -> String {
-    for line in lines.iter().skip(1) {
-        if line.starts_with("RIDSA") || line.starts_with("RID") {
-            if let Some(pos) = line.find(':') {
-                return line[pos + 1..].trim().to_string();
-            }
-        }
-    }
-    String::new()
+/// Enregistrement en cours de lecture
+#[derive(Default)]
+struct Record<'a> {
+    /// Type (`RTY`), `None` avant le premier enregistrement
+    kind: Option<Cow<'a, [u8]>>,
+    /// Champs suivant le `RTY`
+    fields: Vec<(&'a str, Cow<'a, [u8]>)>,
+    /// Identifiant d'une ligne `RID` en erreur
+    id: Option<&'a str>,
+    /// Première erreur du tokenizer dans l'enregistrement
+    error: Option<EdigeoError>,
 }
 
-/// Extrait l'ID depuis un itérateur de lignes (version optimisée)
-fn extract_id_from_iter<'a>(lines: impl Iterator<Item = &'a str>) -> String {
-    for line in lines {
-        if line.starts_with("RIDSA") || line.starts_with("RID") {
-            if let Some(pos) = line.find(':') {
-                return line[pos + 1..].trim().to_string();
+impl<'a> Record<'a> {
+    /// Ajoute l'enregistrement au VEC, ou l'écarte s'il est en erreur
+    fn finish(
+        &mut self,
+        encoding: &'static Encoding,
+        result: &mut ParsedVec<'a>,
+    ) -> Result<(), EdigeoError> {
+        let kind = self.kind.take();
+        let recovered = self.id.take();
+        let parsed = match (self.error.take(), kind) {
+            (Some(error), _) => {
+                let id = recovered
+                    .or_else(|| {
+                        let (code, id) = self.fields.iter().find(|(code, _)| *code == "RID")?;
+                        text(code, id).ok()
+                    })
+                    .filter(|id| !id.is_empty());
+                result.errors.push(match error {
+                    EdigeoError::ParseError { file, reason } => EdigeoError::parse_error(
+                        format!("VEC {}", file),
+                        format!("{}; record {} skipped", reason, id.unwrap_or("?")),
+                    ),
+                    error => error,
+                });
+                if let Some(id) = id {
+                    let id = result.ids.intern(id);
+                    result.rejected.push(id);
+                }
+                Ok(())
             }
-        }
-    }
-    String::new()
-}
-
-/// Parse une référence (SCP, FTP, QAP, etc.)
-/// Format: SID;GID;RTY;RID
-/// Optimisé pour éviter les allocations du Vec
-#[inline]
-fn parse_reference(value: &str) -> Reference {
-    let mut parts = value.splitn(5, ';'); // Max 4 parties + reste
-    Reference {
-        sid: parts.next().unwrap_or("").to_string(),
-        gid: parts.next().unwrap_or("").to_string(),
-        rty: parts.next().unwrap_or("").to_string(),
-        rid: parts.next().unwrap_or("").to_string(),
+            (None, Some(kind)) => parse_record(&kind, &self.fields, encoding, result),
+            (None, None) => Ok(()),
+        };
+        self.fields.clear();
+        parsed
     }
 }
 
-/// Parse des coordonnées au format EDIGEO: +X;+Y; ou +X;+Y
-/// Optimisé pour éviter les allocations
-#[inline]
-fn parse_coords(value: &str) -> Option<(f64, f64)> {
-    // Format: +881824.53;+6663821.17; (avec trailing ;)
-    // Utiliser find au lieu de split().collect()
-    let semicolon_pos = value.find(';')?;
-
-    let x_str = value[..semicolon_pos].trim().trim_start_matches('+');
-    let rest = &value[semicolon_pos + 1..];
-
-    // Trouver la fin de Y (soit le prochain ; soit la fin)
-    let y_end = rest.find(';').unwrap_or(rest.len());
-    let y_str = rest[..y_end].trim().trim_start_matches('+');
-
-    let x = fast_parse_f64(x_str)?;
-    let y = fast_parse_f64(y_str)?;
-    Some((x, y))
-}
-
-/// Parse f64 optimisé pour les coordonnées EDIGEO (format simple: digits.digits)
-/// Utilise fast-float pour un parsing 4-10x plus rapide que std::parse
-#[inline]
-fn fast_parse_f64(s: &str) -> Option<f64> {
-    fast_float::parse(s).ok()
-}
-
-/// Extrait la clé d'une ligne (premiers caractères jusqu'à ce qu'on trouve un modèle)
-fn extract_key(line: &str) -> &str {
-    // Les clés sont des patterns comme SCPCP, CORCC, ATPCP, ATVST, etc.
-    // On veut extraire juste les 3 premiers caractères pour le matching
-    if line.len() >= 3 {
-        &line[..3]
-    } else {
-        line
+/// Valeur ASCII (identifiant, référence), empruntée au contenu du fichier
+///
+/// # Errors
+///
+/// Une valeur qui n'est pas en UTF-8, ou prolongée par des enregistrements de
+/// suite (copie), n'est pas acceptée comme identifiant: plutôt que de perdre
+/// l'enregistrement, le VEC est en erreur.
+fn text<'a>(code: &str, value: &Cow<'a, [u8]>) -> Result<&'a str, EdigeoError> {
+    let invalid = |reason: &str| {
+        EdigeoError::parse_error(
+            "VEC",
+            format!("{} {:?}: {}", code, String::from_utf8_lossy(value), reason),
+        )
+    };
+    match value {
+        Cow::Borrowed(value) => {
+            std::str::from_utf8(value).map_err(|_| invalid("identifier is not valid UTF-8"))
+        }
+        Cow::Owned(_) => Err(invalid("identifier continued over several records")),
     }
 }
 
-/// Extrait la valeur après ':'
-fn extract_value(line: &str) -> Option<&str> {
-    line.find(':').map(|pos| line[pos + 1..].trim())
-}
-
-/// Parse un bloc PNO (point)
-fn parse_pno(lines: &[&str], id: &str) -> Result<Point, EdigeoError> {
-    let mut pno = Point {
-        id: id.to_string(),
-        coords: Vec::new(),
-        scp: None,
+/// Parse un enregistrement (type et champs suivant le `RTY`)
+fn parse_record<'a>(
+    kind: &[u8],
+    fields: &Fields<'a>,
+    encoding: &'static Encoding,
+    result: &mut ParsedVec<'a>,
+) -> Result<(), EdigeoError> {
+    // L'ID est sur la ligne RIDSA..:id
+    let Some((code, id)) = fields.iter().find(|(code, _)| *code == "RID") else {
+        return Ok(());
     };
+    let id = text(code, id)?;
+    if id.is_empty() {
+        return Ok(());
+    }
 
-    for line in lines.iter().skip(1) {
-        if line.is_empty() {
-            continue;
-        }
-
-        let key = extract_key(line);
-        let Some(value) = extract_value(line) else {
-            continue;
-        };
-
-        match key {
-            "SCP" => pno.scp = Some(parse_reference(value)),
-            "COR" => {
-                if let Some(coord) = parse_coords(value) {
-                    pno.coords.push(coord);
+    match kind {
+        b"PNO" | b"PAR" => {
+            let id = result.ids.intern(id);
+            let mut scp = None;
+            let mut coords = Vec::new();
+            for (code, value) in fields {
+                match *code {
+                    "SCP" => scp = Some(parse_reference(code, value, &mut result.ids)?),
+                    "COR" => coords.extend(parse_coords(value)),
+                    _ => {}
                 }
             }
-            _ => {}
+            if kind == b"PNO" {
+                result.pno.insert(id, Point { id, coords, scp });
+            } else {
+                result.par.insert(id, Arc { id, coords, scp });
+            }
         }
-    }
-
-    Ok(pno)
-}
-
-/// Parse un bloc PAR (arc)
-fn parse_par(lines: &[&str], id: &str) -> Result<Arc, EdigeoError> {
-    let mut par = Arc {
-        id: id.to_string(),
-        coords: Vec::new(),
-        scp: None,
-    };
-
-    for line in lines.iter().skip(1) {
-        if line.is_empty() {
-            continue;
+        b"PFE" => {
+            let id = result.ids.intern(id);
+            let scp = fields
+                .iter()
+                .find(|(code, _)| *code == "SCP")
+                .map(|(code, value)| parse_reference(code, value, &mut result.ids))
+                .transpose()?;
+            result.pfe.insert(
+                id,
+                Face {
+                    id,
+                    scp,
+                    arcs: Vec::new(),
+                },
+            );
         }
-
-        let key = extract_key(line);
-        let Some(value) = extract_value(line) else {
-            continue;
-        };
-
-        match key {
-            "SCP" => par.scp = Some(parse_reference(value)),
-            "COR" => {
-                if let Some(coord) = parse_coords(value) {
-                    par.coords.push(coord);
+        b"FEA" => {
+            let id = result.ids.intern(id);
+            let fea = parse_fea(fields, id, encoding, &mut result.ids)?;
+            result.fea.insert(id, fea);
+        }
+        b"LNK" => {
+            let id = result.ids.intern(id);
+            let mut lnk = Link {
+                id,
+                scp: None,
+                ftp: Vec::new(),
+            };
+            for (code, value) in fields {
+                match *code {
+                    "SCP" => lnk.scp = Some(parse_reference(code, value, &mut result.ids)?),
+                    "FTP" => lnk.ftp.push(parse_reference(code, value, &mut result.ids)?),
+                    _ => {}
                 }
             }
-            _ => {}
+            result.lnk.push(lnk);
         }
+        _ => {}
     }
-
-    Ok(par)
+    Ok(())
 }
 
-/// Parse un bloc PFE (face)
-fn parse_pfe(lines: &[&str], id: &str) -> Result<Face, EdigeoError> {
-    let mut pfe = Face {
-        id: id.to_string(),
-        scp: None,
-        arcs: Vec::new(),
-    };
-
-    for line in lines.iter().skip(1) {
-        if line.is_empty() {
-            continue;
-        }
+/// Parties d'une référence `SID;GID;RTY;RID` (vides si absentes)
+fn reference_parts<'a>(code: &str, value: &Cow<'a, [u8]>) -> Result<[&'a str; 4], EdigeoError> {
+    let mut parts = text(code, value)?.splitn(5, ';');
+    Ok([
+        parts.next().unwrap_or(""),
+        parts.next().unwrap_or(""),
+        parts.next().unwrap_or(""),
+        parts.next().unwrap_or(""),
+    ])
+}
 
-        let key = extract_key(line);
-        let Some(value) = extract_value(line) else {
-            continue;
-        };
+/// Parse une référence (SCP, FTP, etc.), cible internée
+#[inline]
+fn parse_reference<'a>(
+    code: &str,
+    value: &Cow<'a, [u8]>,
+    ids: &mut Interner<'a>,
+) -> Result<Reference<'a>, EdigeoError> {
+    let [sid, gid, rty, rid] = reference_parts(code, value)?;
+    Ok(Reference {
+        sid,
+        gid,
+        rty,
+        rid: ids.intern(rid),
+    })
+}
 
-        if key == "SCP" {
-            pfe.scp = Some(parse_reference(value));
-        }
-    }
+/// Parse des coordonnées au format EDIGEO: +X;+Y; ou +X;+Y
+#[inline]
+fn parse_coords(value: &[u8]) -> Option<(f64, f64)> {
+    // Format: +881824.53;+6663821.17; (avec trailing ;)
+    let mut parts = value.split(|&b| b == b';');
+    let x = fast_parse_f64(parts.next()?)?;
+    let y = fast_parse_f64(parts.next()?)?;
+    Some((x, y))
+}
 
-    Ok(pfe)
+/// Parse f64 optimisé pour les coordonnées EDIGEO (format simple: digits.digits)
+/// Utilise fast-float pour un parsing 4-10x plus rapide que std::parse
+#[inline]
+fn fast_parse_f64(s: &[u8]) -> Option<f64> {
+    let s = s.trim_ascii();
+    fast_float::parse(s.strip_prefix(b"+").unwrap_or(s)).ok()
 }
 
 /// Parse un bloc FEA (feature)
-fn parse_fea(lines: &[&str], id: &str) -> Result<Feature, EdigeoError> {
+fn parse_fea<'a>(
+    fields: &Fields<'a>,
+    id: Id,
+    encoding: &'static Encoding,
+    ids: &mut Interner<'a>,
+) -> Result<Feature<'a>, EdigeoError> {
     let mut fea = Feature {
-        id: id.to_string(),
+        id,
         scp: None,
-        attributes: HashMap::new(),
+        attributes: Vec::new(),
         qap: None,
     };
 
-    let mut current_attr_key: Option<String> = None;
-
-    for line in lines.iter().skip(1) {
-        if line.is_empty() {
-            continue;
-        }
-
-        let key = extract_key(line);
-        let Some(value) = extract_value(line) else {
-            continue;
-        };
+    let mut current_attr_key: Option<&'a str> = None;
 
-        match key {
-            "SCP" => fea.scp = Some(parse_reference(value)),
+    for (code, value) in fields {
+        match *code {
+            "SCP" => fea.scp = Some(parse_reference(code, value, ids)?),
             "ATP" => {
                 // ATPCP - référence d'attribut
-                let reference = parse_reference(value);
                 // Extraire le nom de l'attribut depuis RID (ex: TEX2_id -> TEX2, IDU_id -> IDU)
-                current_attr_key = Some(reference.rid.trim_end_matches("_id").to_string());
+                let [.., rid] = reference_parts(code, value)?;
+                current_attr_key = Some(rid.trim_end_matches("_id"));
             }
             "TEX" => {
                 // TEXT - indication d'encodage, on l'ignore mais on garde current_attr_key
                 // La valeur viendra dans le prochain ATV
             }
             "ATV" => {
-                // ATVST, ATVSA, ATVSR - valeur d'attribut
+                // ATVST, ATVSA, ATVSR - valeur d'attribut, seul champ décodé
                 if let Some(attr_key) = current_attr_key.take() {
-                    fea.attributes.insert(attr_key, value.to_string());
+                    let value = match value {
+                        Cow::Borrowed(value) => encoding.decode_without_bom_handling(value).0,
+                        Cow::Owned(value) => {
+                            Cow::Owned(encoding.decode_without_bom_handling(value).0.into_owned())
+                        }
+                    };
+                    fea.attributes.push((attr_key, value));
                 }
             }
             "QAP" => {
                 // QAPCP - référence qualité
-                let reference = parse_reference(value);
-                fea.qap = Some(reference.rid);
+                let [.., rid] = reference_parts(code, value)?;
+                fea.qap = Some(rid);
             }
             _ => {}
         }
@@ -348,61 +466,42 @@ fn parse_fea(lines: &[&str], id: &str) -> Result<Feature, EdigeoError> {
     Ok(fea)
 }
 
-/// Parse un bloc LNK (link)
-fn parse_lnk(lines: &[&str], id: &str) -> Result<Link, EdigeoError> {
-    let mut lnk = Link {
-        id: id.to_string(),
-        scp: None,
-        ftp: Vec::new(),
-    };
-
-    for line in lines.iter().skip(1) {
-        if line.is_empty() {
-            continue;
-        }
-
-        let key = extract_key(line);
-        let Some(value) = extract_value(line) else {
+/// Associe les arcs aux faces via les relations LNK
+fn associate_arcs_to_faces(result: &mut ParsedVec) {
+    let mut incomplete = Vec::new();
+    for lnk in &result.lnk {
+        let Some(ref scp) = lnk.scp else {
             continue;
         };
-
-        match key {
-            "SCP" => lnk.scp = Some(parse_reference(value)),
-            "FTP" => lnk.ftp.push(parse_reference(value)),
-            _ => {}
+        // Les relations de composition face-arc, face à gauche ou à droite
+        let relation = result.ids.resolve(scp.rid);
+        if !relation.contains("RCO_FAC") {
+            continue;
         }
-    }
-
-    Ok(lnk)
-}
+        let arc_ref = lnk.ftp.iter().find(|r| r.rty == "PAR");
+        let face_ref = lnk.ftp.iter().find(|r| r.rty == "PFE");
+        let side = if relation.ends_with("GAUCHE") {
+            Some(Side::Left)
+        } else if relation.ends_with("DROITE") {
+            Some(Side::Right)
+        } else {
+            None
+        };
 
-/// Associe les arcs aux faces via les relations LNK
-fn associate_arcs_to_faces(result: &mut ParsedVec) {
-    // Collecter les associations arc->face
-    let mut associations: Vec<(String, String)> = Vec::new();
-
-    for lnk in result.lnk.values() {
-        if let Some(ref scp) = lnk.scp {
-            // Les relations de composition face-arc
-            if scp.rid.contains("RCO_FAC") {
-                let arc_ref = lnk.ftp.iter().find(|r| r.rty == "PAR");
-                let face_ref = lnk.ftp.iter().find(|r| r.rty == "PFE");
-
-                if let (Some(arc_ref), Some(face_ref)) = (arc_ref, face_ref) {
-                    associations.push((face_ref.rid.clone(), arc_ref.rid.clone()));
-                }
+        if let (Some(arc_ref), Some(face_ref)) = (arc_ref, face_ref) {
+            // Face incomplète si son arc a été écarté
+            if result.rejected.contains(&arc_ref.rid) {
+                incomplete.push(face_ref.rid);
+                continue;
             }
-        }
-    }
-
-    // Appliquer les associations
-    for (face_id, arc_id) in associations {
-        if let Some(arc) = result.par.get(&arc_id) {
-            if let Some(face) = result.pfe.get_mut(&face_id) {
-                face.arcs.push(arc.clone());
+            if result.par.contains_key(&arc_ref.rid) {
+                if let Some(face) = result.pfe.get_mut(&face_ref.rid) {
+                    face.arcs.push((arc_ref.rid, side));
+                }
             }
         }
     }
+    result.rejected.extend(incomplete);
 }
 
 /// Parse rapide avec SIMD pour trouver le type d'une feature
@@ -442,18 +541,65 @@ pub fn find_feature_type(content: &[u8], feature_id: &str) -> Option<String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_interner() {
+        let mut ids = Interner::default();
+        let first = ids.intern("Noeud_1");
+        let second = ids.intern("Noeud_2");
+        assert_ne!(first, second);
+        // Même nom, même identifiant, dans l'ordre de première rencontre
+        assert_eq!(ids.intern("Noeud_1"), first);
+        assert!(first < second);
+        assert_eq!(ids.resolve(first), "Noeud_1");
+        assert_eq!(ids.resolve(second), "Noeud_2");
+        assert_eq!(ids.get("Noeud_2"), Some(second));
+        assert_eq!(ids.get("Noeud_3"), None);
+    }
+
     #[test]
     fn test_parse_reference() {
-        let reference = parse_reference("EDAB01;SeSD;PGE;Noeud_123");
+        let mut ids = Interner::default();
+        let reference = parse_reference(
+            "SCP",
+            &Cow::Borrowed(b"EDAB01;SeSD;PGE;Noeud_123"),
+            &mut ids,
+        )
+        .unwrap();
         assert_eq!(reference.sid, "EDAB01");
         assert_eq!(reference.gid, "SeSD");
         assert_eq!(reference.rty, "PGE");
-        assert_eq!(reference.rid, "Noeud_123");
+        assert_eq!(ids.resolve(reference.rid), "Noeud_123");
+        assert_eq!(ids.get("Noeud_123"), Some(reference.rid));
+
+        // Même cible, même identifiant
+        let other = parse_reference(
+            "FTP",
+            &Cow::Borrowed(b"EDAB01;SeSD;PNO;Noeud_123"),
+            &mut ids,
+        )
+        .unwrap();
+        assert_eq!(other.rid, reference.rid);
+
+        // Parties absentes: vides
+        let partial = parse_reference("FTP", &Cow::Borrowed(b"EDAB01;SeSD"), &mut ids).unwrap();
+        assert_eq!((partial.gid, partial.rty), ("SeSD", ""));
+        assert_eq!(ids.resolve(partial.rid), "");
+
+        // Référence illisible ou prolongée: erreur, pas de cible silencieusement perdue
+        for value in [
+            Cow::Borrowed(&b"EDAB01;SeSD;PNO;Noeud_\xe9"[..]),
+            Cow::Owned(b"EDAB01;SeSD;PNO;Noeud_1".to_vec()),
+        ] {
+            assert!(matches!(
+                parse_reference("FTP", &value, &mut ids),
+                Err(EdigeoError::ParseError { .. })
+            ));
+        }
     }
 
     #[test]
     fn test_parse_coords() {
-        let coords = parse_coords("+881824.53;+6663821.17;");
+        let coords = parse_coords(b"+881824.53;+6663821.17;");
         assert!(coords.is_some());
         let (x, y) = coords.unwrap();
         assert!((x - 881824.53).abs() < 0.01);
@@ -462,7 +608,7 @@ mod tests {
 
     #[test]
     fn test_parse_coords_no_plus() {
-        let coords = parse_coords("881824.53;6663821.17");
+        let coords = parse_coords(b"881824.53;6663821.17");
         assert!(coords.is_some());
         let (x, y) = coords.unwrap();
         assert!((x - 881824.53).abs() < 0.01);
@@ -470,9 +616,117 @@ mod tests {
     }
 
     #[test]
-    fn test_extract_id() {
-        let lines = vec!["PAR", "RIDSA11:Arc_1625270", "SCPCP28:..."];
-        let id = extract_id(&lines);
-        assert_eq!(id, "Arc_1625270");
+    fn test_parse_borrowed() {
+        let data: &[u8] = b"RTYSA03:PAR\r\nRIDSA11:Arc_1625270\r\n\
+            SCPCP28:EDAB01;SeSD;OBJ;ID_S_OBJ_ARC\r\n\
+            CORCC23:+881824.53;+6663821.17;\r\nCORCC23:+881830.00;+6663830.00;\r\n\
+            RTYSA03:PFE\r\nRIDSA06:Face_1\r\n\
+            RTYSA03:FEA\r\nRIDSA05:Obj_1\r\nSCPCP26:EDAB01;SeSD;OBJ;LIEUDIT_id\r\n\
+            ATPCP22:EDAB01;SeSD;ATT;TEX_id\r\nTEXT 06:8859-1\r\nATVST08:Les Pr\xe9s\r\n\
+            ATPCP22:EDAB01;SeSD;ATT;IDU_id\r\nATVST03:001\r\n\
+            QAPCP27:EDAB01;SeSD;QUP;Actualite_1\r\n\
+            RTYSA03:LNK\r\nRIDSA05:Lnk_1\r\nSCPCP35:EDAB01;SeSD;REL;ID_S_RCO_FAC_DROITE\r\n\
+            FTPCP22:EDAB01;SeSD;PFE;Face_1\r\nFTPCP27:EDAB01;SeSD;PAR;Arc_1625270\r\n";
+        let parsed = parse(data, encoding_rs::WINDOWS_1252).unwrap();
+
+        let arc = parsed.ids.get("Arc_1625270").unwrap();
+        assert_eq!(parsed.par[&arc].coords.len(), 2);
+        let face = parsed.ids.get("Face_1").unwrap();
+        assert_eq!(parsed.pfe[&face].arcs, vec![(arc, Some(Side::Right))]);
+
+        let fea = &parsed.fea[&parsed.ids.get("Obj_1").unwrap()];
+        assert_eq!(fea.attribute("TEX"), Some("Les Prés"));
+        assert_eq!(fea.attribute("IDU"), Some("001"));
+        // Valeur ASCII: empruntée au contenu du fichier
+        assert!(matches!(fea.attributes[1].1, Cow::Borrowed("001")));
+        assert_eq!(fea.qap, Some("Actualite_1"));
+        assert_eq!(parsed.name(fea.scp.unwrap().rid), "LIEUDIT_id");
+    }
+
+    #[test]
+    fn test_parse_continuation() {
+        let data: &[u8] = b"RTYSA03:FEA\r\nRIDSA05:Obj_1\r\n\
+            ATPCP22:EDAB01;SeSD;ATT;TEX_id\r\nATVST06:Les Pr\r\nATVNT04:\xe9s  \r\n";
+        let parsed = parse(data, encoding_rs::WINDOWS_1252).unwrap();
+
+        let fea = &parsed.fea[&parsed.ids.get("Obj_1").unwrap()];
+        assert_eq!(fea.attribute("TEX"), Some("Les Prés"));
+    }
+
+    #[test]
+    fn test_parse_invalid_identifiers() {
+        // RID en Latin-1: le VEC est en erreur plutôt que privé de l'entité
+        let data: &[u8] =
+            b"RTYSA03:PNO\r\nRIDSA07:Noeud_\xe9\r\nCORCC23:+881824.53;+6663821.17;\r\n";
+        let error = parse(data, encoding_rs::WINDOWS_1252).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("RID \"Noeud_\u{fffd}\": identifier is not valid UTF-8"),
+            "{}",
+            error
+        );
+
+        // Référence d'un lien sur deux enregistrements (suite)
+        let data: &[u8] = b"RTYSA03:LNK\r\nRIDSA05:Lnk_1\r\n\
+            FTPCP15:EDAB01;SeSD;FEA\r\nFTPNP06:;Obj_1\r\n";
+        let error = parse(data, encoding_rs::WINDOWS_1252).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("FTP \"EDAB01;SeSD;FEA;Obj_1\": identifier continued"),
+            "{}",
+            error
+        );
+
+        // Attribut ou qualité illisible
+        let data: &[u8] = b"RTYSA03:FEA\r\nRIDSA05:Obj_1\r\n\
+            QAPCP18:EDAB01;SeSD;QUP;Q\xe9\r\n";
+        assert!(parse(data, encoding_rs::WINDOWS_1252).is_err());
+    }
+
+    #[test]
+    fn test_parse_invalid_length() {
+        let data: &[u8] = b"RTYSA03:PNO\r\nRIDSA08:Noeud_1\r\n";
+        let parsed = parse(data, encoding_rs::WINDOWS_1252).unwrap();
+        assert!(parsed.pno.is_empty());
+        assert_eq!(parsed.rejected, vec![parsed.ids.get("Noeud_1").unwrap()]);
+        assert!(matches!(
+            parsed.errors.as_slice(),
+            [EdigeoError::ParseError { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_parse_skips_corrupt_record() {
+        // Arc au descripteur invalide au milieu d'un VEC valide: la lecture
+        // reprend à l'enregistrement suivant, la face de l'arc est écartée
+        let data: &[u8] = b"RTYSA03:PAR\r\nRIDSA05:Arc_1\r\n\
+            CORCC23:+881824.53;+6663821.17;\r\n\
+            RTYSA03:PAR\r\nRIDSA05:Arc_2\r\nCORCC2x:+881830.00;+6663830.00;\r\n\
+            SCPNP04:;xyz\r\n\
+            RTYSA03:PFE\r\nRIDSA06:Face_1\r\nRTYSA03:PFE\r\nRIDSA06:Face_2\r\n\
+            RTYSA03:LNK\r\nRIDSA05:Lnk_1\r\nSCPCP35:EDAB01;SeSD;REL;ID_S_RCO_FAC_DROITE\r\n\
+            FTPCP21:EDAB01;SeSD;PAR;Arc_1\r\nFTPCP22:EDAB01;SeSD;PFE;Face_1\r\n\
+            RTYSA03:LNK\r\nRIDSA05:Lnk_2\r\nSCPCP35:EDAB01;SeSD;REL;ID_S_RCO_FAC_DROITE\r\n\
+            FTPCP21:EDAB01;SeSD;PAR;Arc_2\r\nFTPCP22:EDAB01;SeSD;PFE;Face_2\r\n";
+        let parsed = parse(data, encoding_rs::WINDOWS_1252).unwrap();
+
+        let id = |name| parsed.ids.get(name).unwrap();
+        assert!(parsed.par.contains_key(&id("Arc_1")));
+        assert!(!parsed.par.contains_key(&id("Arc_2")));
+        assert_eq!(parsed.pfe.len(), 2);
+        assert_eq!(parsed.lnk.len(), 2);
+        assert_eq!(parsed.pfe[&id("Face_1")].arcs.len(), 1);
+        assert_eq!(parsed.rejected, vec![id("Arc_2"), id("Face_2")]);
+
+        // Une erreur pour l'enregistrement, même avec plusieurs lignes invalides
+        let errors: Vec<_> = parsed.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].contains("VEC line 6") && errors[0].contains("record Arc_2 skipped"),
+            "{:?}",
+            errors
+        );
     }
 }
@@ -1,109 +1,259 @@
 //! Réparation et construction des géométries
 
-pub mod fallback;
+pub mod polygonize;
 pub mod ring;
+pub mod snap;
 pub mod topology;
+pub mod valid;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use geo::{Coord, Geometry, LineString, Point};
 use tracing::warn;
 
-use crate::parser::vec::{ParsedVec, Reference};
-use crate::types::{Feature, Quality};
+use crate::parser;
+use crate::parser::vec::{Id, Link, ParsedVec, Reference, Side};
+use crate::types::{Feature, Quality, RelationGraph, RepairAction, RepairEntry, RepairLog};
 use crate::EdigeoError;
 
 /// Construit les géométries à partir des entités VEC parsées
+///
+/// Les sommets distants de moins de `tolerance` sont confondus lors de
+/// l'assemblage des faces. Une entité surfacique dont aucun polygone valide ne
+/// peut être construit est écartée: l'erreur `EdigeoError::RepairFailed` est
+/// ajoutée à `errors`. Chaque retouche (et chaque rejet) est consignée dans
+/// `repairs`.
 pub fn build_geometries(
     parsed: &ParsedVec,
     quality: &HashMap<String, Quality>,
+    tolerance: f64,
+    errors: &mut Vec<EdigeoError>,
+    repairs: &mut RepairLog,
 ) -> Result<Vec<Feature>, EdigeoError> {
+    // Parcourir les LNK pour trouver les associations FEA -> géométrie
+    Ok(parsed
+        .lnk
+        .iter()
+        .filter_map(|lnk| build_feature(parsed, lnk, quality, tolerance, errors, repairs))
+        .collect())
+}
+
+/// Équivalent de `build_geometries` qui répartit les liens du VEC en lots
+/// construits en parallèle (pool rayon courant)
+///
+/// Les features, erreurs et retouches sont rendues dans le même ordre qu'avec
+/// `build_geometries`.
+#[cfg(feature = "parallel")]
+pub fn build_geometries_parallel(
+    parsed: &ParsedVec,
+    quality: &HashMap<String, Quality>,
+    tolerance: f64,
+    errors: &mut Vec<EdigeoError>,
+    repairs: &mut RepairLog,
+) -> Result<Vec<Feature>, EdigeoError> {
+    use rayon::prelude::*;
+
+    /// Liens par lot: un lot suffit aux petits VEC
+    const CHUNK: usize = 256;
+
+    let chunks: Vec<_> = parsed
+        .lnk
+        .par_chunks(CHUNK)
+        .map(|chunk| {
+            let mut errors = Vec::new();
+            let mut repairs = RepairLog::default();
+            let features: Vec<Feature> = chunk
+                .iter()
+                .filter_map(|lnk| {
+                    build_feature(parsed, lnk, quality, tolerance, &mut errors, &mut repairs)
+                })
+                .collect();
+            (features, errors, repairs)
+        })
+        .collect();
+
     let mut features = Vec::new();
+    for (chunk, chunk_errors, chunk_repairs) in chunks {
+        features.extend(chunk);
+        errors.extend(chunk_errors);
+        repairs.entries.extend(chunk_repairs.entries);
+    }
+    Ok(features)
+}
 
-    // Parcourir les LNK pour trouver les associations FEA -> géométrie
-    for lnk in parsed.lnk.values() {
-        let Some(ref scp) = lnk.scp else {
+/// Écarte les entités dont la géométrie utilise une primitive retirée du VEC
+///
+/// `removed` désigne des arcs, des nœuds (ex: sommets non transformables
+/// lors de l'arrondi topologique) ou des faces; une face est touchée si
+/// l'un de ses arcs l'est. Les liens de ces entités sont retirés du VEC: les autres entités
+/// sont construites normalement. Chaque rejet est consigné dans `repairs` et
+/// ajouté à `errors` (`EdigeoError::RepairFailed`).
+pub fn reject_primitives(
+    parsed: &mut ParsedVec,
+    removed: &[Id],
+    reason: &str,
+    errors: &mut Vec<EdigeoError>,
+    repairs: &mut RepairLog,
+) {
+    if removed.is_empty() {
+        return;
+    }
+    let removed: HashSet<Id> = removed.iter().copied().collect();
+    let faces: HashSet<Id> = parsed
+        .pfe
+        .values()
+        .filter(|face| face.arcs.iter().any(|(arc, _)| removed.contains(arc)))
+        .map(|face| face.id)
+        .collect();
+
+    let mut rejected = vec![false; parsed.lnk.len()];
+    for (lnk, rejected) in parsed.lnk.iter().zip(rejected.iter_mut()) {
+        let Some(fea) = lnk
+            .ftp
+            .iter()
+            .find(|r| r.rty == "FEA")
+            .and_then(|r| parsed.fea.get(&r.rid))
+        else {
             continue;
         };
-
-        if scp.rty != "REL" {
+        let touched = lnk.ftp.iter().any(|r| match r.rty {
+            "PFE" => faces.contains(&r.rid) || removed.contains(&r.rid),
+            "PAR" | "PNO" => removed.contains(&r.rid),
+            _ => false,
+        });
+        if !touched {
             continue;
         }
-
-        // Trouver la FEA associée
-        let fea_ref = lnk.ftp.iter().find(|r| r.rty == "FEA");
-        let Some(fea_ref) = fea_ref else {
-            continue;
+        let entity = Entity {
+            id: feature_id(parsed, fea),
+            feature_type: feature_type(parsed, fea),
         };
+        let e = entity.reject(repairs, reason, None);
+        warn!(error = %e, "Feature rejected");
+        errors.push(e);
+        *rejected = true;
+    }
 
-        let Some(fea) = parsed.fea.get(&fea_ref.rid) else {
-            continue;
-        };
+    let mut rejected = rejected.into_iter();
+    parsed.lnk.retain(|_| !rejected.next().unwrap_or(false));
+}
 
-        // Déterminer le type de géométrie
-        let pfe_refs: Vec<&Reference> = lnk.ftp.iter().filter(|r| r.rty == "PFE").collect();
-        let par_refs: Vec<&Reference> = lnk.ftp.iter().filter(|r| r.rty == "PAR").collect();
-        let pno_refs: Vec<&Reference> = lnk.ftp.iter().filter(|r| r.rty == "PNO").collect();
-
-        let geometry = if !pfe_refs.is_empty() {
-            // Polygon depuis PFE
-            build_polygon_from_pfe(parsed, &pfe_refs, &fea.id)?
-        } else if !par_refs.is_empty() {
-            // LineString depuis PAR
-            build_linestring_from_par(parsed, &par_refs)
-        } else if !pno_refs.is_empty() {
-            // Point depuis PNO
-            build_point_from_pno(parsed, &pno_refs)
-        } else {
-            continue;
-        };
+/// Feature décrite par un lien FEA -> géométrie, `None` pour les autres liens
+/// et les entités écartées
+fn build_feature(
+    parsed: &ParsedVec,
+    lnk: &Link,
+    quality: &HashMap<String, Quality>,
+    tolerance: f64,
+    errors: &mut Vec<EdigeoError>,
+    repairs: &mut RepairLog,
+) -> Option<Feature> {
+    if lnk.scp.as_ref()?.rty != "REL" {
+        return None;
+    }
 
-        let Some(geometry) = geometry else {
-            continue;
-        };
+    // Trouver la FEA associée
+    let fea_ref = lnk.ftp.iter().find(|r| r.rty == "FEA")?;
+    let fea = parsed.fea.get(&fea_ref.rid)?;
 
-        // Construire les propriétés
-        let mut properties = fea.attributes.clone();
+    // Déterminer le type de géométrie
+    let pfe_refs: Vec<&Reference> = lnk.ftp.iter().filter(|r| r.rty == "PFE").collect();
+    let par_refs: Vec<&Reference> = lnk.ftp.iter().filter(|r| r.rty == "PAR").collect();
+    let pno_refs: Vec<&Reference> = lnk.ftp.iter().filter(|r| r.rty == "PNO").collect();
 
-        // Ajouter les informations de qualité si disponibles
-        if let Some(qap_id) = &fea.qap {
-            if let Some(q) = quality.get(qap_id) {
-                if let Some(ref date) = q.create_date {
-                    properties.insert("createDate".to_string(), date.clone());
-                }
-                if let Some(ref date) = q.update_date {
-                    properties.insert("updateDate".to_string(), date.clone());
-                }
+    let entity = Entity {
+        id: feature_id(parsed, fea),
+        feature_type: feature_type(parsed, fea),
+    };
+    let geometry = if !pfe_refs.is_empty() {
+        // Polygon depuis PFE (entité rejetée si aucun polygone valide)
+        match build_polygon_from_pfe(parsed, &pfe_refs, &entity, tolerance, repairs) {
+            Ok(geometry) => geometry,
+            Err(e) => {
+                warn!(error = %e, "Feature rejected");
+                errors.push(e);
+                return None;
             }
         }
+    } else if !par_refs.is_empty() {
+        // LineString depuis PAR
+        let geometry = build_linestring_from_par(parsed, &par_refs);
+        record_snapped(parsed, par_refs.iter().map(|r| r.rid), &entity, repairs);
+        geometry
+    } else if !pno_refs.is_empty() {
+        // Point depuis PNO
+        let geometry = build_point_from_pno(parsed, &pno_refs);
+        record_snapped(parsed, pno_refs.first().map(|r| r.rid), &entity, repairs);
+        geometry
+    } else {
+        return None;
+    };
 
-        // Déterminer le type de feature
-        //
-        // On conserve le suffixe `_id` pour rester compatible avec les fichiers de configuration
-        // (hérités de la version Node.js) qui référencent des types comme `PARCELLE_id`.
-        let feature_type = fea
-            .scp
-            .as_ref()
-            .map(|s| s.rid.to_string())
-            .unwrap_or_else(|| "UNKNOWN".to_string());
-
-        // Utiliser IDU comme ID si disponible (format cadastral), sinon ID interne
-        let feature_id = fea
-            .attributes
-            .get("IDU")
-            .filter(|s| !s.is_empty())
-            .cloned()
-            .unwrap_or_else(|| fea.id.clone());
-
-        features.push(Feature {
-            id: feature_id,
-            geometry,
-            properties,
-            feature_type,
-        });
+    let geometry = geometry?;
+
+    // Construire les propriétés
+    let mut properties: HashMap<String, String> = fea
+        .attributes
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+    // Ajouter les informations de qualité si disponibles (dates ISO)
+    let quality = fea.qap.and_then(|qap_id| quality.get(qap_id));
+    if let Some(q) = quality {
+        if let Some(date) = q.create_date {
+            properties.insert("createDate".to_string(), date.to_string());
+        }
+        if let Some(date) = q.update_date {
+            properties.insert("updateDate".to_string(), date.to_string());
+        }
+        if let Some(update_type) = &q.update_type {
+            properties.insert("updateType".to_string(), update_type.as_str().to_string());
+        }
     }
 
-    Ok(features)
+    Some(Feature {
+        id: feature_id(parsed, fea).to_string(),
+        geometry,
+        properties,
+        feature_type: feature_type(parsed, fea).to_string(),
+        quality: quality.cloned(),
+    })
+}
+
+/// Graphe des relations sémantiques entre les features d'un VEC
+///
+/// Les extrémités des liens sont résolues dans ce VEC: un identifiant
+/// d'enregistrement repris par un autre VEC du lot désigne une autre FEA. Un
+/// lien vers une FEA absente du VEC est ignoré.
+pub fn build_relations(parsed: &ParsedVec) -> RelationGraph {
+    let mut graph = RelationGraph::default();
+    for (relation, from, to) in parsed.feature_links() {
+        let (Some(from), Some(to)) = (parsed.fea.get(&from), parsed.fea.get(&to)) else {
+            continue;
+        };
+        graph.insert(
+            relation,
+            (feature_id(parsed, from), feature_type(parsed, from)),
+            (feature_id(parsed, to), feature_type(parsed, to)),
+        );
+    }
+    graph
+}
+
+/// Identifiant d'une feature: IDU si disponible (format cadastral), sinon ID interne
+pub(crate) fn feature_id<'a>(parsed: &ParsedVec<'a>, fea: &'a parser::vec::Feature<'a>) -> &'a str {
+    fea.attribute("IDU")
+        .filter(|s| !s.is_empty())
+        .unwrap_or(parsed.name(fea.id))
+}
+
+/// Type d'une feature
+///
+/// On conserve le suffixe `_id` pour rester compatible avec les fichiers de configuration
+/// (hérités de la version Node.js) qui référencent des types comme `PARCELLE_id`.
+pub(crate) fn feature_type<'a>(parsed: &ParsedVec<'a>, fea: &parser::vec::Feature) -> &'a str {
+    fea.scp.as_ref().map_or("UNKNOWN", |s| parsed.name(s.rid))
 }
 
 /// Construit un Point depuis des références PNO
@@ -152,64 +302,615 @@ fn build_linestring_from_par(parsed: &ParsedVec, refs: &[&Reference]) -> Option<
     }
 }
 
+/// Consigne les sommets d'une ligne ou d'un point déplacés par l'arrondi
+/// topologique (sans surface)
+fn record_snapped(
+    parsed: &ParsedVec,
+    ids: impl IntoIterator<Item = Id>,
+    entity: &Entity,
+    repairs: &mut RepairLog,
+) {
+    let moved = snapped_vertices(parsed, ids);
+    if moved > 0 {
+        entity.record(repairs, RepairAction::VerticesSnapped, moved, Some(0.0));
+    }
+}
+
+/// Sommets d'origine des primitives `ids` déplacés par l'arrondi topologique
+fn snapped_vertices(parsed: &ParsedVec, ids: impl IntoIterator<Item = Id>) -> usize {
+    let ids: HashSet<Id> = ids.into_iter().collect();
+    ids.into_iter()
+        .filter_map(|id| {
+            let original = parsed.unsnapped.get(&id)?;
+            let snapped = match parsed.par.get(&id) {
+                Some(arc) => &arc.coords,
+                None => &parsed.pno.get(&id)?.coords,
+            };
+            let snapped: HashSet<(u64, u64)> = snapped
+                .iter()
+                .map(|(x, y)| (x.to_bits(), y.to_bits()))
+                .collect();
+            Some(
+                original
+                    .iter()
+                    .filter(|(x, y)| !snapped.contains(&(x.to_bits(), y.to_bits())))
+                    .count(),
+            )
+        })
+        .sum()
+}
+
+/// Entité en cours de construction (pour le journal des retouches)
+struct Entity<'a> {
+    id: &'a str,
+    feature_type: &'a str,
+}
+
+impl Entity<'_> {
+    fn record(
+        &self,
+        repairs: &mut RepairLog,
+        action: RepairAction,
+        vertices: usize,
+        area_delta: Option<f64>,
+    ) {
+        repairs.record(RepairEntry {
+            entity_id: self.id.to_string(),
+            feature_type: self.feature_type.to_string(),
+            action,
+            vertices,
+            area_delta,
+        });
+    }
+
+    /// Rejet consigné, avec la surface perdue si un polygone candidat existe
+    fn reject(&self, repairs: &mut RepairLog, reason: &str, area: Option<f64>) -> EdigeoError {
+        self.record(
+            repairs,
+            RepairAction::Rejected(reason.to_string()),
+            0,
+            area.map(|a| -a),
+        );
+        repair_failed(self.id, reason)
+    }
+}
+
+/// Arcs des faces d'une entité, orientés avec l'entité à gauche
+struct EntityArcs {
+    arcs: Vec<Vec<Coord>>,
+    /// Fin des arcs de chaque face non vide dans `arcs`
+    faces: Vec<usize>,
+    /// Faces référencées absentes ou sans arcs
+    empty_faces: usize,
+    /// Côté de l'entité connu pour chaque arc
+    oriented: bool,
+    /// Arcs déplacés par l'arrondi topologique
+    snapped: Vec<Id>,
+}
+
+impl EntityArcs {
+    /// Arcs des faces `refs`; avec `unsnapped`, coordonnées d'avant l'arrondi
+    /// topologique
+    fn collect(parsed: &ParsedVec, refs: &[&Reference], unsnapped: bool) -> Self {
+        let mut arcs = Self {
+            arcs: Vec::new(),
+            faces: Vec::new(),
+            empty_faces: 0,
+            oriented: true,
+            snapped: Vec::new(),
+        };
+        for pfe_ref in refs {
+            let before = arcs.arcs.len();
+            if let Some(face) = parsed.pfe.get(&pfe_ref.rid) {
+                for &(id, side) in &face.arcs {
+                    let Some(arc) = parsed.par.get(&id) else {
+                        continue;
+                    };
+                    let original = parsed.unsnapped.get(&id);
+                    if original.is_some() {
+                        arcs.snapped.push(id);
+                    }
+                    let source = match original {
+                        Some(original) if unsnapped => original,
+                        _ => &arc.coords,
+                    };
+                    let mut coords: Vec<Coord> =
+                        source.iter().map(|&(x, y)| Coord { x, y }).collect();
+                    if coords.is_empty() {
+                        continue;
+                    }
+                    match side {
+                        Some(Side::Left) => {}
+                        Some(Side::Right) => coords.reverse(),
+                        None => arcs.oriented = false,
+                    }
+                    arcs.arcs.push(coords);
+                }
+            }
+            if arcs.arcs.len() == before {
+                arcs.empty_faces += 1;
+            } else {
+                arcs.faces.push(arcs.arcs.len());
+            }
+        }
+        arcs
+    }
+}
+
 /// Construit un Polygon depuis des références PFE
+///
+/// Chaque retouche est consignée avec sa variation de surface: surface du
+/// polygone retenu moins celle du polygone construit sans la retouche
+/// (sommets d'origine, faces assemblées séparément, segments pendants
+/// conservés ou coordonnées d'avant l'arrondi).
 fn build_polygon_from_pfe(
     parsed: &ParsedVec,
     refs: &[&Reference],
-    entity_id: &str,
+    entity: &Entity,
+    tolerance: f64,
+    repairs: &mut RepairLog,
 ) -> Result<Option<Geometry>, EdigeoError> {
     if refs.is_empty() {
         return Ok(None);
     }
 
-    // Collecter les arcs de toutes les faces référencées
-    let mut all_arcs: Vec<Vec<Coord>> = Vec::new();
+    let arcs = EntityArcs::collect(parsed, refs, false);
+    if arcs.empty_faces > 0 {
+        warn!(
+            entity = entity.id,
+            faces = arcs.empty_faces,
+            "Empty faces skipped"
+        );
+        // Une face sans arc n'apporte aucune surface
+        entity.record(repairs, RepairAction::EmptyFaceSkipped, 0, Some(0.0));
+    }
 
-    for pfe_ref in refs {
-        if let Some(face) = parsed.pfe.get(&pfe_ref.rid) {
-            for arc in &face.arcs {
-                let coords: Vec<Coord> = arc.coords.iter().map(|&(x, y)| Coord { x, y }).collect();
-                if !coords.is_empty() {
-                    all_arcs.push(coords);
-                }
-            }
+    if arcs.arcs.is_empty() {
+        return Ok(None);
+    }
+
+    // Graphe des arcs: frontières internes annulées, faces assemblées avec leurs trous
+    let faces = ring::reconstruct_rings(&arcs.arcs, arcs.oriented, tolerance)
+        .map_err(|_| entity.reject(repairs, "no closed face in arc graph", None))?;
+    let layout = topology::nest_rings(&faces.rings);
+    let ring_areas: Vec<f64> = faces.rings.iter().map(topology::ring_area).collect();
+    let area = layout_area(&layout, &ring_areas);
+    let geometry = assemble(topology::into_polygons(faces.rings, layout.clone()));
+    if let Err(reason) = valid::check(&geometry) {
+        return Err(entity.reject(repairs, &reason, Some(area)));
+    }
+
+    if faces.merged > 0 {
+        let before = layout_area(&layout, &faces.raw_areas);
+        entity.record(
+            repairs,
+            RepairAction::NodesMerged,
+            faces.merged,
+            Some(area - before),
+        );
+    }
+    if faces.dissolved > 0 {
+        let mut start = 0;
+        let mut before = 0.0;
+        for &end in &arcs.faces {
+            before += polygon_area(&arcs.arcs[start..end], arcs.oriented, tolerance);
+            start = end;
         }
+        entity.record(
+            repairs,
+            RepairAction::EdgesDissolved,
+            faces.dissolved,
+            Some(area - before),
+        );
+    }
+    if faces.dangling > 0 {
+        let before = rings_area(&faces.with_dangles);
+        entity.record(
+            repairs,
+            RepairAction::DanglingRemoved,
+            faces.dangling,
+            Some(area - before),
+        );
     }
+    if !arcs.snapped.is_empty() {
+        let unsnapped = EntityArcs::collect(parsed, refs, true);
+        let before = polygon_area(&unsnapped.arcs, unsnapped.oriented, tolerance);
+        entity.record(
+            repairs,
+            RepairAction::VerticesSnapped,
+            snapped_vertices(parsed, arcs.snapped),
+            Some(area - before),
+        );
+    }
+    Ok(Some(geometry))
+}
 
-    if all_arcs.is_empty() {
-        return Ok(None);
+/// Polygone ou multipolygone
+fn assemble(mut polygons: Vec<geo::Polygon>) -> Geometry {
+    if polygons.len() == 1 {
+        Geometry::Polygon(polygons.remove(0))
+    } else {
+        Geometry::MultiPolygon(geo::MultiPolygon::new(polygons))
     }
+}
 
-    // Tenter de reconstruire les rings
-    match ring::reconstruct_rings(&all_arcs) {
-        Ok(rings) => {
-            if rings.is_empty() {
-                return Ok(None);
-            }
+/// Surface du polygone formé par des arcs, nulle sans face fermée
+fn polygon_area(arcs: &[Vec<Coord>], oriented: bool, tolerance: f64) -> f64 {
+    rings_area(&polygonize::polygonize(arcs, oriented, tolerance).rings)
+}
 
-            // Organiser en polygones avec trous
-            let polygons = topology::organize_rings(rings);
+/// Surface des polygones formés par des rings, trous déduits
+fn rings_area(rings: &[LineString]) -> f64 {
+    let areas: Vec<f64> = rings.iter().map(topology::ring_area).collect();
+    layout_area(&topology::nest_rings(rings), &areas)
+}
 
-            if polygons.len() == 1 {
-                Ok(Some(Geometry::Polygon(
-                    polygons.into_iter().next().unwrap(),
-                )))
-            } else {
-                Ok(Some(Geometry::MultiPolygon(geo::MultiPolygon::new(
-                    polygons,
-                ))))
-            }
+/// Surface des polygones d'une organisation de rings (voir
+/// `topology::nest_rings`) d'aires signées `areas`
+fn layout_area(layout: &[(usize, Vec<usize>)], areas: &[f64]) -> f64 {
+    layout
+        .iter()
+        .map(|(shell, holes)| {
+            areas[*shell].abs() - holes.iter().map(|&h| areas[h].abs()).sum::<f64>()
+        })
+        .sum()
+}
+
+/// Rejet d'une entité sans polygone valide
+fn repair_failed(entity_id: &str, reason: &str) -> EdigeoError {
+    EdigeoError::RepairFailed {
+        entity_id: entity_id.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::vec::{Arc, Face};
+    use geo::Area;
+
+    const ENTITY: Entity = Entity {
+        id: "P1",
+        feature_type: "PARCELLE_id",
+    };
+
+    const ARCS: [&str; 4] = ["Arc_0", "Arc_1", "Arc_2", "Arc_3"];
+
+    /// Ajoute une face et ses arcs, retourne la référence PFE vers la face
+    fn add_face(
+        parsed: &mut ParsedVec<'static>,
+        name: &'static str,
+        arcs: &[&[(f64, f64)]],
+    ) -> Reference<'static> {
+        let arcs = arcs
+            .iter()
+            .map(|coords| {
+                let id = parsed.ids.intern(ARCS[parsed.par.len()]);
+                parsed.par.insert(
+                    id,
+                    Arc {
+                        id,
+                        coords: coords.to_vec(),
+                        scp: None,
+                    },
+                );
+                (id, None)
+            })
+            .collect();
+        let id = parsed.ids.intern(name);
+        parsed.pfe.insert(
+            id,
+            Face {
+                id,
+                scp: None,
+                arcs,
+            },
+        );
+        Reference {
+            sid: "EDAB01",
+            gid: "SeSD",
+            rty: "PFE",
+            rid: id,
         }
-        Err(_) => {
-            // Fallback: convex hull
-            warn!(entity_id = %entity_id, "Ring reconstruction failed, using convex hull");
-            match fallback::convex_hull_fallback(&all_arcs) {
-                Ok(polygon) => Ok(Some(Geometry::Polygon(polygon))),
-                Err(e) => {
-                    warn!(entity_id = %entity_id, error = %e, "Convex hull fallback failed");
-                    Ok(None)
-                }
-            }
+    }
+
+    fn face(arcs: &[&[(f64, f64)]]) -> (ParsedVec<'static>, Reference<'static>) {
+        let mut parsed = ParsedVec::default();
+        let reference = add_face(&mut parsed, "Face_1", arcs);
+        (parsed, reference)
+    }
+
+    #[test]
+    fn test_polygon_dangling_arc_removed() {
+        // Arc prolongé au-delà du nœud de fermeture: le cul-de-sac est retiré
+        let (parsed, reference) = face(&[
+            &[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)],
+            &[(0.0, 2.0), (0.0, 0.0), (-1.0, -1.0)],
+        ]);
+        let mut repairs = RepairLog::default();
+        let geometry = build_polygon_from_pfe(
+            &parsed,
+            &[&reference],
+            &ENTITY,
+            ring::DEFAULT_TOLERANCE,
+            &mut repairs,
+        )
+        .unwrap()
+        .unwrap();
+        let Geometry::Polygon(polygon) = geometry else {
+            panic!("expected a polygon");
+        };
+        assert_eq!(polygon.exterior().0.len(), 5);
+        assert!(valid::check(&Geometry::Polygon(polygon)).is_ok());
+
+        assert_eq!(
+            repairs.entries,
+            vec![RepairEntry {
+                entity_id: "P1".to_string(),
+                feature_type: "PARCELLE_id".to_string(),
+                action: RepairAction::DanglingRemoved,
+                vertices: 1,
+                area_delta: Some(0.0),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_unrepairable_polygon_rejected() {
+        // Contour ouvert: aucune surface n'est inventée
+        let (parsed, reference) = face(&[&[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0)]]);
+        let mut repairs = RepairLog::default();
+        let err = build_polygon_from_pfe(
+            &parsed,
+            &[&reference],
+            &ENTITY,
+            ring::DEFAULT_TOLERANCE,
+            &mut repairs,
+        )
+        .unwrap_err();
+        let EdigeoError::RepairFailed { entity_id, reason } = err else {
+            panic!("expected a repair failure");
+        };
+        assert_eq!(entity_id, "P1");
+        assert!(reason.contains("no closed face"), "{}", reason);
+
+        let [entry] = &repairs.entries[..] else {
+            panic!("expected one entry: {:?}", repairs);
+        };
+        assert_eq!(entry.action, RepairAction::Rejected(reason));
+        assert_eq!(entry.area_delta, None);
+    }
+
+    #[test]
+    fn test_merged_nodes_and_empty_face_logged() {
+        // Fermeture décalée de 1 mm, face sans arcs
+        let (mut parsed, reference) = face(&[
+            &[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0)],
+            &[(2.0, 2.0), (0.0, 2.0), (0.0005, 0.0)],
+        ]);
+        let empty = add_face(&mut parsed, "Face_2", &[]);
+
+        let mut repairs = RepairLog::default();
+        build_polygon_from_pfe(&parsed, &[&reference, &empty], &ENTITY, 1e-3, &mut repairs)
+            .unwrap()
+            .unwrap();
+        let actions: Vec<(RepairAction, usize)> = repairs
+            .entries
+            .iter()
+            .map(|e| (e.action.clone(), e.vertices))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                (RepairAction::EmptyFaceSkipped, 0),
+                (RepairAction::NodesMerged, 1),
+            ]
+        );
+        // Sommets d'origine: le contour fermé par le décalage perd un
+        // triangle de 0,5 × 0,0005 × 2
+        assert_eq!(repairs.entries[0].area_delta, Some(0.0));
+        let delta = repairs.entries[1].area_delta.unwrap();
+        assert!((delta - 0.0005).abs() < 1e-12, "{}", delta);
+    }
+
+    #[test]
+    fn test_dissolved_edges_logged() {
+        // Deux faces de 2 × 2 qui partagent le côté x = 2
+        let mut parsed = ParsedVec::default();
+        let left = add_face(
+            &mut parsed,
+            "Face_1",
+            &[&[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0), (0.0, 0.0)]],
+        );
+        let right = add_face(
+            &mut parsed,
+            "Face_2",
+            &[&[(2.0, 0.0), (4.0, 0.0), (4.0, 2.0), (2.0, 2.0), (2.0, 0.0)]],
+        );
+
+        let mut repairs = RepairLog::default();
+        let geometry = build_polygon_from_pfe(
+            &parsed,
+            &[&left, &right],
+            &ENTITY,
+            ring::DEFAULT_TOLERANCE,
+            &mut repairs,
+        )
+        .unwrap()
+        .unwrap();
+        let Geometry::Polygon(polygon) = geometry else {
+            panic!("expected a polygon");
+        };
+        assert_eq!(polygon.exterior().0.len(), 7);
+
+        // Le côté commun est annulé dans chacune des deux faces; les faces
+        // assemblées séparément couvrent la même surface
+        let [entry] = &repairs.entries[..] else {
+            panic!("expected one entry: {:?}", repairs);
+        };
+        assert_eq!(
+            (&entry.action, entry.vertices, entry.area_delta),
+            (&RepairAction::EdgesDissolved, 2, Some(0.0))
+        );
+    }
+
+    #[test]
+    fn test_snapped_vertices_logged() {
+        // Coin (2.0004, 2) ramené sur la grille en (2, 2)
+        let (mut parsed, reference) =
+            face(&[&[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0), (0.0, 0.0)]]);
+        let arc = parsed.pfe[&reference.rid].arcs[0].0;
+        parsed.unsnapped.insert(
+            arc,
+            vec![
+                (0.0, 0.0),
+                (2.0, 0.0),
+                (2.0004, 2.0),
+                (0.0, 2.0),
+                (0.0, 0.0),
+            ],
+        );
+
+        let mut repairs = RepairLog::default();
+        build_polygon_from_pfe(
+            &parsed,
+            &[&reference],
+            &ENTITY,
+            ring::DEFAULT_TOLERANCE,
+            &mut repairs,
+        )
+        .unwrap()
+        .unwrap();
+        let [entry] = &repairs.entries[..] else {
+            panic!("expected one entry: {:?}", repairs);
+        };
+        assert_eq!(
+            (&entry.action, entry.vertices),
+            (&RepairAction::VerticesSnapped, 1)
+        );
+        // Trapèze d'origine: 4 + 0,5 × 0,0004 × 2
+        let delta = entry.area_delta.unwrap();
+        assert!((delta + 0.0004).abs() < 1e-12, "{}", delta);
+    }
+
+    /// Relie une nouvelle FEA d'IDU `idu` à la face désignée par `face`
+    fn link_feature(parsed: &mut ParsedVec<'static>, idu: &'static str, face: Reference<'static>) {
+        use crate::parser::vec::Feature;
+
+        let reference = |rty, rid| Reference {
+            sid: "EDAB01",
+            gid: "SeSD",
+            rty,
+            rid,
+        };
+        let fea = parsed.ids.intern(idu);
+        parsed.fea.insert(
+            fea,
+            Feature {
+                id: fea,
+                scp: Some(reference("OBJ", parsed.ids.intern("PARCELLE_id"))),
+                attributes: vec![("IDU", idu.into())],
+                qap: None,
+            },
+        );
+        let id = parsed.ids.intern(["Lnk_1", "Lnk_2"][parsed.lnk.len()]);
+        let rel = parsed.ids.intern("ID_S_REL_FEA_PFE");
+        parsed.lnk.push(Link {
+            id,
+            scp: Some(reference("REL", rel)),
+            ftp: vec![reference("FEA", fea), face],
+        });
+    }
+
+    #[test]
+    fn test_reject_primitives_keeps_other_features() {
+        // Deux parcelles; un arc de la seconde n'a pas pu être transformé
+        let mut parsed = ParsedVec::default();
+        let square: [&[(f64, f64)]; 1] =
+            [&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.0, 0.0)]];
+        let first = add_face(&mut parsed, "Face_1", &square);
+        let second = add_face(&mut parsed, "Face_2", &square);
+        link_feature(&mut parsed, "P1", first);
+        link_feature(&mut parsed, "P2", second);
+        let bad = parsed.pfe[&second.rid].arcs[0].0;
+        parsed.par.remove(&bad);
+
+        let mut errors = Vec::new();
+        let mut repairs = RepairLog::default();
+        reject_primitives(
+            &mut parsed,
+            &[bad],
+            "coordinate transform failed",
+            &mut errors,
+            &mut repairs,
+        );
+
+        assert!(
+            matches!(&errors[..], [EdigeoError::RepairFailed { entity_id, .. }] if entity_id == "P2")
+        );
+        let [entry] = &repairs.entries[..] else {
+            panic!("expected one entry: {:?}", repairs);
+        };
+        assert_eq!(entry.entity_id, "P2");
+        assert_eq!(
+            entry.action,
+            RepairAction::Rejected("coordinate transform failed".into())
+        );
+
+        let features = build_geometries(
+            &parsed,
+            &HashMap::new(),
+            ring::DEFAULT_TOLERANCE,
+            &mut errors,
+            &mut repairs,
+        )
+        .unwrap();
+        let ids: Vec<&str> = features.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(ids, ["P1"]);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_polygon_hole_touching_shell() {
+        // Trou triangulaire qui touche le contour en (5, 0): polygone valide
+        // avec un trou, avec ou sans le côté des faces
+        let (mut parsed, reference) = face(&[
+            &[
+                (0.0, 0.0),
+                (5.0, 0.0),
+                (10.0, 0.0),
+                (10.0, 10.0),
+                (0.0, 10.0),
+                (0.0, 0.0),
+            ],
+            &[(5.0, 0.0), (7.0, 3.0), (3.0, 3.0), (5.0, 0.0)],
+        ]);
+        for sides in [None, Some(Side::Left)] {
+            let face = parsed.pfe.get_mut(&reference.rid).unwrap();
+            face.arcs[0].1 = sides;
+            // Le trou est parcouru à contresens: la face est à sa droite
+            face.arcs[1].1 = sides.map(|_| Side::Right);
+
+            let mut repairs = RepairLog::default();
+            let geometry = build_polygon_from_pfe(
+                &parsed,
+                &[&reference],
+                &ENTITY,
+                ring::DEFAULT_TOLERANCE,
+                &mut repairs,
+            )
+            .unwrap()
+            .unwrap();
+            let Geometry::Polygon(polygon) = &geometry else {
+                panic!("expected a polygon: {:?}", geometry);
+            };
+            assert_eq!(polygon.exterior().0.len(), 6);
+            assert_eq!(polygon.interiors().len(), 1);
+            assert_eq!(polygon.interiors()[0].0.len(), 4);
+            assert!((geometry.unsigned_area() - 94.0).abs() < 1e-9);
+            assert!(valid::check(&geometry).is_ok());
+            assert!(repairs.is_empty(), "{:?}", repairs);
         }
     }
 }
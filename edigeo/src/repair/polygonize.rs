@@ -0,0 +1,486 @@
+//! Polygonisation d'un réseau d'arcs (graphe planaire)
+//!
+//! Les arcs sont découpés en segments entre sommets consécutifs; les sommets
+//! confondus (à la tolérance près) forment les nœuds du graphe. Un segment
+//! présent un nombre pair de fois (frontière entre deux faces d'une même
+//! entité) s'annule. Les segments pendants (culs-de-sac) sont retirés, puis
+//! chaque face est parcourue en gardant l'intérieur à gauche. Un parcours qui
+//! repasse par un nœud (trou qui touche son contour) est découpé en rings
+//! simples: ce sont les rings à assembler par `topology::organize_rings`.
+//!
+//! Les faces retenues sont celles de l'entité: si chaque arc est orienté avec
+//! l'entité à sa gauche, celles dont les segments la laissent à gauche; sinon,
+//! faute de mieux, les faces bornées (aire positive), dont les trous sont
+//! distingués par imbrication.
+
+use std::collections::{HashMap, HashSet};
+
+use geo::{Coord, LineString};
+
+/// Faces d'un graphe d'arcs et retouches nécessaires à leur construction
+#[derive(Debug)]
+pub struct Faces {
+    /// Rings simples et fermés des faces: contours dans le sens
+    /// trigonométrique, trous dans le sens horaire (arcs orientés seulement)
+    pub rings: Vec<LineString>,
+    /// Aire signée de chaque ring de `rings` mesurée sur les sommets d'origine
+    /// des arcs, avant leur fusion avec un nœud voisin (écarts refermés par
+    /// un segment)
+    pub raw_areas: Vec<f64>,
+    /// Sommets déplacés sur un nœud voisin (à la tolérance près)
+    pub merged: usize,
+    /// Segments annulés par parité (frontières internes de l'entité)
+    pub dissolved: usize,
+    /// Nœuds retirés avec les segments pendants
+    pub dangling: usize,
+    /// Rings des faces avant le retrait des segments pendants (vide sans
+    /// segment pendant)
+    pub with_dangles: Vec<LineString>,
+}
+
+/// Faces du graphe formé par les arcs
+///
+/// `oriented`: chaque arc laisse l'entité à sa gauche (côtés des faces connus).
+pub fn polygonize(arcs: &[Vec<Coord>], oriented: bool, tolerance: f64) -> Faces {
+    let mut graph = Graph::new(arcs, oriented, tolerance);
+    graph.sort();
+    let with_dangles = if graph.has_dangles() {
+        graph.faces().0
+    } else {
+        Vec::new()
+    };
+    let dangling = graph.remove_dangles();
+    let (rings, raw_areas) = graph.faces();
+    Faces {
+        rings,
+        raw_areas,
+        merged: graph.merged,
+        dissolved: graph.dissolved,
+        dangling,
+        with_dangles,
+    }
+}
+
+/// Graphe non orienté des segments
+struct Graph {
+    nodes: Vec<Coord>,
+    /// Sommets confondus avec un nœud de coordonnées différentes
+    merged: usize,
+    /// Segments annulés par parité
+    dissolved: usize,
+    /// Voisins de chaque nœud, triés par angle (voir `sort`)
+    adjacency: Vec<Vec<usize>>,
+    /// Sommets d'origine de chaque segment `(a, b)` avec `a < b`, de `a` vers `b`
+    originals: HashMap<(usize, usize), (Coord, Coord)>,
+    /// Segments orientés avec l'entité à gauche, `None` si les arcs ne sont
+    /// pas orientés
+    directed: Option<HashSet<(usize, usize)>>,
+}
+
+impl Graph {
+    fn new(arcs: &[Vec<Coord>], oriented: bool, tolerance: f64) -> Self {
+        let mut nodes = Nodes::new(tolerance);
+
+        // Parité des segments: ceux parcourus deux fois s'annulent. Le solde
+        // des sens de parcours donne le sens d'un segment restant.
+        let mut edges: HashMap<(usize, usize), (usize, i64)> = HashMap::new();
+        let mut originals = HashMap::new();
+        for arc in arcs {
+            let ids: Vec<usize> = arc.iter().map(|&c| nodes.insert(c)).collect();
+            for (w, c) in ids.windows(2).zip(arc.windows(2)) {
+                if w[0] != w[1] {
+                    let key = (w[0].min(w[1]), w[0].max(w[1]));
+                    let edge = edges.entry(key).or_default();
+                    edge.0 += 1;
+                    edge.1 += if w[0] < w[1] { 1 } else { -1 };
+                    originals.entry(key).or_insert(if w[0] < w[1] {
+                        (c[0], c[1])
+                    } else {
+                        (c[1], c[0])
+                    });
+                }
+            }
+        }
+        let Nodes {
+            coords: nodes,
+            merged,
+            ..
+        } = nodes;
+
+        let mut adjacency = vec![Vec::new(); nodes.len()];
+        let mut directed = HashSet::new();
+        let mut dissolved = 0;
+        for ((a, b), (count, balance)) in edges {
+            dissolved += count - count % 2;
+            if count % 2 == 0 {
+                continue;
+            }
+            adjacency[a].push(b);
+            adjacency[b].push(a);
+            directed.insert(if balance > 0 { (a, b) } else { (b, a) });
+        }
+        Self {
+            nodes,
+            merged,
+            dissolved,
+            adjacency,
+            originals,
+            directed: oriented.then_some(directed),
+        }
+    }
+
+    /// Trie les voisins de chaque nœud par angle
+    fn sort(&mut self) {
+        let nodes = &self.nodes;
+        for (n, neighbours) in self.adjacency.iter_mut().enumerate() {
+            let angle = |m: &usize| (nodes[*m].y - nodes[n].y).atan2(nodes[*m].x - nodes[n].x);
+            neighbours.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+        }
+    }
+
+    /// Au moins un nœud de degré 1
+    fn has_dangles(&self) -> bool {
+        self.adjacency.iter().any(|n| n.len() == 1)
+    }
+
+    /// Retire itérativement les segments dont une extrémité est de degré 1,
+    /// retourne le nombre de nœuds retirés (l'ordre des voisins est conservé)
+    fn remove_dangles(&mut self) -> usize {
+        let connected =
+            |adjacency: &[Vec<usize>]| adjacency.iter().filter(|n| !n.is_empty()).count();
+        let before = connected(&self.adjacency);
+        let mut stack: Vec<usize> = (0..self.nodes.len())
+            .filter(|&n| self.adjacency[n].len() == 1)
+            .collect();
+        while let Some(n) = stack.pop() {
+            let Some(&other) = self.adjacency[n].first() else {
+                continue;
+            };
+            self.adjacency[n].clear();
+            self.adjacency[other].retain(|&m| m != n);
+            if self.adjacency[other].len() == 1 {
+                stack.push(other);
+            }
+        }
+        before - connected(&self.adjacency)
+    }
+
+    /// Rings simples des faces de l'entité et leurs aires sur les sommets
+    /// d'origine (voisins triés)
+    fn faces(&self) -> (Vec<LineString>, Vec<f64>) {
+        let edge_count: usize = self.adjacency.iter().map(Vec::len).sum();
+        let mut visited: HashSet<(usize, usize)> = HashSet::new();
+        let mut walks = Vec::new();
+
+        for start in 0..self.nodes.len() {
+            for &first in &self.adjacency[start] {
+                if visited.contains(&(start, first)) {
+                    continue;
+                }
+                let mut walk = vec![start];
+                let (mut from, mut to) = (start, first);
+                // Chaque demi-segment n'appartient qu'à une face
+                while visited.insert((from, to)) && walk.len() <= edge_count {
+                    walk.push(to);
+                    let next = self.next(from, to);
+                    (from, to) = (to, next);
+                }
+                if walk.last() == Some(&start) {
+                    walks.push(walk);
+                }
+            }
+        }
+
+        // Côtés incohérents (aire totale non positive): repli sur les faces bornées
+        if let Some(directed) = &self.directed {
+            let faces = self.select(&walks, Some(directed));
+            if faces.0.iter().map(signed_area).sum::<f64>() > 0.0 {
+                return faces;
+            }
+        }
+        self.select(&walks, None)
+    }
+
+    /// Rings simples des parcours de faces de l'entité: celles qui la laissent
+    /// à gauche de la majorité de leurs segments orientés, ou à défaut les
+    /// faces bornées (aire positive)
+    fn select(
+        &self,
+        walks: &[Vec<usize>],
+        directed: Option<&HashSet<(usize, usize)>>,
+    ) -> (Vec<LineString>, Vec<f64>) {
+        let mut rings = Vec::new();
+        let mut raw_areas = Vec::new();
+        for walk in walks {
+            if let Some(directed) = directed {
+                let left = walk
+                    .windows(2)
+                    .filter(|w| directed.contains(&(w[0], w[1])))
+                    .count();
+                if 2 * left < walk.len() {
+                    continue;
+                }
+            }
+            for ring in split_walk(walk) {
+                let nodes = &ring;
+                let ring = LineString::new(nodes.iter().map(|&n| self.nodes[n]).collect());
+                let area = signed_area(&ring);
+                if area > 0.0 || (area < 0.0 && directed.is_some()) {
+                    rings.push(ring);
+                    raw_areas.push(if self.merged > 0 {
+                        self.raw_area(nodes)
+                    } else {
+                        area
+                    });
+                }
+            }
+        }
+        (rings, raw_areas)
+    }
+
+    /// Aire signée d'un ring de nœuds, mesurée sur les sommets d'origine de
+    /// ses segments: l'écart entre deux segments consécutifs est refermé
+    fn raw_area(&self, ring: &[usize]) -> f64 {
+        let mut points: Vec<Coord> = Vec::with_capacity(2 * ring.len());
+        for w in ring.windows(2) {
+            let (start, end) = if w[0] < w[1] {
+                self.originals[&(w[0], w[1])]
+            } else {
+                let (a, b) = self.originals[&(w[1], w[0])];
+                (b, a)
+            };
+            if points.last() != Some(&start) {
+                points.push(start);
+            }
+            points.push(end);
+        }
+        points.push(points[0]);
+        signed_area(&LineString::new(points))
+    }
+
+    /// Nœud suivant sur la face à gauche du demi-segment `from → to`:
+    /// premier voisin de `to` dans le sens horaire après `from`
+    fn next(&self, from: usize, to: usize) -> usize {
+        let neighbours = &self.adjacency[to];
+        let back = neighbours
+            .iter()
+            .position(|&n| n == from)
+            .expect("symmetric adjacency");
+        neighbours[(back + neighbours.len() - 1) % neighbours.len()]
+    }
+}
+
+/// Découpe un parcours fermé en rings simples, à chaque nœud repris
+///
+/// Un nœud déjà présent dans le parcours en cours ferme la boucle parcourue
+/// depuis son premier passage; le parcours reprend à ce nœud. Les boucles de
+/// moins de trois segments (aller-retour) sont ignorées.
+fn split_walk(walk: &[usize]) -> Vec<Vec<usize>> {
+    let mut rings = Vec::new();
+    let mut stack: Vec<usize> = Vec::with_capacity(walk.len());
+    let mut position: HashMap<usize, usize> = HashMap::new();
+    for &node in walk {
+        if let Some(&at) = position.get(&node) {
+            let mut ring: Vec<usize> = stack.drain(at + 1..).collect();
+            for n in &ring {
+                position.remove(n);
+            }
+            ring.insert(0, node);
+            ring.push(node);
+            if ring.len() >= 4 {
+                rings.push(ring);
+            }
+            continue;
+        }
+        position.insert(node, stack.len());
+        stack.push(node);
+    }
+    rings
+}
+
+/// Nœuds du graphe: un sommet à moins de `tolerance` d'un nœud existant
+/// (sur chaque axe) est confondu avec lui
+struct Nodes {
+    tolerance: f64,
+    coords: Vec<Coord>,
+    /// Sommets confondus avec un nœud de coordonnées différentes
+    merged: usize,
+    /// Nœuds par case de `tolerance` de côté
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl Nodes {
+    fn new(tolerance: f64) -> Self {
+        Self {
+            tolerance,
+            coords: Vec::new(),
+            merged: 0,
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Index du nœud confondu avec `c`, créé au besoin
+    fn insert(&mut self, c: Coord) -> usize {
+        let cell = (
+            (c.x / self.tolerance).floor() as i64,
+            (c.y / self.tolerance).floor() as i64,
+        );
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let Some(ids) = self.cells.get(&(cell.0 + dx, cell.1 + dy)) else {
+                    continue;
+                };
+                if let Some(&id) = ids.iter().find(|&&id| {
+                    let n = self.coords[id];
+                    (n.x - c.x).abs() < self.tolerance && (n.y - c.y).abs() < self.tolerance
+                }) {
+                    if self.coords[id] != c {
+                        self.merged += 1;
+                    }
+                    return id;
+                }
+            }
+        }
+        self.coords.push(c);
+        let id = self.coords.len() - 1;
+        self.cells.entry(cell).or_default().push(id);
+        id
+    }
+}
+
+/// Aire signée d'un ring fermé (positive dans le sens trigonométrique)
+fn signed_area(ring: &LineString) -> f64 {
+    ring.lines()
+        .map(|l| l.start.x * l.end.y - l.end.x * l.start.y)
+        .sum::<f64>()
+        / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arc(points: &[(f64, f64)]) -> Vec<Coord> {
+        points.iter().map(|&(x, y)| Coord { x, y }).collect()
+    }
+
+    #[test]
+    fn test_polygonize_faces() {
+        // Carré coupé par une diagonale, avec un cul-de-sac
+        let arcs = vec![
+            arc(&[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0)]),
+            arc(&[(2.0, 2.0), (0.0, 2.0), (0.0, 0.0)]),
+            arc(&[(0.0, 0.0), (2.0, 2.0)]),
+            arc(&[(2.0, 0.0), (3.0, -1.0), (4.0, -1.0)]),
+        ];
+        let faces = polygonize(&arcs, false, 1e-6);
+        // Cul-de-sac: deux nœuds retirés, faces inchangées
+        assert_eq!((faces.dangling, faces.merged, faces.dissolved), (2, 0, 0));
+        assert_eq!(faces.with_dangles, faces.rings);
+        assert_eq!(faces.raw_areas, vec![2.0, 2.0]);
+        let rings = faces.rings;
+
+        assert_eq!(rings.len(), 2);
+        for ring in &rings {
+            assert!(ring.is_closed());
+            assert_eq!(ring.0.len(), 4);
+            assert!((signed_area(ring) - 2.0).abs() < 1e-9);
+        }
+
+        // Diagonale en double: frontière interne annulée
+        let mut arcs = arcs;
+        arcs.push(arc(&[(2.0, 2.0), (0.0, 0.0)]));
+        let faces = polygonize(&arcs, false, 1e-6);
+        assert_eq!(faces.dissolved, 2);
+        let rings = faces.rings;
+        assert_eq!(rings.len(), 1);
+        assert!((signed_area(&rings[0]) - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_polygonize_raw_areas() {
+        // Sommet (2, 2) décalé sur l'un des deux arcs: aire mesurée sur le
+        // contour d'origine, écart refermé
+        let arcs = vec![
+            arc(&[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0)]),
+            arc(&[(2.0, 2.001), (0.0, 2.0), (0.0, 0.0)]),
+        ];
+        let faces = polygonize(&arcs, false, 1e-2);
+        assert_eq!(faces.merged, 1);
+        assert_eq!(faces.rings.len(), 1);
+        assert!((signed_area(&faces.rings[0]) - 4.0).abs() < 1e-12);
+        // Triangle (0, 2), (2, 2), (2, 2.001) en plus
+        assert!((faces.raw_areas[0] - 4.001).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_polygonize_hole_and_open_chain() {
+        let arcs = vec![
+            arc(&[
+                (0.0, 0.0),
+                (10.0, 0.0),
+                (10.0, 10.0),
+                (0.0, 10.0),
+                (0.0, 0.0),
+            ]),
+            arc(&[(2.0, 2.0), (2.0, 4.0), (4.0, 4.0), (4.0, 2.0), (2.0, 2.0)]),
+            // Chaîne ouverte: aucun polygone inventé
+            arc(&[(20.0, 0.0), (30.0, 0.0), (30.0, 10.0)]),
+        ];
+        let mut rings = polygonize(&arcs, false, 1e-6).rings;
+        rings.sort_by(|a, b| signed_area(b).total_cmp(&signed_area(a)));
+
+        let areas: Vec<f64> = rings.iter().map(signed_area).collect();
+        assert_eq!(areas, vec![100.0, 4.0]);
+    }
+
+    #[test]
+    fn test_polygonize_hole_touching_shell() {
+        // Carré 10 × 10 et trou triangulaire qui touche le contour en (5, 0):
+        // le parcours de la face repasse par ce nœud et est découpé
+        let arcs = vec![
+            arc(&[
+                (0.0, 0.0),
+                (5.0, 0.0),
+                (10.0, 0.0),
+                (10.0, 10.0),
+                (0.0, 10.0),
+                (0.0, 0.0),
+            ]),
+            arc(&[(5.0, 0.0), (3.0, 3.0), (7.0, 3.0), (5.0, 0.0)]),
+        ];
+
+        // Sans orientation: contour et intérieur du trou, distingués ensuite
+        // par imbrication
+        let mut rings = polygonize(&arcs, false, 1e-6).rings;
+        rings.sort_by(|a, b| signed_area(b).total_cmp(&signed_area(a)));
+        let shape: Vec<(usize, f64)> = rings.iter().map(|r| (r.0.len(), signed_area(r))).collect();
+        assert_eq!(shape, vec![(6, 100.0), (4, 6.0)]);
+
+        // Arcs orientés avec l'entité à gauche: l'intérieur du trou est écarté,
+        // le trou est rendu dans le sens horaire
+        let mut rings = polygonize(&arcs, true, 1e-6).rings;
+        rings.sort_by(|a, b| signed_area(b).total_cmp(&signed_area(a)));
+        let shape: Vec<(usize, f64)> = rings.iter().map(|r| (r.0.len(), signed_area(r))).collect();
+        assert_eq!(shape, vec![(6, 100.0), (4, -6.0)]);
+
+        // Côtés inversés (aire totale négative): repli sur les faces bornées
+        let reversed: Vec<Vec<Coord>> = arcs
+            .iter()
+            .map(|a| a.iter().rev().copied().collect())
+            .collect();
+        let mut rings = polygonize(&reversed, true, 1e-6).rings;
+        rings.sort_by(|a, b| signed_area(b).total_cmp(&signed_area(a)));
+        let areas: Vec<f64> = rings.iter().map(signed_area).collect();
+        assert_eq!(areas, vec![100.0, 6.0]);
+    }
+
+    #[test]
+    fn test_split_walk() {
+        // Contour 0-1-2-3 repassant par 1 pour le trou 1-4-5
+        let rings = split_walk(&[0, 1, 4, 5, 1, 2, 3, 0]);
+        assert_eq!(rings, vec![vec![1, 4, 5, 1], vec![0, 1, 2, 3, 0]]);
+        // Aller-retour ignoré
+        assert_eq!(split_walk(&[0, 1, 2, 1, 3, 0]), vec![vec![0, 1, 3, 0]]);
+    }
+}
@@ -0,0 +1,514 @@
+//! Arrondi topologique (snap rounding) du réseau d'arcs
+//!
+//! Arrondir chaque sommet d'un polygone indépendamment déplace ses segments:
+//! deux arcs voisins peuvent alors se croiser, et une frontière commune n'est
+//! plus garantie identique dans les deux polygones. L'arrondi est donc fait
+//! une seule fois, sur les arcs (PAR), avant l'assemblage des faces: les faces
+//! désignent leurs arcs et voient donc la même géométrie arrondie.
+//!
+//! Algorithme de Hobby: les pixels de la grille qui contiennent un sommet ou
+//! une intersection de deux segments sont « chauds »; chaque segment est
+//! remplacé par la suite des centres des pixels chauds qu'il traverse.
+//! L'arrondi ne crée donc aucune intersection, même entre des arcs qui se
+//! croisent ailleurs qu'en leurs extrémités (données reprojetées ou fautives).
+
+use std::collections::HashMap;
+
+use crate::parser::vec::{Id, ParsedVec};
+use crate::types::Projection;
+
+/// Transformation des sommets appliquée avant l'arrondi (ex: reprojection)
+pub trait CoordTransform {
+    /// Transforme un sommet exprimé dans `source`, `None` s'il est hors du domaine
+    fn transform(&self, source: &Projection, x: f64, y: f64) -> Option<(f64, f64)>;
+}
+
+/// Arrondi topologique des coordonnées d'un lot
+#[derive(Clone, Copy)]
+pub struct SnapRounding<'a> {
+    /// Nombre de décimales conservées
+    pub decimals: u8,
+
+    /// Transformation des sommets avant l'arrondi: les features sont alors
+    /// produites dans le système cible de la transformation
+    pub transform: Option<&'a dyn CoordTransform>,
+}
+
+/// Pixel de la grille: coordonnées multipliées par 10^décimales, arrondies
+type Pixel = (i64, i64);
+
+impl SnapRounding<'_> {
+    /// Arrondit les arcs et les nœuds d'un VEC
+    ///
+    /// Les arcs et nœuds dont un sommet ne peut pas être transformé sont
+    /// retirés du VEC; leurs identifiants sont retournés (triés) pour écarter
+    /// les entités qui les utilisent (voir `repair::reject_primitives`). Les
+    /// coordonnées transformées d'avant l'arrondi des primitives déplacées
+    /// sont conservées dans `parsed.unsnapped`.
+    pub fn apply(&self, parsed: &mut ParsedVec, projection: &Projection) -> Vec<Id> {
+        let factor = 10_f64.powi(i32::from(self.decimals));
+
+        // Coordonnées en unités de pixel, `false` si un sommet est hors du domaine
+        let to_grid = |coords: &mut Vec<(f64, f64)>| {
+            coords.iter_mut().all(|(x, y)| {
+                let transformed = match self.transform {
+                    Some(t) => t.transform(projection, *x, *y),
+                    None => Some((*x, *y)),
+                };
+                let Some((tx, ty)) = transformed else {
+                    return false;
+                };
+                (*x, *y) = (tx * factor, ty * factor);
+                true
+            })
+        };
+        let mut removed: Vec<Id> = Vec::new();
+        parsed.par.retain(|&id, arc| {
+            let ok = to_grid(&mut arc.coords);
+            if !ok {
+                removed.push(id);
+            }
+            ok
+        });
+        parsed.pno.retain(|&id, pno| {
+            let ok = to_grid(&mut pno.coords);
+            if !ok {
+                removed.push(id);
+            }
+            ok
+        });
+        removed.sort_unstable();
+
+        let hot = HotPixels::new(parsed.par.values().map(|arc| arc.coords.as_slice()));
+        let center = |(i, j): Pixel| (i as f64 / factor, j as f64 / factor);
+
+        let unscale = |coords: &[(f64, f64)]| -> Vec<(f64, f64)> {
+            coords
+                .iter()
+                .map(|&(x, y)| (x / factor, y / factor))
+                .collect()
+        };
+        for (&id, arc) in parsed.par.iter_mut() {
+            let pixels = hot.snap(&arc.coords);
+            // Arc réduit à un pixel: ses extrémités sont confondues
+            let snapped: Vec<(f64, f64)> = if pixels.len() < 2 {
+                Vec::new()
+            } else {
+                pixels.into_iter().map(center).collect()
+            };
+            let original = unscale(&arc.coords);
+            if snapped != original {
+                parsed.unsnapped.insert(id, original);
+            }
+            arc.coords = snapped;
+        }
+        for (&id, pno) in parsed.pno.iter_mut() {
+            let original = unscale(&pno.coords);
+            for c in pno.coords.iter_mut() {
+                *c = center(pixel(*c));
+            }
+            if pno.coords != original {
+                parsed.unsnapped.insert(id, original);
+            }
+        }
+
+        removed
+    }
+}
+
+/// Pixel contenant un point (unités de pixel)
+fn pixel((x, y): (f64, f64)) -> Pixel {
+    (x.round() as i64, y.round() as i64)
+}
+
+/// Pixels chauds, indexés par cases de `bucket` pixels de côté
+struct HotPixels {
+    bucket: i64,
+    buckets: HashMap<Pixel, Vec<Pixel>>,
+}
+
+impl HotPixels {
+    fn new<'a>(arcs: impl Iterator<Item = &'a [(f64, f64)]> + Clone) -> Self {
+        // Cases de la taille d'un segment médian: un segment couvre peu de cases
+        let mut lengths: Vec<f64> = arcs
+            .clone()
+            .flat_map(|coords| coords.windows(2))
+            .map(|w| (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1))
+            .collect();
+        let bucket = if lengths.is_empty() {
+            1
+        } else {
+            let mid = lengths.len() / 2;
+            let (_, median, _) = lengths.select_nth_unstable_by(mid, f64::total_cmp);
+            (median.ceil() as i64).max(1)
+        };
+
+        let mut hot = Self {
+            bucket,
+            buckets: HashMap::new(),
+        };
+        for &c in arcs.clone().flatten() {
+            hot.insert(pixel(c));
+        }
+        for c in intersections(arcs, bucket) {
+            hot.insert(pixel(c));
+        }
+        hot
+    }
+
+    fn insert(&mut self, p: Pixel) {
+        let cell = self
+            .buckets
+            .entry((p.0.div_euclid(self.bucket), p.1.div_euclid(self.bucket)))
+            .or_default();
+        if !cell.contains(&p) {
+            cell.push(p);
+        }
+    }
+
+    /// Pixels chauds traversés par une polyligne, dans l'ordre de parcours
+    fn snap(&self, coords: &[(f64, f64)]) -> Vec<Pixel> {
+        let mut out: Vec<Pixel> = Vec::with_capacity(coords.len());
+        let mut push = |p: Pixel| {
+            if out.last() == Some(&p) {
+                return;
+            }
+            // Aller-retour A → B → A: l'arrondi a replié le segment sur lui-même
+            if out.len() >= 2 && out[out.len() - 2] == p {
+                out.pop();
+                return;
+            }
+            out.push(p);
+        };
+
+        match coords {
+            [] => {}
+            [only] => push(pixel(*only)),
+            _ => {
+                for w in coords.windows(2) {
+                    for p in self.crossed(w[0], w[1]) {
+                        push(p);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Pixels chauds traversés par un segment, triés par abscisse curviligne
+    ///
+    /// Seules les cases traversées par le segment sont examinées.
+    fn crossed(&self, a: (f64, f64), b: (f64, f64)) -> Vec<Pixel> {
+        let mut hits: Vec<(f64, f64, Pixel)> = Vec::new();
+        for cell in cells(a, b, self.bucket) {
+            let Some(pixels) = self.buckets.get(&cell) else {
+                continue;
+            };
+            for &p in pixels {
+                if let Some((enter, exit)) = clip(a, b, p) {
+                    hits.push((enter, exit, p));
+                }
+            }
+        }
+
+        hits.sort_by(|x, y| x.0.total_cmp(&y.0).then(x.1.total_cmp(&y.1)));
+        hits.into_iter().map(|(_, _, p)| p).collect()
+    }
+}
+
+/// Cases de `bucket` pixels de côté touchées par le segment `a → b`
+///
+/// La case `k` regroupe les pixels de centres `k * bucket .. (k + 1) * bucket`:
+/// elle couvre l'intervalle fermé `[k * bucket - 0.5, (k + 1) * bucket - 0.5]`,
+/// qui contient ses pixels. Le segment est parcouru colonne par colonne; une
+/// case dont il ne fait que toucher le bord est retenue.
+fn cells(a: (f64, f64), b: (f64, f64), bucket: i64) -> Vec<Pixel> {
+    let size = bucket as f64;
+    // Cases dont l'intervalle fermé rencontre [low, high]
+    let span = |low: f64, high: f64| {
+        let first = ((low + 0.5) / size).ceil() as i64 - 1;
+        let last = ((high + 0.5) / size).floor() as i64;
+        first..=last
+    };
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+
+    let mut out = Vec::new();
+    for bx in span(a.0.min(b.0), a.0.max(b.0)) {
+        // Portion du segment dans la colonne
+        let (y0, y1) = if dx == 0.0 {
+            (a.1, b.1)
+        } else {
+            let left = (bx as f64 * size - 0.5).max(a.0.min(b.0));
+            let right = ((bx + 1) as f64 * size - 0.5).min(a.0.max(b.0));
+            let at = |x: f64| a.1 + (x - a.0) / dx * dy;
+            (at(left), at(right))
+        };
+        for by in span(y0.min(y1), y0.max(y1)) {
+            out.push((bx, by));
+        }
+    }
+    out
+}
+
+/// Points d'intersection des segments du réseau, hors extrémités communes
+///
+/// Les segments sont indexés par les cases qu'ils traversent: seuls les
+/// segments d'une même case sont comparés.
+fn intersections<'a>(arcs: impl Iterator<Item = &'a [(f64, f64)]>, bucket: i64) -> Vec<(f64, f64)> {
+    let segments: Vec<((f64, f64), (f64, f64))> = arcs
+        .flat_map(|coords| coords.windows(2).map(|w| (w[0], w[1])))
+        .collect();
+    let mut index: HashMap<Pixel, Vec<usize>> = HashMap::new();
+    for (i, &(a, b)) in segments.iter().enumerate() {
+        for cell in cells(a, b, bucket) {
+            index.entry(cell).or_default().push(i);
+        }
+    }
+
+    let mut out = Vec::new();
+    for members in index.values() {
+        for (k, &i) in members.iter().enumerate() {
+            for &j in &members[k + 1..] {
+                if let Some(p) = intersection(segments[i], segments[j]) {
+                    out.push(p);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Intersection de deux segments, `None` s'ils sont disjoints, parallèles ou
+/// ne se touchent qu'en une extrémité commune (sommet déjà chaud)
+///
+/// Les recouvrements colinéaires sont délimités par des sommets, déjà chauds.
+fn intersection(
+    (p, p2): ((f64, f64), (f64, f64)),
+    (q, q2): ((f64, f64), (f64, f64)),
+) -> Option<(f64, f64)> {
+    let r = (p2.0 - p.0, p2.1 - p.1);
+    let s = (q2.0 - q.0, q2.1 - q.1);
+    let denom = r.0 * s.1 - r.1 * s.0;
+    if denom == 0.0 {
+        return None;
+    }
+    let qp = (q.0 - p.0, q.1 - p.1);
+    let t = (qp.0 * s.1 - qp.1 * s.0) / denom;
+    let u = (qp.0 * r.1 - qp.1 * r.0) / denom;
+    if !(0.0..=1.0).contains(&t) || !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    // Extrémités communes: déjà chaudes
+    if (t == 0.0 || t == 1.0) && (u == 0.0 || u == 1.0) {
+        return None;
+    }
+    Some((p.0 + t * r.0, p.1 + t * r.1))
+}
+
+/// Intervalle de paramètres `[entrée, sortie]` du segment `a → b` dans le
+/// pixel `p` (Liang-Barsky), `None` s'il ne le traverse pas
+fn clip(a: (f64, f64), b: (f64, f64), p: Pixel) -> Option<(f64, f64)> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (px, py) = (p.0 as f64, p.1 as f64);
+    let (mut t0, mut t1) = (0.0_f64, 1.0_f64);
+
+    for (delta, low, high) in [
+        (dx, px - 0.5 - a.0, px + 0.5 - a.0),
+        (dy, py - 0.5 - a.1, py + 0.5 - a.1),
+    ] {
+        if delta == 0.0 {
+            if low > 0.0 || high < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let (u, v) = (low / delta, high / delta);
+        let (enter, exit) = if u < v { (u, v) } else { (v, u) };
+        t0 = t0.max(enter);
+        t1 = t1.min(exit);
+        if t0 > t1 {
+            return None;
+        }
+    }
+    Some((t0, t1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::vec::{Arc, Face};
+
+    fn network(arcs: &[(&'static str, &[(f64, f64)])]) -> ParsedVec<'static> {
+        let mut parsed = ParsedVec::default();
+        for (name, coords) in arcs {
+            let id = parsed.ids.intern(name);
+            parsed.par.insert(
+                id,
+                Arc {
+                    id,
+                    coords: coords.to_vec(),
+                    scp: None,
+                },
+            );
+        }
+        parsed
+    }
+
+    fn coords<'p>(parsed: &'p ParsedVec, name: &str) -> &'p [(f64, f64)] {
+        &parsed.par[&parsed.ids.get(name).unwrap()].coords
+    }
+
+    #[test]
+    fn test_snap_inserts_hot_pixels() {
+        // Le sommet (1.004, 0.004) de l'arc B passe à 4 mm de l'arc A: arrondi
+        // seul, il tomberait sur A; arrondi topologique, A passe par lui
+        let mut parsed = network(&[
+            ("A", &[(0.0, 0.0), (2.0, 0.0)]),
+            ("B", &[(1.004, 0.004), (1.0, 1.0)]),
+        ]);
+        let snap = SnapRounding {
+            decimals: 2,
+            transform: None,
+        };
+        snap.apply(&mut parsed, &Projection::default());
+
+        assert_eq!(coords(&parsed, "A"), [(0.0, 0.0), (1.0, 0.0), (2.0, 0.0)]);
+        assert_eq!(coords(&parsed, "B"), [(1.0, 0.0), (1.0, 1.0)]);
+    }
+
+    #[test]
+    fn test_snap_shared_arc_in_faces() {
+        // Deux faces voisines partagent l'arc C: même géométrie des deux côtés
+        let mut parsed = network(&[("C", &[(1.00049, 0.0), (1.00051, 0.50049), (1.0, 1.0)])]);
+        let shared = parsed.ids.get("C").unwrap();
+        for name in ["F1", "F2"] {
+            let id = parsed.ids.intern(name);
+            parsed.pfe.insert(
+                id,
+                Face {
+                    id,
+                    scp: None,
+                    arcs: vec![(shared, None)],
+                },
+            );
+        }
+        let snap = SnapRounding {
+            decimals: 3,
+            transform: None,
+        };
+        snap.apply(&mut parsed, &Projection::default());
+
+        let expected = [(1.0, 0.0), (1.001, 0.5), (1.0, 1.0)];
+        assert_eq!(coords(&parsed, "C"), expected);
+        for face in parsed.pfe.values() {
+            assert_eq!(parsed.par[&face.arcs[0].0].coords, expected);
+        }
+    }
+
+    #[test]
+    fn test_snap_collapsed_arc() {
+        let mut parsed = network(&[("A", &[(0.001, 0.0), (0.0, 0.002), (0.003, 0.0)])]);
+        let snap = SnapRounding {
+            decimals: 1,
+            transform: None,
+        };
+        snap.apply(&mut parsed, &Projection::default());
+        assert!(coords(&parsed, "A").is_empty());
+    }
+
+    struct Shift;
+
+    impl CoordTransform for Shift {
+        fn transform(&self, source: &Projection, x: f64, y: f64) -> Option<(f64, f64)> {
+            (source.epsg == 2154 && x >= 0.0).then_some((x + 0.25, y))
+        }
+    }
+
+    #[test]
+    fn test_snap_transform() {
+        let mut parsed = network(&[("A", &[(0.0, 0.0), (1.0, 1.0)])]);
+        let snap = SnapRounding {
+            decimals: 1,
+            transform: Some(&Shift),
+        };
+        snap.apply(&mut parsed, &Projection::default());
+        assert_eq!(coords(&parsed, "A"), [(0.3, 0.0), (1.3, 1.0)]);
+
+        // Sommet hors du domaine: seul l'arc concerné est retiré
+        let mut parsed = network(&[
+            ("A", &[(-1.0, 0.0), (1.0, 1.0)]),
+            ("B", &[(0.0, 0.0), (1.0, 0.0)]),
+        ]);
+        let removed = snap.apply(&mut parsed, &Projection::default());
+        assert_eq!(removed, vec![parsed.ids.get("A").unwrap()]);
+        assert!(!parsed.par.contains_key(&parsed.ids.get("A").unwrap()));
+        assert_eq!(coords(&parsed, "B"), [(0.3, 0.0), (1.3, 0.0)]);
+    }
+
+    #[test]
+    fn test_snap_crossing_arcs() {
+        // Arcs qui se croisent hors de leurs sommets, en (0.26, 0.24): le pixel
+        // de l'intersection est chaud, les deux arcs y passent
+        let mut parsed = network(&[
+            ("A", &[(0.02, 0.0), (0.52, 0.5)]),
+            ("B", &[(0.0, 0.5), (0.5, 0.0)]),
+        ]);
+        let snap = SnapRounding {
+            decimals: 1,
+            transform: None,
+        };
+        snap.apply(&mut parsed, &Projection::default());
+
+        assert_eq!(coords(&parsed, "A"), [(0.0, 0.0), (0.3, 0.2), (0.5, 0.5)]);
+        assert_eq!(coords(&parsed, "B"), [(0.0, 0.5), (0.3, 0.2), (0.5, 0.0)]);
+
+        // Coordonnées d'origine conservées pour le journal des retouches
+        let original = &parsed.unsnapped[&parsed.ids.get("A").unwrap()];
+        assert_eq!(original, &[(0.02, 0.0), (0.52, 0.5)]);
+        assert_eq!(
+            parsed.unsnapped[&parsed.ids.get("B").unwrap()],
+            [(0.0, 0.5), (0.5, 0.0)]
+        );
+    }
+
+    #[test]
+    fn test_crossed_matches_full_scan() {
+        // Pixels chauds sur une grille régulière, cases de 3 pixels: le
+        // parcours des cases traversées retrouve tous les pixels d'un balayage
+        // complet, y compris pour des segments posés sur les bords des cases
+        let vertices: Vec<(f64, f64)> = (-6..=6)
+            .flat_map(|i| (-6..=6).map(move |j| (f64::from(i) * 2.0, f64::from(j) * 2.0)))
+            .collect();
+        let hot = HotPixels {
+            bucket: 3,
+            buckets: HashMap::new(),
+        };
+        let hot = vertices.iter().fold(hot, |mut hot, &c| {
+            hot.insert(pixel(c));
+            hot
+        });
+
+        let segments = [
+            ((-9.0, -7.3), (11.2, 8.9)),
+            ((-12.0, 2.5), (12.0, 2.5)),
+            ((2.5, -12.0), (2.5, 12.0)),
+            ((-0.5, -0.5), (5.5, 5.5)),
+            ((4.0, 4.0), (4.0, 4.0)),
+            ((7.9, -11.1), (-3.3, 6.6)),
+        ];
+        for (a, b) in segments {
+            let mut walked = hot.crossed(a, b);
+            let mut scanned: Vec<Pixel> = hot
+                .buckets
+                .values()
+                .flatten()
+                .copied()
+                .filter(|&p| clip(a, b, p).is_some())
+                .collect();
+            walked.sort_unstable();
+            walked.dedup();
+            scanned.sort_unstable();
+            assert_eq!(walked, scanned, "{:?} → {:?}", a, b);
+        }
+    }
+}
@@ -1,65 +1,174 @@
 //! Gestion de la topologie (trous, multipolygones)
 
-use geo::{Contains, Coord, LineString, Point, Polygon};
+use std::collections::HashMap;
+
+use geo::coordinate_position::{coord_pos_relative_to_ring, CoordPos};
+use geo::{BoundingRect, Coord, LineString, Polygon, Rect};
 
 /// Organise les rings en polygones avec trous
+///
+/// Les rings ne se croisent pas: chacun est imbriqué dans ceux qui le
+/// contiennent. Un ring contenu dans un nombre pair de rings est un contour,
+/// dans un nombre impair un trou du contour qui l'enclôt directement (ex:
+/// enclave dans une cour: contour, trou, contour). Les polygones sont rendus
+/// dans l'ordre de leurs contours.
 pub fn organize_rings(rings: Vec<LineString>) -> Vec<Polygon> {
-    if rings.is_empty() {
-        return Vec::new();
+    let layout = nest_rings(&rings);
+    into_polygons(rings, layout)
+}
+
+/// Polygones d'une organisation des rings calculée par `nest_rings`
+pub fn into_polygons(rings: Vec<LineString>, layout: Vec<(usize, Vec<usize>)>) -> Vec<Polygon> {
+    let mut rings: Vec<Option<LineString>> = rings.into_iter().map(Some).collect();
+    layout
+        .into_iter()
+        .map(|(shell, holes)| {
+            let exterior = rings[shell].take().expect("shell taken once");
+            let interiors = holes
+                .into_iter()
+                .map(|h| rings[h].take().expect("hole taken once"))
+                .collect();
+            Polygon::new(exterior, interiors)
+        })
+        .collect()
+}
+
+/// Polygones formés par les rings (voir `organize_rings`): index du contour
+/// et de ses trous, dans l'ordre des contours
+pub fn nest_rings(rings: &[LineString]) -> Vec<(usize, Vec<usize>)> {
+    if rings.len() <= 1 {
+        return (0..rings.len()).map(|i| (i, Vec::new())).collect();
     }
 
-    if rings.len() == 1 {
-        return vec![Polygon::new(rings.into_iter().next().unwrap(), vec![])];
+    let bounds: Vec<Rect> = rings
+        .iter()
+        .map(|ring| {
+            ring.bounding_rect()
+                .unwrap_or_else(|| Rect::new(Coord::zero(), Coord::zero()))
+        })
+        .collect();
+    let areas: Vec<f64> = rings.iter().map(|ring| ring_area(ring).abs()).collect();
+    let index = RingIndex::new(&bounds);
+
+    // Aires décroissantes: un ring est traité après tous ceux qui l'enclosent
+    let mut order: Vec<usize> = (0..rings.len()).collect();
+    order.sort_by(|&a, &b| areas[b].total_cmp(&areas[a]).then(a.cmp(&b)));
+    let mut rank = vec![0; rings.len()];
+    for (r, &i) in order.iter().enumerate() {
+        rank[i] = r;
     }
 
-    // Déterminer quels rings sont contenus dans d'autres
-    let mut outer_indices: Vec<usize> = Vec::new();
-    let mut inner_map: std::collections::HashMap<usize, Vec<usize>> =
-        std::collections::HashMap::new();
-    let mut assigned: std::collections::HashSet<usize> = std::collections::HashSet::new();
+    let mut parent: Vec<Option<usize>> = vec![None; rings.len()];
+    let mut depth = vec![0usize; rings.len()];
+    for &i in &order {
+        let Some(probe) = rings[i].0.first() else {
+            continue;
+        };
+        // Ring englobant le plus proche: le plus petit qui contient `i`
+        parent[i] = index
+            .candidates(*probe)
+            .filter(|&j| rank[j] < rank[i] && contains_rect(&bounds[j], &bounds[i]))
+            .filter(|&j| encloses(&rings[j], &rings[i]))
+            .max_by_key(|&j| rank[j]);
+        depth[i] = parent[i].map_or(0, |p| depth[p] + 1);
+    }
 
+    let mut holes: HashMap<usize, Vec<usize>> = HashMap::new();
     for i in 0..rings.len() {
-        let mut is_inner = false;
+        if let (1, Some(shell)) = (depth[i] % 2, parent[i]) {
+            holes.entry(shell).or_default().push(i);
+        }
+    }
 
-        for j in 0..rings.len() {
-            if i == j {
-                continue;
-            }
+    (0..rings.len())
+        .filter(|&i| depth[i] % 2 == 0)
+        .map(|i| (i, holes.remove(&i).unwrap_or_default()))
+        .collect()
+}
 
-            // Vérifier si le premier point de rings[i] est dans rings[j]
-            if let Some(first_coord) = rings[i].0.first() {
-                let point = Point::new(first_coord.x, first_coord.y);
-                let poly = Polygon::new(rings[j].clone(), vec![]);
-
-                if poly.contains(&point) {
-                    // rings[i] est à l'intérieur de rings[j]
-                    if !assigned.contains(&i) {
-                        inner_map.entry(j).or_default().push(i);
-                        assigned.insert(i);
-                        is_inner = true;
-                    }
-                    break;
+/// Index des rings par cases d'une grille couvrant leur emprise
+///
+/// Un ring est inscrit dans toutes les cases que couvre son emprise: les rings
+/// qui contiennent un point sont parmi ceux de sa case.
+struct RingIndex {
+    origin: Coord,
+    cell: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl RingIndex {
+    fn new(bounds: &[Rect]) -> Self {
+        let (mut min, mut max) = (bounds[0].min(), bounds[0].max());
+        for b in bounds {
+            min = Coord {
+                x: min.x.min(b.min().x),
+                y: min.y.min(b.min().y),
+            };
+            max = Coord {
+                x: max.x.max(b.max().x),
+                y: max.y.max(b.max().y),
+            };
+        }
+        // Environ une case par ring
+        let side = (bounds.len() as f64).sqrt().ceil();
+        let cell = ((max.x - min.x).max(max.y - min.y) / side).max(f64::MIN_POSITIVE);
+
+        let mut index = Self {
+            origin: min,
+            cell,
+            cells: HashMap::new(),
+        };
+        for (i, b) in bounds.iter().enumerate() {
+            let (x0, y0) = index.key(b.min());
+            let (x1, y1) = index.key(b.max());
+            for x in x0..=x1 {
+                for y in y0..=y1 {
+                    index.cells.entry((x, y)).or_default().push(i);
                 }
             }
         }
+        index
+    }
 
-        if !is_inner && !assigned.contains(&i) {
-            outer_indices.push(i);
-        }
+    fn key(&self, c: Coord) -> (i64, i64) {
+        (
+            ((c.x - self.origin.x) / self.cell).floor() as i64,
+            ((c.y - self.origin.y) / self.cell).floor() as i64,
+        )
     }
 
-    // Construire les polygones
-    outer_indices
-        .into_iter()
-        .map(|outer_idx| {
-            let outer = rings[outer_idx].clone();
-            let holes: Vec<LineString> = inner_map
-                .get(&outer_idx)
-                .map(|inners| inners.iter().map(|&i| rings[i].clone()).collect())
-                .unwrap_or_default();
-            Polygon::new(outer, holes)
-        })
-        .collect()
+    /// Rings dont l'emprise peut contenir `c`
+    fn candidates(&self, c: Coord) -> impl Iterator<Item = usize> + '_ {
+        self.cells.get(&self.key(c)).into_iter().flatten().copied()
+    }
+}
+
+/// Emprise `outer` contenant l'emprise `inner` (bords compris)
+fn contains_rect(outer: &Rect, inner: &Rect) -> bool {
+    outer.min().x <= inner.min().x
+        && outer.min().y <= inner.min().y
+        && outer.max().x >= inner.max().x
+        && outer.max().y >= inner.max().y
+}
+
+/// `inner` est à l'intérieur de `outer`
+///
+/// Les rings ne se croisant pas, le premier sommet de `inner` hors du bord de
+/// `outer` suffit (deux rings peuvent se toucher en un point).
+fn encloses(outer: &LineString, inner: &LineString) -> bool {
+    inner
+        .coords()
+        .map(|&c| coord_pos_relative_to_ring(c, outer))
+        .find(|pos| *pos != CoordPos::OnBoundary)
+        == Some(CoordPos::Inside)
+}
+
+/// Aire signée d'un ring fermé (formule du lacet)
+pub fn ring_area(ring: &LineString) -> f64 {
+    ring.lines()
+        .map(|l| l.start.x * l.end.y - l.end.x * l.start.y)
+        .sum::<f64>()
+        / 2.0
 }
 
 /// Supprime les arcs en cul-de-sac
@@ -123,4 +232,49 @@ mod tests {
         assert_eq!(polygons.len(), 1);
         assert!(polygons[0].interiors().is_empty());
     }
+
+    fn square(x: f64, y: f64, size: f64) -> LineString {
+        LineString::from(vec![
+            (x, y),
+            (x + size, y),
+            (x + size, y + size),
+            (x, y + size),
+            (x, y),
+        ])
+    }
+
+    #[test]
+    fn test_organize_island_in_hole() {
+        // Parcelle, cour, enclave dans la cour, puis trou dans l'enclave
+        let rings = vec![
+            square(4.0, 4.0, 2.0),
+            square(0.0, 0.0, 10.0),
+            square(4.5, 4.5, 1.0),
+            square(2.0, 2.0, 6.0),
+        ];
+        let polygons = organize_rings(rings);
+
+        assert_eq!(polygons.len(), 2);
+        // Enclave (ordre d'entrée) avec son trou, puis parcelle avec la cour
+        assert_eq!(polygons[0].exterior(), &square(4.0, 4.0, 2.0));
+        assert_eq!(polygons[0].interiors(), &[square(4.5, 4.5, 1.0)]);
+        assert_eq!(polygons[1].exterior(), &square(0.0, 0.0, 10.0));
+        assert_eq!(polygons[1].interiors(), &[square(2.0, 2.0, 6.0)]);
+    }
+
+    #[test]
+    fn test_organize_touching_and_disjoint_rings() {
+        // Trou qui touche son contour en un coin, îlot disjoint avec son trou
+        let rings = vec![
+            square(0.0, 0.0, 4.0),
+            square(0.0, 0.0, 1.0),
+            square(10.0, 0.0, 4.0),
+            square(11.0, 1.0, 1.0),
+        ];
+        let polygons = organize_rings(rings);
+
+        assert_eq!(polygons.len(), 2);
+        assert_eq!(polygons[0].interiors(), &[square(0.0, 0.0, 1.0)]);
+        assert_eq!(polygons[1].interiors(), &[square(11.0, 1.0, 1.0)]);
+    }
 }
@@ -0,0 +1,254 @@
+//! Contrôle de validité OGC des géométries surfaciques
+//!
+//! Règles vérifiées (OGC Simple Features): rings fermés d'au moins 4 points
+//! et d'aire non nulle, aucun segment qui en croise ou en recouvre un autre,
+//! aucun ring qui se touche lui-même, trous à l'intérieur de leur contour et
+//! non imbriqués, polygones d'un multipolygone sans intérieur commun. Les
+//! rings distincts peuvent se toucher en un point.
+
+use geo::line_intersection::{line_intersection, LineIntersection};
+use geo::{Area, Contains, Coord, Geometry, Intersects, Line, LineString, Point, Polygon};
+
+/// Vérifie la validité OGC d'un polygone ou d'un multipolygone
+///
+/// # Errors
+///
+/// Retourne la raison de l'invalidité.
+pub fn check(geometry: &Geometry) -> Result<(), String> {
+    let polygons: &[Polygon] = match geometry {
+        Geometry::Polygon(polygon) => std::slice::from_ref(polygon),
+        Geometry::MultiPolygon(multi) => &multi.0,
+        _ => return Ok(()),
+    };
+    if polygons.is_empty() {
+        return Err("empty geometry".to_string());
+    }
+
+    let mut segments = Vec::new();
+    for (p, polygon) in polygons.iter().enumerate() {
+        for (r, ring) in std::iter::once(polygon.exterior())
+            .chain(polygon.interiors())
+            .enumerate()
+        {
+            check_ring(ring)?;
+            collect_segments(ring, (p, r), &mut segments);
+        }
+    }
+    check_intersections(&mut segments)?;
+
+    // Sans recouvrement de segments, une aire nulle ne reste possible
+    // qu'avec des sommets tous confondus
+    for polygon in polygons {
+        if polygon.unsigned_area() == 0.0 {
+            return Err("polygon with zero area".to_string());
+        }
+    }
+
+    for polygon in polygons {
+        check_holes(polygon)?;
+    }
+    check_shells(polygons)
+}
+
+/// Ring fermé d'au moins 4 points
+fn check_ring(ring: &LineString) -> Result<(), String> {
+    if ring.0.len() < 4 {
+        return Err(format!("ring with {} points", ring.0.len()));
+    }
+    if !ring.is_closed() {
+        return Err("unclosed ring".to_string());
+    }
+    Ok(())
+}
+
+/// Segment d'un ring: (polygone, ring), position dans le ring, nombre de segments du ring
+struct Segment {
+    ring: (usize, usize),
+    index: usize,
+    count: usize,
+    line: Line,
+}
+
+impl Segment {
+    /// Segments consécutifs d'un même ring (le premier suit le dernier)
+    fn adjacent(&self, other: &Segment) -> bool {
+        self.ring == other.ring
+            && ((self.index + 1) % self.count == other.index
+                || (other.index + 1) % other.count == self.index)
+    }
+}
+
+/// Segments de longueur non nulle d'un ring
+fn collect_segments(ring: &LineString, id: (usize, usize), segments: &mut Vec<Segment>) {
+    let lines: Vec<Line> = ring.lines().filter(|l| l.start != l.end).collect();
+    let count = lines.len();
+    segments.extend(lines.into_iter().enumerate().map(|(index, line)| Segment {
+        ring: id,
+        index,
+        count,
+        line,
+    }));
+}
+
+/// Aucun croisement ni recouvrement; contact ponctuel seulement entre rings
+/// distincts ou segments consécutifs
+fn check_intersections(segments: &mut [Segment]) -> Result<(), String> {
+    let min_x = |s: &Segment| s.line.start.x.min(s.line.end.x);
+    let max_x = |s: &Segment| s.line.start.x.max(s.line.end.x);
+    segments.sort_by(|a, b| min_x(a).total_cmp(&min_x(b)));
+
+    // Balayage: seuls les segments dont les abscisses se chevauchent sont comparés
+    for (i, a) in segments.iter().enumerate() {
+        let (a_min_y, a_max_y) = (
+            a.line.start.y.min(a.line.end.y),
+            a.line.start.y.max(a.line.end.y),
+        );
+        for b in segments[i + 1..]
+            .iter()
+            .take_while(|b| min_x(b) <= max_x(a))
+        {
+            if b.line.start.y.max(b.line.end.y) < a_min_y
+                || b.line.start.y.min(b.line.end.y) > a_max_y
+            {
+                continue;
+            }
+            match line_intersection(a.line, b.line) {
+                None => {}
+                Some(LineIntersection::Collinear { intersection }) => {
+                    return Err(format!(
+                        "overlapping segments at {}",
+                        fmt(intersection.start)
+                    ));
+                }
+                Some(LineIntersection::SinglePoint {
+                    intersection,
+                    is_proper,
+                }) => {
+                    if is_proper {
+                        return Err(format!("self-intersection at {}", fmt(intersection)));
+                    }
+                    if a.ring == b.ring && !a.adjacent(b) {
+                        return Err(format!("ring self-touch at {}", fmt(intersection)));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Trous à l'intérieur du contour et non imbriqués
+fn check_holes(polygon: &Polygon) -> Result<(), String> {
+    let shell = Polygon::new(polygon.exterior().clone(), vec![]);
+    let holes: Vec<Polygon> = polygon
+        .interiors()
+        .iter()
+        .map(|ring| Polygon::new(ring.clone(), vec![]))
+        .collect();
+
+    for (i, hole) in holes.iter().enumerate() {
+        if let Some(c) = hole
+            .exterior()
+            .coords()
+            .find(|c| !shell.intersects(&Point::from(**c)))
+        {
+            return Err(format!("hole outside its shell at {}", fmt(*c)));
+        }
+        for (j, other) in holes.iter().enumerate() {
+            if i != j && interior_vertex(hole, other) {
+                return Err("nested holes".to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Polygones d'un multipolygone sans intérieur commun
+fn check_shells(polygons: &[Polygon]) -> Result<(), String> {
+    for (i, polygon) in polygons.iter().enumerate() {
+        for (j, other) in polygons.iter().enumerate() {
+            if i != j && interior_vertex(polygon, other) {
+                return Err("overlapping polygons".to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Un sommet du contour de `inner` est strictement à l'intérieur de `outer`
+///
+/// Les segments ne se croisant pas, un contour est soit dedans, soit dehors.
+fn interior_vertex(inner: &Polygon, outer: &Polygon) -> bool {
+    inner
+        .exterior()
+        .coords()
+        .any(|c| outer.contains(&Point::from(*c)))
+}
+
+fn fmt(c: Coord) -> String {
+    format!("({}, {})", c.x, c.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{polygon, MultiPolygon};
+
+    #[test]
+    fn test_valid_polygon_with_hole() {
+        let polygon = polygon!(
+            exterior: [(x: 0.0, y: 0.0), (x: 10.0, y: 0.0), (x: 10.0, y: 10.0), (x: 0.0, y: 10.0)],
+            interiors: [[(x: 2.0, y: 2.0), (x: 4.0, y: 2.0), (x: 4.0, y: 4.0), (x: 2.0, y: 4.0)]],
+        );
+        assert_eq!(check(&Geometry::Polygon(polygon)), Ok(()));
+    }
+
+    #[test]
+    fn test_invalid_polygons() {
+        // Papillon: les côtés se croisent
+        let bowtie =
+            polygon![(x: 0.0, y: 0.0), (x: 2.0, y: 2.0), (x: 2.0, y: 0.0), (x: 0.0, y: 2.0)];
+        let err = check(&Geometry::Polygon(bowtie)).unwrap_err();
+        assert!(err.starts_with("self-intersection"), "{}", err);
+
+        // Trou hors du contour
+        let outside = polygon!(
+            exterior: [(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0), (x: 0.0, y: 1.0)],
+            interiors: [[(x: 2.0, y: 2.0), (x: 3.0, y: 2.0), (x: 3.0, y: 3.0)]],
+        );
+        let err = check(&Geometry::Polygon(outside)).unwrap_err();
+        assert!(err.starts_with("hole outside"), "{}", err);
+
+        // Ring qui se touche lui-même en (1, 1)
+        let touch = polygon![
+            (x: 0.0, y: 0.0), (x: 2.0, y: 0.0), (x: 1.0, y: 1.0),
+            (x: 2.0, y: 2.0), (x: 0.0, y: 2.0), (x: 1.0, y: 1.0)
+        ];
+        let err = check(&Geometry::Polygon(touch)).unwrap_err();
+        assert!(err.starts_with("ring self-touch"), "{}", err);
+    }
+
+    #[test]
+    fn test_multipolygon_shells() {
+        let square = |x: f64| polygon![(x: x, y: 0.0), (x: x + 1.0, y: 0.0), (x: x + 1.0, y: 1.0), (x: x, y: 1.0)];
+
+        // Contact en un point: valide
+        let corner =
+            polygon![(x: 1.0, y: 1.0), (x: 2.0, y: 1.0), (x: 2.0, y: 2.0), (x: 1.0, y: 2.0)];
+        let multi = MultiPolygon::new(vec![square(0.0), corner]);
+        assert_eq!(check(&Geometry::MultiPolygon(multi)), Ok(()));
+
+        // Côté commun: invalide
+        let multi = MultiPolygon::new(vec![square(0.0), square(1.0)]);
+        let err = check(&Geometry::MultiPolygon(multi)).unwrap_err();
+        assert!(err.starts_with("overlapping segments"), "{}", err);
+
+        // Polygone dans un autre
+        let inner = polygon![(x: 0.2, y: 0.2), (x: 0.8, y: 0.2), (x: 0.8, y: 0.8)];
+        let multi = MultiPolygon::new(vec![square(0.0), inner]);
+        assert_eq!(
+            check(&Geometry::MultiPolygon(multi)),
+            Err("overlapping polygons".to_string())
+        );
+    }
+}
@@ -1,8 +1,11 @@
 //! Types de données pour le crate edigeo
 
 use geo::Geometry;
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
 
+use crate::repair::ring::DEFAULT_TOLERANCE;
+use crate::repair::snap::SnapRounding;
 use crate::EdigeoError;
 
 /// Résultat du parsing d'une archive EDIGEO
@@ -20,8 +23,174 @@ pub struct ParseResult {
     /// Code département (2 ou 3 caractères, ex: "01", "2A", "2B")
     pub departement: String,
 
+    /// Métadonnées d'échange (THF): date de production, producteur, lots
+    pub exchange: Exchange,
+
+    /// Schéma conceptuel (types d'objets, attributs, relations)
+    pub schema: Schema,
+
+    /// Informations générales (emprise)
+    pub general: General,
+
+    /// Relations sémantiques entre features (ex: parcelle dans sa section),
+    /// résolues dans chaque VEC puis fusionnées (voir `RelationGraph::extend`)
+    pub relations: RelationGraph,
+
+    /// Erreurs non fatales rencontrées pendant le parsing
+    pub errors: Vec<EdigeoError>,
+
+    /// Retouches apportées aux géométries des entités
+    pub repairs: RepairLog,
+}
+
+/// Métadonnées d'une archive, connues avant la première feature
+#[derive(Debug, Clone)]
+pub struct ArchiveInfo {
+    /// Projection source détectée
+    pub projection: Projection,
+
+    /// Année du millésime
+    pub year: u16,
+
+    /// Code département (2 ou 3 caractères, ex: "01", "2A", "2B")
+    pub departement: String,
+
+    /// Métadonnées d'échange (THF): date de production, producteur, lots
+    pub exchange: Exchange,
+
+    /// Schéma conceptuel du lot (DIC, SCD): types d'objets, attributs, relations
+    pub schema: Schema,
+}
+
+/// Résultat d'un parsing en streaming (les features ont été transmises au fil de l'eau)
+#[derive(Debug)]
+pub struct StreamSummary {
+    /// Métadonnées de l'archive
+    pub info: ArchiveInfo,
+
+    /// Nombre de features transmises
+    pub features: usize,
+
+    /// Informations générales (emprise)
+    pub general: General,
+
+    /// Relations sémantiques entre features (ex: parcelle dans sa section),
+    /// résolues dans chaque VEC puis fusionnées (voir `RelationGraph::extend`)
+    pub relations: RelationGraph,
+
     /// Erreurs non fatales rencontrées pendant le parsing
     pub errors: Vec<EdigeoError>,
+
+    /// Fichiers VEC illisibles (erreur dans `errors`): leurs features
+    /// manquent au lot
+    pub failed_vecs: usize,
+
+    /// Retouches apportées aux géométries des entités
+    pub repairs: RepairLog,
+}
+
+/// Options de parsing d'un lot (voir `parse_lot_with_options`)
+#[derive(Clone, Copy)]
+pub struct ParseOptions<'a> {
+    /// Arrondi topologique des arcs avant l'assemblage des géométries
+    pub snap: Option<SnapRounding<'a>>,
+
+    /// Distance en deçà de laquelle deux sommets d'arcs sont confondus lors de
+    /// l'assemblage des faces (unités des coordonnées, défaut 1e-6). Avec
+    /// `snap`, elle est ramenée au plus à un demi-pas de la grille.
+    pub tolerance: f64,
+}
+
+impl Default for ParseOptions<'_> {
+    fn default() -> Self {
+        Self {
+            snap: None,
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+}
+
+/// Journal des retouches de géométrie d'un lot
+///
+/// Une entrée par entité et par retouche: les entités reconstruites telles
+/// quelles n'y figurent pas.
+#[derive(Debug, Clone, Default)]
+pub struct RepairLog {
+    /// Entrées, dans l'ordre de construction des entités
+    pub entries: Vec<RepairEntry>,
+}
+
+impl RepairLog {
+    /// Ajoute une entrée
+    pub fn record(&mut self, entry: RepairEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Aucune retouche
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Retouche de la géométrie d'une entité
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepairEntry {
+    /// Identifiant de l'entité (IDU si disponible)
+    pub entity_id: String,
+
+    /// Type de l'entité (ex: "PARCELLE_id")
+    pub feature_type: String,
+
+    /// Retouche appliquée
+    pub action: RepairAction,
+
+    /// Nombre de sommets concernés
+    pub vertices: usize,
+
+    /// Variation de surface (unités des coordonnées au carré): surface de la
+    /// géométrie retenue moins celle obtenue sans la retouche. Pour un rejet,
+    /// surface perdue (négative), `None` faute de polygone candidat.
+    pub area_delta: Option<f64>,
+}
+
+/// Retouche appliquée à une entité
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepairAction {
+    /// Sommets confondus avec un nœud voisin (tolérance d'assemblage)
+    NodesMerged,
+    /// Segments communs à deux faces de l'entité annulés: sommets = segments
+    /// annulés
+    EdgesDissolved,
+    /// Sommets déplacés par l'arrondi topologique
+    VerticesSnapped,
+    /// Segments pendants (culs-de-sac) retirés: sommets = nœuds retirés
+    DanglingRemoved,
+    /// Faces référencées absentes ou sans arcs, ignorées
+    EmptyFaceSkipped,
+    /// Entité écartée faute de polygone valide (raison)
+    Rejected(String),
+}
+
+impl RepairAction {
+    /// Libellé stable (ex: "dangling_removed")
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RepairAction::NodesMerged => "nodes_merged",
+            RepairAction::EdgesDissolved => "edges_dissolved",
+            RepairAction::VerticesSnapped => "vertices_snapped",
+            RepairAction::DanglingRemoved => "dangling_removed",
+            RepairAction::EmptyFaceSkipped => "empty_face_skipped",
+            RepairAction::Rejected(_) => "rejected",
+        }
+    }
+
+    /// Raison du rejet
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            RepairAction::Rejected(reason) => Some(reason),
+            _ => None,
+        }
+    }
 }
 
 /// Une feature cadastrale avec sa géométrie et ses attributs
@@ -38,6 +207,9 @@ pub struct Feature {
 
     /// Type de feature (ex: "PARCELLE_id", "SECTION_id")
     pub feature_type: String,
+
+    /// Qualité de l'objet (QAL), si le lot en fournit une
+    pub quality: Option<Quality>,
 }
 
 /// Informations de projection
@@ -65,19 +237,576 @@ pub struct ThfData {
     /// Encodage des fichiers texte
     pub encoding: &'static encoding_rs::Encoding,
 
-    /// Année du millésime
+    /// Métadonnées de l'échange
+    pub exchange: Exchange,
+}
+
+/// Métadonnées d'échange d'un lot (fichier THF)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exchange {
+    /// Date de production des données (TDA)
+    pub production_date: Date,
+
+    /// Producteur des données (AUT, ex: "DGFiP")
+    pub producer: Option<String>,
+
+    /// Adresse du producteur (ADR)
+    pub address: Option<String>,
+
+    /// Version de la norme EDIGEO (VER)
+    pub version: Option<String>,
+
+    /// Date de la version de la norme (VDA)
+    pub version_date: Option<Date>,
+
+    /// Commentaire du support (INF)
+    pub description: Option<String>,
+
+    /// Lots décrits par l'échange (enregistrements GTL)
+    pub lots: Vec<ExchangeLot>,
+
+    /// Code INSEE de la commune (ex: "38001"), déduit de l'identifiant de feuille
+    pub commune: Option<String>,
+
+    /// Identifiant de la feuille cadastrale (ex: "380010000S01")
+    pub sheet: Option<String>,
+}
+
+impl Exchange {
+    /// Échange sans autre métadonnée que sa date de production
+    pub fn new(production_date: Date) -> Self {
+        Self {
+            production_date,
+            producer: None,
+            address: None,
+            version: None,
+            version_date: None,
+            description: None,
+            lots: Vec::new(),
+            commune: None,
+            sheet: None,
+        }
+    }
+
+    /// Département de la commune (ex: "38", "2A", "971")
+    pub fn departement(&self) -> Option<&str> {
+        let commune = self.commune.as_deref()?;
+        Some(if commune.starts_with("97") || commune.starts_with("98") {
+            &commune[..3]
+        } else {
+            &commune[..2]
+        })
+    }
+}
+
+/// Lot décrit dans le fichier THF
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExchangeLot {
+    /// Identifiant de l'enregistrement (RID)
+    pub id: String,
+
+    /// Nom du lot (LON)
+    pub name: Option<String>,
+
+    /// Commentaire du lot (INF)
+    pub description: Option<String>,
+
+    /// Fichiers du lot (GEN, GEO, QAL, DIC, SCD puis VEC), sans extension
+    pub files: Vec<String>,
+}
+
+/// Informations de qualité d'un objet (bloc QUP du QAL)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Quality {
+    /// Date de l'observation d'origine (ODA)
+    pub create_date: Option<Date>,
+
+    /// Date de la dernière mise à jour (UDA)
+    pub update_date: Option<Date>,
+
+    /// Type de la dernière mise à jour (UTY)
+    pub update_type: Option<UpdateType>,
+
+    /// Autres champs du bloc, par code (ex: "EDA"), valeurs brutes
+    pub other: HashMap<String, String>,
+}
+
+/// Type de mise à jour d'un objet (champ UTY du QAL)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UpdateType {
+    /// Création de l'objet
+    Creation,
+    /// Modification de l'objet
+    Modification,
+    /// Suppression de l'objet
+    Deletion,
+    /// Code non prévu par la norme
+    Other(String),
+}
+
+impl UpdateType {
+    /// Type d'après le code EDIGEO (initiale: C, M, S ou D)
+    pub fn from_code(code: &str) -> Self {
+        let code = code.trim();
+        match code.chars().next().map(|c| c.to_ascii_uppercase()) {
+            Some('C') => UpdateType::Creation,
+            Some('M') => UpdateType::Modification,
+            Some('S') | Some('D') => UpdateType::Deletion,
+            _ => UpdateType::Other(code.to_string()),
+        }
+    }
+
+    /// Libellé stable (ex: "modification"), le code brut pour `Other`
+    pub fn as_str(&self) -> &str {
+        match self {
+            UpdateType::Creation => "creation",
+            UpdateType::Modification => "modification",
+            UpdateType::Deletion => "deletion",
+            UpdateType::Other(code) => code,
+        }
+    }
+}
+
+/// Date calendaire d'un fichier EDIGEO
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    /// Année (ex: 2024)
     pub year: u16,
+
+    /// Mois (1 à 12)
+    pub month: u8,
+
+    /// Jour du mois (1 à 31)
+    pub day: u8,
+}
+
+impl Date {
+    /// Date valide, `None` sinon (ex: 30 février)
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Self> {
+        let leap =
+            year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+        let days = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap => 29,
+            2 => 28,
+            _ => return None,
+        };
+        (1..=days)
+            .contains(&day)
+            .then_some(Self { year, month, day })
+    }
+
+    /// Parse une date EDIGEO `AAAAMMJJ` (ou ISO `AAAA-MM-JJ`)
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().as_bytes();
+        // Positions de l'année, du mois et du jour
+        let (year, month, day) = match value.len() {
+            8 => (&value[..4], &value[4..6], &value[6..]),
+            10 if value[4] == b'-' && value[7] == b'-' => (&value[..4], &value[5..7], &value[8..]),
+            _ => return None,
+        };
+        let number = |digits: &[u8]| {
+            digits.iter().try_fold(0u16, |n, &b| {
+                b.is_ascii_digit().then(|| n * 10 + u16::from(b - b'0'))
+            })
+        };
+        Self::new(
+            number(year)?,
+            u8::try_from(number(month)?).ok()?,
+            u8::try_from(number(day)?).ok()?,
+        )
+    }
+}
+
+impl std::fmt::Display for Date {
+    /// Format ISO 8601 (`AAAA-MM-JJ`)
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Type de valeur d'un attribut (champ TYP du DIC ou du SCD)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeType {
+    /// `A`: alphanumérique
+    Alphanumeric,
+    /// `T`: texte
+    Text,
+    /// `I`: entier
+    Integer,
+    /// `R`: réel
+    Real,
+    /// `N`: numérique (entier ou réel)
+    Numeric,
+    /// `D`: date (AAAAMMJJ)
+    Date,
+    /// Code non prévu par la norme
+    Other(String),
 }
 
-/// Informations de qualité d'un objet (depuis QAL)
+impl AttributeType {
+    /// Type d'après le code EDIGEO
+    pub fn from_code(code: &str) -> Self {
+        match code.trim().to_ascii_uppercase().as_str() {
+            "A" => AttributeType::Alphanumeric,
+            "T" => AttributeType::Text,
+            "I" => AttributeType::Integer,
+            "R" => AttributeType::Real,
+            "N" => AttributeType::Numeric,
+            "D" => AttributeType::Date,
+            other => AttributeType::Other(other.to_string()),
+        }
+    }
+
+    /// Le type porte une valeur numérique
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            AttributeType::Integer | AttributeType::Real | AttributeType::Numeric
+        )
+    }
+}
+
+/// Définition d'un objet, d'un attribut ou d'une relation (fichier DIC)
 #[derive(Debug, Clone, Default)]
-pub struct Quality {
-    /// Date de création
-    pub create_date: Option<String>,
+pub struct Definition {
+    /// Libellé court (LAB, ex: "PARCELLE", "IDU")
+    pub label: String,
 
-    /// Date de mise à jour
-    pub update_date: Option<String>,
+    /// Définition en clair (DEF)
+    pub definition: Option<String>,
 
-    /// Type de mise à jour
-    pub update_type: Option<String>,
+    /// Origine de la définition (ORI)
+    pub origin: Option<String>,
+
+    /// Catégorie (CAT)
+    pub category: Option<String>,
+
+    /// Type de valeur (TYP, définitions d'attributs seulement)
+    pub value_type: Option<AttributeType>,
+}
+
+/// Dictionnaire d'un lot (fichier DIC), par identifiant d'enregistrement
+#[derive(Debug, Clone, Default)]
+pub struct Dictionary {
+    /// Définitions d'objets (enregistrements DID)
+    pub objects: HashMap<String, Definition>,
+
+    /// Définitions d'attributs (enregistrements DIA)
+    pub attributes: HashMap<String, Definition>,
+
+    /// Définitions de relations (enregistrements DIR)
+    pub relations: HashMap<String, Definition>,
+}
+
+/// Nature géométrique d'un type d'objet (champ KND du SCD)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeometryKind {
+    /// `PCT`: ponctuel
+    Point,
+    /// `LIN`: linéaire
+    Line,
+    /// `ARE`: surfacique
+    Area,
+    /// `CPX`: complexe
+    Complex,
+}
+
+impl GeometryKind {
+    /// Nature d'après le code EDIGEO
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim().to_ascii_uppercase().as_str() {
+            "PCT" => Some(GeometryKind::Point),
+            "LIN" => Some(GeometryKind::Line),
+            "ARE" => Some(GeometryKind::Area),
+            "CPX" => Some(GeometryKind::Complex),
+            _ => None,
+        }
+    }
+}
+
+/// Type d'objet du schéma conceptuel (enregistrement OBJ du SCD)
+#[derive(Debug, Clone)]
+pub struct ObjectType {
+    /// Identifiant (ex: "PARCELLE_id", type des features)
+    pub id: String,
+
+    /// Libellé du dictionnaire (ex: "PARCELLE")
+    pub label: String,
+
+    /// Définition en clair
+    pub definition: Option<String>,
+
+    /// Nature géométrique
+    pub kind: Option<GeometryKind>,
+
+    /// Identifiants des attributs de l'objet (ex: "IDU_id")
+    pub attributes: Vec<String>,
+}
+
+/// Valeur énumérée d'un attribut
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeValue {
+    /// Code tel qu'il apparaît dans les VEC (ex: "01")
+    pub code: String,
+
+    /// Libellé du code (ex: "Bâti dur")
+    pub label: Option<String>,
+}
+
+/// Attribut du schéma conceptuel (enregistrement ATT du SCD)
+#[derive(Debug, Clone)]
+pub struct AttributeDef {
+    /// Identifiant (ex: "IDU_id")
+    pub id: String,
+
+    /// Libellé du dictionnaire (ex: "IDU")
+    pub label: String,
+
+    /// Définition en clair
+    pub definition: Option<String>,
+
+    /// Type de valeur (SCD, sinon DIC)
+    pub value_type: Option<AttributeType>,
+
+    /// Nombre maximal de caractères (CAN)
+    pub width: Option<usize>,
+
+    /// Nombre de décimales (CAD)
+    pub decimals: Option<usize>,
+
+    /// Unité de mesure (UNI)
+    pub unit: Option<String>,
+
+    /// Valeurs autorisées (vide si libre)
+    pub values: Vec<AttributeValue>,
+}
+
+impl AttributeDef {
+    /// Libellé d'un code énuméré
+    pub fn value_label(&self, code: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|v| v.code == code)
+            .and_then(|v| v.label.as_deref())
+    }
+}
+
+/// Relation du schéma conceptuel (enregistrement REL du SCD)
+#[derive(Debug, Clone)]
+pub struct RelationDef {
+    /// Identifiant (ex: "ID_S_OBJ_Z_1_2_2")
+    pub id: String,
+
+    /// Libellé du dictionnaire
+    pub label: String,
+
+    /// Définition en clair
+    pub definition: Option<String>,
+
+    /// Nature de la relation (KND)
+    pub kind: Option<String>,
+
+    /// Identifiants des objets et primitives reliés
+    pub members: Vec<String>,
+}
+
+/// Schéma conceptuel d'un lot (fichier SCD complété par le DIC)
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    /// Types d'objets, par identifiant (ex: "PARCELLE_id")
+    pub objects: HashMap<String, ObjectType>,
+
+    /// Attributs, par identifiant (ex: "IDU_id")
+    pub attributes: HashMap<String, AttributeDef>,
+
+    /// Relations, par identifiant
+    pub relations: HashMap<String, RelationDef>,
+}
+
+impl Schema {
+    /// Aucun fichier SCD lu
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty() && self.attributes.is_empty() && self.relations.is_empty()
+    }
+
+    /// Ajoute le schéma d'un autre lot
+    ///
+    /// Les définitions déjà connues sont conservées; les attributs d'un type
+    /// d'objet déjà connu sont complétés.
+    pub fn merge(&mut self, other: Schema) {
+        for (id, object) in other.objects {
+            match self.objects.entry(id) {
+                Entry::Occupied(mut known) => {
+                    let known = known.get_mut();
+                    for attribute in object.attributes {
+                        if !known.attributes.contains(&attribute) {
+                            known.attributes.push(attribute);
+                        }
+                    }
+                }
+                Entry::Vacant(slot) => {
+                    slot.insert(object);
+                }
+            }
+        }
+        for (id, attribute) in other.attributes {
+            self.attributes.entry(id).or_insert(attribute);
+        }
+        for (id, relation) in other.relations {
+            self.relations.entry(id).or_insert(relation);
+        }
+    }
+
+    /// Type d'objet d'une feature (`Feature::feature_type`)
+    pub fn object(&self, feature_type: &str) -> Option<&ObjectType> {
+        self.objects.get(feature_type)
+    }
+
+    /// Attribut d'une propriété de feature (ex: "IDU" ou "IDU_id")
+    pub fn attribute(&self, property: &str) -> Option<&AttributeDef> {
+        self.attributes
+            .get(property)
+            .or_else(|| self.attributes.get(&format!("{}_id", property)))
+    }
+
+    /// Attributs d'un type d'objet, dans l'ordre du SCD
+    pub fn object_attributes(&self, feature_type: &str) -> Vec<&AttributeDef> {
+        self.object(feature_type)
+            .map(|object| {
+                object
+                    .attributes
+                    .iter()
+                    .filter_map(|id| self.attributes.get(id))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Informations générales d'un lot (fichier GEN)
+#[derive(Debug, Clone, Default)]
+pub struct General {
+    /// Emprise des données (coordonnées de la projection source)
+    pub extent: Option<geo::Rect<f64>>,
+}
+
+/// Graphe des relations sémantiques d'un lot (liens LNK entre deux FEA)
+///
+/// Les relations d'inclusion (parcelle dans une subdivision de section,
+/// bâtiment dans une parcelle...) relient les features par leur identifiant
+/// (`Feature::id`), sans orientation.
+#[derive(Debug, Clone, Default)]
+pub struct RelationGraph {
+    /// Type de chaque feature reliée (ex: "PARCELLE_id")
+    types: HashMap<String, String>,
+
+    /// Voisins de chaque feature: (relation, identifiant)
+    links: HashMap<String, Vec<(String, String)>>,
+}
+
+impl RelationGraph {
+    /// Profondeur maximale de recherche (subdivision fiscale → commune)
+    pub const MAX_DEPTH: usize = 4;
+
+    /// Ajoute une relation entre deux features (identifiant, type)
+    pub fn insert(&mut self, relation: &str, from: (&str, &str), to: (&str, &str)) {
+        for (id, feature_type) in [from, to] {
+            self.types
+                .entry(id.to_string())
+                .or_insert_with(|| feature_type.to_string());
+        }
+        self.links
+            .entry(from.0.to_string())
+            .or_default()
+            .push((relation.to_string(), to.0.to_string()));
+        self.links
+            .entry(to.0.to_string())
+            .or_default()
+            .push((relation.to_string(), from.0.to_string()));
+    }
+
+    /// Ajoute les relations d'un autre graphe (un autre VEC du lot)
+    ///
+    /// Les features sont fusionnées par identifiant: deux features sans IDU
+    /// dont l'identifiant d'enregistrement se répète d'un VEC à l'autre
+    /// deviennent un seul nœud.
+    pub fn extend(&mut self, other: RelationGraph) {
+        for (id, feature_type) in other.types {
+            self.types.entry(id).or_insert(feature_type);
+        }
+        for (id, links) in other.links {
+            self.links.entry(id).or_default().extend(links);
+        }
+    }
+
+    /// Aucune relation
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    /// Type d'une feature reliée
+    pub fn feature_type(&self, id: &str) -> Option<&str> {
+        self.types.get(id).map(String::as_str)
+    }
+
+    /// Features directement reliées: (relation, identifiant)
+    pub fn neighbors(&self, id: &str) -> impl Iterator<Item = (&str, &str)> {
+        self.links
+            .get(id)
+            .into_iter()
+            .flatten()
+            .map(|(relation, neighbor)| (relation.as_str(), neighbor.as_str()))
+    }
+
+    /// Feature du type demandé la plus proche dans le graphe (la feature
+    /// elle-même si elle est de ce type, `None` si elle n'est pas reliée)
+    ///
+    /// Les relations étant des inclusions, la plus proche est le parent: une
+    /// section est à un ou deux liens d'une parcelle, une autre section à au
+    /// moins quatre. La recherche s'arrête à `MAX_DEPTH` liens.
+    pub fn nearest(&self, id: &str, feature_type: &str) -> Option<&str> {
+        let (start, _) = self.types.get_key_value(id)?;
+        let mut visited: HashSet<&str> = HashSet::from([start.as_str()]);
+        let mut queue: VecDeque<(&str, usize)> = VecDeque::from([(start.as_str(), 0)]);
+
+        while let Some((current, depth)) = queue.pop_front() {
+            if self.feature_type(current) == Some(feature_type) {
+                return Some(current);
+            }
+            if depth == Self::MAX_DEPTH {
+                continue;
+            }
+            for (_, neighbor) in self.neighbors(current) {
+                if visited.insert(neighbor) {
+                    queue.push_back((neighbor, depth + 1));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_edigeo_date() {
+        let parse = |raw: &str| Date::parse(raw).map(|date| date.to_string());
+        assert_eq!(parse("20240115").as_deref(), Some("2024-01-15"));
+        assert_eq!(parse(" 19991231 ").as_deref(), Some("1999-12-31"));
+        assert_eq!(parse("2024-01-15").as_deref(), Some("2024-01-15"));
+        assert_eq!(parse("20240229").as_deref(), Some("2024-02-29"));
+        assert_eq!(parse("20241315"), None);
+        assert_eq!(parse("20230229"), None);
+        assert_eq!(parse("20240431"), None);
+        assert_eq!(parse(""), None);
+        // Séparateurs à leur place seulement
+        assert_eq!(parse("2024-1--15"), None);
+        assert_eq!(parse("202401-15-"), None);
+        assert_eq!(parse("2024+01+15"), None);
+        assert_eq!(parse("2024011a"), None);
+    }
 }
@@ -39,7 +39,7 @@ fn test_parse_real_archive() {
             );
 
             // Vérifier que les features ont des géométries valides
-            for (_, features) in &parse_result.features {
+            for features in parse_result.features.values() {
                 for feature in features {
                     assert!(!feature.id.is_empty(), "Feature should have an ID");
                     // La géométrie est toujours présente (c'est un champ non optionnel)
@@ -71,7 +71,7 @@ fn test_parse_multiple_archives() {
     let archives: Vec<_> = std::fs::read_dir(fixtures_dir)
         .unwrap()
         .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().map_or(false, |ext| ext == "bz2"))
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "bz2"))
         .collect();
 
     let mut total_features = 0;